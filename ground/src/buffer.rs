@@ -2,6 +2,7 @@ use std::collections::BinaryHeap;
 use std::sync::Mutex;
 use crate::types::{TelemetryPacket};
 
+#[derive(Debug)]
 pub struct BoundedBuffer {
     heap: Mutex<BinaryHeap<TelemetryPacket>>,
     pub capacity: usize,
@@ -50,4 +51,8 @@ impl BoundedBuffer {
     pub fn len(&self) -> usize {
         self.heap.lock().unwrap().len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.lock().unwrap().is_empty()
+    }
 }
\ No newline at end of file
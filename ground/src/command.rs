@@ -5,140 +5,111 @@ use std::time::Duration;
 use std::thread;
 use thread_priority::*;
 
-use crate::config::{COMMAND_MS, COMMAND_PRIORITY, SEQUENCE_NOT_CONFIRMED, COMMAND_DISPATCH_DEADLINE_MS};
-use crate::state::GroundState;
-use crate::buffer::BoundedBuffer;
+use crate::config::SEQUENCE_NOT_CONFIRMED;
+use crate::state::{GroundState, SpacecraftState, PendingCommand, ScheduledCommand};
+use crate::planner::PlannedCommand;
 use crate::types::*;
 
 pub fn run_command_scheduler(
     state: Arc<GroundState>,
-    uplink_buffer: Arc<BoundedBuffer>,
     log_tx: SyncSender<Log>,
 ) {
     set_current_thread_priority(ThreadPriority::Crossplatform(
-        COMMAND_PRIORITY.try_into().unwrap()
+        state.config.command_priority.try_into().unwrap()
     )).unwrap();
 
     while state.is_running.load(Ordering::SeqCst) {
         let now = state.uptime_ms();
 
-        let schedule = state.command_schedule.lock().unwrap();
-        for entry in schedule.iter() {
-            if !entry.enabled.load(Ordering::Acquire) {
-                continue;
-            }
+        for spacecraft in &state.spacecraft {
+            run_schedule(&state, spacecraft, &log_tx, now);
+            check_command_timeouts(&state, spacecraft, &log_tx, now);
+        }
 
-            let next = entry.next_send_time.load(Ordering::Acquire);
+        thread::sleep(Duration::from_micros(state.config.command_ms));
+    }
+}
 
-            if next == 0 {
-                entry.next_send_time.store(now + entry.interval_ms, Ordering::Release);
-                continue;
-            }
+// Every spacecraft keeps its own cycle, a blocked interlock on one never holds up another
+fn run_schedule(state: &Arc<GroundState>, spacecraft: &SpacecraftState, log_tx: &SyncSender<Log>, now: u64) {
+    let schedule = spacecraft.command_schedule.lock().unwrap();
+    for entry in schedule.iter() {
+        if !entry.enabled.load(Ordering::Acquire) {
+            continue;
+        }
 
-            if now < next {
-                continue;
-            }
+        let next = entry.next_send_time.load(Ordering::Acquire);
 
-            if now > next {
-                log_scheduling_drift(&log_tx, &entry.command, now, now - next);
-            }
+        if next == 0 {
+            entry.next_send_time.store(now + entry.interval_ms, Ordering::Release);
+            continue;
+        }
 
-            if interlock_blocks(&state, &uplink_buffer, &log_tx, &entry.command) {
-                log_interlock_rejection(&log_tx, &state, &entry.command, now);
-                entry.next_send_time.store(now + entry.interval_ms, Ordering::Release);
-                continue;
-            }
+        if now < next {
+            continue;
+        }
 
-            dispatch_command(&state, &uplink_buffer, &log_tx, &entry.command, entry.priority, now);
+        if now > next {
+            log_scheduling_drift(spacecraft, log_tx, &entry.command, now, now - next);
+        }
+
+        if interlock_blocks(state, spacecraft, log_tx, &entry.command) {
+            log_interlock_rejection(spacecraft, log_tx, &entry.command, now);
             entry.next_send_time.store(now + entry.interval_ms, Ordering::Release);
+            continue;
         }
-        drop(schedule);
 
-        thread::sleep(Duration::from_micros(COMMAND_MS));
+        dispatch_command(state, spacecraft, log_tx, entry, now);
+        entry.next_send_time.store(now + entry.interval_ms, Ordering::Release);
     }
 }
 
-fn interlock_blocks(state: &Arc<GroundState>, uplink_buffer: &Arc<BoundedBuffer>, log_tx: &SyncSender<Log>, command: &Command) -> bool {
-    if let Some(required_subsystem) = command.required_health() {
-        if let Some(sub) = state.find_subsystem(required_subsystem) {
-            let interlock = sub.interlock.load(Ordering::Acquire);
-
-            if interlock {
-                let packet = TelemetryPacket {
-                    priority: Priority::Emergency,
-                    creation_time: state.uptime_ms(),
-                    payload: SatelliteMessage::Command { 
-                        command: Command::ClearSubsystemFault { 
-                            subsystem_id: required_subsystem 
-                        }, 
-                        sent_at: state.uptime_ms(), 
-                    },
-                    sequence_no: SEQUENCE_NOT_CONFIRMED,
-                };
-
-                if let Some(dropped) = uplink_buffer.push(packet) {
-                    log_tx.try_send(Log {
-                        source: LogSource::CommandScheduler,
-                        event: Event {
-                            task_id: dropped.payload.command_task_id(),
-                            event_id: EventID::DataLoss,
-                            data: EventData::None,
-                            timestamp: state.uptime_ms(),
-                        },
-                    }).ok();
-                }
-            }
+fn interlock_blocks(state: &Arc<GroundState>, spacecraft: &SpacecraftState, log_tx: &SyncSender<Log>, command: &Command) -> bool {
+    if let Some(required_subsystem) = command.required_health()
+        && let Some(sub) = spacecraft.find_subsystem(required_subsystem) {
+        let interlock = sub.interlock.load(Ordering::Acquire);
+
+        let clear = Command::ClearSubsystemFault { subsystem_id: required_subsystem };
 
-            return interlock;
+        if interlock && !command_outstanding(spacecraft, &clear) { // One clear is enough, it waits for the next pass like everything else
+            queue_command(state, spacecraft, log_tx, LogSource::CommandScheduler, clear, Priority::Emergency, 0);
         }
+
+        return interlock;
     }
     false
 }
 
+fn command_outstanding(spacecraft: &SpacecraftState, command: &Command) -> bool {
+    spacecraft.command_tracking.lock().unwrap().pending.iter()
+        .any(|entry| entry.command == *command && entry.stage < CommandStage::Accepted)
+}
+
 fn dispatch_command(
     state: &Arc<GroundState>,
-    uplink_buffer: &Arc<BoundedBuffer>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
-    command: &Command,
-    priority: Priority,
+    entry: &ScheduledCommand,
     enqueued_at: u64,
 ) {
-    let task_id = command.task_id();
-
-    let packet = TelemetryPacket {
-        priority,
-        creation_time: state.uptime_ms(),
-        payload: SatelliteMessage::Command {
-            command: *command,
-            sent_at: state.uptime_ms(),
-        },
-        sequence_no: SEQUENCE_NOT_CONFIRMED,
-    };
+    let task_id = entry.command.task_id();
+    let execute_at = if entry.execute_after_ms > 0 { enqueued_at + entry.execute_after_ms } else { 0 };
 
-    if let Some(dropped) = uplink_buffer.push(packet) {
-        log_tx.try_send(Log {
-            source: LogSource::CommandScheduler,
-            event: Event {
-                task_id: dropped.payload.command_task_id(),
-                event_id: EventID::DataLoss,
-                data: EventData::None,
-                timestamp: state.uptime_ms(),
-            },
-        }).ok();
-    }
+    queue_command(state, spacecraft, log_tx, LogSource::CommandScheduler, entry.command, entry.priority, execute_at);
 
     let dispatch_time = state.uptime_ms();
     let latency = dispatch_time.saturating_sub(enqueued_at);
-    state.command_dispatch_latency.insert_new_metric(latency);
+    spacecraft.command_dispatch_latency.insert_new_metric(latency);
 
-    if latency > COMMAND_DISPATCH_DEADLINE_MS {
+    if latency > state.tunables.command_dispatch_deadline_ms.load(Ordering::Acquire) {
         log_tx.try_send(Log {
             source: LogSource::CommandScheduler,
+            spacecraft_id: Some(spacecraft.id),
             event: Event {
                 task_id,
                 event_id: EventID::CompletionDelay,
                 data: EventData::SchedulingDrift {
-                    drift_ms: latency.saturating_sub(COMMAND_DISPATCH_DEADLINE_MS) as u32,
+                    drift_ms: latency.saturating_sub(state.tunables.command_dispatch_deadline_ms.load(Ordering::Acquire)) as u32,
                 },
                 timestamp: dispatch_time,
             },
@@ -147,14 +118,15 @@ fn dispatch_command(
 
     log_tx.try_send(Log {
         source: LogSource::CommandScheduler,
+        spacecraft_id: Some(spacecraft.id),
         event: Event {
             task_id,
             event_id: EventID::TaskCompletion,
             data: EventData::Hardware {
                 value: 0,
                 latency_ms: latency,
-                jitter_ms: state.command_dispatch_latency.last_jitter_ms.load(Ordering::Relaxed),
-                sample_count: state.command_dispatch_latency
+                jitter_ms: spacecraft.command_dispatch_latency.last_jitter_ms.load(Ordering::Relaxed),
+                sample_count: spacecraft.command_dispatch_latency
                     .number_of_samples.load(Ordering::Relaxed),
             },
             timestamp: dispatch_time,
@@ -162,9 +134,10 @@ fn dispatch_command(
     }).ok();
 }
 
-fn log_scheduling_drift(log_tx: &SyncSender<Log>, command: &Command, now: u64, drift: u64) {
+fn log_scheduling_drift(spacecraft: &SpacecraftState, log_tx: &SyncSender<Log>, command: &Command, now: u64, drift: u64) {
     log_tx.try_send(Log {
         source: LogSource::CommandScheduler,
+        spacecraft_id: Some(spacecraft.id),
         event: Event {
             task_id: command.task_id(),
             event_id: EventID::StartDelay,
@@ -175,25 +148,294 @@ fn log_scheduling_drift(log_tx: &SyncSender<Log>, command: &Command, now: u64, d
 }
 
 fn log_interlock_rejection(
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
-    state: &Arc<GroundState>,
     command: &Command,
     now: u64,
 ) {
-    if let Some(subsystem_id) = command.required_health() {
-        if let Some(sub) = state.find_subsystem(subsystem_id) {
-            let detected_at = sub.fault_detected_at.load(Ordering::Acquire);
-            if detected_at > 0 {
-                log_tx.try_send(Log {
-                    source: LogSource::CommandScheduler,
-                    event: Event {
-                        task_id: TaskID::GlobalSystem,
-                        event_id: EventID::CommandNotFound,
-                        data: EventData::Subsystem { subsystem_id },
-                        timestamp: now,
-                    },
-                }).ok();
+    if let Some(subsystem_id) = command.required_health()
+        && let Some(sub) = spacecraft.find_subsystem(subsystem_id) {
+        let detected_at = sub.fault_detected_at.load(Ordering::Acquire);
+        if detected_at > 0 {
+            log_tx.try_send(Log {
+                source: LogSource::CommandScheduler,
+                spacecraft_id: Some(spacecraft.id),
+                event: Event {
+                    task_id: TaskID::GlobalSystem,
+                    event_id: EventID::CommandNotFound,
+                    data: EventData::Subsystem { subsystem_id },
+                    timestamp: now,
+                },
+            }).ok();
+        }
+    }
+}
+
+// Every uplinked command goes through here so it gets an ID, an entry in the pending-command table and a place in the plan.
+// A time-tagged one no pass has sent by its execute_at is given up on, the satellite would only run it late
+pub fn queue_command(
+    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    source: LogSource,
+    command: Command,
+    priority: Priority,
+    execute_at: u64,
+) -> u32 {
+    let command_id = state.next_command_id.fetch_add(1, Ordering::Relaxed);
+    let now = state.uptime_ms();
+    let expires_at = tagged_expiry(now + state.config.command_expiry_ms, execute_at);
+
+    spacecraft.command_tracking.lock().unwrap().pending.push(PendingCommand {
+        command_id,
+        command,
+        priority,
+        stage: CommandStage::Queued,
+        attempts: 0,
+        stage_changed_at: now,
+        expires_at,
+        execute_at,
+    });
+
+    log_command_stage(spacecraft, log_tx, source, &command, EventData::CommandStage { command_id, stage: CommandStage::Queued, attempts: 0 }, now);
+    hold_command(state, spacecraft, log_tx, source, PlannedCommand { command_id, command, priority, expires_at, execute_at });
+
+    command_id
+}
+
+// Every step of every configured sequence, the satellite stores them in whatever order the passes deliver them
+pub fn upload_sequences(state: &Arc<GroundState>, spacecraft: &SpacecraftState, log_tx: &SyncSender<Log>) {
+    for sequence in &state.config.sequences {
+        let count = sequence.steps.len() as u8;
+
+        for (index, step) in sequence.steps.iter().enumerate() {
+            let command = Command::UploadSequenceStep { sequence_id: sequence.sequence_id, index: index as u8, count, delay_ms: step.delay_ms, step: step.step };
+            queue_command(state, spacecraft, log_tx, LogSource::CommandScheduler, command, Priority::Normal, 0);
+        }
+    }
+}
+
+// A command still Queued at LOS goes back to the plan with the expiry it already had, returns false for anything else
+pub fn carry_over_command(
+    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    command_id: u32,
+) -> bool {
+    let planned = spacecraft.command_tracking.lock().unwrap().pending.iter()
+        .find(|entry| entry.command_id == command_id && entry.stage == CommandStage::Queued)
+        .map(|entry| PlannedCommand {
+            command_id,
+            command: entry.command,
+            priority: entry.priority,
+            expires_at: entry.expires_at,
+            execute_at: entry.execute_at,
+        });
+
+    let Some(planned) = planned else {
+        return false;
+    };
+
+    hold_command(state, spacecraft, log_tx, LogSource::Network, planned);
+    true
+}
+
+// Moves as many held commands into the uplink buffer as the rest of the pass has slots for, returns how many
+pub fn release_planned(
+    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    los_us: u64,
+) -> u32 {
+    let queued = spacecraft.uplink_buffer.len();
+    let fit = (los_us.saturating_sub(wall_clock_us()) / state.config.uplink_slot_ms) as usize;
+    let slots = fit.saturating_sub(queued).min(spacecraft.uplink_buffer.capacity.saturating_sub(queued));
+
+    let released = spacecraft.command_plan.lock().unwrap().release(slots);
+
+    for planned in &released {
+        push_command(state, spacecraft, log_tx, LogSource::Network, planned);
+    }
+
+    released.len() as u32
+}
+
+fn hold_command(
+    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    source: LogSource,
+    planned: PlannedCommand,
+) {
+    let dropped = spacecraft.command_plan.lock().unwrap().hold(planned);
+
+    if let Some(dropped) = dropped {
+        log_command_loss(state, spacecraft, log_tx, source, dropped.command.task_id(), dropped.command_id);
+    }
+}
+
+fn push_command(
+    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    source: LogSource,
+    planned: &PlannedCommand,
+) {
+    let packet = TelemetryPacket {
+        priority: planned.priority,
+        creation_time: state.uptime_ms(),
+        payload: SatelliteMessage::Command {
+            command: planned.command,
+            command_id: planned.command_id,
+            sent_at: state.uptime_ms(),
+            execute_at: planned.execute_at,
+            auth: CommandAuth::UNSIGNED,
+        },
+        sequence_no: SEQUENCE_NOT_CONFIRMED,
+    };
+
+    if let Some(dropped) = spacecraft.uplink_buffer.push(packet)
+        && let SatelliteMessage::Command { command_id, .. } = dropped.payload {
+        log_command_loss(state, spacecraft, log_tx, source, dropped.payload.command_task_id(), command_id);
+    }
+}
+
+fn log_command_loss(
+    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    source: LogSource,
+    task_id: TaskID,
+    command_id: u32,
+) {
+    log_tx.try_send(Log {
+        source,
+        spacecraft_id: Some(spacecraft.id),
+        event: Event {
+            task_id,
+            event_id: EventID::DataLoss,
+            data: EventData::None,
+            timestamp: state.uptime_ms(),
+        },
+    }).ok();
+
+    advance_command(state, spacecraft, log_tx, source, command_id, CommandStage::Failed);
+}
+
+// Moves a pending command to a later stage, returns the command if the transition was applied
+pub fn advance_command(
+    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    source: LogSource,
+    command_id: u32,
+    stage: CommandStage,
+) -> Option<Command> {
+    let now = state.uptime_ms();
+    let mut tracking = spacecraft.command_tracking.lock().unwrap();
+
+    let idx = tracking.pending.iter().position(|p| p.command_id == command_id)?;
+    let entry = &mut tracking.pending[idx];
+
+    if stage <= entry.stage { // Late or duplicated ack
+        return None;
+    }
+
+    entry.stage = stage;
+    entry.stage_changed_at = now;
+    if stage == CommandStage::Uplinked {
+        entry.attempts += 1;
+    }
+
+    let (command, attempts) = (entry.command, entry.attempts);
+
+    if stage.is_terminal() {
+        tracking.pending.swap_remove(idx);
+
+        match stage {
+            CommandStage::Executed => tracking.executed += 1,
+            CommandStage::Failed => tracking.failed += 1,
+            _ => tracking.timed_out += 1,
+        }
+    }
+    drop(tracking);
+
+    log_command_stage(spacecraft, log_tx, source, &command, EventData::CommandStage { command_id, stage, attempts }, now);
+    Some(command)
+}
+
+// Unacknowledged commands are held for another pass, accepted ones that never complete and held ones that expire are given up on
+pub fn check_command_timeouts(
+    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    now: u64,
+) {
+    let mut retries = Vec::new();
+    let mut expired: Vec<u32> = spacecraft.command_plan.lock().unwrap().expire(now).iter().map(|planned| planned.command_id).collect();
+
+    let mut tracking = spacecraft.command_tracking.lock().unwrap();
+    for entry in tracking.pending.iter_mut() {
+        let waited = now.saturating_sub(entry.stage_changed_at);
+
+        match entry.stage {
+            CommandStage::Uplinked if waited > state.config.command_ack_timeout_ms => {
+                if entry.attempts < state.config.command_max_attempts {
+                    entry.stage = CommandStage::Queued;
+                    entry.stage_changed_at = now;
+                    entry.expires_at = tagged_expiry(now + state.config.command_expiry_ms, entry.execute_at);
+                    retries.push((PlannedCommand {
+                        command_id: entry.command_id,
+                        command: entry.command,
+                        priority: entry.priority,
+                        expires_at: entry.expires_at,
+                        execute_at: entry.execute_at,
+                    }, entry.attempts));
+                } else {
+                    expired.push(entry.command_id);
+                }
             }
+            CommandStage::Accepted if now.saturating_sub(entry.stage_changed_at.max(entry.execute_at)) > state.config.command_execution_timeout_ms => {
+                expired.push(entry.command_id);
+            }
+            _ => {}
         }
     }
-}
\ No newline at end of file
+    tracking.retries += retries.len() as u32;
+    drop(tracking);
+
+    for (planned, attempts) in retries {
+        log_command_stage(spacecraft, log_tx, LogSource::CommandScheduler, &planned.command,
+            EventData::CommandStage { command_id: planned.command_id, stage: CommandStage::Queued, attempts }, now);
+        hold_command(state, spacecraft, log_tx, LogSource::CommandScheduler, planned);
+    }
+
+    for command_id in expired {
+        advance_command(state, spacecraft, log_tx, LogSource::CommandScheduler, command_id, CommandStage::TimedOut);
+    }
+}
+
+fn tagged_expiry(expires_at: u64, execute_at: u64) -> u64 {
+    if execute_at > 0 { expires_at.min(execute_at) } else { expires_at }
+}
+
+// Data is always an EventData::CommandStage
+fn log_command_stage(
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    source: LogSource,
+    command: &Command,
+    data: EventData,
+    now: u64,
+) {
+    log_tx.try_send(Log {
+        source,
+        spacecraft_id: Some(spacecraft.id),
+        event: Event {
+            task_id: command.task_id(),
+            event_id: EventID::CommandStageChange,
+            data,
+            timestamp: now,
+        },
+    }).ok();
+}
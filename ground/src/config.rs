@@ -1,37 +1,416 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use crate::types::{Capabilities, Command, GroundSite, OrbitConfig, Priority, SequenceStep, TransportKind, ENCRYPTION_SUPPORT, MAX_SEQUENCE_STEPS};
+
 pub const TICK_RATE: u64 = 1000; // 1ms
 
-pub const NETWORK_PORT: &str = "127.0.0.1:8000";
-pub const NETWORK_READ_TIMEOUT: u64 = 100;
-pub const NETWORK_WRITE_TIMEOUT: u64 = 1 * TICK_RATE;
 pub const SEQUENCE_NOT_CONFIRMED: u32 = 0;
+pub const MAX_SUBSYSTEM: usize = 2;
 
-pub const PACKET_HISTORY_BUFFER_CAPACITY: usize = 1024;
+pub const CONFIG_PATH_ENV: &str = "RTS_GROUND_CONFIG"; // Read when no path is given on the command line
 
-pub const UPLINK_BUFFER_CAPACITY: usize = 64;
+pub const STATION_ID_SHIFT: u32 = 24; // Command IDs carry the station in their top byte
+pub const PASS_PREDICTION_ORBITS: u64 = 16; // About a day, every site sees the ground track come back within it
 
-pub const LOG_BUFFER_CAPACITY: usize = 1024;
+pub const SUPPORTED_CAPABILITIES: Capabilities = Capabilities::SPACE_PACKETS.union(Capabilities::AUTH).union(ENCRYPTION_SUPPORT); // Drop SPACE_PACKETS to fall back to bincode
+pub const REQUIRED_CAPABILITIES: Capabilities = Capabilities::NONE;
 
-pub const VISIBILITY_WINDOW_LIMIT_MS: u64 = 30 * TICK_RATE;
+const MAX_THREAD_PRIORITY: u8 = 99; // ThreadPriority::Crossplatform refuses anything higher
 
-pub const DECODE_DEADLINE_MS: u64 = 3 * TICK_RATE;
-pub const COMMAND_DISPATCH_DEADLINE_MS: u64 = 2 * TICK_RATE;
+// Keys SIGHUP may change while running, the threads read them from GroundState::tunables
+pub const RELOADABLE_KEYS: [&str; 8] = [
+    "decode_deadline_ms",
+    "command_dispatch_deadline_ms",
+    "loss_of_contact_threshold",
+    "fault_alert_threshold_ms",
+    "sync_interval_windows",
+    "sync_calibrated_interval_windows",
+    "log_level",
+    "command_schedule",
+];
 
-pub const LOSS_OF_CONTACT_THRESHOLD: u32 = 3;
-pub const FAULT_ALERT_THRESHOLD_MS: u64 = 100 * TICK_RATE;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum LogLevel {
+    Warning = 0, // Faults, losses and rejections
+    Info = 1, // Plus passes, clock sync and command progress
+    Debug = 2, // Plus every periodic sample
+}
 
-pub const NETWORK_MS: u64 = 2 * TICK_RATE;
-pub const COMMAND_MS: u64 = 5 * TICK_RATE;
-pub const MONITOR_MS: u64 = 5 * TICK_RATE;
-pub const MAIN_MS: u64 = 1000 * TICK_RATE;
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleEntry {
+    pub command: Command,
+    pub priority: Priority,
+    pub interval_ms: u64,
+    #[serde(default)]
+    pub execute_after_ms: u64, // Time-tags each send to run this long after it was queued, 0 runs it on arrival
+}
 
-pub const MONITOR_PRIORITY: u8 = 10;
-pub const NETWORK_PRIORITY: u8 = 5;
-pub const COMMAND_PRIORITY: u8 = 4;
-pub const LOGGING_PRIORITY: u8 = 0;
+// A stored sequence, uploaded to every spacecraft at startup and run with a StartSequence command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SequenceConfig {
+    pub name: String, // Only for operators, the satellite knows it by sequence_id
+    pub sequence_id: u8,
+    pub steps: Vec<SequenceStepConfig>,
+}
 
-pub const MAX_SUBSYSTEM: usize = 2;
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SequenceStepConfig {
+    #[serde(default)]
+    pub delay_ms: u32, // After the previous step, or after the start for the first
+    pub step: SequenceStep,
+}
+
+// Every key with a different value as "key: old -> new", command_schedule as the entries kept, added and removed
+#[derive(Debug, Default)]
+pub struct ConfigDiff {
+    pub changed: Vec<String>, // Reloadable
+    pub changed_keys: Vec<u8>, // Index into RELOADABLE_KEYS of each entry of changed
+    pub rejected: Vec<String>, // Need a restart
+}
+
+// How a reload's command_schedule differs from the running one, an entry is kept only if an identical one was already scheduled
+#[derive(Debug, Default, PartialEq)]
+pub struct ScheduleChanges {
+    pub kept: usize,
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl ScheduleChanges {
+    pub fn between(old: &[ScheduleEntry], new: &[ScheduleEntry]) -> Self {
+        let kept = new.iter().filter(|entry| old.contains(entry)).count();
+        let removed = old.iter().filter(|entry| !new.contains(entry)).count();
+
+        Self { kept, added: new.len() - kept, removed }
+    }
+}
+
+impl fmt::Display for ScheduleChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} kept, {} added, {} removed", self.kept, self.added, self.removed)
+    }
+}
+
+// Tunables loaded at startup, every key left out of the file keeps the value below. All *_ms values are in μs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub network_port: SocketAddr,
+    pub link_transport: TransportKind, // Must match the satellite
+    pub network_read_timeout: u64,
+    pub network_write_timeout: u64,
+    pub init_handshake_limit_ms: u64,
+
+    pub spacecraft_ids: Vec<u16>, // Every satellite of the constellation, each gets its own link and command state
+    pub uplink_key_path: String, // Shared HMAC key, hex on one line
+
+    pub station_id: u8, // Unique across the ground network, must match the satellite's ground_stations entry
+    pub log_path: String,
+    pub archive_path: String, // Mission archive every station appends to, empty for none
+
+    pub packet_history_buffer_capacity: usize,
+
+    // Downlink ARQ
+    pub downlink_retransmit_window: u32, // Satellite packet_history_buffer_capacity
+    pub nack_interval_ms: u64,
+    pub nack_retry_ms: u64, // Retransmissions queue behind up to 10 cycles of downlink
+
+    pub uplink_buffer_capacity: usize,
+    pub log_buffer_capacity: usize,
+
+    // Pass Planning
+    pub command_plan_capacity: usize, // Commands held between passes
+    pub command_expiry_ms: u64, // A command no pass has sent by then is given up on
+    pub uplink_slot_ms: u64, // Link time budgeted per command when deciding how many fit before LOS
+
+    pub decode_deadline_ms: u64,
+    pub command_dispatch_deadline_ms: u64,
+
+    // Command Tracking
+    pub command_ack_timeout_ms: u64, // Acks can wait out the longest gap between passes
+    pub command_execution_timeout_ms: u64, // Satellite stale_packet_limit_ms plus the downlink
+    pub command_max_attempts: u32,
+
+    pub loss_of_contact_threshold: u32,
+    pub fault_alert_threshold_ms: u64, // Satellite fault_recovery_ms
+
+    pub network_ms: u64,
+    pub command_ms: u64,
+    pub monitor_ms: u64,
+    pub main_ms: u64,
+
+    pub monitor_priority: u8,
+    pub network_priority: u8,
+    pub command_priority: u8,
+    pub logging_priority: u8,
+
+    pub sync_interval_windows: u32,
+    pub sync_calibrated_interval_windows: u32,
+
+    pub number_of_cores: u64,
+
+    pub log_level: LogLevel,
+
+    pub site: GroundSite, // Passes are predicted over here, must match this station's entry in the satellite's ground_stations
+    pub command_schedule: Vec<ScheduleEntry>, // Last so TOML can write these as [[command_schedule]], [[orbits]] and [[sequences]] tables
+    pub orbits: Vec<OrbitConfig>, // One per spacecraft_ids entry, in the same order
+    pub sequences: Vec<SequenceConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            network_port: SocketAddr::from(([127, 0, 0, 1], 8000)),
+            link_transport: TransportKind::Tcp,
+            network_read_timeout: 100,
+            network_write_timeout: TICK_RATE,
+            init_handshake_limit_ms: 5 * TICK_RATE,
+
+            spacecraft_ids: vec![1],
+            uplink_key_path: "uplink.key".to_string(),
+
+            station_id: 0,
+            log_path: "gcs_mission.log".to_string(),
+            archive_path: String::new(),
+
+            packet_history_buffer_capacity: 1024,
+
+            downlink_retransmit_window: 1024,
+            nack_interval_ms: 10 * TICK_RATE,
+            nack_retry_ms: 1000 * TICK_RATE,
+
+            uplink_buffer_capacity: 64,
+            log_buffer_capacity: 1024,
+
+            command_plan_capacity: 256,
+            command_expiry_ms: 5_000 * TICK_RATE, // Over half a simulated day at the default time_scale
+            uplink_slot_ms: TICK_RATE / 2,
+
+            decode_deadline_ms: 3 * TICK_RATE,
+            command_dispatch_deadline_ms: 2 * TICK_RATE,
+
+            command_ack_timeout_ms: 10_000 * TICK_RATE,
+            command_execution_timeout_ms: 15_000 * TICK_RATE,
+            command_max_attempts: 3,
+
+            loss_of_contact_threshold: 3,
+            fault_alert_threshold_ms: 10_000 * TICK_RATE,
+
+            network_ms: 2 * TICK_RATE,
+            command_ms: 5 * TICK_RATE,
+            monitor_ms: 5 * TICK_RATE,
+            main_ms: 1000 * TICK_RATE,
+
+            monitor_priority: 10,
+            network_priority: 5,
+            command_priority: 4,
+            logging_priority: 0,
+
+            sync_interval_windows: 1,
+            sync_calibrated_interval_windows: 5,
+
+            number_of_cores: 4,
+
+            log_level: LogLevel::Debug,
+
+            site: GroundSite::default(),
+            command_schedule: vec![
+                ScheduleEntry {
+                    command: Command::RotateAntenna { target_angle: 9000 },
+                    priority: Priority::Normal,
+                    interval_ms: 5 * TICK_RATE,
+                    execute_after_ms: 0,
+                },
+                ScheduleEntry {
+                    command: Command::SetPowerMode { mode: 1 },
+                    priority: Priority::Normal,
+                    interval_ms: 20 * TICK_RATE,
+                    execute_after_ms: 0,
+                },
+            ],
+            orbits: vec![OrbitConfig::default()],
+            sequences: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+
+        let config: Config = toml::from_str(&text)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        config.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(config)
+    }
+
+    pub fn diff(&self, new_config: &Config) -> ConfigDiff {
+        let old = toml::Table::try_from(self).expect("Config always serializes");
+        let new = toml::Table::try_from(new_config).expect("Config always serializes");
+        let mut diff = ConfigDiff::default();
+
+        for (key, old_value) in &old {
+            let new_value = &new[key];
+
+            if old_value == new_value {
+                continue;
+            }
+
+            let change = if key == "command_schedule" { // The whole table on both sides would bury what actually changed
+                format!("{}: {}", key, ScheduleChanges::between(&self.command_schedule, &new_config.command_schedule))
+            } else {
+                format!("{}: {} -> {}", key, old_value, new_value)
+            };
+
+            if let Some(index) = RELOADABLE_KEYS.iter().position(|reloadable| reloadable == key) {
+                diff.changed.push(change);
+                diff.changed_keys.push(index as u8);
+            } else {
+                diff.rejected.push(change);
+            }
+        }
+
+        diff
+    }
+
+    // The old and new value a ConfigChange event carries for RELOADABLE_KEYS[key], command_schedule as entries removed -> entries added
+    pub fn reloadable_change(&self, new: &Config, key: u8) -> (u64, u64) {
+        if RELOADABLE_KEYS[key as usize] == "command_schedule" {
+            let changes = ScheduleChanges::between(&self.command_schedule, &new.command_schedule);
+            return (changes.removed as u64, changes.added as u64);
+        }
+
+        (self.reloadable_values()[key as usize], new.reloadable_values()[key as usize])
+    }
+
+    // In RELOADABLE_KEYS order
+    fn reloadable_values(&self) -> [u64; RELOADABLE_KEYS.len()] {
+        [
+            self.decode_deadline_ms,
+            self.command_dispatch_deadline_ms,
+            self.loss_of_contact_threshold as u64,
+            self.fault_alert_threshold_ms,
+            self.sync_interval_windows as u64,
+            self.sync_calibrated_interval_windows as u64,
+            self.log_level as u64,
+            0, // Compared entry by entry in reloadable_change
+        ]
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("packet_history_buffer_capacity", self.packet_history_buffer_capacity as u64),
+            ("downlink_retransmit_window", self.downlink_retransmit_window as u64),
+            ("uplink_buffer_capacity", self.uplink_buffer_capacity as u64),
+            ("log_buffer_capacity", self.log_buffer_capacity as u64),
+            ("command_plan_capacity", self.command_plan_capacity as u64),
+            ("command_expiry_ms", self.command_expiry_ms),
+            ("uplink_slot_ms", self.uplink_slot_ms),
+            ("command_max_attempts", self.command_max_attempts as u64),
+            ("loss_of_contact_threshold", self.loss_of_contact_threshold as u64),
+            ("network_ms", self.network_ms),
+            ("command_ms", self.command_ms),
+            ("monitor_ms", self.monitor_ms),
+            ("main_ms", self.main_ms),
+            ("sync_interval_windows", self.sync_interval_windows as u64),
+            ("sync_calibrated_interval_windows", self.sync_calibrated_interval_windows as u64),
+            ("number_of_cores", self.number_of_cores),
+        ] {
+            if value == 0 {
+                return Err(format!("{} must be greater than 0", name));
+            }
+        }
+
+        for (name, priority) in [
+            ("monitor_priority", self.monitor_priority),
+            ("network_priority", self.network_priority),
+            ("command_priority", self.command_priority),
+            ("logging_priority", self.logging_priority),
+        ] {
+            if priority > MAX_THREAD_PRIORITY {
+                return Err(format!("{} must be at most {}, got {}", name, MAX_THREAD_PRIORITY, priority));
+            }
+        }
+
+        if self.spacecraft_ids.is_empty() {
+            return Err("spacecraft_ids must name at least one spacecraft".to_string());
+        }
+
+        for (index, id) in self.spacecraft_ids.iter().enumerate() {
+            if self.spacecraft_ids[..index].contains(id) {
+                return Err(format!("spacecraft_ids lists spacecraft {} twice", id));
+            }
+        }
+
+        if self.link_transport == TransportKind::Udp && self.spacecraft_ids.len() > 1 { // A UDP link learns its peer from the first datagram, it cannot share the port
+            return Err(format!("udp links serve one spacecraft, got {} in spacecraft_ids", self.spacecraft_ids.len()));
+        }
+
+        for (index, entry) in self.command_schedule.iter().enumerate() {
+            if entry.interval_ms == 0 {
+                return Err(format!("command_schedule[{}].interval_ms must be greater than 0", index));
+            }
+
+            if let Command::RequestRetransmit { .. } = entry.command {
+                return Err(format!("command_schedule[{}] cannot schedule RequestRetransmit, NACKs replace it", index));
+            }
+
+            if let Command::UploadSequenceStep { .. } = entry.command {
+                return Err(format!("command_schedule[{}] cannot schedule UploadSequenceStep, sequences are uploaded from [[sequences]]", index));
+            }
+
+            if let Err(reason) = entry.command.validate() {
+                return Err(format!("command_schedule[{}] would be rejected onboard: {:?}", index, reason));
+            }
+
+            if entry.priority == Priority::Emergency && !matches!(entry.command, Command::ClearSubsystemFault { .. }) {
+                return Err(format!("command_schedule[{}] cannot use Emergency priority, the satellite keeps it for fault clears", index));
+            }
+        }
+
+        for (index, sequence) in self.sequences.iter().enumerate() {
+            if sequence.steps.is_empty() || sequence.steps.len() > MAX_SEQUENCE_STEPS {
+                return Err(format!("sequences[{}] must hold 1 to {} steps, got {}", index, MAX_SEQUENCE_STEPS, sequence.steps.len()));
+            }
+
+            for (step_index, step) in sequence.steps.iter().enumerate() {
+                if let Some(Err(reason)) = step.step.command().map(|command| command.validate()) {
+                    return Err(format!("sequences[{}].steps[{}] would be rejected onboard: {:?}", index, step_index, reason));
+                }
+            }
+
+            if self.sequences[..index].iter().any(|other| other.sequence_id == sequence.sequence_id || other.name == sequence.name) {
+                return Err(format!("sequences[{}] reuses the sequence_id {} or the name {}", index, sequence.sequence_id, sequence.name));
+            }
+        }
+
+        if !self.archive_path.is_empty() && self.archive_path == self.log_path {
+            return Err(format!("archive_path and log_path are both {}, the station log is truncated at startup", self.log_path));
+        }
+
+        if !(-90.0..=90.0).contains(&self.site.latitude_deg) || !(0.0..90.0).contains(&self.site.min_elevation_deg) {
+            return Err("site needs a latitude_deg in [-90, 90] and a min_elevation_deg in [0, 90)".to_string());
+        }
+
+        if self.orbits.len() != self.spacecraft_ids.len() {
+            return Err(format!("orbits must hold one orbit per spacecraft_ids entry, got {} for {}", self.orbits.len(), self.spacecraft_ids.len()));
+        }
 
-pub const SYNC_INTERVAL_WINDOWS: u32 = 1;
-pub const SYNC_CALIBRATED_INTERVAL_WINDOWS: u32 = 5;
+        for (index, orbit) in self.orbits.iter().enumerate() {
+            orbit.build().map_err(|e| format!("orbits[{}]: {}", index, e))?;
+        }
 
-pub const NUMBER_OF_CORES: u64 = 4;
\ No newline at end of file
+        Ok(())
+    }
+}
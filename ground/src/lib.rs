@@ -0,0 +1,9 @@
+pub mod config;
+pub mod logging;
+pub mod network;
+pub mod monitor;
+pub mod types;
+pub mod state;
+pub mod buffer;
+pub mod command;
+pub mod planner;
@@ -1,14 +1,17 @@
-use crate::config::LOGGING_PRIORITY;
-use crate::types::{Log, LogSource, TaskID, EventID, EventData, SubsystemID, Priority};
+use crate::types::{Log, LogSource, TaskID, EventID, EventData, SubsystemID, Priority, HandshakeReject, AuthReject, RejectReason, Capabilities, Utc};
+use crate::config::{LogLevel, RELOADABLE_KEYS};
+use crate::state::GroundState;
 use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use thread_priority::*;
 use std::fs::OpenOptions;
 use std::io::Write as IoWrite;
 use std::fmt::Write as FmtWrite;
 
-pub fn run_logger(log_rx: Receiver<Log>) {
+pub fn run_logger(log_rx: Receiver<Log>, state: Arc<GroundState>) {
     set_current_thread_priority(ThreadPriority::Crossplatform(
-        LOGGING_PRIORITY.try_into().unwrap()
+        state.config.logging_priority.try_into().unwrap()
     )).unwrap();
 
     let mut format_buffer = String::with_capacity(512);
@@ -16,29 +19,71 @@ pub fn run_logger(log_rx: Receiver<Log>) {
         .create(true)
         .write(true)
         .truncate(true)
-        .open("gcs_mission.log")
-        .expect("Failed to open GCS log file");
+        .open(&state.config.log_path)
+        .unwrap_or_else(|e| panic!("Failed to open GCS log file {}: {}", state.config.log_path, e));
+
+    // Shared with the other stations, so never truncated and each line goes out in a single append
+    let mut archive = (!state.config.archive_path.is_empty()).then(|| OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&state.config.archive_path)
+        .unwrap_or_else(|e| panic!("Failed to open mission archive {}: {}", state.config.archive_path, e)));
 
     while let Ok(log) = log_rx.recv() {
+        if event_level(log.event.event_id) as u8 > state.tunables.log_level.load(Ordering::Relaxed) {
+            continue;
+        }
+
         format_buffer.clear();
 
         let source_str = format_source(&log.source);
         let task_str = format_task(&log.event.task_id);
         let event_str = format_event_id(&log.event.event_id, &log.source);
 
+        let _ = write!(format_buffer, "[GCS] ");
+
+        match log.spacecraft_id {
+            Some(id) => { let _ = write!(format_buffer, "[SC-{:<5}] ", id); }
+            None => { let _ = write!(format_buffer, "[{:<8}] ", "GROUND"); }
+        }
+
         let _ = write!(
             format_buffer,
-            "[GCS] [{:>8}]\tTask: [{:>30}]\tEvent: [{:>35}]\t",
+            "[{:>8}]\tTask: [{:>30}]\tEvent: [{:>35}]\t",
             source_str, task_str, event_str
         );
 
         format_event_data(&mut format_buffer, &log.event.data);
 
-        let _ = write!(format_buffer, "Timestamp: [{} μs uptime]", log.event.timestamp);
+        let _ = write!(format_buffer, "Timestamp: [{} μs uptime]  UTC: [{}]", log.event.timestamp, Utc(state.utc_us(log.event.timestamp)));
 
         if let Err(e) = writeln!(file, "{}", format_buffer) {
             eprintln!("Failed to write to GCS log: {}", e);
         }
+
+        if let Some(archive) = archive.as_mut() {
+            let line = format!("[STATION-{:<3}] {}\n", state.config.station_id, format_buffer);
+
+            if let Err(e) = archive.write_all(line.as_bytes()) {
+                eprintln!("Failed to write to mission archive: {}", e);
+            }
+        }
+    }
+}
+
+// Lowest log_level an event is still written at
+fn event_level(event_id: EventID) -> LogLevel {
+    match event_id {
+        EventID::CommandNotFound | EventID::SubsystemFault | EventID::StartDelay | EventID::CompletionDelay
+        | EventID::TaskFault | EventID::DataCorruption | EventID::DegradedMode | EventID::MissionAbort
+        | EventID::MissedCommunication | EventID::DataLoss | EventID::RetransmitFailed
+        | EventID::AuthenticationFailure | EventID::CommandRejected | EventID::UplinkGap
+        | EventID::SequenceAborted | EventID::ConfigReload | EventID::ConfigChanged | EventID::ParameterChanged => LogLevel::Warning, // A reload that lowers the level still shows up
+
+        EventID::TaskCompletion | EventID::SyncOngoing | EventID::QueuePerformance
+        | EventID::ResourceUtilization | EventID::NetworkPerformance => LogLevel::Debug,
+
+        _ => LogLevel::Info,
     }
 }
 
@@ -58,11 +103,23 @@ fn format_task(task: &TaskID) -> &'static str {
         TaskID::SetPowerMode       => "Set Power Mode",
         TaskID::ClearSubsystemFault => "Clear Subsystem Fault",
         TaskID::RequestRetransmit  => "Request Retransmit",
+        TaskID::ListTimeTags       => "List Time Tags",
+        TaskID::DeleteTimeTag      => "Delete Time Tag",
+        TaskID::UploadSequenceStep => "Upload Sequence Step",
+        TaskID::ListSequences      => "List Sequences",
+        TaskID::StartSequence      => "Start Sequence",
+        TaskID::AbortSequence      => "Abort Sequence",
+        TaskID::DeleteSequence     => "Delete Sequence",
+        TaskID::SetParameter       => "Set Parameter",
+        TaskID::GetParameter       => "Get Parameter",
+        TaskID::DumpParameters     => "Dump Parameters",
         TaskID::ThermalSensor      => "Thermal Sensor",
         TaskID::PitchAndYawSensor  => "Pitch & Yaw Sensor",
         TaskID::MoistureSensor     => "Moisture Sensor",
         TaskID::GlobalSystem       => "Global System",
         TaskID::NetworkService     => "Network Service",
+        TaskID::DownlinkNetworkService => "Downlink Network Service",
+        TaskID::UplinkNetworkService => "Uplink Network Service",
         TaskID::None               => "-",
     }
 }
@@ -94,7 +151,33 @@ fn format_event_id(event_id: &EventID, source: &LogSource) -> &'static str {
         EventID::ConnectionEnd      => "Connection End",
         EventID::QueuePerformance   => if is_external { "Satellite: Queue Performance" }    else { "GCS Queue Performance" },
         EventID::ResourceUtilization => if is_external { "Satellite: CPU Utilization" }     else { "GCS CPU Utilization" },
-        EventID::NetworkPerformance => "Network Performance"
+        EventID::NetworkPerformance => "Network Performance",
+        EventID::AuthenticationFailure => if is_external { "Satellite: Command Auth Failed" } else { "Command Auth Failed" },
+        EventID::CommandAccepted    => "Satellite: Command Accepted",
+        EventID::CommandRejected    => if is_external { "Satellite: Command Rejected" }     else { "Command Rejected" },
+        EventID::CommandStageChange => "Command Stage",
+        EventID::UplinkGap          => "Satellite: Uplink Gap",
+        EventID::DownlinkUsage      => "Satellite: Downlink Usage",
+        EventID::ConfigReload       => if is_external { "Satellite: Config Reloaded" }      else { "Config Reloaded" },
+        EventID::Handover           => "Satellite: Station Handover",
+        EventID::PassPredicted      => "Pass Predicted",
+        EventID::PassPlanned        => "Pass Planned",
+        EventID::UplinkCarriedOver  => "Uplink Carried Over",
+        EventID::TimeTagQueued      => "Satellite: Time Tag Queued",
+        EventID::TimeTagListed      => "Satellite: Time Tag Listed",
+        EventID::TimeTagDeleted     => "Satellite: Time Tag Deleted",
+        EventID::TimeTagExecuted    => "Satellite: Time Tag Executed",
+        EventID::TimeCorrelation    => "Time Correlation",
+        EventID::SequenceStored     => "Satellite: Sequence Step Stored",
+        EventID::SequenceListed     => "Satellite: Sequence Listed",
+        EventID::SequenceStarted    => "Satellite: Sequence Started",
+        EventID::SequenceStepRun    => "Satellite: Sequence Step Run",
+        EventID::SequenceCompleted  => "Satellite: Sequence Completed",
+        EventID::SequenceAborted    => "Satellite: Sequence Aborted",
+        EventID::SequenceDeleted    => "Satellite: Sequence Deleted",
+        EventID::ParameterChanged   => "Satellite: Parameter Changed",
+        EventID::ParameterValue     => "Satellite: Parameter Value",
+        EventID::ConfigChanged      => "Config Changed",
     }
 }
 
@@ -136,7 +219,7 @@ fn format_event_data(buf: &mut String, data: &EventData) {
 
         EventData::SystemStats { active_ms, inactive_ms } => {
             let total = active_ms + inactive_ms;
-            let pct = if total > 0 { (active_ms * 100) / total } else { 0 };
+            let pct = (active_ms * 100).checked_div(total).unwrap_or(0);
             let _ = write!(buf,
                 "Active: {}μs  Idle: {}μs  Utilization: {}%\t",
                 active_ms, inactive_ms, pct
@@ -165,5 +248,129 @@ fn format_event_data(buf: &mut String, data: &EventData) {
 
             let _ = write!(buf, "NETWORK PERFORMANCE: Priority: {} Latency: {}μs Jitter: {}μs Sample Count: {}\t", priority_string, latency_ms, jitter_ms, sample_count);
         }
+
+        EventData::Handshake { version, spacecraft_id, capabilities } => {
+            let _ = write!(buf,
+                "Protocol: v{}  Spacecraft: {}  Capabilities: {:#06x}  Link: {}\t",
+                version, spacecraft_id, capabilities.0,
+                if capabilities.contains(Capabilities::ENCRYPTION) { "Encrypted" } else { "Clear" }
+            );
+        }
+
+        EventData::HandshakeRejected { reason, peer_version, peer_spacecraft_id } => {
+            let _ = write!(buf,
+                "Link Refused: {}  Peer Protocol: v{}  Peer Spacecraft: {}\t",
+                format_handshake_reject(reason), peer_version, peer_spacecraft_id
+            );
+        }
+
+        EventData::FrameIntegrity { crc_failures, resyncs, decode_failures } => {
+            let _ = write!(buf,
+                "CRC Failures: {}  Resyncs: {}  Decode Failures: {}\t",
+                crc_failures, resyncs, decode_failures
+            );
+        }
+
+        EventData::AuthFailure { reason, counter, last_counter } => {
+            let _ = write!(buf,
+                "Reason: {}  Counter: {}  Last Accepted: {}\t",
+                match reason {
+                    AuthReject::BadTag   => "Bad HMAC Tag",
+                    AuthReject::Replayed => "Replayed Counter",
+                },
+                counter, last_counter
+            );
+        }
+        EventData::CommandAck { command_id } => {
+            let _ = write!(buf, "Command ID: {}\t", command_id);
+        }
+        EventData::CommandRejected { command_id, reason } => {
+            let _ = write!(buf, "Command ID: {}  Reason: {}\t", command_id, format_reject_reason(reason));
+        }
+        EventData::CommandStage { command_id, stage, attempts } => {
+            let _ = write!(buf, "Command ID: {}  Stage: {:?}  Attempts: {}\t", command_id, stage, attempts);
+        }
+        EventData::SequenceGap { first, count } => {
+            let _ = write!(buf, "Missing Uplink: {}..{}  Count: {}\t", first, first.saturating_add(*count), count);
+        }
+        EventData::RetransmitUnavailable { base, bitmap } => {
+            let _ = write!(buf, "NACK Base: {}  Unavailable: {}\t", base, bitmap.count_ones());
+        }
+        EventData::ConfigReload { changed, rejected } => {
+            let _ = write!(buf, "Changed: {}  Rejected: {}\t", changed, rejected);
+        }
+        EventData::ConfigChange { key, old_value, new_value } => {
+            let _ = write!(buf, "Key: {}  {} -> {}\t", RELOADABLE_KEYS.get(*key as usize).unwrap_or(&"-"), old_value, new_value);
+        }
+        EventData::DownlinkUsage { bytes_sent, budget_bytes, queued } => {
+            let _ = write!(buf, "Downlink Bytes: {}  Budget: {}  Utilization: {:.2}%  Left Queued: {}\t",
+                bytes_sent, budget_bytes, *bytes_sent as f64 / (*budget_bytes).max(1) as f64 * 100.0, queued);
+        }
+        EventData::Handover { from_station, to_station } => {
+            let _ = write!(buf, "From Station: {}  To Station: {}\t", from_station, to_station);
+        }
+        EventData::PassPrediction { aos_ms, los_ms, max_elevation } => {
+            let _ = write!(buf, "AOS: {}μs  LOS: {}μs  Duration: {}μs  Max Elevation: {:.2}°\t",
+                aos_ms, los_ms, los_ms.saturating_sub(*aos_ms), *max_elevation as f32 / 100.0);
+        }
+        EventData::UplinkPlan { planned, held } => {
+            let _ = write!(buf, "Planned: {}  Held: {}\t", planned, held);
+        }
+        EventData::SyncSample { offset_us, rtt_us } => {
+            let _ = write!(buf, "Offset: {}μs  RTT: {}μs\t", offset_us, rtt_us);
+        }
+        EventData::ClockSync { offset_us, drift_ppb, error_us, quality } => {
+            let _ = write!(buf, "Offset: {}μs  Drift: {}ppb  Error: ±{}μs  Quality: {:?}\t", offset_us, drift_ppb, error_us, quality);
+        }
+        EventData::TimeTag { command_id, execute_at, queued } => {
+            let _ = write!(buf, "Command ID: {}  Execute At: {}μs  Queued: {}\t", command_id, execute_at, queued);
+        }
+        EventData::TimeCorrelation { reference_us, utc_us, drift_ppb, error_us, quality } => {
+            let _ = write!(buf, "Uptime {}μs Is {}  Drift: {}ppb  Error: ±{}μs  Quality: {:?}\t", reference_us, Utc(*utc_us), drift_ppb, error_us, quality);
+        }
+        EventData::TimeTagExecution { command_id, tagged_at, started_at } => {
+            let _ = write!(buf, "Command ID: {}  Tagged At: {}μs  Started At: {}μs  Late By: {}μs\t",
+                command_id, tagged_at, started_at, *started_at as i64 - *tagged_at as i64);
+        }
+        EventData::Sequence { sequence_id, step, steps } => {
+            let _ = write!(buf, "Sequence: {}  Step: {}/{}\t", sequence_id, step, steps);
+        }
+        EventData::SequenceAborted { sequence_id, step, reason } => {
+            let _ = write!(buf, "Sequence: {}  Before Step: {}  Reason: {:?}\t", sequence_id, step, reason);
+        }
+        EventData::Parameter { parameter, value, version } => {
+            let _ = write!(buf, "Parameter: {:?}  Value: {}{}  Table Version: {}\t", parameter, value, parameter.limits().kind.unit(), version);
+        }
+        EventData::ParameterChange { parameter, old_value, new_value, version } => {
+            let unit = parameter.limits().kind.unit();
+            let _ = write!(buf, "Parameter: {:?}  {}{} -> {}{}  Table Version: {}\t", parameter, old_value, unit, new_value, unit, version);
+        }
     }
-}
\ No newline at end of file
+}
+
+fn format_reject_reason(reason: &RejectReason) -> &'static str {
+    match reason {
+        RejectReason::Interlock          => "Subsystem Interlocked",
+        RejectReason::OutOfRange         => "Parameter Out Of Range",
+        RejectReason::WrongMode          => "Wrong Mode",
+        RejectReason::Unauthorized       => "Unauthorized",
+        RejectReason::Stale              => "Stale Onboard",
+        RejectReason::Full               => "No Room Onboard",
+        RejectReason::NotFound           => "Not Found Onboard",
+        RejectReason::Unsynced           => "Clock Unsynced",
+        RejectReason::Cancelled          => "Cancelled Onboard",
+        RejectReason::PriorityNotAllowed => "Priority Not Allowed",
+        RejectReason::Conflicts          => "Conflicts With Another Parameter",
+    }
+}
+
+fn format_handshake_reject(reason: &HandshakeReject) -> &'static str {
+    match reason {
+        HandshakeReject::VersionMismatch     => "Protocol Version Mismatch",
+        HandshakeReject::SpacecraftMismatch  => "Unknown Spacecraft",
+        HandshakeReject::MissingCapabilities => "Missing Required Capabilities",
+        HandshakeReject::Timeout             => "Handshake Timeout",
+        HandshakeReject::Malformed           => "Malformed Handshake",
+        HandshakeReject::AlreadyConnected    => "Spacecraft Already Connected",
+    }
+}
@@ -1,53 +1,76 @@
-mod config;
-mod logging;
-mod network;
-mod monitor;
-mod types;
-mod state;
-mod buffer;
-mod command;
-
 use std::sync::{Arc, mpsc};
-use std::sync::atomic::Ordering;
+use std::sync::mpsc::SyncSender;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use std::thread;
 
-use crate::types::*;
-use crate::state::GroundState;
-use crate::buffer::BoundedBuffer;
-use crate::logging::run_logger;
-use crate::network::run_network_thread;
-use crate::monitor::run_fault_monitor;
-use crate::command::run_command_scheduler;
-use crate::config::{UPLINK_BUFFER_CAPACITY, LOG_BUFFER_CAPACITY, MAIN_MS};
+use rts_ground::types::*;
+use rts_ground::state::{GroundState, SpacecraftState};
+use rts_ground::logging::run_logger;
+use rts_ground::network::{run_network_thread, report_next_pass, log_time_correlation, LinkSessions};
+use rts_ground::monitor::run_fault_monitor;
+use rts_ground::command::{run_command_scheduler, upload_sequences};
+use rts_ground::config::{Config, CONFIG_PATH_ENV};
 
 fn main() {
-    let state = Arc::new(GroundState::new());
-    let uplink_buffer = Arc::new(BoundedBuffer::new(UPLINK_BUFFER_CAPACITY));
-    let (log_tx, log_rx) = mpsc::sync_channel::<Log>(LOG_BUFFER_CAPACITY);
+    // Command line first, then the environment, otherwise every default
+    let config_path = std::env::args().nth(1).or_else(|| std::env::var(CONFIG_PATH_ENV).ok());
+    let mut config = match &config_path {
+        Some(config_path) => Config::load(config_path)
+            .unwrap_or_else(|e| panic!("GCS failed to load config from {}: {}", config_path, e)),
+        None => Config::default(),
+    };
+
+    println!("GROUND CONFIG: [{:?}]", config);
+
+    let uplink_key = AuthKey::load(&config.uplink_key_path)
+        .unwrap_or_else(|e| panic!("GCS failed to load uplink key from {}: {}", config.uplink_key_path, e));
+
+    let state = Arc::new(GroundState::new(config.clone()));
+    let (log_tx, log_rx) = mpsc::sync_channel::<Log>(state.config.log_buffer_capacity);
     let now = state.uptime_ms();
 
+    let l_state = Arc::clone(&state);
     let logger_handle = thread::spawn(move || {
-        run_logger(log_rx);
+        run_logger(log_rx, l_state);
     });
 
     let m_state = Arc::clone(&state);
-    let m_buffer = Arc::clone(&uplink_buffer);
     let m_log = log_tx.clone();
-    thread::spawn(move || { run_fault_monitor(m_state, m_buffer, m_log); });
+    thread::spawn(move || { run_fault_monitor(m_state, m_log); });
 
     let c_state = Arc::clone(&state);
-    let c_buffer = Arc::clone(&uplink_buffer);
     let c_log = log_tx.clone();
-    thread::spawn(move || { run_command_scheduler(c_state, c_buffer, c_log); });
-
-    let n_state = Arc::clone(&state);
-    let n_buffer = Arc::clone(&uplink_buffer);
-    let n_log = log_tx.clone();
-    thread::spawn(move || { run_network_thread(n_state, n_buffer, n_log); });
+    thread::spawn(move || { run_command_scheduler(c_state, c_log); });
+
+    let sessions = Arc::new(LinkSessions::new(&state, uplink_key));
+    let listen_addr = state.config.network_port.to_string();
+
+    match state.config.link_transport {
+        TransportKind::Tcp => {
+            let listener = TcpTransport::listen(&listen_addr, Duration::from_micros(state.config.network_write_timeout))
+                .expect("GCS failed to bind TCP listener");
+
+            for _ in &state.spacecraft { // One accepting thread per spacecraft, so the whole constellation can be in a pass at once
+                let transport = listener.try_clone().expect("GCS failed to share TCP listener");
+                let n_state = Arc::clone(&state);
+                let n_log = log_tx.clone();
+                let n_sessions = Arc::clone(&sessions);
+                thread::spawn(move || { run_network_thread(n_state, n_log, n_sessions, transport); });
+            }
+        }
+        TransportKind::Udp => {
+            let transport = UdpTransport::listen(&listen_addr)
+                .expect("GCS failed to bind UDP socket");
+            let n_state = Arc::clone(&state);
+            let n_log = log_tx.clone();
+            thread::spawn(move || { run_network_thread(n_state, n_log, sessions, transport); });
+        }
+    }
 
     log_tx.send(Log {
         source: LogSource::Main,
+        spacecraft_id: None,
         event: Event {
             task_id: TaskID::GlobalSystem,
             event_id: EventID::Startup,
@@ -56,6 +79,14 @@ fn main() {
         },
     }).ok();
 
+    // Our own uptime, which every ground timestamp is on, satellite ones included once they are synchronized
+    log_time_correlation(&log_tx, None, &TimeCorrelation { utc_us: state.boot_utc_us, quality: SyncQuality::Locked, ..TimeCorrelation::NONE }, 0);
+
+    for spacecraft in &state.spacecraft {
+        report_next_pass(&state, spacecraft, &log_tx);
+        upload_sequences(&state, spacecraft, &log_tx);
+    }
+
     state.cpu_active_ms.fetch_add(state.uptime_ms() - now, Ordering::SeqCst);
 
     let h_state = Arc::clone(&state);
@@ -66,12 +97,21 @@ fn main() {
     })
     .expect("Error setting Ctrl+C handler");
 
+    let reload_requested = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, Arc::clone(&reload_requested))
+        .expect("Error setting SIGHUP handler");
+
     while state.is_running.load(Ordering::SeqCst) {
-        thread::sleep(Duration::from_micros(MAIN_MS));
+        thread::sleep(Duration::from_micros(state.config.main_ms));
+
+        if reload_requested.swap(false, Ordering::SeqCst) {
+            reload_config(&state, &log_tx, config_path.as_deref(), &mut config);
+        }
     }
 
     log_tx.send(Log {
         source: LogSource::Main,
+        spacecraft_id: None,
         event: Event {
             task_id: TaskID::GlobalSystem,
             event_id: EventID::Shutdown,
@@ -87,23 +127,109 @@ fn main() {
     logger_handle.join().unwrap();
 }
 
+// SIGHUP, the reloadable keys take effect at once and any other change refuses the whole file
+fn reload_config(state: &GroundState, log_tx: &SyncSender<Log>, config_path: Option<&str>, current: &mut Config) {
+    let Some(config_path) = config_path else {
+        println!("CONFIG RELOAD REJECTED: [Started on defaults, there is no config file to re-read]");
+        return;
+    };
+
+    let new = match Config::load(config_path) {
+        Ok(new) => new,
+        Err(e) => {
+            println!("CONFIG RELOAD REJECTED: [GCS failed to load config from {}: {}]", config_path, e);
+            return;
+        }
+    };
+
+    let diff = current.diff(&new);
+
+    let changed = if diff.rejected.is_empty() {
+        let changes: Vec<(u8, (u64, u64))> = diff.changed_keys.iter().map(|&key| (key, current.reloadable_change(&new, key))).collect();
+
+        state.tunables.apply(&new);
+        if new.command_schedule != current.command_schedule {
+            for spacecraft in &state.spacecraft {
+                spacecraft.replace_schedule(&new.command_schedule);
+            }
+        }
+        *current = new;
+        println!("CONFIG RELOAD: [{}]", diff.changed.join(", "));
+
+        for (key, (old_value, new_value)) in changes {
+            log_tx.send(Log {
+                source: LogSource::Main,
+                spacecraft_id: None,
+                event: Event {
+                    task_id: TaskID::GlobalSystem,
+                    event_id: EventID::ConfigChanged,
+                    data: EventData::ConfigChange { key, old_value, new_value },
+                    timestamp: state.uptime_ms(),
+                },
+            }).ok();
+        }
+
+        diff.changed.len()
+    } else {
+        println!("CONFIG RELOAD REJECTED: [Restart needed for {}]", diff.rejected.join(", "));
+        0
+    };
+
+    log_tx.send(Log {
+        source: LogSource::Main,
+        spacecraft_id: None,
+        event: Event {
+            task_id: TaskID::GlobalSystem,
+            event_id: EventID::ConfigReload,
+            data: EventData::ConfigReload { changed: changed as u16, rejected: diff.rejected.len() as u16 },
+            timestamp: state.uptime_ms(),
+        },
+    }).ok();
+}
 
 pub fn display_summary(state: &GroundState) {
     println!("--------------------------------------SUMMARY--------------------------------------");
 
-    println!("GENERAL METRICS: [ACTIVE_MS: {}, CPU_UTILIZATION: {:.2}%]", 
+    println!("GENERAL METRICS: [STATION: {}, ACTIVE_MS: {}, CPU_UTILIZATION: {:.2}%]", 
+                state.config.station_id,
                 state.cpu_active_ms.load(Ordering::Relaxed), 
                 state.cpu_active_ms.load(Ordering::Relaxed) as f64 / state.uptime_ms() as f64 * 100.0);
 
-    println!();
-
-    println!("COMMAND METRICS: [{:?}, Average Jitter: {}, Average Latency: {}]", state.command_dispatch_latency,
-        state.command_dispatch_latency.get_average_jitter(), 
-        state.command_dispatch_latency.get_average_latency());
-
-    println!();
+    for spacecraft in &state.spacecraft {
+        println!();
+        display_spacecraft_summary(spacecraft);
+    }
+}
 
-    println!("NETWORK METRICS: [{:?}, Average Jitter: {}, Average Latency: {}]", state.telemetry_reception_latency, 
-        state.telemetry_reception_latency.get_average_jitter(), 
-        state.telemetry_reception_latency.get_average_latency());
+fn display_spacecraft_summary(spacecraft: &SpacecraftState) {
+    let tag = format!("SC-{}", spacecraft.id);
+
+    println!("[{}] COMMAND METRICS: [{:?}, Average Jitter: {}, Average Latency: {}]", tag, spacecraft.command_dispatch_latency,
+        spacecraft.command_dispatch_latency.get_average_jitter(), 
+        spacecraft.command_dispatch_latency.get_average_latency());
+
+    println!("[{}] NETWORK METRICS: [{:?}, Average Jitter: {}, Average Latency: {}]", tag, spacecraft.telemetry_reception_latency, 
+        spacecraft.telemetry_reception_latency.get_average_jitter(), 
+        spacecraft.telemetry_reception_latency.get_average_latency());
+
+    println!("[{}] LINK INTEGRITY METRICS: [CRC Failures: {}, Resyncs: {}, Decode Failures: {}]", tag,
+        spacecraft.link.crc_failures.load(Ordering::Relaxed),
+        spacecraft.link.resyncs.load(Ordering::Relaxed),
+        spacecraft.link.decode_failures.load(Ordering::Relaxed));
+
+    println!("[{}] DOWNLINK ARQ METRICS: [NACKs Sent: {}, Recovered: {}, Lost: {}, Outstanding: {}]", tag,
+        spacecraft.link.nacks_sent.load(Ordering::Relaxed),
+        spacecraft.link.downlink_recovered.load(Ordering::Relaxed),
+        spacecraft.link.downlink_lost.load(Ordering::Relaxed),
+        spacecraft.link.downlink_outstanding.load(Ordering::Relaxed));
+
+    println!("[{}] UPLINK RECOVERY METRICS: [Frames Sent: {}, Replayed: {}, No Longer In History: {}]", tag,
+        spacecraft.link.uplink_sequence_no.load(Ordering::Relaxed) - 1,
+        spacecraft.link.uplink_replays.load(Ordering::Relaxed),
+        spacecraft.link.uplink_replay_misses.load(Ordering::Relaxed));
+
+    let tracking = spacecraft.command_tracking.lock().unwrap();
+    println!("[{}] COMMAND TRACKING: [Executed: {}, Failed: {}, Timed Out: {}, Retries: {}, Pending: {}, Held For A Pass: {}]", tag,
+        tracking.executed, tracking.failed, tracking.timed_out, tracking.retries, tracking.pending.len(),
+        spacecraft.command_plan.lock().unwrap().len());
 }
\ No newline at end of file
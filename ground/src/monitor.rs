@@ -5,44 +5,42 @@ use std::time::Duration;
 use std::thread;
 use thread_priority::*;
 
-use crate::config::{
-    MONITOR_MS, MONITOR_PRIORITY, LOSS_OF_CONTACT_THRESHOLD,
-    FAULT_ALERT_THRESHOLD_MS, NUMBER_OF_CORES,
-};
-use crate::state::GroundState;
-use crate::buffer::BoundedBuffer;
+use crate::state::{GroundState, SpacecraftState};
 use crate::types::*;
 
 pub fn run_fault_monitor(
     state: Arc<GroundState>,
-    uplink_buffer: Arc<BoundedBuffer>,
     log_tx: SyncSender<Log>,
 ) {
     set_current_thread_priority(ThreadPriority::Crossplatform(
-        MONITOR_PRIORITY.try_into().unwrap()
+        state.config.monitor_priority.try_into().unwrap()
     )).unwrap();
 
     while state.is_running.load(Ordering::SeqCst) {
         let now = state.uptime_ms();
 
-        check_loss_of_contact(&state, &log_tx, now);
-        check_subsystem_interlock_alerts(&state, &log_tx, now);
-        report_buffer_fill_rate(&state, &uplink_buffer);
+        for spacecraft in &state.spacecraft {
+            check_loss_of_contact(&state, spacecraft, &log_tx, now);
+            check_subsystem_interlock_alerts(&state, spacecraft, &log_tx, now);
+            report_buffer_fill_rate(spacecraft);
+            report_queue_performance(spacecraft, &log_tx, now);
+        }
+
         report_cpu_utilization(&state, &log_tx, now);
-        report_queue_performance(&state,  &log_tx, now);
 
-        thread::sleep(Duration::from_micros(MONITOR_MS));
+        thread::sleep(Duration::from_micros(state.config.monitor_ms));
     }
 }
 
-fn check_loss_of_contact(state: &Arc<GroundState>, log_tx: &SyncSender<Log>, now: u64) {
-    let consecutive = state.link.consecutive_missing.load(Ordering::Acquire);
-    if consecutive < LOSS_OF_CONTACT_THRESHOLD {
+fn check_loss_of_contact(state: &Arc<GroundState>, spacecraft: &SpacecraftState, log_tx: &SyncSender<Log>, now: u64) {
+    let consecutive = spacecraft.link.consecutive_missing.load(Ordering::Acquire);
+    if consecutive < state.tunables.loss_of_contact_threshold.load(Ordering::Acquire) {
         return;
     }
     
     log_tx.try_send(Log {
         source: LogSource::HealthMonitor,
+        spacecraft_id: Some(spacecraft.id),
         event: Event {
             task_id: TaskID::NetworkService,
             event_id: EventID::MissedCommunication,
@@ -52,8 +50,8 @@ fn check_loss_of_contact(state: &Arc<GroundState>, log_tx: &SyncSender<Log>, now
     }).ok();
 }
 
-fn check_subsystem_interlock_alerts(state: &Arc<GroundState>, log_tx: &SyncSender<Log>, now: u64) {
-    for subsystem in &state.subsystem_health {
+fn check_subsystem_interlock_alerts(state: &Arc<GroundState>, spacecraft: &SpacecraftState, log_tx: &SyncSender<Log>, now: u64) {
+    for subsystem in &spacecraft.subsystem_health {
         if !subsystem.interlock.load(Ordering::Acquire) {
             continue;
         }
@@ -62,12 +60,13 @@ fn check_subsystem_interlock_alerts(state: &Arc<GroundState>, log_tx: &SyncSende
             continue;
         }
         let fault_duration = now.saturating_sub(detected_at);
-        if fault_duration > FAULT_ALERT_THRESHOLD_MS
+        if fault_duration > state.tunables.fault_alert_threshold_ms.load(Ordering::Acquire)
             && !subsystem.alert_sent.load(Ordering::Acquire)
         {
             subsystem.alert_sent.store(true, Ordering::Release);
             log_tx.try_send(Log {
                 source: LogSource::HealthMonitor,
+                spacecraft_id: Some(spacecraft.id),
                 event: Event {
                     task_id: TaskID::GlobalSystem,
                     event_id: EventID::MissionAbort,
@@ -79,19 +78,21 @@ fn check_subsystem_interlock_alerts(state: &Arc<GroundState>, log_tx: &SyncSende
     }
 }
 
-fn report_buffer_fill_rate(state: &Arc<GroundState>, uplink_buffer: &Arc<BoundedBuffer>) {
+fn report_buffer_fill_rate(spacecraft: &SpacecraftState) {
+    let uplink_buffer = &spacecraft.uplink_buffer;
     let fill_rate = ((uplink_buffer.len() as f32 / uplink_buffer.capacity as f32) * 100.0) as u32;
-    state.buffer_fill_rate.store(fill_rate, Ordering::Relaxed);
+    spacecraft.buffer_fill_rate.store(fill_rate, Ordering::Relaxed);
 }
 
 fn report_cpu_utilization(state: &Arc<GroundState>, log_tx: &SyncSender<Log>, now: u64) {
     let current_uptime = state.uptime_ms();
     let total_active = state.cpu_active_ms.fetch_add(current_uptime - now, Ordering::SeqCst) as f32;
-    let total_possible = (current_uptime * NUMBER_OF_CORES).max(1) as f32;
+    let total_possible = (current_uptime * state.config.number_of_cores).max(1) as f32;
     let cpu_active_ms = (current_uptime as f32 * (total_active / total_possible)) as u64;
 
     log_tx.try_send(Log {
         source: LogSource::HealthMonitor,
+        spacecraft_id: None,
         event: Event {
             task_id: TaskID::GlobalSystem,
             event_id: EventID::ResourceUtilization,
@@ -105,11 +106,11 @@ fn report_cpu_utilization(state: &Arc<GroundState>, log_tx: &SyncSender<Log>, no
 }
 
 fn report_queue_performance(
-    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
     now: u64,
 ) {
-    let metrics = &state.command_dispatch_latency;
+    let metrics = &spacecraft.command_dispatch_latency;
     let sample_count = metrics.number_of_samples.load(Ordering::Relaxed);
     if sample_count == 0 {
         return;
@@ -117,13 +118,14 @@ fn report_queue_performance(
 
     log_tx.try_send(Log {
         source: LogSource::HealthMonitor,
+        spacecraft_id: Some(spacecraft.id),
         event: Event {
             task_id: TaskID::NetworkService,
             event_id: EventID::QueuePerformance,
             data: EventData::QueuePerformance {
                 latency_ms: metrics.last_latency_ms.load(Ordering::Relaxed),
                 jitter_ms: metrics.last_jitter_ms.load(Ordering::Relaxed),
-                buffer_fill_rate: state.buffer_fill_rate.load(Ordering::Relaxed),
+                buffer_fill_rate: spacecraft.buffer_fill_rate.load(Ordering::Relaxed),
                 sample_count,
             },
             timestamp: now,
@@ -1,106 +1,255 @@
 use std::sync::mpsc::SyncSender;
 use std::time::Duration;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::sync::atomic::Ordering;
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
 use std::thread;
-use bincode;
 use thread_priority::*;
 
-use crate::config::{
-    NETWORK_PORT, NETWORK_PRIORITY, NETWORK_READ_TIMEOUT, NETWORK_WRITE_TIMEOUT,
-    NETWORK_MS, VISIBILITY_WINDOW_LIMIT_MS, SEQUENCE_NOT_CONFIRMED,
-    PACKET_HISTORY_BUFFER_CAPACITY, SYNC_INTERVAL_WINDOWS, SYNC_CALIBRATED_INTERVAL_WINDOWS,
-    DECODE_DEADLINE_MS, COMMAND_DISPATCH_DEADLINE_MS,
-};
-use crate::state::GroundState;
-use crate::buffer::BoundedBuffer;
+use crate::config::{SEQUENCE_NOT_CONFIRMED, SUPPORTED_CAPABILITIES, REQUIRED_CAPABILITIES, PASS_PREDICTION_ORBITS};
+use crate::state::{GroundState, SpacecraftState};
+use crate::command::{queue_command, advance_command, release_planned, carry_over_command};
 use crate::types::*;
 
-type PacketHistory = [Option<TelemetryPacket>; PACKET_HISTORY_BUFFER_CAPACITY];
+// Ring of the most recently uplinked packets, slotted by uplink sequence number
+struct PacketHistory {
+    packets: Vec<Option<TelemetryPacket>>, // Sized once at startup, never grows
+}
+
+impl PacketHistory {
+    fn new(capacity: usize) -> Self {
+        Self { packets: vec![None; capacity] }
+    }
+
+    fn record(&mut self, packet: TelemetryPacket) {
+        let slot = packet.sequence_no as usize % self.packets.len();
+        self.packets[slot] = Some(packet);
+    }
+
+    fn get(&self, sequence_no: u32) -> Option<TelemetryPacket> {
+        self.packets[sequence_no as usize % self.packets.len()]
+            .filter(|packet| packet.sequence_no == sequence_no)
+    }
+}
+
+// Link state carried from one pass to the next
+struct LinkSession {
+    codec: PacketCodec,
+    cipher: LinkCipher, // Replaced at every handshake
+    signer: CommandSigner,
+    history: PacketHistory,
+    arq: SelectiveRepeat, // Downlink gaps outlive the pass they were found in
+}
+
+// One session per spacecraft, in GroundState::spacecraft order. Whichever network thread takes the pass holds its lock
+pub struct LinkSessions {
+    sessions: Vec<Mutex<LinkSession>>,
+    uplink_key: AuthKey,
+}
+
+impl LinkSessions {
+    pub fn new(state: &GroundState, uplink_key: AuthKey) -> Self {
+        let sessions = state.spacecraft.iter().map(|_| Mutex::new(LinkSession {
+            codec: PacketCodec::new(WireFormat::Bincode),
+            cipher: LinkCipher::clear(),
+            signer: CommandSigner::new(uplink_key.clone(), wall_clock_counter()),
+            history: PacketHistory::new(state.config.packet_history_buffer_capacity),
+            arq: SelectiveRepeat::new(state.config.downlink_retransmit_window),
+        })).collect();
+
+        Self { sessions, uplink_key }
+    }
+
+    fn claim(&self, state: &GroundState, spacecraft_id: u16) -> Result<(usize, MutexGuard<'_, LinkSession>), HandshakeReject> {
+        let index = state.spacecraft.iter()
+            .position(|spacecraft| spacecraft.id == spacecraft_id)
+            .ok_or(HandshakeReject::SpacecraftMismatch)?;
 
-pub fn run_network_thread(
+        let session = self.sessions[index].try_lock().map_err(|_| HandshakeReject::AlreadyConnected)?;
+
+        Ok((index, session))
+    }
+}
+
+// Several of these can share one listener, each takes whichever satellite calls next
+pub fn run_network_thread<T: LinkTransport>(
     state: Arc<GroundState>,
-    uplink_buffer: Arc<BoundedBuffer>,
     log_tx: SyncSender<Log>,
+    sessions: Arc<LinkSessions>,
+    mut transport: T,
 ) {
     set_current_thread_priority(ThreadPriority::Crossplatform(
-        NETWORK_PRIORITY.try_into().unwrap()
+        state.config.network_priority.try_into().unwrap()
     )).unwrap();
 
-    let listener = TcpListener::bind(NETWORK_PORT)
-        .expect("GCS failed to bind TCP listener");
+    while state.is_running.load(Ordering::SeqCst) {
+        if !transport.open() {
+            thread::sleep(Duration::from_micros(state.config.network_ms));
+            continue;
+        }
 
-    listener.set_nonblocking(false).unwrap();
+        let Some((index, mut session, cipher)) = accept_handshake(&state, &log_tx, &mut transport, &sessions) else {
+            transport.close();
+            continue;
+        };
 
-    let mut history: PacketHistory = [None; PACKET_HISTORY_BUFFER_CAPACITY];
-    let mut history_idx: usize = 0;
+        let spacecraft = &state.spacecraft[index];
 
-    while state.is_running.load(Ordering::SeqCst) {
-        match listener.accept() {
-            Ok((mut stream, _addr)) => {
-                configure_stream(&mut stream);
-                let connect_time = state.uptime_ms();
-
-                log_tx.send(Log {
-                    source: LogSource::Network,
-                    event: Event {
-                        task_id: TaskID::NetworkService,
-                        event_id: EventID::ConnectionStart,
-                        data: EventData::None,
-                        timestamp: connect_time,
-                    },
-                }).ok();
-
-                handle_visibility_window(
-                    &state, &uplink_buffer, &log_tx,
-                    &mut stream, &mut history, &mut history_idx,
-                );
-
-                let disconnect_time = state.uptime_ms();
-
-                log_tx.send(Log {
-                    source: LogSource::Network,
-                    event: Event {
-                        task_id: TaskID::NetworkService,
-                        event_id: EventID::ConnectionEnd,
-                        data: EventData::None,
-                        timestamp: disconnect_time,
-                    },
-                }).ok();
+        session.codec.format = WireFormat::negotiated(Capabilities(spacecraft.link.capabilities.load(Ordering::Acquire)));
+        session.cipher = cipher;
 
+        handle_visibility_window(&state, spacecraft, &log_tx, &mut transport, &mut session);
+        transport.close();
 
-                drain_stale_uplink(&uplink_buffer, &log_tx, &state);
-            }
-            Err(e) => {
-                eprintln!("[GCS] accept() error: {}", e);
-                thread::sleep(Duration::from_micros(NETWORK_MS));
-            }
-        }
+        let disconnect_time = state.uptime_ms();
+
+        log_tx.send(Log {
+            source: LogSource::Network,
+            spacecraft_id: Some(spacecraft.id),
+            event: Event {
+                task_id: TaskID::NetworkService,
+                event_id: EventID::ConnectionEnd,
+                data: EventData::None,
+                timestamp: disconnect_time,
+            },
+        }).ok();
+
+
+        carry_over_uplink(&state, spacecraft, &log_tx);
+        report_next_pass(&state, spacecraft, &log_tx);
     }
 }
 
-fn configure_stream(stream: &mut TcpStream) {
-    let _ = stream.set_read_timeout(Some(Duration::from_micros(NETWORK_READ_TIMEOUT)));
-    let _ = stream.set_write_timeout(Some(Duration::from_micros(NETWORK_WRITE_TIMEOUT)));
-    let _ = stream.set_nodelay(true);
+// Predicted with the same propagator the satellite uses to decide a station is in view
+pub fn report_next_pass(state: &Arc<GroundState>, spacecraft: &SpacecraftState, log_tx: &SyncSender<Log>) {
+    let now = wall_clock_us();
+    let horizon = PASS_PREDICTION_ORBITS * spacecraft.orbit.period_us();
+
+    let Some(pass) = spacecraft.orbit.next_pass(&state.config.site, now, horizon) else {
+        return;
+    };
+
+    let uptime = state.uptime_ms();
+
+    log_tx.send(Log {
+        source: LogSource::Network,
+        spacecraft_id: Some(spacecraft.id),
+        event: Event {
+            task_id: TaskID::NetworkService,
+            event_id: EventID::PassPredicted,
+            data: EventData::PassPrediction {
+                aos_ms: uptime + (pass.aos_us - now),
+                los_ms: uptime + (pass.los_us - now),
+                max_elevation: (pass.max_elevation_deg * 100.0) as u16,
+            },
+            timestamp: uptime,
+        },
+    }).ok();
+}
+
+// Wall clock in μs, so neither a restarted station nor the next station in a handover reuses a counter the satellite has already accepted
+fn wall_clock_counter() -> u64 {
+    wall_clock_us().max(1)
 }
 
-fn drain_stale_uplink(
-    uplink_buffer: &Arc<BoundedBuffer>,
+// Waits for a Hello and answers with a HelloAck, the link is only used if the spacecraft is ours and free and both sides agree
+fn accept_handshake<'a>(
+    state: &Arc<GroundState>,
     log_tx: &SyncSender<Log>,
+    transport: &mut impl LinkTransport,
+    sessions: &'a LinkSessions,
+) -> Option<(usize, MutexGuard<'a, LinkSession>, LinkCipher)> {
+    let peer = match transport.recv_frame(Duration::from_micros(state.config.init_handshake_limit_ms)) {
+        Some(bytes) => bincode::deserialize::<Hello>(&bytes).map_err(|_| HandshakeReject::Malformed),
+        None => Err(HandshakeReject::Timeout),
+    };
+
+    // Answers as the spacecraft the peer names, the lookup is what refuses one outside the constellation
+    let claim = peer.and_then(|p| sessions.claim(state, p.spacecraft_id));
+    let spacecraft = claim.as_ref().ok().map(|(index, _)| &state.spacecraft[*index]);
+
+    let local = Hello {
+        next_sequence_no: spacecraft.map_or(0, |s| s.link.uplink_sequence_no.load(Ordering::Acquire)),
+        ..Hello::new(peer.map_or(0, |p| p.spacecraft_id), SUPPORTED_CAPABILITIES)
+    };
+
+    let result = claim.as_ref()
+        .map_err(|reason| *reason)
+        .and_then(|_| local.negotiate(&peer?, REQUIRED_CAPABILITIES));
+
+    let ack = HelloAck {
+        hello: Hello { capabilities: result.unwrap_or(Capabilities::NONE), ..local },
+        rejected: result.err(),
+    };
+
+    if let Ok(bytes) = bincode::serialize(&ack) {
+        transport.send_frame(&bytes);
+    }
+
+    let event = match result {
+        Ok(capabilities) => {
+            if let Some(spacecraft) = spacecraft {
+                spacecraft.link.capabilities.store(capabilities.0, Ordering::Release);
+            }
+
+            Event {
+                task_id: TaskID::NetworkService,
+                event_id: EventID::ConnectionStart,
+                data: EventData::Handshake {
+                    version: local.version,
+                    spacecraft_id: local.spacecraft_id,
+                    capabilities,
+                },
+                timestamp: state.uptime_ms(),
+            }
+        }
+        Err(reason) => Event {
+            task_id: TaskID::NetworkService,
+            event_id: EventID::ConnectionEnd,
+            data: EventData::HandshakeRejected {
+                reason,
+                peer_version: peer.map_or(0, |p| p.version),
+                peer_spacecraft_id: peer.map_or(0, |p| p.spacecraft_id),
+            },
+            timestamp: state.uptime_ms(),
+        },
+    };
+
+    log_tx.send(Log { source: LogSource::Network, spacecraft_id: spacecraft.map(|s| s.id), event }).ok();
+
+    let (index, mut session) = claim.ok()?;
+    let peer = peer.ok()?;
+    let cipher = LinkCipher::negotiate(result.ok()?, &sessions.uplink_key, &local, &peer, LinkRole::Ground);
+    session.codec.resume_at(peer.next_sequence_no);
+
+    Some((index, session, cipher))
+}
+
+// Commands the pass had no room for wait for the next one, everything else only meant something in this pass
+fn carry_over_uplink(
     state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
 ) {
     let mut drained = 0u32;
-    while let Some(stale) = uplink_buffer.pop() {
+    let mut carried = 0u32;
+    while let Some(stale) = spacecraft.uplink_buffer.pop() {
+        if let SatelliteMessage::Command { command_id, .. } = stale.payload
+            && carry_over_command(state, spacecraft, log_tx, command_id) {
+            carried += 1;
+            continue;
+        }
+
         drained += 1;
+        log_uplink_drop(state, spacecraft, log_tx, &stale);
+    }
+    if carried > 0 {
         log_tx.try_send(Log {
             source: LogSource::Network,
+            spacecraft_id: Some(spacecraft.id),
             event: Event {
-                task_id: stale.payload.command_task_id(),
-                event_id: EventID::DataLoss,
-                data: EventData::None,
+                task_id: TaskID::NetworkService,
+                event_id: EventID::UplinkCarriedOver,
+                data: EventData::PacketDrain { count: carried },
                 timestamp: state.uptime_ms(),
             },
         }).ok();
@@ -108,6 +257,7 @@ fn drain_stale_uplink(
     if drained > 0 {
         log_tx.try_send(Log {
             source: LogSource::Network,
+            spacecraft_id: Some(spacecraft.id),
             event: Event {
                 task_id: TaskID::NetworkService,
                 event_id: EventID::DataLoss,
@@ -120,54 +270,90 @@ fn drain_stale_uplink(
 
 fn handle_visibility_window(
     state: &Arc<GroundState>,
-    uplink_buffer: &Arc<BoundedBuffer>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
-    stream: &mut TcpStream,
-    history: &mut PacketHistory,
-    history_idx: &mut usize,
+    transport: &mut impl LinkTransport,
+    session: &mut LinkSession,
 ) {
     let pass_start = state.uptime_ms();
-    state.link.consecutive_missing.store(0, Ordering::Release);
+    let los = spacecraft.orbit.next_pass(&state.config.site, wall_clock_us(), 0).map_or(0, |pass| pass.los_us); // 0 if the orbit says it is not overhead
+    spacecraft.link.consecutive_missing.store(0, Ordering::Release);
+
+    maybe_queue_sync_request(state, spacecraft, log_tx);
+    plan_pass(state, spacecraft, log_tx, los);
 
-    maybe_queue_sync_request(state, uplink_buffer, log_tx);
+    let mut last_nack_at = None;
 
-    while state.uptime_ms() - pass_start < VISIBILITY_WINDOW_LIMIT_MS
+    while wall_clock_us() < los
         && state.is_running.load(Ordering::SeqCst)
+        && transport.is_connected()
     {
-        send_uplink(state, uplink_buffer, log_tx, stream, history, history_idx);
-        receive_downlink(state, uplink_buffer, log_tx, stream);
+        let now = state.uptime_ms();
+        if last_nack_at.is_none_or(|at| now.saturating_sub(at) >= state.config.nack_interval_ms) { // First thing in a pass for gaps carried over
+            queue_nacks(state, spacecraft, log_tx, &mut session.arq, now);
+            last_nack_at = Some(now);
+        }
+
+        if spacecraft.uplink_buffer.is_empty() { // Commands queued since AOS
+            release_planned(state, spacecraft, log_tx, los);
+        }
+
+        send_uplink(state, spacecraft, log_tx, transport, session);
+        receive_downlink(state, spacecraft, log_tx, transport, session);
     }
 
     state.cpu_active_ms.fetch_add(state.uptime_ms() - pass_start, Ordering::SeqCst);
 }
 
+fn plan_pass(
+    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    los_us: u64,
+) {
+    let planned = release_planned(state, spacecraft, log_tx, los_us);
+    let held = spacecraft.command_plan.lock().unwrap().len() as u32;
+
+    log_tx.try_send(Log {
+        source: LogSource::Network,
+        spacecraft_id: Some(spacecraft.id),
+        event: Event {
+            task_id: TaskID::NetworkService,
+            event_id: EventID::PassPlanned,
+            data: EventData::UplinkPlan { planned, held },
+            timestamp: state.uptime_ms(),
+        },
+    }).ok();
+}
+
 fn maybe_queue_sync_request(
     state: &Arc<GroundState>,
-    uplink_buffer: &Arc<BoundedBuffer>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
 ) {
-    let windows = state.link.windows_since_sync.fetch_add(1, Ordering::Relaxed);
-    let sync_interval = if state.clock_sync.is_calibrated.load(Ordering::Acquire) {
-        SYNC_CALIBRATED_INTERVAL_WINDOWS
+    let windows = spacecraft.link.windows_since_sync.fetch_add(1, Ordering::Relaxed);
+    let sync_interval = if spacecraft.clock_sync.is_calibrated.load(Ordering::Acquire) {
+        state.tunables.sync_calibrated_interval_windows.load(Ordering::Acquire)
     } else {
-        SYNC_INTERVAL_WINDOWS
+        state.tunables.sync_interval_windows.load(Ordering::Acquire)
     };
 
-    if windows % sync_interval != 0 {
+    if !windows.is_multiple_of(sync_interval) {
         return;
     }
 
-    if let Some(dropped) = uplink_buffer.push(TelemetryPacket {
+    if let Some(dropped) = spacecraft.uplink_buffer.push(TelemetryPacket {
         priority: Priority::Emergency,
         creation_time: 0,
         payload: SatelliteMessage::SyncRequest,
         sequence_no: SEQUENCE_NOT_CONFIRMED,
     }) {
-        log_uplink_drop(log_tx, &dropped, state.uptime_ms());
+        log_uplink_drop(state, spacecraft, log_tx, &dropped);
     }
 
     log_tx.try_send(Log {
         source: LogSource::Network,
+        spacecraft_id: Some(spacecraft.id),
         event: Event {
             task_id: TaskID::NetworkService,
             event_id: EventID::SyncStart,
@@ -179,13 +365,12 @@ fn maybe_queue_sync_request(
 
 fn send_uplink(
     state: &Arc<GroundState>,
-    uplink_buffer: &Arc<BoundedBuffer>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
-    stream: &mut TcpStream,
-    history: &mut PacketHistory,
-    history_idx: &mut usize,
+    transport: &mut impl LinkTransport,
+    session: &mut LinkSession,
 ) {
-    let mut packet = match uplink_buffer.pop() {
+    let mut packet = match spacecraft.uplink_buffer.pop() {
         Some(p) => p,
         None => return,
     };
@@ -193,24 +378,29 @@ fn send_uplink(
     let wire_time = state.uptime_ms();
     packet.creation_time = wire_time;
 
+    if let SatelliteMessage::Command { command_id, .. } = packet.payload {
+        advance_command(state, spacecraft, log_tx, LogSource::Network, command_id, CommandStage::Uplinked);
+    }
+
     let is_sync = packet.payload == SatelliteMessage::SyncRequest;
     if is_sync {
         
-        state.clock_sync.last_sent_at.store(wire_time, Ordering::Release);
+        spacecraft.clock_sync.last_sent_at.store(wire_time, Ordering::Release);
     }
 
     if !is_sync && packet.creation_time > 0 {
         let latency = state.uptime_ms().saturating_sub(packet.creation_time);
-        state.command_dispatch_latency.insert_new_metric(latency);
+        spacecraft.command_dispatch_latency.insert_new_metric(latency);
 
-        if latency > COMMAND_DISPATCH_DEADLINE_MS {
+        if latency > state.tunables.command_dispatch_deadline_ms.load(Ordering::Acquire) {
             log_tx.try_send(Log {
                 source: LogSource::Network,
+                spacecraft_id: Some(spacecraft.id),
                 event: Event {
                     task_id: TaskID::NetworkService,
                     event_id: EventID::CompletionDelay,
                     data: EventData::SchedulingDrift {
-                        drift_ms: latency.saturating_sub(COMMAND_DISPATCH_DEADLINE_MS) as u32,
+                        drift_ms: latency.saturating_sub(state.tunables.command_dispatch_deadline_ms.load(Ordering::Acquire)) as u32,
                     },
                     timestamp: state.uptime_ms(),
                 },
@@ -218,71 +408,81 @@ fn send_uplink(
         }
     }
 
-    history[*history_idx] = Some(packet);
-    *history_idx = (*history_idx + 1) % PACKET_HISTORY_BUFFER_CAPACITY;
+    if packet.sequence_no == SEQUENCE_NOT_CONFIRMED { // Replays go out under their original number
+        packet.sequence_no = spacecraft.link.uplink_sequence_no.fetch_add(1, Ordering::SeqCst);
+    }
+
+    // Signed last so the security counter follows the order commands actually go out in
+    session.signer.advance_to(wall_clock_counter()); // Past anything another station signed since our last pass
+    session.signer.sign(&mut packet.payload);
+    session.history.record(packet);
 
-    if let Ok(bytes) = bincode::serialize(&packet) {
-        let length = bytes.len() as u16;
-        if stream.write_all(&length.to_be_bytes()).is_err() { return; }
-        let _ = stream.write_all(&bytes);
+    if let Some(bytes) = session.codec.encode(&packet).and_then(|bytes| session.cipher.seal(bytes)) {
+        transport.send_frame(&bytes);
     }
 }
 
 fn receive_downlink(
     state: &Arc<GroundState>,
-    uplink_buffer: &Arc<BoundedBuffer>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
-    stream: &mut TcpStream,
+    transport: &mut impl LinkTransport,
+    session: &mut LinkSession,
 ) {
-    let mut length_buf = [0u8; 2];
-    if stream.read_exact(&mut length_buf).is_err() { return; }
+    let frame = transport.recv_frame(Duration::from_micros(state.config.network_read_timeout));
+    report_frame_integrity(state, spacecraft, log_tx, transport.decoder());
 
-    let length = u16::from_be_bytes(length_buf) as usize;
-    let mut payload_buf = vec![0u8; length];
-    if stream.read_exact(&mut payload_buf).is_err() { return; }
+    let payload_buf = match frame {
+        Some(bytes) => bytes,
+        None => return,
+    };
 
     let receive_time = state.uptime_ms();
 
-    let packet = match bincode::deserialize::<TelemetryPacket>(&payload_buf) {
-        Ok(packet) => {
+    let packet = match session.cipher.open(payload_buf).and_then(|bytes| session.codec.decode(&bytes)) {
+        Some(packet) => {
             packet
         },
-        Err(_) => {
+        None => { // CRC passed, so the body is from a peer that encodes or encrypts differently
+            spacecraft.link.decode_failures.fetch_add(1, Ordering::Relaxed);
+            log_frame_integrity(state, spacecraft, log_tx);
             return;
         },
     };
 
     let decode_latency = state.uptime_ms().saturating_sub(receive_time);
 
-    if state.clock_sync.samples.load(Ordering::Relaxed) > 0 {
+    if spacecraft.clock_sync.samples.load(Ordering::Relaxed) > 0 {
         let network_latency = receive_time.saturating_sub(packet.creation_time);
 
-        state.telemetry_reception_latency.insert_new_metric(network_latency);
+        spacecraft.telemetry_reception_latency.insert_new_metric(network_latency);
         
         log_tx.try_send(Log {
             source: LogSource::Network,
+            spacecraft_id: Some(spacecraft.id),
             event: Event {
                 task_id: TaskID::NetworkService,
                 event_id: EventID::NetworkPerformance,
                 data: EventData::NetworkPerformance {
                     priority: packet.priority,
-                    latency_ms: state.telemetry_reception_latency.last_latency_ms.load(Ordering::Relaxed),
-                    jitter_ms: state.telemetry_reception_latency.last_jitter_ms.load(Ordering::Relaxed),
-                    sample_count: state.telemetry_reception_latency.number_of_samples.load(Ordering::Relaxed),
+                    latency_ms: spacecraft.telemetry_reception_latency.last_latency_ms.load(Ordering::Relaxed),
+                    jitter_ms: spacecraft.telemetry_reception_latency.last_jitter_ms.load(Ordering::Relaxed),
+                    sample_count: spacecraft.telemetry_reception_latency.number_of_samples.load(Ordering::Relaxed),
                 },
                 timestamp: receive_time,
             }
         }).ok();
     }
 
-    if decode_latency > DECODE_DEADLINE_MS {
+    if decode_latency > state.tunables.decode_deadline_ms.load(Ordering::Acquire) {
         log_tx.try_send(Log {
             source: LogSource::Network,
+            spacecraft_id: Some(spacecraft.id),
             event: Event {
                 task_id: TaskID::NetworkService,
                 event_id: EventID::CompletionDelay,
                 data: EventData::SchedulingDrift {
-                    drift_ms: decode_latency.saturating_sub(DECODE_DEADLINE_MS) as u32,
+                    drift_ms: decode_latency.saturating_sub(state.tunables.decode_deadline_ms.load(Ordering::Acquire)) as u32,
                 },
                 timestamp: receive_time,
             },
@@ -291,98 +491,208 @@ fn receive_downlink(
 
     log_tx.try_send(Log {
         source: LogSource::Network,
+        spacecraft_id: Some(spacecraft.id),
         event: Event {
             task_id: TaskID::NetworkService,
             event_id: EventID::QueuePerformance,
             data: EventData::QueuePerformance {
                 latency_ms: decode_latency,
-                jitter_ms: state.telemetry_reception_latency.last_jitter_ms.load(Ordering::Relaxed),
-                buffer_fill_rate: state.buffer_fill_rate.load(Ordering::Relaxed),
-                sample_count: state.telemetry_reception_latency
+                jitter_ms: spacecraft.telemetry_reception_latency.last_jitter_ms.load(Ordering::Relaxed),
+                buffer_fill_rate: spacecraft.buffer_fill_rate.load(Ordering::Relaxed),
+                sample_count: spacecraft.telemetry_reception_latency
                     .number_of_samples.load(Ordering::Relaxed),
             },
             timestamp: receive_time,
         },
     }).ok();
 
-    check_sequence_gaps(state, uplink_buffer, log_tx, &packet);
-    route_packet(state, uplink_buffer, log_tx, packet, receive_time);
+    check_sequence_gaps(state, spacecraft, log_tx, &mut session.arq, &packet);
+    route_packet(state, spacecraft, log_tx, session, packet, receive_time);
 }
 
 fn route_packet(
     state: &Arc<GroundState>,
-    uplink_buffer: &Arc<BoundedBuffer>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
+    session: &mut LinkSession,
     packet: TelemetryPacket,
     receive_time: u64,
 ) {
     match packet.payload {
-        SatelliteMessage::SyncResponse { ground_sent, satellite_receive } => {
-            handle_sync_response(state, uplink_buffer, log_tx, ground_sent, satellite_receive, receive_time);
+        SatelliteMessage::SyncResponse { ground_sent, satellite_receive, satellite_send } => {
+            handle_sync_response(state, spacecraft, log_tx, ground_sent, satellite_receive, satellite_send, receive_time);
         }
         SatelliteMessage::Telemetry { event } => {
-            handle_telemetry(state, log_tx, uplink_buffer, event, receive_time);
+            match event.data {
+                EventData::SequenceGap { first, count } => {
+                    replay_uplink(state, spacecraft, log_tx, &session.history, first, count);
+                }
+                EventData::RetransmitUnavailable { base, bitmap } => {
+                    session.arq.abandon(base, bitmap);
+                    report_downlink_losses(state, spacecraft, log_tx, &mut session.arq);
+                }
+                _ => {}
+            }
+            handle_telemetry(state, spacecraft, log_tx, event, receive_time);
         }
         _ => {}
     }
 }
 
+// Requeues the frames the satellite never saw, they are signed again on the way out
+fn replay_uplink(
+    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    history: &PacketHistory,
+    first: u32,
+    count: u32,
+) {
+    let mut missing = 0u32;
+
+    for sequence_no in first..first.saturating_add(count.min(state.config.packet_history_buffer_capacity as u32)) {
+        match history.get(sequence_no) {
+            Some(TelemetryPacket { payload: SatelliteMessage::SyncRequest, .. }) => {} // Would skew the round trip, the next window asks again
+            Some(TelemetryPacket { payload: SatelliteMessage::Nack { .. }, .. }) => {} // Superseded by the periodic NACKs
+            Some(packet) => {
+                spacecraft.link.uplink_replays.fetch_add(1, Ordering::Relaxed);
+                if let Some(dropped) = spacecraft.uplink_buffer.push(packet) {
+                    log_uplink_drop(state, spacecraft, log_tx, &dropped);
+                }
+            }
+            None => missing += 1,
+        }
+    }
+
+    missing += count.saturating_sub(state.config.packet_history_buffer_capacity as u32);
+
+    if missing > 0 {
+        spacecraft.link.uplink_replay_misses.fetch_add(missing, Ordering::Relaxed);
+
+        log_tx.try_send(Log {
+            source: LogSource::Network,
+            spacecraft_id: Some(spacecraft.id),
+            event: Event {
+                task_id: TaskID::NetworkService,
+                event_id: EventID::RetransmitFailed,
+                data: EventData::PacketDrain { count: missing },
+                timestamp: state.uptime_ms(),
+            },
+        }).ok();
+    }
+}
+
+// The satellite fits its own clock, so all four timestamps go back to it
 fn handle_sync_response(
     state: &Arc<GroundState>,
-    uplink_buffer: &Arc<BoundedBuffer>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
     ground_sent: u64,
     satellite_receive: u64,
+    satellite_send: u64,
     receive_time: u64,
 ) {
-    let actual_sent = state.clock_sync.last_sent_at.load(Ordering::Acquire);
-    let g_sent = if ground_sent > 0 { ground_sent } else { actual_sent };
+    let actual_sent = spacecraft.clock_sync.last_sent_at.load(Ordering::Acquire);
 
-    let rtt = receive_time.saturating_sub(g_sent);
-    let one_way = rtt / 2;
+    if ground_sent > 0 && ground_sent != actual_sent { // Answers an older request, or one another station sent on its own clock
+        return;
+    }
 
-    let offset = (g_sent + one_way).saturating_sub(satellite_receive);
+    let exchange = SyncExchange {
+        ground_sent: if ground_sent > 0 { ground_sent } else { actual_sent },
+        satellite_receive,
+        satellite_send,
+        ground_receive: receive_time,
+    };
 
-    if let Some(dropped) = uplink_buffer.push(TelemetryPacket {
+    if let Some(dropped) = spacecraft.uplink_buffer.push(TelemetryPacket {
         priority: Priority::Emergency,
         creation_time: state.uptime_ms(),
-        payload: SatelliteMessage::SyncResult { offset },
+        payload: SatelliteMessage::SyncResult {
+            ground_sent: exchange.ground_sent,
+            satellite_receive,
+            satellite_send,
+            ground_receive: receive_time,
+            auth: CommandAuth::UNSIGNED, // Signed in send_uplink like a command
+        },
         sequence_no: SEQUENCE_NOT_CONFIRMED,
     }) {
-        log_uplink_drop(log_tx, &dropped, state.uptime_ms());
+        log_uplink_drop(state, spacecraft, log_tx, &dropped);
     }
 
-    state.clock_sync.samples.fetch_add(1, Ordering::Relaxed);
+    let correlation = spacecraft.clock_sync.add(&exchange, state.boot_utc_us);
+
+    if correlation.quality != SyncQuality::Unsynced
+        && let Some(dropped) = spacecraft.uplink_buffer.push(TelemetryPacket {
+            priority: Priority::Critical,
+            creation_time: state.uptime_ms(),
+            payload: SatelliteMessage::TimeCorrelation { correlation, auth: CommandAuth::UNSIGNED },
+            sequence_no: SEQUENCE_NOT_CONFIRMED,
+        }) {
+        log_uplink_drop(state, spacecraft, log_tx, &dropped);
+    }
+
+    log_time_correlation(log_tx, Some(spacecraft.id), &correlation, receive_time);
+
+    spacecraft.clock_sync.samples.fetch_add(1, Ordering::Relaxed);
 
     log_tx.try_send(Log {
         source: LogSource::Network,
+        spacecraft_id: Some(spacecraft.id),
         event: Event {
             task_id: TaskID::NetworkService,
             event_id: EventID::SyncCompleted,
-            data: EventData::TimeSync { offset },
+            data: EventData::SyncSample { offset_us: exchange.offset_us(), rtt_us: exchange.rtt_us() },
             timestamp: receive_time,
         },
     }).ok();
 
 }
 
+// Written to the log whenever the coefficients change, so the satellite timestamps around it can be re-timed later
+pub fn log_time_correlation(log_tx: &SyncSender<Log>, spacecraft_id: Option<u16>, correlation: &TimeCorrelation, timestamp: u64) {
+    log_tx.try_send(Log {
+        source: LogSource::Network,
+        spacecraft_id,
+        event: Event {
+            task_id: TaskID::NetworkService,
+            event_id: EventID::TimeCorrelation,
+            data: EventData::TimeCorrelation {
+                reference_us: correlation.reference_us,
+                utc_us: correlation.utc_us,
+                drift_ppb: correlation.drift_ppb.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                error_us: correlation.error_us.min(u32::MAX as u64) as u32,
+                quality: correlation.quality,
+            },
+            timestamp,
+        },
+    }).ok();
+}
+
 fn handle_telemetry(
     state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
-    uplink_buffer: &Arc<BoundedBuffer>,
     event: Event,
     receive_time: u64,
 ) {
     log_tx.try_send(Log {
         source: LogSource::External,
+        spacecraft_id: Some(spacecraft.id),
         event,
     }).ok();
 
     match event.event_id {
-        EventID::SubsystemFault     => handle_subsystem_fault(state, log_tx, uplink_buffer, event, receive_time),
-        EventID::CommandCompletion  => handle_command_completion(state, log_tx, event, receive_time),
-        EventID::SyncCompleted      => {
-            state.clock_sync.is_calibrated.store(true, Ordering::Release);
+        EventID::SubsystemFault     => handle_subsystem_fault(state, spacecraft, log_tx, event, receive_time),
+        EventID::CommandAccepted    => handle_command_ack(state, spacecraft, log_tx, event, CommandStage::Accepted, receive_time),
+        EventID::CommandCompletion  => handle_command_ack(state, spacecraft, log_tx, event, CommandStage::Executed, receive_time),
+        EventID::CommandRejected
+        | EventID::CommandNotFound  => handle_command_ack(state, spacecraft, log_tx, event, CommandStage::Failed, receive_time),
+        EventID::SyncCompleted      => { // Sent while the satellite's clock fit is locked
+            spacecraft.clock_sync.is_calibrated.store(true, Ordering::Release);
+        }
+        EventID::SyncOngoing        => {
+            spacecraft.clock_sync.is_calibrated.store(false, Ordering::Release);
         }
         EventID::MissionAbort => {
             state.is_running.store(false, Ordering::SeqCst);
@@ -393,135 +703,187 @@ fn handle_telemetry(
 
 fn handle_subsystem_fault(
     state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
-    uplink_buffer: &Arc<BoundedBuffer>,
     event: Event,
     receive_time: u64,
 ) {
-    if let EventData::Subsystem { subsystem_id } = event.data {
-        if let Some(sub) = state.find_subsystem(subsystem_id) {
-            sub.interlock.store(true, Ordering::Release);
-            sub.fault_detected_at.store(receive_time, Ordering::Release);
-            sub.alert_sent.store(false, Ordering::Release);
-
-            if let Some(dropped) = uplink_buffer.push(TelemetryPacket {
-                priority: Priority::Emergency,
-                creation_time: state.uptime_ms(),
-                payload: SatelliteMessage::Command {
-                    command: Command::ClearSubsystemFault { subsystem_id },
-                    sent_at: state.uptime_ms(),
-                },
-                sequence_no: SEQUENCE_NOT_CONFIRMED,
-            }) {
-                log_uplink_drop(log_tx, &dropped, state.uptime_ms());
-            }
-
-            log_tx.send(Log {
-                source: LogSource::HealthMonitor,
-                event: Event {
-                    task_id: TaskID::GlobalSystem,
-                    event_id: EventID::SubsystemFault,
-                    data: EventData::Subsystem { subsystem_id },
-                    timestamp: receive_time,
-                },
-            }).ok();
-        }
+    if let EventData::Subsystem { subsystem_id } = event.data
+        && let Some(sub) = spacecraft.find_subsystem(subsystem_id) {
+        sub.interlock.store(true, Ordering::Release);
+        sub.fault_detected_at.store(receive_time, Ordering::Release);
+        sub.alert_sent.store(false, Ordering::Release);
+
+        queue_command(state, spacecraft, log_tx, LogSource::Network,
+            Command::ClearSubsystemFault { subsystem_id }, Priority::Emergency, 0);
+
+        log_tx.send(Log {
+            source: LogSource::HealthMonitor,
+            spacecraft_id: Some(spacecraft.id),
+            event: Event {
+                task_id: TaskID::GlobalSystem,
+                event_id: EventID::SubsystemFault,
+                data: EventData::Subsystem { subsystem_id },
+                timestamp: receive_time,
+            },
+        }).ok();
     }
 }
 
-fn handle_command_completion(
+// Acks echo the command ID, the pending-command table knows which command it was
+fn handle_command_ack(
     state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
     event: Event,
+    stage: CommandStage,
     receive_time: u64,
 ) {
-    if event.task_id != TaskID::ClearSubsystemFault {
-        return;
-    }
+    let command_id = match event.data {
+        EventData::CommandAck { command_id } | EventData::CommandRejected { command_id, .. } => command_id,
+        _ => return,
+    };
 
-    if let EventData::Subsystem { subsystem_id } = event.data {
-        if let Some(sub) = state.find_subsystem(subsystem_id) {
-            if sub.id == subsystem_id && sub.interlock.load(Ordering::Acquire) {
-                sub.clear();
-                log_tx.send(Log {
-                    source: LogSource::HealthMonitor,
-                    event: Event {
-                        task_id: TaskID::ClearSubsystemFault,
-                        event_id: EventID::SubsystemFixed,
-                        data: EventData::Subsystem { subsystem_id },
-                        timestamp: receive_time,
-                    },
-                }).ok();
-            }
-        }
+    let command = advance_command(state, spacecraft, log_tx, LogSource::Network, command_id, stage);
+
+    if stage == CommandStage::Executed
+        && let Some(Command::ClearSubsystemFault { subsystem_id }) = command
+        && let Some(sub) = spacecraft.find_subsystem(subsystem_id)
+        && sub.id == subsystem_id && sub.interlock.load(Ordering::Acquire) {
+        sub.clear();
+        log_tx.send(Log {
+            source: LogSource::HealthMonitor,
+            spacecraft_id: Some(spacecraft.id),
+            event: Event {
+                task_id: TaskID::ClearSubsystemFault,
+                event_id: EventID::SubsystemFixed,
+                data: EventData::Subsystem { subsystem_id },
+                timestamp: receive_time,
+            },
+        }).ok();
     }
 }
 
 
 fn check_sequence_gaps(
     state: &Arc<GroundState>,
-    uplink_buffer: &Arc<BoundedBuffer>,
+    spacecraft: &SpacecraftState,
     log_tx: &SyncSender<Log>,
+    arq: &mut SelectiveRepeat,
     packet: &TelemetryPacket,
 ) {
-    if packet.sequence_no == SEQUENCE_NOT_CONFIRMED {
-        return;
+    if let SatelliteMessage::Telemetry { event } = packet.payload
+        && let EventData::Handover { to_station, .. } = event.data
+        && to_station == state.config.station_id {
+        arq.resume_at(packet.sequence_no); // The frames in between went down to another station
     }
 
-    let last_seq = state.link.last_packet_sequence.load(Ordering::Acquire);
-
-    if last_seq == 0 {
-        state.link.last_packet_sequence.store(packet.sequence_no, Ordering::Release);
-        state.link.last_packet_time.store(state.uptime_ms(), Ordering::Release);
-        return;
+    match arq.observe(packet.sequence_no) {
+        Arrival::Unnumbered => return,
+        Arrival::InOrder => spacecraft.link.consecutive_missing.store(0, Ordering::Release),
+        Arrival::Gap(skipped) => {
+            spacecraft.link.consecutive_missing.fetch_add(skipped, Ordering::Relaxed);
+        }
+        Arrival::Recovered => {
+            spacecraft.link.downlink_recovered.fetch_add(1, Ordering::Relaxed);
+        }
+        Arrival::Duplicate => {}
     }
 
-    if packet.sequence_no > last_seq + 1 {
-        let missing_count = packet.sequence_no - (last_seq + 1);
-        state.link.consecutive_missing.fetch_add(missing_count, Ordering::Relaxed);
+    report_downlink_losses(state, spacecraft, log_tx, arq);
 
-        if missing_count <= 3 {
-            for missing_seq in (last_seq + 1)..packet.sequence_no {
-                let retransmit = TelemetryPacket {
-                    priority: Priority::Critical,
-                    creation_time: state.uptime_ms(),
-                    payload: SatelliteMessage::Command {
-                        command: Command::RequestRetransmit { sequence_no: missing_seq },
-                        sent_at: state.uptime_ms(),
-                    },
-                    sequence_no: SEQUENCE_NOT_CONFIRMED,
-                };
-                if let Some(dropped) = uplink_buffer.push(retransmit) {
-                    log_uplink_drop(log_tx, &dropped, state.uptime_ms());
-                }
-            }
-        } else {
-            log_tx.try_send(Log {
-                source: LogSource::Network,
-                event: Event {
-                    task_id: TaskID::NetworkService,
-                    event_id: EventID::DataLoss,
-                    data: EventData::PacketDrain { count: missing_count },
-                    timestamp: state.uptime_ms(),
-                },
-            }).ok();
+    spacecraft.link.last_packet_sequence.store(arq.highest(), Ordering::Release);
+    spacecraft.link.last_packet_time.store(state.uptime_ms(), Ordering::Release);
+}
+
+// One NACK per 64 outstanding sequence numbers, each number at most once per nack_retry_ms
+fn queue_nacks(
+    state: &Arc<GroundState>,
+    spacecraft: &SpacecraftState,
+    log_tx: &SyncSender<Log>,
+    arq: &mut SelectiveRepeat,
+    now: u64,
+) {
+    for (base, bitmap) in arq.nacks(now, state.config.nack_retry_ms) {
+        spacecraft.link.nacks_sent.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(dropped) = spacecraft.uplink_buffer.push(TelemetryPacket {
+            priority: Priority::Critical,
+            creation_time: now,
+            payload: SatelliteMessage::Nack { base, bitmap },
+            sequence_no: SEQUENCE_NOT_CONFIRMED,
+        }) {
+            log_uplink_drop(state, spacecraft, log_tx, &dropped);
         }
-    } else {
-        state.link.consecutive_missing.store(0, Ordering::Release);
     }
+}
+
+fn report_downlink_losses(state: &Arc<GroundState>, spacecraft: &SpacecraftState, log_tx: &SyncSender<Log>, arq: &mut SelectiveRepeat) {
+    spacecraft.link.downlink_outstanding.store(arq.outstanding() as u32, Ordering::Relaxed);
+
+    let lost = std::mem::take(&mut arq.lost);
+    if lost == 0 {
+        return;
+    }
+
+    spacecraft.link.downlink_lost.fetch_add(lost, Ordering::Relaxed);
 
-    state.link.last_packet_sequence.store(packet.sequence_no, Ordering::Release);
-    state.link.last_packet_time.store(state.uptime_ms(), Ordering::Release);
+    log_tx.try_send(Log {
+        source: LogSource::Network,
+        spacecraft_id: Some(spacecraft.id),
+        event: Event {
+            task_id: TaskID::NetworkService,
+            event_id: EventID::DataLoss,
+            data: EventData::PacketDrain { count: lost },
+            timestamp: state.uptime_ms(),
+        },
+    }).ok();
 }
 
-fn log_uplink_drop(log_tx: &SyncSender<Log>, dropped: &TelemetryPacket, now: u64) {
+fn log_uplink_drop(state: &Arc<GroundState>, spacecraft: &SpacecraftState, log_tx: &SyncSender<Log>, dropped: &TelemetryPacket) {
     log_tx.try_send(Log {
         source: LogSource::Network,
+        spacecraft_id: Some(spacecraft.id),
         event: Event {
             task_id: dropped.payload.command_task_id(),
             event_id: EventID::DataLoss,
             data: EventData::None,
-            timestamp: now,
+            timestamp: state.uptime_ms(),
+        },
+    }).ok();
+
+    if let SatelliteMessage::Command { command_id, .. } = dropped.payload {
+        advance_command(state, spacecraft, log_tx, LogSource::Network, command_id, CommandStage::Failed);
+    }
+}
+
+// Moves the decoder's per-connection counters into the link totals
+fn report_frame_integrity(state: &Arc<GroundState>, spacecraft: &SpacecraftState, log_tx: &SyncSender<Log>, decoder: &mut FrameDecoder) {
+    let crc_failures = std::mem::take(&mut decoder.crc_failures);
+    let resyncs = std::mem::take(&mut decoder.resyncs);
+
+    if crc_failures == 0 && resyncs == 0 {
+        return;
+    }
+
+    spacecraft.link.crc_failures.fetch_add(crc_failures, Ordering::Relaxed);
+    spacecraft.link.resyncs.fetch_add(resyncs, Ordering::Relaxed);
+    log_frame_integrity(state, spacecraft, log_tx);
+}
+
+fn log_frame_integrity(state: &Arc<GroundState>, spacecraft: &SpacecraftState, log_tx: &SyncSender<Log>) {
+    log_tx.try_send(Log {
+        source: LogSource::Network,
+        spacecraft_id: Some(spacecraft.id),
+        event: Event {
+            task_id: TaskID::NetworkService,
+            event_id: EventID::DataCorruption,
+            data: EventData::FrameIntegrity {
+                crc_failures: spacecraft.link.crc_failures.load(Ordering::Relaxed),
+                resyncs: spacecraft.link.resyncs.load(Ordering::Relaxed),
+                decode_failures: spacecraft.link.decode_failures.load(Ordering::Relaxed),
+            },
+            timestamp: state.uptime_ms(),
         },
     }).ok();
-}
\ No newline at end of file
+}
@@ -0,0 +1,65 @@
+use std::cmp::Reverse;
+use crate::types::{Command, Priority};
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlannedCommand {
+    pub command_id: u32,
+    pub command: Command,
+    pub priority: Priority,
+    pub expires_at: u64, // Given up on if no pass has sent it by then
+    pub execute_at: u64,
+}
+
+// Commands waiting on the ground for a pass, released at AOS by priority then deadline as far as the pass has room
+#[derive(Debug)]
+pub struct CommandPlan {
+    held: Vec<PlannedCommand>,
+    pub capacity: usize,
+}
+
+impl CommandPlan {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            held: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    // Once full the least urgent command is dropped, which can be the one just held
+    pub fn hold(&mut self, command: PlannedCommand) -> Option<PlannedCommand> {
+        self.held.push(command);
+
+        if self.held.len() <= self.capacity {
+            return None;
+        }
+
+        self.sort();
+        self.held.pop()
+    }
+
+    pub fn expire(&mut self, now: u64) -> Vec<PlannedCommand> {
+        let (expired, kept) = std::mem::take(&mut self.held).into_iter().partition(|command| command.expires_at <= now);
+        self.held = kept;
+        expired
+    }
+
+    // The most urgent commands up to the number of slots, everything else stays held
+    pub fn release(&mut self, slots: usize) -> Vec<PlannedCommand> {
+        self.sort();
+        let count = slots.min(self.held.len());
+        self.held.drain(..count).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.held.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.held.is_empty()
+    }
+
+    // Highest priority first, the earliest deadline first within a priority
+    fn sort(&mut self) {
+        self.held.sort_by_key(|command| (Reverse(command.priority), command.expires_at));
+    }
+}
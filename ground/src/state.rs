@@ -1,14 +1,30 @@
-use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, AtomicU64, Ordering};
 use std::sync::Mutex;
 use std::time::Instant;
-use crate::types::{Metrics, SubsystemID, Command, Priority};
-use crate::config::{MAX_SUBSYSTEM, TICK_RATE};
+use crate::types::{Metrics, SubsystemID, Command, CommandStage, Priority, Orbit, OrbitConfig, ClockEstimator, SyncExchange, TimeCorrelation, wall_clock_us};
+use crate::config::{Config, ScheduleEntry, MAX_SUBSYSTEM, STATION_ID_SHIFT};
+use crate::buffer::BoundedBuffer;
+use crate::planner::CommandPlan;
 
 #[derive(Debug)]
 pub struct SyncState {
     pub is_calibrated: AtomicBool,
     pub last_sent_at: AtomicU64,
     pub samples: AtomicU64,
+    pub estimator: Mutex<ClockEstimator>, // Satellite uptime onto ours, fitted from the same exchanges as the satellite's
+    pub correlation: Mutex<TimeCorrelation>, // Satellite uptime onto UTC, what the satellite is told after every exchange
+}
+
+impl SyncState {
+    // Folds the exchange into our fit and returns the correlation now in effect
+    pub fn add(&self, exchange: &SyncExchange, boot_utc_us: u64) -> TimeCorrelation {
+        let mut estimator = self.estimator.lock().unwrap();
+        estimator.add(exchange);
+
+        let correlation = TimeCorrelation::from_estimate(&estimator.estimate(), boot_utc_us);
+        *self.correlation.lock().unwrap() = correlation;
+        correlation
+    }
 }
 
 #[derive(Debug)]
@@ -17,6 +33,17 @@ pub struct LinkState {
     pub consecutive_missing: AtomicU32,
     pub last_packet_time: AtomicU64,
     pub windows_since_sync: AtomicU32,
+    pub capabilities: AtomicU32, // Negotiated in the handshake of the current pass
+    pub crc_failures: AtomicU32,
+    pub resyncs: AtomicU32,
+    pub decode_failures: AtomicU32,
+    pub uplink_sequence_no: AtomicU32, // Next number for a fresh uplink frame, replays keep theirs
+    pub uplink_replays: AtomicU32,
+    pub uplink_replay_misses: AtomicU32, // Requested frames already gone from history
+    pub nacks_sent: AtomicU32,
+    pub downlink_outstanding: AtomicU32, // Missing downlink sequence numbers still being NACKed
+    pub downlink_recovered: AtomicU32,
+    pub downlink_lost: AtomicU32,
 }
 
 #[derive(Debug)]
@@ -35,44 +62,131 @@ impl SubsystemInterlockState {
     }
 }
 
+// The RELOADABLE_KEYS of the config, SIGHUP swaps them in while the threads keep running
+#[derive(Debug)]
+pub struct Tunables {
+    pub decode_deadline_ms: AtomicU64,
+    pub command_dispatch_deadline_ms: AtomicU64,
+    pub loss_of_contact_threshold: AtomicU32,
+    pub fault_alert_threshold_ms: AtomicU64,
+    pub sync_interval_windows: AtomicU32,
+    pub sync_calibrated_interval_windows: AtomicU32,
+    pub log_level: AtomicU8,
+}
+
+impl Tunables {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            decode_deadline_ms: AtomicU64::new(config.decode_deadline_ms),
+            command_dispatch_deadline_ms: AtomicU64::new(config.command_dispatch_deadline_ms),
+            loss_of_contact_threshold: AtomicU32::new(config.loss_of_contact_threshold),
+            fault_alert_threshold_ms: AtomicU64::new(config.fault_alert_threshold_ms),
+            sync_interval_windows: AtomicU32::new(config.sync_interval_windows),
+            sync_calibrated_interval_windows: AtomicU32::new(config.sync_calibrated_interval_windows),
+            log_level: AtomicU8::new(config.log_level as u8),
+        }
+    }
+
+    pub fn apply(&self, config: &Config) {
+        self.decode_deadline_ms.store(config.decode_deadline_ms, Ordering::Release);
+        self.command_dispatch_deadline_ms.store(config.command_dispatch_deadline_ms, Ordering::Release);
+        self.loss_of_contact_threshold.store(config.loss_of_contact_threshold, Ordering::Release);
+        self.fault_alert_threshold_ms.store(config.fault_alert_threshold_ms, Ordering::Release);
+        self.sync_interval_windows.store(config.sync_interval_windows, Ordering::Release);
+        self.sync_calibrated_interval_windows.store(config.sync_calibrated_interval_windows, Ordering::Release);
+        self.log_level.store(config.log_level as u8, Ordering::Release);
+    }
+}
+
 #[derive(Debug)]
 pub struct ScheduledCommand {
     pub command: Command,
     pub priority: Priority,
     pub interval_ms: u64,
+    pub execute_after_ms: u64,
     pub next_send_time: AtomicU64, 
     pub enabled: AtomicBool,
 }
 
+impl ScheduledCommand {
+    pub fn new(entry: &ScheduleEntry) -> Self {
+        Self {
+            command: entry.command,
+            priority: entry.priority,
+            interval_ms: entry.interval_ms,
+            execute_after_ms: entry.execute_after_ms,
+            next_send_time: AtomicU64::new(0),
+            enabled: AtomicBool::new(true),
+        }
+    }
+}
+
 #[derive(Debug)]
-pub struct GroundState {
-    pub is_running: AtomicBool,
+pub struct PendingCommand {
+    pub command_id: u32,
+    pub command: Command,
+    pub priority: Priority,
+    pub stage: CommandStage,
+    pub attempts: u32, // Times it has been uplinked
+    pub stage_changed_at: u64,
+    pub expires_at: u64, // While held for a pass
+    pub execute_at: u64, // Time tag on the ground clock, 0 for none
+}
+
+#[derive(Debug, Default)]
+pub struct CommandTracking {
+    pub pending: Vec<PendingCommand>, // Terminal commands are removed once counted
+    pub executed: u32,
+    pub failed: u32,
+    pub timed_out: u32,
+    pub retries: u32,
+}
+
+// Everything the ground keeps about one satellite of the constellation
+#[derive(Debug)]
+pub struct SpacecraftState {
+    pub id: u16,
+    pub orbit: Orbit, // Passes over config.site are predicted from it
     pub clock_sync: SyncState,
-    pub boot_time: Instant,
     pub link: LinkState,
     pub subsystem_health: [SubsystemInterlockState; MAX_SUBSYSTEM],
     pub command_schedule: Mutex<Vec<ScheduledCommand>>,
-    pub cpu_active_ms: AtomicU64,
+    pub command_tracking: Mutex<CommandTracking>,
+    pub command_plan: Mutex<CommandPlan>, // Commands wait here until a pass has room for them
+    pub uplink_buffer: BoundedBuffer, // Only what goes out in the current pass
     pub buffer_fill_rate: AtomicU32,
     pub command_dispatch_latency: Metrics,
     pub telemetry_reception_latency: Metrics,
 }
 
-impl GroundState {
-    pub fn new() -> Self {
+impl SpacecraftState {
+    pub fn new(id: u16, orbit: &OrbitConfig, config: &Config) -> Self {
         Self {
-            is_running: AtomicBool::new(true),
+            id,
+            orbit: orbit.build().expect("orbits are checked when the config loads"),
             clock_sync: SyncState {
                 is_calibrated: AtomicBool::new(false),
                 last_sent_at: AtomicU64::new(0),
                 samples: AtomicU64::new(0),
+                estimator: Mutex::new(ClockEstimator::new()),
+                correlation: Mutex::new(TimeCorrelation::NONE),
             },
-            boot_time: Instant::now(),
             link: LinkState {
                 last_packet_sequence: AtomicU32::new(0),
                 consecutive_missing: AtomicU32::new(0),
                 last_packet_time: AtomicU64::new(0),
                 windows_since_sync: AtomicU32::new(0),
+                capabilities: AtomicU32::new(0),
+                crc_failures: AtomicU32::new(0),
+                resyncs: AtomicU32::new(0),
+                decode_failures: AtomicU32::new(0),
+                uplink_sequence_no: AtomicU32::new(1),
+                uplink_replays: AtomicU32::new(0),
+                uplink_replay_misses: AtomicU32::new(0),
+                nacks_sent: AtomicU32::new(0),
+                downlink_outstanding: AtomicU32::new(0),
+                downlink_recovered: AtomicU32::new(0),
+                downlink_lost: AtomicU32::new(0),
             },
             subsystem_health: [
                 SubsystemInterlockState {
@@ -88,23 +202,10 @@ impl GroundState {
                     alert_sent: AtomicBool::new(false),
                 },
             ],
-            command_schedule: Mutex::new(vec![
-                ScheduledCommand {
-                    command: Command::RotateAntenna { target_angle: 9000 },
-                    priority: Priority::Normal,
-                    interval_ms: 5 * TICK_RATE,
-                    next_send_time: AtomicU64::new(0),
-                    enabled: AtomicBool::new(true),
-                },
-                ScheduledCommand {
-                    command: Command::SetPowerMode { mode: 1 },
-                    priority: Priority::Normal,
-                    interval_ms: 20 * TICK_RATE,
-                    next_send_time: AtomicU64::new(0),
-                    enabled: AtomicBool::new(true),
-                },
-            ]),
-            cpu_active_ms: AtomicU64::new(0),
+            command_schedule: Mutex::new(config.command_schedule.iter().map(ScheduledCommand::new).collect()),
+            command_tracking: Mutex::new(CommandTracking::default()),
+            command_plan: Mutex::new(CommandPlan::new(config.command_plan_capacity)),
+            uplink_buffer: BoundedBuffer::new(config.uplink_buffer_capacity),
             buffer_fill_rate: AtomicU32::new(0),
             command_dispatch_latency: Metrics {
                 last_latency_ms: AtomicU64::new(0),
@@ -131,11 +232,67 @@ impl GroundState {
         }
     }
 
-    pub fn uptime_ms(&self) -> u64 {
-        self.boot_time.elapsed().as_micros() as u64
+    // Entries that did not change keep their place in the cycle, new ones start on the next scheduler tick
+    pub fn replace_schedule(&self, entries: &[ScheduleEntry]) {
+        let mut schedule = self.command_schedule.lock().unwrap();
+
+        let replaced = entries.iter().map(|entry| {
+            let scheduled = ScheduledCommand::new(entry);
+
+            if let Some(kept) = schedule.iter().find(|old| old.command == entry.command
+                && old.priority == entry.priority && old.interval_ms == entry.interval_ms
+                && old.execute_after_ms == entry.execute_after_ms) {
+                scheduled.next_send_time.store(kept.next_send_time.load(Ordering::Acquire), Ordering::Release);
+                scheduled.enabled.store(kept.enabled.load(Ordering::Acquire), Ordering::Release);
+            }
+
+            scheduled
+        }).collect();
+
+        *schedule = replaced;
     }
 
     pub fn find_subsystem(&self, id: SubsystemID) -> Option<&SubsystemInterlockState> {
         self.subsystem_health.iter().find(|s| s.id == id)
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug)]
+pub struct GroundState {
+    pub config: Config, // As loaded at startup
+    pub tunables: Tunables,
+    pub is_running: AtomicBool,
+    pub boot_time: Instant,
+    pub boot_utc_us: u64, // UTC at uptime 0, every timestamp we log is uptime on top of it
+    pub spacecraft: Vec<SpacecraftState>, // In spacecraft_ids order
+    pub next_command_id: AtomicU32, // Shared, so a command ID names one command across the whole constellation and ground network
+    pub cpu_active_ms: AtomicU64,
+}
+
+// First command ID, acks for commands another station sent then never match ours
+fn station_base(config: &Config) -> u32 {
+    ((config.station_id as u32) << STATION_ID_SHIFT) | 1
+}
+
+impl GroundState {
+    pub fn new(config: Config) -> Self {
+        Self {
+            tunables: Tunables::new(&config),
+            spacecraft: config.spacecraft_ids.iter().zip(&config.orbits).map(|(&id, orbit)| SpacecraftState::new(id, orbit, &config)).collect(),
+            next_command_id: AtomicU32::new(station_base(&config)),
+            config,
+            is_running: AtomicBool::new(true),
+            boot_time: Instant::now(),
+            boot_utc_us: wall_clock_us(),
+            cpu_active_ms: AtomicU64::new(0),
+        }
+    }
+
+    pub fn uptime_ms(&self) -> u64 {
+        self.boot_time.elapsed().as_micros() as u64
+    }
+
+    pub fn utc_us(&self, uptime_us: u64) -> u64 {
+        self.boot_utc_us + uptime_us
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+
+use rts_ground::command::{queue_command, advance_command, check_command_timeouts};
+use rts_ground::config::{Config, TICK_RATE};
+use rts_ground::state::{GroundState, PendingCommand};
+use rts_ground::types::*;
+
+fn ground(config: Config) -> (Arc<GroundState>, SyncSender<Log>, Receiver<Log>) {
+    let state = Arc::new(GroundState::new(config));
+    let (log_tx, log_rx) = mpsc::sync_channel(state.config.log_buffer_capacity);
+    (state, log_tx, log_rx)
+}
+
+fn queue(state: &Arc<GroundState>, log_tx: &SyncSender<Log>, execute_at: u64) -> u32 {
+    queue_command(state, &state.spacecraft[0], log_tx, LogSource::CommandScheduler, Command::SetPowerMode { mode: 1 }, Priority::Normal, execute_at)
+}
+
+// What the network thread does when it sends a held command
+fn uplink(state: &Arc<GroundState>, log_tx: &SyncSender<Log>, command_id: u32) {
+    let spacecraft = &state.spacecraft[0];
+    spacecraft.command_plan.lock().unwrap().release(1);
+    assert!(advance_command(state, spacecraft, log_tx, LogSource::Network, command_id, CommandStage::Uplinked).is_some());
+}
+
+fn pending<T>(state: &GroundState, read: impl FnOnce(&PendingCommand) -> T) -> T {
+    read(&state.spacecraft[0].command_tracking.lock().unwrap().pending[0])
+}
+
+#[test]
+fn a_late_or_duplicated_ack_does_not_move_a_command_back() {
+    let (state, log_tx, _log_rx) = ground(Config::default());
+    let command_id = queue(&state, &log_tx, 0);
+    uplink(&state, &log_tx, command_id);
+
+    let spacecraft = &state.spacecraft[0];
+    assert!(advance_command(&state, spacecraft, &log_tx, LogSource::Network, command_id, CommandStage::Accepted).is_some());
+    assert!(advance_command(&state, spacecraft, &log_tx, LogSource::Network, command_id, CommandStage::Accepted).is_none());
+    assert!(advance_command(&state, spacecraft, &log_tx, LogSource::Network, command_id, CommandStage::Uplinked).is_none());
+
+    assert_eq!(pending(&state, |entry| (entry.stage, entry.attempts)), (CommandStage::Accepted, 1));
+
+    assert!(advance_command(&state, spacecraft, &log_tx, LogSource::Network, command_id, CommandStage::Executed).is_some());
+    assert!(advance_command(&state, spacecraft, &log_tx, LogSource::Network, command_id, CommandStage::Executed).is_none());
+
+    let tracking = spacecraft.command_tracking.lock().unwrap();
+    assert!(tracking.pending.is_empty());
+    assert_eq!(tracking.executed, 1);
+}
+
+#[test]
+fn an_unacknowledged_command_is_held_again_once_the_ack_timeout_passes() {
+    let (state, log_tx, _log_rx) = ground(Config::default());
+    let command_id = queue(&state, &log_tx, 0);
+    uplink(&state, &log_tx, command_id);
+
+    let uplinked_at = pending(&state, |entry| entry.stage_changed_at);
+    let timeout = state.config.command_ack_timeout_ms;
+
+    check_command_timeouts(&state, &state.spacecraft[0], &log_tx, uplinked_at + timeout);
+    assert_eq!(pending(&state, |entry| entry.stage), CommandStage::Uplinked);
+
+    check_command_timeouts(&state, &state.spacecraft[0], &log_tx, uplinked_at + timeout + 1);
+    assert_eq!(pending(&state, |entry| (entry.stage, entry.attempts)), (CommandStage::Queued, 1));
+    assert_eq!(pending(&state, |entry| entry.expires_at), uplinked_at + timeout + 1 + state.config.command_expiry_ms);
+
+    let spacecraft = &state.spacecraft[0];
+    assert_eq!(spacecraft.command_plan.lock().unwrap().len(), 1);
+    assert_eq!(spacecraft.command_tracking.lock().unwrap().retries, 1);
+}
+
+#[test]
+fn a_command_unacknowledged_after_its_last_attempt_times_out() {
+    let (state, log_tx, _log_rx) = ground(Config { command_max_attempts: 2, ..Config::default() });
+    let command_id = queue(&state, &log_tx, 0);
+    let timeout = state.config.command_ack_timeout_ms;
+
+    for attempt in 1..=2 {
+        uplink(&state, &log_tx, command_id);
+        let (attempts, uplinked_at) = pending(&state, |entry| (entry.attempts, entry.stage_changed_at));
+        assert_eq!(attempts, attempt);
+
+        check_command_timeouts(&state, &state.spacecraft[0], &log_tx, uplinked_at + timeout + 1);
+    }
+
+    let spacecraft = &state.spacecraft[0];
+    assert!(spacecraft.command_plan.lock().unwrap().is_empty());
+
+    let tracking = spacecraft.command_tracking.lock().unwrap();
+    assert!(tracking.pending.is_empty());
+    assert_eq!((tracking.retries, tracking.timed_out), (1, 1));
+}
+
+#[test]
+fn an_accepted_time_tagged_command_is_timed_from_its_execute_at() {
+    let (state, log_tx, _log_rx) = ground(Config::default());
+    let timeout = state.config.command_execution_timeout_ms;
+    let execute_at = state.uptime_ms() + 3 * timeout;
+
+    let command_id = queue(&state, &log_tx, execute_at);
+    uplink(&state, &log_tx, command_id);
+    advance_command(&state, &state.spacecraft[0], &log_tx, LogSource::Network, command_id, CommandStage::Accepted);
+    let accepted_at = pending(&state, |entry| entry.stage_changed_at);
+
+    // Long past its acceptance but still waiting on the time tag
+    check_command_timeouts(&state, &state.spacecraft[0], &log_tx, accepted_at + timeout + TICK_RATE);
+    assert_eq!(pending(&state, |entry| entry.stage), CommandStage::Accepted);
+
+    check_command_timeouts(&state, &state.spacecraft[0], &log_tx, execute_at + timeout);
+    assert_eq!(pending(&state, |entry| entry.stage), CommandStage::Accepted);
+
+    check_command_timeouts(&state, &state.spacecraft[0], &log_tx, execute_at + timeout + 1);
+    let tracking = state.spacecraft[0].command_tracking.lock().unwrap();
+    assert!(tracking.pending.is_empty());
+    assert_eq!(tracking.timed_out, 1);
+}
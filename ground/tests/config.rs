@@ -0,0 +1,143 @@
+use std::io;
+use std::path::PathBuf;
+
+use rts_ground::config::{Config, LogLevel, ScheduleChanges, ScheduleEntry, RELOADABLE_KEYS, TICK_RATE};
+use rts_ground::types::*;
+
+// Loads text through a file the way the binary does, name keeps tests running in parallel apart
+fn load(name: &str, text: &str) -> io::Result<Config> {
+    let path = std::env::temp_dir().join(format!("rts_ground_{}_{}.toml", std::process::id(), name));
+    std::fs::write(&path, text)?;
+    let config = Config::load(&path);
+    std::fs::remove_file(&path).ok();
+    config
+}
+
+fn rejection(name: &str, text: &str) -> String {
+    load(name, text).expect_err("the config should be refused").to_string()
+}
+
+fn key(name: &str) -> u8 {
+    RELOADABLE_KEYS.iter().position(|key| *key == name).unwrap() as u8
+}
+
+fn entry(command: Command, interval_ms: u64) -> ScheduleEntry {
+    ScheduleEntry { command, priority: Priority::Normal, interval_ms, execute_after_ms: 0 }
+}
+
+#[test]
+fn an_unchanged_config_has_no_diff() {
+    let diff = Config::default().diff(&Config::default());
+
+    assert!(diff.changed.is_empty());
+    assert!(diff.rejected.is_empty());
+}
+
+#[test]
+fn a_reloadable_key_is_reported_with_its_old_and_new_value() {
+    let (old, new) = (Config::default(), Config { log_level: LogLevel::Warning, fault_alert_threshold_ms: 20_000 * TICK_RATE, ..Config::default() });
+    let diff = old.diff(&new);
+
+    assert!(diff.rejected.is_empty());
+    assert_eq!(diff.changed.len(), 2);
+    assert!(diff.changed.contains(&"log_level: \"debug\" -> \"warning\"".to_string()), "{:?}", diff.changed);
+
+    let mut keys = diff.changed_keys.clone();
+    keys.sort();
+    assert_eq!(keys, vec![key("fault_alert_threshold_ms"), key("log_level")]);
+
+    assert_eq!(old.reloadable_change(&new, key("fault_alert_threshold_ms")), (10_000 * TICK_RATE, 20_000 * TICK_RATE));
+}
+
+#[test]
+fn a_schedule_edited_in_place_is_reported_entry_by_entry() {
+    let old = Config::default();
+    let mut new = Config::default();
+    new.command_schedule[1].interval_ms = 30 * TICK_RATE; // Same length, one entry different
+
+    let diff = old.diff(&new);
+
+    assert!(diff.rejected.is_empty());
+    assert_eq!(diff.changed, vec!["command_schedule: 1 kept, 1 added, 1 removed".to_string()]);
+    assert_eq!(diff.changed_keys, vec![key("command_schedule")]);
+    assert_eq!(old.reloadable_change(&new, key("command_schedule")), (1, 1));
+}
+
+#[test]
+fn schedule_changes_count_entries_regardless_of_order() {
+    let antenna = entry(Command::RotateAntenna { target_angle: 9000 }, 5 * TICK_RATE);
+    let power = entry(Command::SetPowerMode { mode: 1 }, 20 * TICK_RATE);
+    let safe = entry(Command::SetPowerMode { mode: 0 }, 20 * TICK_RATE);
+
+    assert_eq!(ScheduleChanges::between(&[antenna, power], &[power, antenna]), ScheduleChanges { kept: 2, added: 0, removed: 0 });
+    assert_eq!(ScheduleChanges::between(&[antenna, power], &[safe]), ScheduleChanges { kept: 0, added: 1, removed: 2 });
+    assert_eq!(ScheduleChanges::between(&[], &[antenna]).to_string(), "0 kept, 1 added, 0 removed");
+}
+
+#[test]
+fn a_structural_key_refuses_the_reload() {
+    let new = Config { station_id: 7, log_level: LogLevel::Info, ..Config::default() };
+    let diff = Config::default().diff(&new);
+
+    assert_eq!(diff.rejected, vec!["station_id: 0 -> 7".to_string()]);
+    assert_eq!(diff.changed_keys, vec![key("log_level")]); // Reported, but main applies nothing while anything is rejected
+}
+
+#[test]
+fn an_empty_file_loads_the_defaults() {
+    assert_eq!(load("empty", "").unwrap(), Config::default());
+}
+
+#[test]
+fn every_shipped_config_loads() {
+    let configs = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("configs");
+
+    for file in std::fs::read_dir(configs).unwrap() {
+        let path = file.unwrap().path();
+        assert!(Config::load(&path).is_ok(), "{}: {:?}", path.display(), Config::load(&path).err());
+    }
+}
+
+#[test]
+fn an_unknown_key_is_refused() {
+    assert!(rejection("unknown", "command_timeout_ms = 5").contains("unknown field"));
+}
+
+#[test]
+fn a_zero_period_is_refused() {
+    assert!(rejection("period", "command_ms = 0").contains("command_ms must be greater than 0"));
+}
+
+#[test]
+fn a_thread_priority_above_the_maximum_is_refused() {
+    assert!(rejection("priority", "network_priority = 100").contains("network_priority must be at most 99"));
+}
+
+#[test]
+fn a_spacecraft_listed_twice_is_refused() {
+    let text = "spacecraft_ids = [1, 1]\n[[orbits]]\n[[orbits]]";
+    assert!(rejection("spacecraft", text).contains("spacecraft_ids lists spacecraft 1 twice"));
+}
+
+#[test]
+fn one_orbit_is_needed_per_spacecraft() {
+    assert!(rejection("orbits", "spacecraft_ids = [1, 2]").contains("one orbit per spacecraft_ids entry"));
+}
+
+#[test]
+fn a_schedule_entry_that_never_fires_is_refused() {
+    let text = "[[command_schedule]]\ncommand = { SetPowerMode = { mode = 1 } }\npriority = \"Normal\"\ninterval_ms = 0";
+    assert!(rejection("interval", text).contains("command_schedule[0].interval_ms must be greater than 0"));
+}
+
+#[test]
+fn only_a_fault_clear_may_be_scheduled_at_emergency_priority() {
+    let text = "[[command_schedule]]\ncommand = { SetPowerMode = { mode = 1 } }\npriority = \"Emergency\"\ninterval_ms = 1000";
+    assert!(rejection("emergency", text).contains("command_schedule[0] cannot use Emergency priority"));
+}
+
+#[test]
+fn the_archive_cannot_be_the_station_log() {
+    let text = "log_path = \"gcs.log\"\narchive_path = \"gcs.log\"";
+    assert!(rejection("archive", text).contains("archive_path and log_path are both gcs.log"));
+}
@@ -0,0 +1,99 @@
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rts_ground::config::Config;
+use rts_ground::network::{run_network_thread, LinkSessions};
+use rts_ground::state::GroundState;
+use rts_ground::types::*;
+
+const KEY: [u8; AUTH_KEY_LEN] = [0x5A; AUTH_KEY_LEN];
+const DEADLINE: Duration = Duration::from_secs(10);
+
+// A geostationary orbit over the site, so a pass the ground accepts lasts until the test closes it
+fn overhead() -> Config {
+    let orbit = OrbitConfig {
+        time_scale: 1.0,
+        elements: OrbitalElements { semi_major_axis_km: 42_164.0, eccentricity: 0.0, inclination_deg: 0.0, ..OrbitalElements::default() },
+        ..OrbitConfig::default()
+    };
+
+    let built = orbit.build().unwrap();
+    let now = wall_clock_us();
+    let site = (0..36).map(|step| GroundSite { latitude_deg: 0.0, longitude_deg: step as f64 * 10.0 - 180.0, min_elevation_deg: 0.0 })
+        .max_by(|a, b| built.elevation_deg(a, now).total_cmp(&built.elevation_deg(b, now)))
+        .unwrap();
+
+    Config { site, orbits: vec![orbit], ..Config::default() }
+}
+
+// One network thread per listener, all claiming spacecraft from the same sessions like the binary's
+struct Ground {
+    state: Arc<GroundState>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Ground {
+    // Returns the satellite end of each listener
+    fn start(listeners: usize) -> (Self, Vec<ChannelTransport>) {
+        let state = Arc::new(GroundState::new(overhead()));
+        let sessions = Arc::new(LinkSessions::new(&state, AuthKey::new(KEY)));
+        let (log_tx, log_rx) = mpsc::sync_channel(state.config.log_buffer_capacity);
+        thread::spawn(move || log_rx.iter().for_each(drop));
+
+        let (ends, threads) = (0..listeners).map(|_| {
+            let (satellite_end, ground_end) = ChannelTransport::pair();
+            let (state, log_tx, sessions) = (Arc::clone(&state), log_tx.clone(), Arc::clone(&sessions));
+
+            (satellite_end, thread::spawn(move || run_network_thread(state, log_tx, sessions, ground_end)))
+        }).unzip();
+
+        (Self { state, threads }, ends)
+    }
+
+    fn stop(self, ends: Vec<ChannelTransport>) {
+        self.state.is_running.store(false, Ordering::SeqCst);
+
+        for mut end in ends {
+            end.close();
+        }
+
+        for handle in self.threads {
+            handle.join().unwrap();
+        }
+    }
+}
+
+// What the ground answers a satellite calling as spacecraft_id
+fn hello(end: &mut ChannelTransport, spacecraft_id: u16) -> HelloAck {
+    assert!(end.open());
+    assert!(end.send_frame(&bincode::serialize(&Hello::new(spacecraft_id, Capabilities::AUTH)).unwrap()));
+
+    bincode::deserialize(&end.recv_frame(DEADLINE).expect("the ground answers every Hello")).unwrap()
+}
+
+#[test]
+fn a_spacecraft_outside_the_constellation_is_refused() {
+    let (ground, mut ends) = Ground::start(1);
+    let unknown = ground.state.spacecraft.iter().map(|spacecraft| spacecraft.id).max().unwrap() + 1;
+
+    let ack = hello(&mut ends[0], unknown);
+    ground.stop(ends);
+
+    assert_eq!(ack.rejected, Some(HandshakeReject::SpacecraftMismatch));
+}
+
+#[test]
+fn a_spacecraft_already_in_a_pass_is_refused_on_a_second_listener() {
+    let (ground, mut ends) = Ground::start(2);
+    let spacecraft_id = ground.state.spacecraft[0].id;
+
+    let first = hello(&mut ends[0], spacecraft_id);
+    let second = hello(&mut ends[1], spacecraft_id);
+    ground.stop(ends);
+
+    assert_eq!(first.rejected, None);
+    assert_eq!(second.rejected, Some(HandshakeReject::AlreadyConnected));
+}
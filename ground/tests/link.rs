@@ -0,0 +1,198 @@
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rts_ground::command::{queue_command, run_command_scheduler};
+use rts_ground::config::{Config as GroundConfig, TICK_RATE};
+use rts_ground::network::{run_network_thread as run_ground_link, LinkSessions};
+use rts_ground::state::GroundState;
+use rts_ground::types::*;
+use rts_satellite::buffer::BoundedBuffer;
+use rts_satellite::command::run_command_executor;
+use rts_satellite::config::{Config as SatelliteConfig, GroundStation, THERMAL_SENSOR};
+use rts_satellite::network::run_network_thread as run_satellite_link;
+use rts_satellite::sensors::run_sensor_task;
+use rts_satellite::state::SatelliteState;
+
+const KEY: [u8; AUTH_KEY_LEN] = [0x5A; AUTH_KEY_LEN];
+const DEADLINE: Duration = Duration::from_secs(10);
+
+// A geostationary orbit never sets, so the ground's pass lasts as long as the test
+fn overhead() -> (OrbitConfig, GroundSite) {
+    let orbit = OrbitConfig {
+        time_scale: 1.0,
+        elements: OrbitalElements { semi_major_axis_km: 42_164.0, eccentricity: 0.0, inclination_deg: 0.0, ..OrbitalElements::default() },
+        ..OrbitConfig::default()
+    };
+
+    let built = orbit.build().unwrap();
+    let now = wall_clock_us();
+    let site = (0..36).map(|step| GroundSite { latitude_deg: 0.0, longitude_deg: step as f64 * 10.0 - 180.0, min_elevation_deg: 0.0 })
+        .max_by(|a, b| built.elevation_deg(a, now).total_cmp(&built.elevation_deg(b, now)))
+        .unwrap();
+
+    (orbit, site)
+}
+
+fn drain_until(log_rx: &Receiver<Log>, mut done: impl FnMut(&Log) -> bool) -> bool {
+    let deadline = Instant::now() + DEADLINE;
+
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match log_rx.recv_timeout(left) {
+            Ok(log) if done(&log) => return true,
+            Ok(_) => {}
+            Err(_) => return false,
+        }
+    }
+
+    false
+}
+
+fn ground_config(station_id: u8) -> GroundConfig {
+    let (orbit, site) = overhead();
+    GroundConfig { station_id, site, orbits: vec![orbit], command_schedule: Vec::new(), ..GroundConfig::default() }
+}
+
+// One ground station process with its network thread on transport and its command scheduler
+fn start_ground(config: GroundConfig, transport: ChannelTransport, threads: &mut Vec<JoinHandle<()>>) -> (Arc<GroundState>, SyncSender<Log>, Receiver<Log>) {
+    let ground = Arc::new(GroundState::new(config));
+    let (log_tx, log_rx) = mpsc::sync_channel::<Log>(ground.config.log_buffer_capacity);
+    let sessions = Arc::new(LinkSessions::new(&ground, AuthKey::new(KEY)));
+
+    let (state, tx) = (Arc::clone(&ground), log_tx.clone());
+    threads.push(thread::spawn(move || run_ground_link(state, tx, sessions, transport)));
+
+    let (state, tx) = (Arc::clone(&ground), log_tx.clone());
+    threads.push(thread::spawn(move || run_command_scheduler(state, tx)));
+
+    (ground, log_tx, log_rx)
+}
+
+// The network thread, command executor and thermal sensor, with one transport per entry of ground_stations
+fn start_satellite(satellite: &Arc<SatelliteState>, verifier_key: [u8; AUTH_KEY_LEN], transports: Vec<ChannelTransport>, threads: &mut Vec<JoinHandle<()>>) {
+    let downlink_buffer = Arc::new(BoundedBuffer::new(satellite.config.data_buffer_capacity));
+    let uplink_buffer = Arc::new(BoundedBuffer::new(satellite.config.data_buffer_capacity));
+    let (satellite_log_tx, satellite_log_rx) = mpsc::sync_channel(satellite.config.log_buffer_capacity);
+
+    let (state, downlink, uplink, log_tx) = (Arc::clone(satellite), Arc::clone(&downlink_buffer), Arc::clone(&uplink_buffer), satellite_log_tx.clone());
+    threads.push(thread::spawn(move || run_satellite_link(state, downlink, uplink, log_tx, AuthKey::new(KEY), CommandVerifier::new(AuthKey::new(verifier_key)), transports)));
+
+    let (state, downlink, uplink, log_tx) = (Arc::clone(satellite), Arc::clone(&downlink_buffer), Arc::clone(&uplink_buffer), satellite_log_tx.clone());
+    threads.push(thread::spawn(move || run_command_executor(state, downlink, uplink, log_tx)));
+
+    let (state, downlink, log_tx) = (Arc::clone(satellite), downlink_buffer, satellite_log_tx);
+    threads.push(thread::spawn(move || run_sensor_task(state, THERMAL_SENSOR, downlink, log_tx)));
+
+    thread::spawn(move || satellite_log_rx.iter().for_each(drop));
+}
+
+fn stop(grounds: &[&GroundState], satellite: &SatelliteState, log_rxs: Vec<Receiver<Log>>, threads: Vec<JoinHandle<()>>) {
+    for ground in grounds {
+        ground.is_running.store(false, Ordering::SeqCst);
+    }
+    satellite.is_running.store(false, Ordering::SeqCst);
+    drop(log_rxs); // A send blocked on a full log channel gives up
+
+    for handle in threads {
+        handle.join().unwrap();
+    }
+}
+
+// Runs both ends over a channel link with SetPowerMode queued, until done says so or DEADLINE passes
+fn run_link(config: GroundConfig, verifier_key: [u8; AUTH_KEY_LEN], mut done: impl FnMut(&Log, u32, &GroundState) -> bool) -> (bool, Arc<GroundState>) {
+    let (satellite_end, ground_end) = ChannelTransport::pair();
+    let mut threads = Vec::new();
+
+    let (ground, ground_log_tx, ground_log_rx) = start_ground(config, ground_end, &mut threads);
+    let command_id = queue_command(&ground, &ground.spacecraft[0], &ground_log_tx, LogSource::CommandScheduler,
+        Command::SetPowerMode { mode: 1 }, Priority::Normal, 0);
+
+    let satellite = Arc::new(SatelliteState::new(SatelliteConfig::default()));
+    satellite.network.stations_in_view.store(1, Ordering::Release); // The simulation thread would, from the orbit
+    start_satellite(&satellite, verifier_key, vec![satellite_end], &mut threads);
+
+    let finished = drain_until(&ground_log_rx, |log| done(log, command_id, &ground));
+
+    stop(&[&ground], &satellite, vec![ground_log_rx], threads);
+
+    (finished, ground)
+}
+
+#[test]
+fn a_command_is_executed_and_telemetry_arrives_over_a_channel_link() {
+    let mut acknowledged = false;
+    let mut telemetry = false;
+
+    let (finished, ground) = run_link(ground_config(0), KEY, |log, command_id, ground| {
+        match (log.source, log.event.event_id, log.event.data) {
+            (LogSource::External, EventID::CommandAccepted, EventData::CommandAck { command_id: acked }) => acknowledged |= acked == command_id,
+            (LogSource::External, _, _) => telemetry |= log.event.task_id == TaskID::ThermalSensor,
+            _ => {}
+        }
+
+        acknowledged && telemetry && ground.spacecraft[0].command_tracking.lock().unwrap().executed == 1
+    });
+
+    assert!(finished, "acknowledged: {}, telemetry: {}", acknowledged, telemetry);
+
+    let tracking = ground.spacecraft[0].command_tracking.lock().unwrap();
+    assert_eq!((tracking.executed, tracking.failed, tracking.timed_out), (1, 0, 0));
+    assert!(tracking.pending.is_empty());
+}
+
+#[test]
+fn a_command_signed_under_another_key_times_out_without_a_rejection() {
+    let config = GroundConfig { command_ack_timeout_ms: 200 * TICK_RATE, command_max_attempts: 1, ..ground_config(0) };
+    let mut rejected = false;
+
+    // The satellite cannot trust a command_id behind a bad tag, so nothing closes the command out but the ack timeout
+    let (finished, ground) = run_link(config, [0xA5; AUTH_KEY_LEN], |log, command_id, _| {
+        rejected |= matches!(log.event.data, EventData::CommandRejected { .. });
+        matches!(log.event.data, EventData::CommandStage { command_id: id, stage: CommandStage::TimedOut, .. } if id == command_id)
+    });
+
+    assert!(finished);
+    assert!(!rejected);
+
+    let tracking = ground.spacecraft[0].command_tracking.lock().unwrap();
+    assert_eq!((tracking.executed, tracking.failed, tracking.timed_out), (0, 0, 1));
+}
+
+#[test]
+fn a_station_taking_over_past_a_14_bit_wrap_rebuilds_full_sequence_numbers() {
+    let (satellite_a, ground_a_end) = ChannelTransport::pair();
+    let (satellite_b, ground_b_end) = ChannelTransport::pair();
+    let mut threads = Vec::new();
+
+    let (ground_a, _, ground_a_log_rx) = start_ground(ground_config(0), ground_a_end, &mut threads);
+    let (ground_b, _, ground_b_log_rx) = start_ground(ground_config(1), ground_b_end, &mut threads);
+
+    let mut config = SatelliteConfig::default();
+    config.ground_stations.push(GroundStation { station_id: 1, ..config.ground_stations[0] });
+
+    // Earlier passes already took the downlink past 16384, so its 14-bit counts have wrapped
+    let satellite = Arc::new(SatelliteState::new(config));
+    satellite.network.packet_sequence_no.store(20_000, Ordering::SeqCst);
+    satellite.network.stations_in_view.store(1 << 0, Ordering::Release);
+    start_satellite(&satellite, KEY, vec![satellite_a, satellite_b], &mut threads);
+
+    let downlink_at = |ground: &GroundState| ground.spacecraft[0].link.last_packet_sequence.load(Ordering::Acquire);
+
+    let first_pass = drain_until(&ground_a_log_rx, |_| downlink_at(&ground_a) > 20_000);
+
+    satellite.network.stations_in_view.store(1 << 1, Ordering::Release); // Station 0 sets as station 1 rises
+    let mut handed_over = false;
+
+    let second_pass = drain_until(&ground_b_log_rx, |log| {
+        handed_over |= matches!((log.source, log.event.event_id), (LogSource::External, EventID::Handover));
+        handed_over && downlink_at(&ground_b) > downlink_at(&ground_a)
+    });
+
+    stop(&[&ground_a, &ground_b], &satellite, vec![ground_a_log_rx, ground_b_log_rx], threads);
+
+    assert!(first_pass, "station 0 reached {}", downlink_at(&ground_a));
+    assert!(second_pass, "handed over: {}, station 1 reached {}", handed_over, downlink_at(&ground_b));
+    assert_eq!(ground_b.spacecraft[0].link.downlink_lost.load(Ordering::Acquire), 0);
+}
@@ -0,0 +1,95 @@
+use std::sync::mpsc;
+use std::sync::Arc;
+
+use rts_ground::command::{queue_command, release_planned, carry_over_command};
+use rts_ground::config::{Config, TICK_RATE};
+use rts_ground::planner::{CommandPlan, PlannedCommand};
+use rts_ground::state::GroundState;
+use rts_ground::types::*;
+
+fn planned(command_id: u32, priority: Priority, expires_at: u64) -> PlannedCommand {
+    PlannedCommand { command_id, command: Command::SetPowerMode { mode: 1 }, priority, expires_at, execute_at: 0 }
+}
+
+fn ids(commands: &[PlannedCommand]) -> Vec<u32> {
+    commands.iter().map(|command| command.command_id).collect()
+}
+
+#[test]
+fn release_takes_the_highest_priority_first_then_the_earliest_deadline() {
+    let mut plan = CommandPlan::new(8);
+    plan.hold(planned(1, Priority::Low, 100));
+    plan.hold(planned(2, Priority::Critical, 900));
+    plan.hold(planned(3, Priority::Normal, 300));
+    plan.hold(planned(4, Priority::Critical, 500));
+
+    assert_eq!(ids(&plan.release(3)), vec![4, 2, 3]);
+    assert_eq!(plan.len(), 1);
+    assert_eq!(ids(&plan.release(3)), vec![1]);
+    assert!(plan.is_empty());
+}
+
+#[test]
+fn a_full_plan_drops_the_least_urgent_command() {
+    let mut plan = CommandPlan::new(2);
+
+    assert!(plan.hold(planned(1, Priority::Normal, 100)).is_none());
+    assert!(plan.hold(planned(2, Priority::Normal, 200)).is_none());
+    assert_eq!(plan.hold(planned(3, Priority::Critical, 900)).map(|command| command.command_id), Some(2));
+    assert_eq!(plan.hold(planned(4, Priority::Low, 50)).map(|command| command.command_id), Some(4));
+    assert_eq!(ids(&plan.release(2)), vec![3, 1]);
+}
+
+#[test]
+fn expire_returns_only_commands_past_their_deadline() {
+    let mut plan = CommandPlan::new(8);
+    plan.hold(planned(1, Priority::Normal, 100));
+    plan.hold(planned(2, Priority::Emergency, 200));
+    plan.hold(planned(3, Priority::Normal, 300));
+
+    let mut expired = ids(&plan.expire(200));
+    expired.sort();
+
+    assert_eq!(expired, vec![1, 2]); // Priority does not keep a command past its deadline
+    assert_eq!(ids(&plan.release(8)), vec![3]);
+}
+
+#[test]
+fn a_command_still_queued_at_los_is_carried_to_the_next_pass_with_its_expiry() {
+    let state = Arc::new(GroundState::new(Config::default()));
+    let spacecraft = &state.spacecraft[0];
+    let (log_tx, _log_rx) = mpsc::sync_channel(state.config.log_buffer_capacity);
+
+    let command_id = queue_command(&state, spacecraft, &log_tx, LogSource::CommandScheduler, Command::SetPowerMode { mode: 1 }, Priority::Normal, 0);
+    let expires_at = spacecraft.command_tracking.lock().unwrap().pending[0].expires_at;
+
+    // AOS with a whole pass ahead, then LOS before the uplink got to it
+    let los_us = wall_clock_us() + 600_000 * TICK_RATE;
+    assert_eq!(release_planned(&state, spacecraft, &log_tx, los_us), 1);
+    assert!(spacecraft.command_plan.lock().unwrap().is_empty());
+
+    let stale = spacecraft.uplink_buffer.pop().unwrap();
+    let SatelliteMessage::Command { command_id: stale_id, .. } = stale.payload else {
+        panic!("released something other than the command: {:?}", stale.payload);
+    };
+    assert!(carry_over_command(&state, spacecraft, &log_tx, stale_id));
+
+    let carried = spacecraft.command_plan.lock().unwrap().release(1);
+    assert_eq!(ids(&carried), vec![command_id]);
+    assert_eq!(carried[0].expires_at, expires_at);
+}
+
+#[test]
+fn only_a_command_still_queued_is_carried_over() {
+    let state = Arc::new(GroundState::new(Config::default()));
+    let spacecraft = &state.spacecraft[0];
+    let (log_tx, _log_rx) = mpsc::sync_channel(state.config.log_buffer_capacity);
+
+    let command_id = queue_command(&state, spacecraft, &log_tx, LogSource::CommandScheduler, Command::SetPowerMode { mode: 1 }, Priority::Normal, 0);
+    spacecraft.command_plan.lock().unwrap().release(1);
+    spacecraft.command_tracking.lock().unwrap().pending[0].stage = CommandStage::Uplinked;
+
+    assert!(!carry_over_command(&state, spacecraft, &log_tx, command_id));
+    assert!(!carry_over_command(&state, spacecraft, &log_tx, command_id + 1));
+    assert!(spacecraft.command_plan.lock().unwrap().is_empty());
+}
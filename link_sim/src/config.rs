@@ -0,0 +1,10 @@
+pub const TICK_RATE: u64 = 1000; // 1ms
+
+pub const DEFAULT_PROFILE_PATH: &str = "link_profile.toml";
+
+pub const GROUND_CONNECT_TIMEOUT_MS: u64 = 1000 * TICK_RATE;
+pub const NETWORK_WRITE_TIMEOUT: u64 = TICK_RATE;
+pub const RELAY_POLL_MS: u64 = TICK_RATE / 20; // Sleep between sweeps of both sides, bounds the delay the relay itself adds
+pub const RELAY_IDLE_MS: u64 = 50 * TICK_RATE; // Ends a UDP pass, longer than any quiet spell in a pass and shorter than the gap between two
+pub const RELAY_RETRY_MS: u64 = 100 * TICK_RATE; // After the ground could not be reached
+pub const MAIN_MS: u64 = 1000 * TICK_RATE;
@@ -0,0 +1,131 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::atomic::Ordering;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rts_protocol::encode_frame;
+
+use crate::config::TICK_RATE;
+use crate::profile::Impairments;
+use crate::state::ImpairmentStats;
+
+const MICROS_PER_SECOND: u64 = 1000 * TICK_RATE;
+
+// One direction of the link. Frames go in as they arrive and come out once the simulated link would have delivered them
+pub struct Channel {
+    impairments: Impairments,
+    rng: StdRng,
+    in_flight: BinaryHeap<Reverse<(u64, u64, Vec<u8>)>>, // (release time, arrival order, encoded frame)
+    arrivals: u64,
+    line_free_at: u64, // When the bandwidth cap lets the next frame start
+    last_release: u64, // Later frames never overtake earlier ones unless held back for reordering
+}
+
+impl Channel {
+    pub fn new(impairments: Impairments, seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Self { impairments, rng, in_flight: BinaryHeap::new(), arrivals: 0, line_free_at: 0, last_release: 0 }
+    }
+
+    pub fn push(&mut self, body: &[u8], now: u64, stats: &ImpairmentStats) {
+        stats.frames.fetch_add(1, Ordering::Relaxed);
+
+        if self.roll(self.impairments.loss_rate) {
+            stats.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        let mut frame = encode_frame(body).expect("a decoded body never exceeds MAX_FRAME_BODY");
+        if self.flip_bits(&mut frame) > 0 {
+            stats.corrupted.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut release = self.transmit(frame.len(), now);
+
+        if self.roll(self.impairments.reorder_rate) {
+            release += self.impairments.reorder_delay_ms;
+            stats.reordered.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.last_release = release;
+        }
+
+        if self.roll(self.impairments.duplicate_rate) {
+            self.schedule(release, frame.clone());
+            stats.duplicated.fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.schedule(release, frame);
+    }
+
+    pub fn pop_due(&mut self, now: u64) -> Option<Vec<u8>> {
+        match self.in_flight.peek() {
+            Some(Reverse((release, _, _))) if *release <= now => self.in_flight.pop().map(|Reverse((_, _, frame))| frame),
+            _ => None,
+        }
+    }
+
+    // Whatever is still in flight when a pass ends is lost with it
+    pub fn clear(&mut self) {
+        self.in_flight.clear();
+        self.line_free_at = 0;
+        self.last_release = 0;
+    }
+
+    // Time the frame lands on the far side: queueing behind the bandwidth cap, then latency and jitter
+    fn transmit(&mut self, len: usize, now: u64) -> u64 {
+        let mut sent = now;
+
+        if let Some(wire_time) = (len as u64 * 8 * MICROS_PER_SECOND).checked_div(self.impairments.bandwidth_bps) { // No cap at 0
+            self.line_free_at = now.max(self.line_free_at) + wire_time;
+            sent = self.line_free_at;
+        }
+
+        let jitter = self.rng.gen_range(0..=self.impairments.jitter_ms);
+
+        (sent + self.impairments.latency_ms + jitter).max(self.last_release)
+    }
+
+    fn schedule(&mut self, release: u64, frame: Vec<u8>) {
+        self.in_flight.push(Reverse((release, self.arrivals, frame)));
+        self.arrivals += 1;
+    }
+
+    fn roll(&mut self, rate: f64) -> bool {
+        rate > 0.0 && self.rng.gen_bool(rate)
+    }
+
+    // Walks the frame in geometrically distributed steps instead of rolling for every bit
+    fn flip_bits(&mut self, frame: &mut [u8]) -> u32 {
+        let ber = self.impairments.bit_error_rate;
+        if ber <= 0.0 {
+            return 0;
+        }
+
+        let bits = frame.len() * 8;
+        let mut flips = 0;
+        let mut bit = self.error_gap(ber);
+
+        while bit < bits {
+            frame[bit / 8] ^= 1 << (bit % 8);
+            flips += 1;
+            bit += 1 + self.error_gap(ber);
+        }
+
+        flips
+    }
+
+    // Clean bits before the next error
+    fn error_gap(&mut self, ber: f64) -> usize {
+        if ber >= 1.0 {
+            return 0;
+        }
+
+        let u: f64 = self.rng.gen_range(0.0..1.0);
+        ((1.0 - u).ln() / (1.0 - ber).ln()) as usize
+    }
+}
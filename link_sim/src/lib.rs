@@ -0,0 +1,5 @@
+pub mod config;
+pub mod impairment;
+pub mod profile;
+pub mod relay;
+pub mod state;
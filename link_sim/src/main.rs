@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use rts_protocol::{TcpTransport, TransportKind, UdpTransport};
+
+use rts_link_sim::config::{DEFAULT_PROFILE_PATH, GROUND_CONNECT_TIMEOUT_MS, MAIN_MS, NETWORK_WRITE_TIMEOUT};
+use rts_link_sim::profile::Profile;
+use rts_link_sim::relay::run_relay;
+use rts_link_sim::state::{ImpairmentStats, RelayState};
+
+// Sits between the satellite and the ground and degrades the link according to a profile file
+fn main() {
+    let profile_path = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_PROFILE_PATH.to_string());
+    let profile = Profile::load(&profile_path)
+        .unwrap_or_else(|e| panic!("Failed to load link profile from {}: {}", profile_path, e));
+
+    println!("LINK PROFILE: [{:?}]", profile);
+
+    let state = Arc::new(RelayState::new());
+    let write_timeout = Duration::from_micros(NETWORK_WRITE_TIMEOUT);
+    let r_state = Arc::clone(&state);
+
+    match profile.transport {
+        TransportKind::Tcp => {
+            let satellite = TcpTransport::listen(&profile.satellite_addr.to_string(), write_timeout)
+                .expect("Link simulator failed to bind TCP listener");
+            let ground = TcpTransport::connect(profile.ground_addr, Duration::from_micros(GROUND_CONNECT_TIMEOUT_MS), write_timeout);
+            thread::spawn(move || { run_relay(r_state, profile, satellite, ground); });
+        }
+        TransportKind::Udp => {
+            let satellite = UdpTransport::listen(&profile.satellite_addr.to_string())
+                .expect("Link simulator failed to bind UDP socket");
+            let ground = UdpTransport::connect("127.0.0.1:0", profile.ground_addr)
+                .expect("Link simulator failed to bind UDP socket");
+            thread::spawn(move || { run_relay(r_state, profile, satellite, ground); });
+        }
+    }
+
+    let h_state = Arc::clone(&state);
+
+    ctrlc::set_handler(move || {
+        println!("\nCtrl+C detected! Shutting down...");
+        h_state.is_running.store(false, Ordering::SeqCst);
+    })
+    .expect("Error setting Ctrl+C handler");
+
+    while state.is_running.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_micros(MAIN_MS));
+    }
+
+    display_summary(&state);
+}
+
+pub fn display_summary(state: &RelayState) {
+    println!("--------------------------------------SUMMARY--------------------------------------");
+
+    println!("PASSES RELAYED: [{}]", state.passes.load(Ordering::Relaxed));
+
+    println!();
+
+    display_direction("UPLINK IMPAIRMENT", &state.uplink);
+    display_direction("DOWNLINK IMPAIRMENT", &state.downlink);
+}
+
+fn display_direction(label: &str, stats: &ImpairmentStats) {
+    println!("{}: [Frames: {}, Dropped: {}, Corrupted: {}, Duplicated: {}, Reordered: {}, Delivered: {}]",
+        label,
+        stats.frames.load(Ordering::Relaxed),
+        stats.dropped.load(Ordering::Relaxed),
+        stats.corrupted.load(Ordering::Relaxed),
+        stats.duplicated.load(Ordering::Relaxed),
+        stats.reordered.load(Ordering::Relaxed),
+        stats.delivered.load(Ordering::Relaxed));
+}
@@ -0,0 +1,74 @@
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use serde::Deserialize;
+use rts_protocol::TransportKind;
+
+// What one direction of the link does to the frames crossing it. Anything left out is a clean link
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Impairments {
+    pub latency_ms: u64, // One-way, in μs like every *_ms value. How long a pass lasts is up to the orbit and the site
+    pub jitter_ms: u64, // Extra delay drawn evenly from 0..=jitter_ms, frames still arrive in order
+    pub loss_rate: f64, // Share of frames that never arrive
+    pub bit_error_rate: f64, // Chance of each bit on the wire being flipped, the receiver's CRC catches it
+    pub duplicate_rate: f64,
+    pub reorder_rate: f64, // Share of frames held back by reorder_delay_ms so later ones overtake them
+    pub reorder_delay_ms: u64,
+    pub bandwidth_bps: u64, // 0 for no cap, otherwise frames queue behind each other on the wire
+}
+
+impl Impairments {
+    fn rates(&self) -> [(&'static str, f64); 4] {
+        [
+            ("loss_rate", self.loss_rate),
+            ("bit_error_rate", self.bit_error_rate),
+            ("duplicate_rate", self.duplicate_rate),
+            ("reorder_rate", self.reorder_rate),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub transport: TransportKind, // Must match the satellite and the ground
+    pub satellite_addr: SocketAddr, // Where the satellite connects, its network_port
+    pub ground_addr: SocketAddr, // Where the ground listens, point its network_port here
+    #[serde(default)]
+    pub seed: Option<u64>, // Fixes every random draw so a run can be repeated
+    #[serde(default)]
+    pub uplink: Impairments,
+    #[serde(default)]
+    pub downlink: Impairments,
+}
+
+impl Profile {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+
+        let profile: Profile = toml::from_str(&text)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        profile.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(profile)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.satellite_addr == self.ground_addr {
+            return Err("satellite_addr and ground_addr must differ".to_string());
+        }
+
+        for (direction, impairments) in [("uplink", &self.uplink), ("downlink", &self.downlink)] {
+            for (name, rate) in impairments.rates() {
+                if !(0.0..=1.0).contains(&rate) {
+                    return Err(format!("{}.{} must be between 0 and 1, got {}", direction, name, rate));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rts_protocol::{LinkTransport, TransportKind};
+
+use crate::config::{RELAY_IDLE_MS, RELAY_POLL_MS, RELAY_RETRY_MS};
+use crate::impairment::Channel;
+use crate::profile::Profile;
+use crate::state::{ImpairmentStats, RelayState};
+
+// Stands in for the ground towards the satellite and for the satellite towards the ground, one pass at a time
+pub fn run_relay<S: LinkTransport, G: LinkTransport>(state: Arc<RelayState>, profile: Profile, mut satellite: S, mut ground: G) {
+    let mut uplink = Channel::new(profile.uplink, profile.seed);
+    let mut downlink = Channel::new(profile.downlink, profile.seed.map(|seed| seed.wrapping_add(1)));
+    let started = Instant::now();
+    let ends_when_idle = profile.transport == TransportKind::Udp; // A UDP link never reports a disconnect
+
+    while state.is_running.load(Ordering::SeqCst) {
+        if !satellite.open() {
+            thread::sleep(Duration::from_micros(RELAY_RETRY_MS));
+            continue;
+        }
+
+        if !ground.open() {
+            println!("Satellite called but the ground at {} is not answering", profile.ground_addr);
+            satellite.close();
+            thread::sleep(Duration::from_micros(RELAY_RETRY_MS));
+            continue;
+        }
+
+        let pass = state.passes.fetch_add(1, Ordering::Relaxed) + 1;
+        let downlink_start = state.downlink.frames.load(Ordering::Relaxed);
+        let uplink_start = state.uplink.frames.load(Ordering::Relaxed);
+        let mut last_heard = started.elapsed().as_micros() as u64;
+
+        while state.is_running.load(Ordering::SeqCst) && satellite.is_connected() && ground.is_connected() {
+            let now = started.elapsed().as_micros() as u64;
+
+            let heard = relay_frames(&mut satellite, &mut ground, &mut downlink, &state.downlink, now)
+                | relay_frames(&mut ground, &mut satellite, &mut uplink, &state.uplink, now);
+
+            if heard {
+                last_heard = now;
+            } else if ends_when_idle && now - last_heard > RELAY_IDLE_MS {
+                break;
+            }
+
+            thread::sleep(Duration::from_micros(RELAY_POLL_MS));
+        }
+
+        satellite.close();
+        ground.close();
+        uplink.clear();
+        downlink.clear();
+
+        println!("PASS {}: [Downlink Frames: {}, Uplink Frames: {}]", pass,
+            state.downlink.frames.load(Ordering::Relaxed) - downlink_start,
+            state.uplink.frames.load(Ordering::Relaxed) - uplink_start);
+    }
+}
+
+// Takes in everything the sender has ready, then hands over whatever the channel has finished delivering.
+// Neither side is waited on, a blocking read on one would hold up the other for a whole scheduler tick. Returns
+// whether the sender had anything
+fn relay_frames(from: &mut impl LinkTransport, to: &mut impl LinkTransport, channel: &mut Channel, stats: &ImpairmentStats, now: u64) -> bool {
+    let mut heard = false;
+
+    while let Some(body) = from.recv_frame(Duration::ZERO) {
+        channel.push(&body, now, stats);
+        heard = true;
+    }
+
+    while let Some(frame) = channel.pop_due(now) {
+        if to.send_encoded(&frame) {
+            stats.delivered.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    heard
+}
@@ -0,0 +1,37 @@
+use std::sync::atomic::{AtomicBool, AtomicU32};
+
+// Running totals for one direction of the link
+#[derive(Debug, Default)]
+pub struct ImpairmentStats {
+    pub frames: AtomicU32,
+    pub dropped: AtomicU32,
+    pub corrupted: AtomicU32,
+    pub duplicated: AtomicU32,
+    pub reordered: AtomicU32,
+    pub delivered: AtomicU32, // Copies handed to the far side, duplicates included
+}
+
+#[derive(Debug)]
+pub struct RelayState {
+    pub is_running: AtomicBool,
+    pub passes: AtomicU32,
+    pub uplink: ImpairmentStats, // Ground -> Satellite
+    pub downlink: ImpairmentStats, // Satellite -> Ground
+}
+
+impl RelayState {
+    pub fn new() -> Self {
+        Self {
+            is_running: AtomicBool::new(true),
+            passes: AtomicU32::new(0),
+            uplink: ImpairmentStats::default(),
+            downlink: ImpairmentStats::default(),
+        }
+    }
+}
+
+impl Default for RelayState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
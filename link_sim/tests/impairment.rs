@@ -0,0 +1,139 @@
+use std::sync::atomic::Ordering;
+
+use rts_link_sim::impairment::Channel;
+use rts_link_sim::profile::Impairments;
+use rts_link_sim::state::ImpairmentStats;
+use rts_protocol::encode_frame;
+
+const SEED: Option<u64> = Some(7);
+const BODY: &[u8] = b"telemetry";
+
+// Steps the clock until the channel lets the next frame out
+fn release_time(channel: &mut Channel, from: u64, until: u64) -> Option<(u64, Vec<u8>)> {
+    (from..=until).find_map(|now| channel.pop_due(now).map(|frame| (now, frame)))
+}
+
+#[test]
+fn a_clean_channel_passes_frames_straight_through() {
+    let mut channel = Channel::new(Impairments::default(), SEED);
+    let stats = ImpairmentStats::default();
+
+    channel.push(BODY, 100, &stats);
+    assert_eq!(channel.pop_due(100), encode_frame(BODY));
+    assert_eq!(channel.pop_due(100), None);
+}
+
+#[test]
+fn bandwidth_queues_frames_behind_each_other() {
+    let impairments = Impairments { bandwidth_bps: 8_000_000, latency_ms: 10, ..Impairments::default() }; // 1 μs a byte
+    let mut channel = Channel::new(impairments, SEED);
+    let stats = ImpairmentStats::default();
+    let wire_time = encode_frame(BODY).unwrap().len() as u64;
+
+    for _ in 0..3 {
+        channel.push(BODY, 0, &stats);
+    }
+
+    for n in 1..=3 {
+        assert_eq!(release_time(&mut channel, 0, 1_000).map(|(at, _)| at), Some(n * wire_time + 10));
+    }
+
+    channel.push(BODY, 10_000, &stats); // The line went idle in between, nothing left to queue behind
+    assert_eq!(release_time(&mut channel, 10_000, 11_000).map(|(at, _)| at), Some(10_000 + wire_time + 10));
+}
+
+#[test]
+fn jitter_stays_within_its_bound_and_keeps_order() {
+    let impairments = Impairments { latency_ms: 1_000, jitter_ms: 300, ..Impairments::default() };
+    let mut channel = Channel::new(impairments, SEED);
+    let stats = ImpairmentStats::default();
+    let mut delays = Vec::new();
+
+    for n in 0..20u64 {
+        let sent = n * 10_000; // Far enough apart that no frame waits on the one before
+        channel.push(&n.to_le_bytes(), sent, &stats);
+
+        let (at, frame) = release_time(&mut channel, sent, sent + 2_000).unwrap();
+        assert_eq!(Some(frame), encode_frame(&n.to_le_bytes()));
+        delays.push(at - sent);
+    }
+
+    assert!(delays.iter().all(|delay| (1_000..=1_300).contains(delay)), "{:?}", delays);
+    assert!(delays.iter().any(|&delay| delay != delays[0]), "{:?}", delays);
+
+    for n in 0..20u64 { // Sent together, each is held until the one before it is out
+        channel.push(&n.to_le_bytes(), 500_000, &stats);
+    }
+
+    let order: Vec<Vec<u8>> = std::iter::from_fn(|| release_time(&mut channel, 500_000, 502_000).map(|(_, frame)| frame)).collect();
+    assert_eq!(order, (0..20u64).map(|n| encode_frame(&n.to_le_bytes()).unwrap()).collect::<Vec<_>>());
+}
+
+#[test]
+fn reordered_frames_are_overtaken() {
+    let impairments = Impairments { latency_ms: 100, reorder_rate: 0.3, reorder_delay_ms: 500, ..Impairments::default() };
+    let mut channel = Channel::new(impairments, SEED);
+    let stats = ImpairmentStats::default();
+
+    for n in 0..50u64 {
+        channel.push(&n.to_le_bytes(), n, &stats);
+    }
+
+    let received: Vec<u64> = std::iter::from_fn(|| release_time(&mut channel, 0, 1_000))
+        .map(|(_, frame)| (0..50).find(|n: &u64| encode_frame(&n.to_le_bytes()).as_deref() == Some(frame.as_slice())).unwrap())
+        .collect();
+
+    let reordered = stats.reordered.load(Ordering::Relaxed) as usize;
+    assert!(reordered > 0 && reordered < 50, "{}", reordered);
+
+    let mut sorted = received.clone();
+    sorted.sort();
+    assert_eq!(sorted, (0..50).collect::<Vec<_>>()); // Late, never lost
+    assert_ne!(received, sorted);
+
+    let in_order: Vec<u64> = received[..50 - reordered].to_vec(); // The held back ones come out last
+    assert!(in_order.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", received);
+}
+
+#[test]
+fn bit_errors_flip_bits_on_the_wire() {
+    let clean = encode_frame(BODY).unwrap();
+
+    let mut channel = Channel::new(Impairments { bit_error_rate: 1.0, ..Impairments::default() }, SEED);
+    let stats = ImpairmentStats::default();
+    channel.push(BODY, 0, &stats);
+    assert_eq!(channel.pop_due(0), Some(clean.iter().map(|byte| !byte).collect()));
+    assert_eq!(stats.corrupted.load(Ordering::Relaxed), 1);
+
+    let mut channel = Channel::new(Impairments { bit_error_rate: 0.01, ..Impairments::default() }, SEED);
+    let stats = ImpairmentStats::default();
+    let mut flipped = 0;
+
+    for _ in 0..100 {
+        channel.push(BODY, 0, &stats);
+        let frame = channel.pop_due(0).unwrap();
+        flipped += frame.iter().zip(&clean).map(|(a, b)| (a ^ b).count_ones()).sum::<u32>();
+    }
+
+    let bits = 100 * clean.len() as u32 * 8;
+    assert!(flipped > bits / 200 && flipped < bits / 50, "{} of {}", flipped, bits); // Around 1%
+    assert!(stats.corrupted.load(Ordering::Relaxed) > 0);
+}
+
+#[test]
+fn the_same_seed_repeats_a_run() {
+    let impairments = Impairments { latency_ms: 100, jitter_ms: 50, loss_rate: 0.2, bit_error_rate: 0.001, duplicate_rate: 0.1, reorder_rate: 0.1, reorder_delay_ms: 200, bandwidth_bps: 0 };
+
+    let run = || {
+        let mut channel = Channel::new(impairments, SEED);
+        let stats = ImpairmentStats::default();
+
+        for n in 0..100u64 {
+            channel.push(&n.to_le_bytes(), n * 10, &stats);
+        }
+
+        std::iter::from_fn(|| release_time(&mut channel, 0, 5_000)).collect::<Vec<_>>()
+    };
+
+    assert_eq!(run(), run());
+}
@@ -0,0 +1,255 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use hmac::{Hmac, Mac};
+use serde::{Serialize, Deserialize};
+use sha2::Sha256;
+
+use crate::types::SatelliteMessage;
+
+pub const AUTH_KEY_LEN: usize = 32;
+pub const AUTH_TAG_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Shared uplink secret, deliberately not Debug so it never ends up in a log line
+#[derive(Clone)]
+pub struct AuthKey([u8; AUTH_KEY_LEN]);
+
+impl AuthKey {
+    pub fn new(bytes: [u8; AUTH_KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    // Key file = the key as hex on a single line
+    pub fn from_hex(text: &str) -> Option<Self> {
+        let text = text.trim();
+
+        if text.len() != AUTH_KEY_LEN * 2 || !text.is_ascii() {
+            return None;
+        }
+
+        let mut key = [0u8; AUTH_KEY_LEN];
+
+        for (idx, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&text[idx * 2..idx * 2 + 2], 16).ok()?;
+        }
+
+        Some(Self(key))
+    }
+
+    pub(crate) fn bytes(&self) -> &[u8; AUTH_KEY_LEN] {
+        &self.0
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+
+        Self::from_hex(&text).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("expected {} hex characters", AUTH_KEY_LEN * 2))
+        })
+    }
+}
+
+// Carried by every uplinked command, sync result and time correlation. The counter only ever increases so a captured command cannot be replayed
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub struct CommandAuth {
+    pub counter: u64,
+    pub tag: [u8; AUTH_TAG_LEN],
+}
+
+impl CommandAuth {
+    // Placeholder until the network thread signs the command right before it goes on the wire
+    pub const UNSIGNED: CommandAuth = CommandAuth { counter: 0, tag: [0; AUTH_TAG_LEN] };
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum AuthReject {
+    BadTag,
+    Replayed,
+}
+
+fn auth_mut(message: &mut SatelliteMessage) -> Option<&mut CommandAuth> {
+    match message {
+        SatelliteMessage::SyncResult { auth, .. }
+        | SatelliteMessage::Command { auth, .. }
+        | SatelliteMessage::TimeCorrelation { auth, .. } => Some(auth),
+        _ => None,
+    }
+}
+
+// HMAC-SHA256 over the big-endian counter followed by the bincode of the message with its auth left unsigned.
+// The variant index leads the bincode, so a tag taken off one kind of message never fits another
+fn message_mac(key: &AuthKey, message: &SatelliteMessage, counter: u64) -> Option<HmacSha256> {
+    let mut unsigned = *message;
+    *auth_mut(&mut unsigned)? = CommandAuth::UNSIGNED;
+
+    let mut mac = HmacSha256::new_from_slice(&key.0).expect("HMAC accepts any key length");
+
+    mac.update(&counter.to_be_bytes());
+    mac.update(&bincode::serialize(&unsigned).unwrap_or_default());
+
+    Some(mac)
+}
+
+pub fn message_tag(key: &AuthKey, message: &SatelliteMessage, counter: u64) -> Option<[u8; AUTH_TAG_LEN]> {
+    message_mac(key, message, counter).map(|mac| mac.finalize().into_bytes().into())
+}
+
+// Ground side, the counter must survive restarts so it is seeded from the wall clock by the caller
+pub struct CommandSigner {
+    key: AuthKey,
+    counter: u64,
+}
+
+impl CommandSigner {
+    pub fn new(key: AuthKey, first_counter: u64) -> Self {
+        Self { key, counter: first_counter }
+    }
+
+    // Never lets the next counter fall below floor. Stations sharing a satellite sign from their wall clocks,
+    // so whichever one uplinks later also holds the higher counter
+    pub fn advance_to(&mut self, floor: u64) {
+        self.counter = self.counter.max(floor);
+    }
+
+    // Signs in place every message that carries a CommandAuth, anything else is left untouched
+    pub fn sign(&mut self, message: &mut SatelliteMessage) {
+        let Some(tag) = message_tag(&self.key, message, self.counter) else {
+            return;
+        };
+
+        let signed = CommandAuth { counter: self.counter, tag };
+        self.counter += 1;
+
+        if let Some(auth) = auth_mut(message) {
+            *auth = signed;
+        }
+    }
+}
+
+// Satellite side, remembers the highest counter accepted so far. Only a persistent one keeps it across a restart,
+// otherwise every command captured before the restart is accepted again
+pub struct CommandVerifier {
+    key: AuthKey,
+    pub last_counter: u64,
+    store: Option<CounterStore>,
+}
+
+impl CommandVerifier {
+    pub fn new(key: AuthKey) -> Self {
+        Self { key, last_counter: 0, store: None }
+    }
+
+    // Starts from the counter the last run left in counter_path, a missing file is a first boot
+    pub fn persistent<P: AsRef<Path>>(key: AuthKey, counter_path: P) -> io::Result<Self> {
+        let counter_path = counter_path.as_ref().to_path_buf();
+
+        let last_counter = match std::fs::read_to_string(&counter_path) {
+            Ok(text) => text.trim().parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "expected a decimal counter"))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self { key, last_counter, store: Some(CounterStore::start(counter_path, last_counter)?) })
+    }
+
+    // Commands and anything that moves onboard time must be signed, the rest carry no authority and always pass
+    pub fn verify(&mut self, message: &SatelliteMessage) -> Result<(), AuthReject> {
+        let Some(auth) = message.auth() else {
+            return Ok(());
+        };
+
+        if message_mac(&self.key, message, auth.counter).is_none_or(|mac| mac.verify_slice(&auth.tag).is_err()) {
+            return Err(AuthReject::BadTag);
+        }
+
+        if auth.counter <= self.last_counter {
+            return Err(AuthReject::Replayed);
+        }
+
+        self.last_counter = auth.counter;
+
+        if let Some(store) = &self.store {
+            store.save(self.last_counter);
+        }
+
+        Ok(())
+    }
+
+    // Counter writes that failed, a restart may accept again whatever was signed since the last good one
+    pub fn store_failures(&self) -> u32 {
+        self.store.as_ref().map_or(0, |store| store.failures.load(Ordering::Relaxed))
+    }
+}
+
+// Writes the accepted counter from its own thread so verify never waits on the disk. A burst of commands
+// only costs the writer one write, and dropping the store writes whatever is still pending before it returns
+struct CounterStore {
+    accepted: Arc<AtomicU64>,
+    failures: Arc<AtomicU32>,
+    wake: Option<SyncSender<()>>, // Dropped to stop the writer
+    writer: Option<JoinHandle<()>>,
+}
+
+impl CounterStore {
+    fn start(counter_path: PathBuf, written: u64) -> io::Result<Self> {
+        let accepted = Arc::new(AtomicU64::new(written));
+        let failures = Arc::new(AtomicU32::new(0));
+        let (wake, woken) = mpsc::sync_channel(1);
+
+        let (pending, failed) = (Arc::clone(&accepted), Arc::clone(&failures));
+        let writer = thread::Builder::new()
+            .name("uplink-counter".to_string())
+            .spawn(move || write_counters(&counter_path, written, &pending, &failed, woken))?;
+
+        Ok(Self { accepted, failures, wake: Some(wake), writer: Some(writer) })
+    }
+
+    fn save(&self, counter: u64) {
+        self.accepted.store(counter, Ordering::Release);
+
+        if let Some(wake) = &self.wake {
+            wake.try_send(()).ok(); // Full means the writer has yet to look, it will see this counter too
+        }
+    }
+}
+
+impl Drop for CounterStore {
+    fn drop(&mut self) {
+        self.wake.take();
+
+        if let Some(writer) = self.writer.take() {
+            writer.join().ok();
+        }
+    }
+}
+
+fn write_counters(counter_path: &Path, mut written: u64, accepted: &AtomicU64, failures: &AtomicU32, woken: Receiver<()>) {
+    loop {
+        let stopping = woken.recv().is_err();
+        let counter = accepted.load(Ordering::Acquire);
+
+        if counter != written {
+            match store_counter(counter_path, counter) {
+                Ok(()) => written = counter,
+                Err(_) => { failures.fetch_add(1, Ordering::Relaxed); } // The command itself is genuine, refusing it would only lock the ground out
+            }
+        }
+
+        if stopping {
+            return;
+        }
+    }
+}
+
+// Written aside and renamed over, so a crash mid-write never leaves a truncated counter behind
+fn store_counter(counter_path: &Path, counter: u64) -> io::Result<()> {
+    let staged = counter_path.with_extension("tmp");
+    std::fs::write(&staged, counter.to_string())?;
+    std::fs::rename(&staged, counter_path)
+}
@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+use serde::{Serialize, Deserialize};
+
+pub const CLOCK_SYNC_WINDOW: usize = 16; // Exchanges the fit runs over, older ones fall out
+pub const MIN_DRIFT_SAMPLES: usize = 4; // Below this the offset is taken from the best exchange and drift is left at 0
+pub const RTT_OUTLIER_FACTOR: u64 = 3; // Times the median round trip, a slower exchange was queued somewhere on one leg
+pub const RTT_OUTLIER_LIMIT: u32 = 4; // Rejected in a row, then the link itself got slower and the next one is kept
+pub const CLOCK_STEP_DRIFT_PPB: u64 = 200_000; // Drift the fit may have missed since the last exchange, past any crystal's temperature swing
+
+const PPB: f64 = 1e9;
+
+// One four-timestamp exchange, ground_sent and ground_receive on the ground clock, the other two on the satellite's
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncExchange {
+    pub ground_sent: u64, // t1
+    pub satellite_receive: u64, // t2
+    pub satellite_send: u64, // t3
+    pub ground_receive: u64, // t4
+}
+
+impl SyncExchange {
+    // Ground clock minus satellite clock, exact when both legs take as long
+    pub fn offset_us(&self) -> i64 {
+        let uplink = self.ground_sent as i128 - self.satellite_receive as i128;
+        let downlink = self.ground_receive as i128 - self.satellite_send as i128;
+        ((uplink + downlink) / 2) as i64
+    }
+
+    // Time spent on the link, the satellite's turnaround taken out
+    pub fn rtt_us(&self) -> u64 {
+        self.ground_receive.saturating_sub(self.ground_sent)
+            .saturating_sub(self.satellite_send.saturating_sub(self.satellite_receive))
+    }
+
+    fn midpoint_us(&self) -> u64 {
+        self.satellite_receive / 2 + self.satellite_send / 2
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum SyncQuality {
+    Unsynced, // No exchange yet, timestamps are the satellite's own clock
+    Coarse, // Offset only
+    Locked, // Offset and drift, and the fit agrees with every exchange to within its round trip
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockEstimate {
+    pub offset_us: i64, // Ground minus satellite at reference_us
+    pub drift_ppb: i64, // How fast the offset grows, in ns per second of satellite clock
+    pub reference_us: u64, // Satellite clock
+    pub error_us: u64, // Spread of the exchanges around the fit plus half the best round trip
+    pub quality: SyncQuality,
+}
+
+impl ClockEstimate {
+    pub const UNSYNCED: Self = Self { offset_us: 0, drift_ppb: 0, reference_us: 0, error_us: 0, quality: SyncQuality::Unsynced };
+
+    // Satellite clock to ground clock, extrapolating the drift from the last fit
+    pub fn ground_time(&self, local_us: u64) -> u64 {
+        let elapsed = local_us as i128 - self.reference_us as i128;
+        let corrected = local_us as i128 + self.offset_us as i128 + elapsed * self.drift_ppb as i128 / PPB as i128;
+        corrected.max(0) as u64
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ClockSample {
+    local_us: u64,
+    offset_us: i64,
+    rtt_us: u64,
+}
+
+// Least-squares line through the offsets of recent exchanges, slow ones are left out before they can skew it
+#[derive(Debug, Clone)]
+pub struct ClockEstimator {
+    samples: VecDeque<ClockSample>,
+    estimate: ClockEstimate,
+    rejected_in_a_row: u32,
+    pub rejected: u32,
+    pub steps: u32, // Times the fit started over after the clock jumped
+}
+
+impl Default for ClockEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClockEstimator {
+    pub fn new() -> Self {
+        Self {
+            samples: VecDeque::with_capacity(CLOCK_SYNC_WINDOW),
+            estimate: ClockEstimate::UNSYNCED,
+            rejected_in_a_row: 0,
+            rejected: 0,
+            steps: 0,
+        }
+    }
+
+    // False if the exchange was rejected as an outlier
+    pub fn add(&mut self, exchange: &SyncExchange) -> bool {
+        let rtt_us = exchange.rtt_us();
+
+        if self.samples.len() >= MIN_DRIFT_SAMPLES
+            && rtt_us > self.median_rtt_us().max(1) * RTT_OUTLIER_FACTOR
+            && self.rejected_in_a_row < RTT_OUTLIER_LIMIT {
+            self.rejected_in_a_row += 1;
+            self.rejected += 1;
+            return false;
+        }
+
+        self.rejected_in_a_row = 0;
+
+        let midpoint_us = exchange.midpoint_us();
+
+        if self.samples.back().is_some_and(|last| self.is_step(last, midpoint_us, exchange.offset_us(), rtt_us)) {
+            self.samples.clear(); // Nothing before the jump fits the clock after it
+            self.steps += 1;
+        }
+
+        if self.samples.len() == CLOCK_SYNC_WINDOW {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(ClockSample {
+            local_us: midpoint_us,
+            offset_us: exchange.offset_us(),
+            rtt_us,
+        });
+
+        self.estimate = self.fit();
+        true
+    }
+
+    pub fn estimate(&self) -> ClockEstimate {
+        self.estimate
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples.len()
+    }
+
+    // Each offset is only known to within half its round trip, a change past both and the drift means the clock jumped
+    fn is_step(&self, last: &ClockSample, local_us: u64, offset_us: i64, rtt_us: u64) -> bool {
+        let elapsed_us = local_us.abs_diff(last.local_us) as i128;
+        let drift_ppb = if self.estimate.quality == SyncQuality::Locked { self.estimate.drift_ppb as i128 } else { 0 }; // A coarse slope can be anything
+        let expected_us = last.offset_us as i128 + (local_us as i128 - last.local_us as i128) * drift_ppb / PPB as i128;
+        let tolerance_us = (rtt_us / 2 + last.rtt_us / 2) as i128 + elapsed_us * CLOCK_STEP_DRIFT_PPB as i128 / PPB as i128;
+
+        (offset_us as i128 - expected_us).abs() > tolerance_us
+    }
+
+    fn median_rtt_us(&self) -> u64 {
+        let mut rtts: Vec<u64> = self.samples.iter().map(|sample| sample.rtt_us).collect();
+        rtts.sort_unstable();
+        rtts[rtts.len() / 2]
+    }
+
+    fn fit(&self) -> ClockEstimate {
+        let reference_us = self.samples.back().map_or(0, |sample| sample.local_us);
+        let best = self.samples.iter().min_by_key(|sample| sample.rtt_us).copied().expect("fit runs after a sample is added");
+
+        let points: Vec<(f64, f64)> = self.samples.iter()
+            .map(|sample| (sample.local_us as f64 - reference_us as f64, sample.offset_us as f64))
+            .collect();
+
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        let sxy: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+
+        let (offset, slope) = if points.len() >= MIN_DRIFT_SAMPLES && sxx > 0.0 {
+            let slope = sxy / sxx;
+            (mean_y - slope * mean_x, slope)
+        } else {
+            (best.offset_us as f64, 0.0) // Too few exchanges to tell drift from noise, trust the fastest one
+        };
+
+        let rms = (points.iter().map(|(x, y)| (y - (offset + slope * x)).powi(2)).sum::<f64>() / n).sqrt();
+
+        let quality = if points.len() >= MIN_DRIFT_SAMPLES && rms <= self.median_rtt_us() as f64 / 2.0 {
+            SyncQuality::Locked
+        } else {
+            SyncQuality::Coarse
+        };
+
+        ClockEstimate {
+            offset_us: offset.round() as i64,
+            drift_ppb: (slope * PPB).round() as i64,
+            reference_us,
+            error_us: rms.round() as u64 + best.rtt_us / 2,
+            quality,
+        }
+    }
+}
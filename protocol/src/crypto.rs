@@ -0,0 +1,167 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::auth::AuthKey;
+use crate::handshake::{Capabilities, Hello};
+
+// Added to SUPPORTED_CAPABILITIES, so a build without the feature never offers encryption
+pub const ENCRYPTION_SUPPORT: Capabilities = if cfg!(feature = "encryption") {
+    Capabilities::ENCRYPTION
+} else {
+    Capabilities::NONE
+};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum LinkRole {
+    Satellite,
+    Ground,
+}
+
+// Session key = HMAC-SHA256(uplink key, label + satellite nonce + ground nonce), new for every pass
+fn session_key(key: &AuthKey, satellite_nonce: u64, ground_nonce: u64) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.bytes()).expect("HMAC accepts any key length");
+
+    mac.update(b"RTS link encryption");
+    mac.update(&satellite_nonce.to_be_bytes());
+    mac.update(&ground_nonce.to_be_bytes());
+
+    mac.finalize().into_bytes().into()
+}
+
+// Sits between the packet codec and the frame layer. Clear passes hand bodies through untouched
+pub struct LinkCipher(Option<aead::SessionCipher>);
+
+impl LinkCipher {
+    pub fn clear() -> Self {
+        Self(None)
+    }
+
+    pub fn negotiate(capabilities: Capabilities, key: &AuthKey, local: &Hello, peer: &Hello, role: LinkRole) -> Self {
+        if !capabilities.contains(Capabilities::ENCRYPTION) {
+            return Self::clear();
+        }
+
+        let (satellite, ground) = match role {
+            LinkRole::Satellite => (local, peer),
+            LinkRole::Ground => (peer, local),
+        };
+
+        Self(aead::SessionCipher::new(&session_key(key, satellite.session_nonce, ground.session_nonce), role))
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn seal(&mut self, plaintext: Vec<u8>) -> Option<Vec<u8>> {
+        match &mut self.0 {
+            Some(cipher) => cipher.seal(&plaintext),
+            None => Some(plaintext),
+        }
+    }
+
+    // None when the tag does not verify or the frame sequence number went backwards
+    pub fn open(&mut self, body: Vec<u8>) -> Option<Vec<u8>> {
+        match &mut self.0 {
+            Some(cipher) => cipher.open(&body),
+            None => Some(body),
+        }
+    }
+}
+
+// Sealed body = big-endian frame sequence number (8) + ciphertext + Poly1305 tag (16)
+// Nonce = direction (1) + zero padding (3) + frame sequence number (8)
+#[cfg(feature = "encryption")]
+mod aead {
+    use chacha20poly1305::aead::{Aead, KeyInit};
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+    use super::LinkRole;
+
+    const SEQUENCE_LEN: usize = 8;
+
+    pub struct SessionCipher {
+        cipher: ChaCha20Poly1305,
+        role: LinkRole,
+        next_send: u64,
+        next_receive: u64,
+    }
+
+    // First byte of the nonce keeps the two directions apart under the same session key
+    fn nonce(sender: LinkRole, sequence: u64) -> Nonce {
+        let mut nonce = [0u8; 12];
+        nonce[0] = match sender {
+            LinkRole::Satellite => 0x01,
+            LinkRole::Ground => 0x02,
+        };
+        nonce[4..].copy_from_slice(&sequence.to_be_bytes());
+        Nonce::from(nonce)
+    }
+
+    impl SessionCipher {
+        pub fn new(key: &[u8; 32], role: LinkRole) -> Option<Self> {
+            Some(Self {
+                cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+                role,
+                next_send: 0,
+                next_receive: 0,
+            })
+        }
+
+        pub fn seal(&mut self, plaintext: &[u8]) -> Option<Vec<u8>> {
+            let sequence = self.next_send;
+            let ciphertext = self.cipher.encrypt(&nonce(self.role, sequence), plaintext).ok()?;
+            self.next_send += 1;
+
+            let mut body = Vec::with_capacity(SEQUENCE_LEN + ciphertext.len());
+            body.extend_from_slice(&sequence.to_be_bytes());
+            body.extend_from_slice(&ciphertext);
+
+            Some(body)
+        }
+
+        pub fn open(&mut self, body: &[u8]) -> Option<Vec<u8>> {
+            if body.len() < SEQUENCE_LEN {
+                return None;
+            }
+
+            let sequence = u64::from_be_bytes(body[..SEQUENCE_LEN].try_into().unwrap());
+
+            if sequence < self.next_receive {
+                return None;
+            }
+
+            let sender = match self.role {
+                LinkRole::Satellite => LinkRole::Ground,
+                LinkRole::Ground => LinkRole::Satellite,
+            };
+
+            let plaintext = self.cipher.decrypt(&nonce(sender, sequence), &body[SEQUENCE_LEN..]).ok()?;
+            self.next_receive = sequence + 1; // Gaps are fine, the frame layer may have dropped a corrupted one
+
+            Some(plaintext)
+        }
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+mod aead {
+    use super::LinkRole;
+
+    // Never constructed, ENCRYPTION is not offered without the feature
+    pub enum SessionCipher {}
+
+    impl SessionCipher {
+        pub fn new(_key: &[u8; 32], _role: LinkRole) -> Option<Self> {
+            None
+        }
+
+        pub fn seal(&mut self, _plaintext: &[u8]) -> Option<Vec<u8>> {
+            match *self {}
+        }
+
+        pub fn open(&mut self, _body: &[u8]) -> Option<Vec<u8>> {
+            match *self {}
+        }
+    }
+}
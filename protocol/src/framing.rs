@@ -0,0 +1,173 @@
+use std::io::{Read, Write};
+
+// Frame = SYNC_MARKER (4) + big-endian length (2) + body + big-endian CRC-32 of length and body (4)
+pub const SYNC_MARKER: [u8; 4] = [0x1A, 0xCF, 0xFC, 0x1D]; // CCSDS attached sync marker
+pub const MAX_FRAME_BODY: usize = 4096; // Anything longer is a corrupted length prefix
+
+const HEADER_LEN: usize = SYNC_MARKER.len() + 2;
+const CRC_LEN: usize = 4;
+pub const FRAME_OVERHEAD: usize = HEADER_LEN + CRC_LEN; // Wire bytes on top of the body
+const READ_CHUNK: usize = 512;
+
+const CRC32_TABLE: [u32; 256] = build_crc32_table();
+
+const fn build_crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+// CRC-32 (IEEE 802.3), same as zlib/PNG
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+
+    for &byte in bytes {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+
+    !crc
+}
+
+// None for a body over MAX_FRAME_BODY, the peer's decoder would take its length for a corrupted one
+pub fn encode_frame(body: &[u8]) -> Option<Vec<u8>> {
+    if body.len() > MAX_FRAME_BODY {
+        return None;
+    }
+
+    let mut frame = Vec::with_capacity(HEADER_LEN + body.len() + CRC_LEN);
+
+    frame.extend_from_slice(&SYNC_MARKER);
+    frame.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    frame.extend_from_slice(body);
+
+    let crc = crc32(&frame[SYNC_MARKER.len()..]);
+    frame.extend_from_slice(&crc.to_be_bytes());
+
+    Some(frame)
+}
+
+pub fn write_frame<W: Write>(writer: &mut W, body: &[u8]) -> bool {
+    encode_frame(body).is_some_and(|frame| writer.write_all(&frame).is_ok())
+}
+
+// Reassembles frames from a byte stream, hunting for the next SYNC_MARKER whenever a frame is bad
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    hunting: bool,
+    pub crc_failures: u32,
+    pub resyncs: u32, // Times sync was regained after bytes had to be skipped
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn next_frame(&mut self) -> Option<Vec<u8>> {
+        loop {
+            match find_marker(&self.buffer) {
+                Some(0) => {}
+                Some(idx) => {
+                    self.buffer.drain(..idx);
+                    self.hunting = true;
+                }
+                None => {
+                    // Keep a tail that could be the start of a marker split across reads
+                    let keep = self.buffer.len().min(SYNC_MARKER.len() - 1);
+                    let discard = self.buffer.len() - keep;
+
+                    if discard > 0 {
+                        self.buffer.drain(..discard);
+                        self.hunting = true;
+                    }
+                    return None;
+                }
+            }
+
+            if self.buffer.len() < HEADER_LEN {
+                return None;
+            }
+
+            let length = u16::from_be_bytes([self.buffer[4], self.buffer[5]]) as usize;
+
+            if length > MAX_FRAME_BODY {
+                self.skip_marker();
+                continue;
+            }
+
+            let frame_len = HEADER_LEN + length + CRC_LEN;
+            if self.buffer.len() < frame_len {
+                return None;
+            }
+
+            let crc_offset = HEADER_LEN + length;
+            let expected = u32::from_be_bytes([
+                self.buffer[crc_offset],
+                self.buffer[crc_offset + 1],
+                self.buffer[crc_offset + 2],
+                self.buffer[crc_offset + 3],
+            ]);
+
+            if crc32(&self.buffer[SYNC_MARKER.len()..crc_offset]) != expected {
+                self.crc_failures += 1;
+                self.skip_marker();
+                continue;
+            }
+
+            let body = self.buffer[HEADER_LEN..crc_offset].to_vec();
+            self.buffer.drain(..frame_len);
+
+            if self.hunting {
+                self.hunting = false;
+                self.resyncs += 1;
+            }
+
+            return Some(body);
+        }
+    }
+
+    // Reads whatever the stream has ready, None on timeout, EOF or error with no complete frame buffered
+    pub fn read_frame<R: Read>(&mut self, reader: &mut R) -> Option<Vec<u8>> {
+        let mut chunk = [0u8; READ_CHUNK];
+
+        loop {
+            if let Some(frame) = self.next_frame() {
+                return Some(frame);
+            }
+
+            match reader.read(&mut chunk) {
+                Ok(0) | Err(_) => return None,
+                Ok(n) => self.push(&chunk[..n]),
+            }
+        }
+    }
+
+    // The marker at the front belongs to a bad frame, drop one byte so the search moves past it
+    fn skip_marker(&mut self) {
+        self.buffer.drain(..1);
+        self.hunting = true;
+    }
+}
+
+fn find_marker(buffer: &[u8]) -> Option<usize> {
+    buffer.windows(SYNC_MARKER.len()).position(|window| window == SYNC_MARKER)
+}
@@ -0,0 +1,87 @@
+use serde::{Serialize, Deserialize};
+
+// Bumped whenever a change to the wire types breaks decoding on an older peer
+pub const PROTOCOL_VERSION: u16 = 18;
+
+// Optional link features, negotiated per pass so each side can roll them out independently
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Capabilities(pub u32);
+
+impl Capabilities {
+    pub const NONE: Capabilities = Capabilities(0);
+    pub const COMPRESSION: Capabilities = Capabilities(1 << 0);
+    pub const AUTH: Capabilities = Capabilities(1 << 1);
+    pub const BATCHING: Capabilities = Capabilities(1 << 2);
+    pub const SPACE_PACKETS: Capabilities = Capabilities(1 << 3); // CCSDS Space Packets instead of bincode after the handshake
+    pub const ENCRYPTION: Capabilities = Capabilities(1 << 4); // Only offered by builds with the encryption feature
+
+    pub const fn union(self, other: Capabilities) -> Capabilities {
+        Capabilities(self.0 | other.0)
+    }
+
+    pub const fn intersection(self, other: Capabilities) -> Capabilities {
+        Capabilities(self.0 & other.0)
+    }
+
+    pub const fn contains(self, other: Capabilities) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum HandshakeReject {
+    VersionMismatch,
+    SpacecraftMismatch,
+    MissingCapabilities,
+    Timeout,
+    Malformed,
+    AlreadyConnected, // The ground is already in a pass with this spacecraft ID
+}
+
+// First frame of every pass, Satellite -> Ground
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Hello {
+    pub version: u16,
+    pub spacecraft_id: u16,
+    pub capabilities: Capabilities,
+    pub session_nonce: u64, // Fresh every pass, mixed into the per-pass encryption key
+    pub next_sequence_no: u32, // The next number this side puts on a fresh packet, the peer unwraps 14-bit space packet counts from it
+}
+
+// Reply to Hello, Ground -> Satellite. Capabilities are the negotiated set when accepted
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct HelloAck {
+    pub hello: Hello,
+    pub rejected: Option<HandshakeReject>,
+}
+
+impl Hello {
+    pub fn new(spacecraft_id: u16, capabilities: Capabilities) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            spacecraft_id,
+            capabilities,
+            session_nonce: rand::random(),
+            next_sequence_no: 0,
+        }
+    }
+
+    // Checks the peer against this side's Hello, returns the capabilities both sides share
+    pub fn negotiate(&self, peer: &Hello, required: Capabilities) -> Result<Capabilities, HandshakeReject> {
+        if peer.version != self.version {
+            return Err(HandshakeReject::VersionMismatch);
+        }
+
+        if peer.spacecraft_id != self.spacecraft_id {
+            return Err(HandshakeReject::SpacecraftMismatch);
+        }
+
+        let shared = self.capabilities.intersection(peer.capabilities);
+
+        if !shared.contains(required) {
+            return Err(HandshakeReject::MissingCapabilities);
+        }
+
+        Ok(shared)
+    }
+}
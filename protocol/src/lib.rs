@@ -0,0 +1,33 @@
+// Wire types shared by the satellite and the ground station.
+// bincode encodes enums by variant index, so both binaries MUST use these definitions.
+pub mod types;
+pub mod handshake;
+pub mod framing;
+pub mod space_packet;
+pub mod auth;
+pub mod crypto;
+pub mod recovery;
+pub mod transport;
+pub mod rate;
+pub mod orbit;
+pub mod clock_sync;
+pub mod oscillator;
+pub mod time_correlation;
+pub mod sequence;
+pub mod parameter;
+
+pub use types::*;
+pub use handshake::*;
+pub use framing::*;
+pub use space_packet::*;
+pub use auth::*;
+pub use crypto::*;
+pub use recovery::*;
+pub use transport::*;
+pub use rate::*;
+pub use orbit::*;
+pub use clock_sync::*;
+pub use oscillator::*;
+pub use time_correlation::*;
+pub use sequence::*;
+pub use parameter::*;
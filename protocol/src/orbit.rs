@@ -0,0 +1,348 @@
+// Two-body orbit with the J2 secular drift of the node, perigee and mean anomaly, the same model on both ends
+// so the satellite's view of a pass and the ground's prediction of it agree
+use std::f64::consts::{PI, TAU};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+pub const EARTH_MU_KM3_S2: f64 = 398_600.441_8;
+pub const EARTH_RADIUS_KM: f64 = 6378.137; // WGS84 equatorial
+const EARTH_FLATTENING: f64 = 1.0 / 298.257_223_563;
+const EARTH_ROTATION_RAD_S: f64 = 7.292_115_146_7e-5;
+const J2: f64 = 1.082_626_68e-3;
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+const PASS_SEARCH_STEP_S: f64 = 10.0; // Simulated, shorter passes than this can be stepped over
+const PASS_EDGE_TOLERANCE_S: f64 = 0.01;
+
+// Mean elements at the epoch
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OrbitalElements {
+    pub semi_major_axis_km: f64,
+    pub eccentricity: f64,
+    pub inclination_deg: f64,
+    pub raan_deg: f64,
+    pub arg_perigee_deg: f64,
+    pub mean_anomaly_deg: f64,
+}
+
+impl Default for OrbitalElements {
+    fn default() -> Self {
+        Self {
+            semi_major_axis_km: EARTH_RADIUS_KM + 500.0,
+            eccentricity: 0.001,
+            inclination_deg: 51.6,
+            raan_deg: 0.0,
+            arg_perigee_deg: 0.0,
+            mean_anomaly_deg: 0.0,
+        }
+    }
+}
+
+// Geodetic position of a ground station and the elevation its antenna can track down to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GroundSite {
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+    pub min_elevation_deg: f64,
+}
+
+impl Default for GroundSite {
+    fn default() -> Self {
+        Self { latitude_deg: 40.0, longitude_deg: 0.0, min_elevation_deg: 10.0 }
+    }
+}
+
+impl GroundSite {
+    fn ecef_and_up(&self) -> ([f64; 3], [f64; 3]) {
+        let (lat, lon) = (self.latitude_deg.to_radians(), self.longitude_deg.to_radians());
+        let e2 = EARTH_FLATTENING * (2.0 - EARTH_FLATTENING);
+        let n = EARTH_RADIUS_KM / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+        let up = [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()];
+
+        ([n * up[0], n * up[1], n * (1.0 - e2) * up[2]], up)
+    }
+}
+
+// All times in and out are wall clock μs since the Unix epoch, time_scale stretches them into simulated seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pass {
+    pub aos_us: u64,
+    pub los_us: u64,
+    pub max_elevation_deg: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orbit {
+    elements: OrbitalElements,
+    epoch_unix_s: f64, // When the elements hold, simulated time starts counting here
+    time_scale: f64, // Simulated seconds per wall clock second
+    anomaly_rate: f64, // rad/s including the J2 drift
+    raan_rate: f64,
+    arg_perigee_rate: f64,
+    gmst_at_epoch: f64,
+}
+
+impl Orbit {
+    pub fn new(elements: OrbitalElements, epoch_unix_s: f64, time_scale: f64) -> Result<Self, String> {
+        if !(0.0..1.0).contains(&elements.eccentricity) { // First, past 1 the perigee below would read as negative
+            return Err(format!("eccentricity must be in [0, 1), got {}", elements.eccentricity));
+        }
+
+        if elements.semi_major_axis_km * (1.0 - elements.eccentricity) <= EARTH_RADIUS_KM {
+            return Err(format!("perigee of a {} km, e {} orbit is below the surface", elements.semi_major_axis_km, elements.eccentricity));
+        }
+
+        if !(time_scale > 0.0 && time_scale.is_finite()) {
+            return Err(format!("time_scale must be positive, got {}", time_scale));
+        }
+
+        let a = elements.semi_major_axis_km;
+        let e = elements.eccentricity;
+        let cos_i = elements.inclination_deg.to_radians().cos();
+        let mean_motion = (EARTH_MU_KM3_S2 / a.powi(3)).sqrt();
+        let j2_term = 0.75 * mean_motion * J2 * (EARTH_RADIUS_KM / (a * (1.0 - e * e))).powi(2);
+
+        Ok(Self {
+            elements,
+            epoch_unix_s,
+            time_scale,
+            anomaly_rate: mean_motion + j2_term * (1.0 - e * e).sqrt() * (3.0 * cos_i * cos_i - 1.0),
+            raan_rate: -2.0 * j2_term * cos_i,
+            arg_perigee_rate: j2_term * (5.0 * cos_i * cos_i - 1.0),
+            gmst_at_epoch: gmst(epoch_unix_s),
+        })
+    }
+
+    // NORAD two-line elements, taken as mean elements for the model above rather than run through SGP4
+    pub fn from_tle(line1: &str, line2: &str, time_scale: f64) -> Result<Self, String> {
+        for (number, line) in [(1, line1), (2, line2)] {
+            let line = line.trim_end();
+
+            if line.len() != 69 || !line.is_ascii() || !line.starts_with(&number.to_string()) {
+                return Err(format!("TLE line {} must be 69 characters starting with {}", number, number));
+            }
+
+            let checksum = line[..68].bytes().fold(0u32, |sum, byte| match byte {
+                b'0'..=b'9' => sum + (byte - b'0') as u32,
+                b'-' => sum + 1,
+                _ => sum,
+            });
+
+            if line[68..].parse::<u32>() != Ok(checksum % 10) {
+                return Err(format!("TLE line {} fails its checksum", number));
+            }
+        }
+
+        let field = |line: &str, range: std::ops::Range<usize>, name: &str| -> Result<f64, String> {
+            line[range].trim().parse::<f64>().map_err(|_| format!("TLE {} is not a number", name))
+        };
+
+        let epoch_year = field(line1, 18..20, "epoch year")? as i64;
+        let epoch_day = field(line1, 20..32, "epoch day")?;
+        let year = if epoch_year < 57 { 2000 + epoch_year } else { 1900 + epoch_year };
+
+        let revs_per_day = field(line2, 52..63, "mean motion")?;
+        let mean_motion = revs_per_day * TAU / SECONDS_PER_DAY;
+
+        let elements = OrbitalElements {
+            semi_major_axis_km: (EARTH_MU_KM3_S2 / (mean_motion * mean_motion)).cbrt(),
+            eccentricity: field(line2, 26..33, "eccentricity")? * 1e-7, // Leading decimal point implied
+            inclination_deg: field(line2, 8..16, "inclination")?,
+            raan_deg: field(line2, 17..25, "RAAN")?,
+            arg_perigee_deg: field(line2, 34..42, "argument of perigee")?,
+            mean_anomaly_deg: field(line2, 43..51, "mean anomaly")?,
+        };
+
+        let epoch_unix_s = (days_from_civil(year, 1, 1) as f64 + epoch_day - 1.0) * SECONDS_PER_DAY;
+
+        Self::new(elements, epoch_unix_s, time_scale)
+    }
+
+    pub fn elements(&self) -> &OrbitalElements {
+        &self.elements
+    }
+
+    // One revolution in wall clock μs
+    pub fn period_us(&self) -> u64 {
+        (TAU / self.anomaly_rate / self.time_scale * 1e6) as u64
+    }
+
+    fn simulated_s(&self, unix_us: u64) -> f64 {
+        (unix_us as f64 * 1e-6 - self.epoch_unix_s) * self.time_scale
+    }
+
+    // Rounded up, so both the AOS and the LOS edge stay on the side next_pass found them on
+    fn wall_clock_us(&self, simulated_s: f64) -> u64 {
+        ((simulated_s / self.time_scale + self.epoch_unix_s) * 1e6).ceil().max(0.0) as u64
+    }
+
+    // Earth-fixed position in km, t in simulated seconds since the epoch
+    fn position_ecef(&self, t: f64) -> [f64; 3] {
+        let e = self.elements.eccentricity;
+        let mean_anomaly = (self.elements.mean_anomaly_deg.to_radians() + self.anomaly_rate * t).rem_euclid(TAU);
+
+        let mut eccentric_anomaly = if e < 0.8 { mean_anomaly } else { PI };
+        for _ in 0..10 { // Newton on Kepler's equation, converged well before this for LEO eccentricities
+            eccentric_anomaly -= (eccentric_anomaly - e * eccentric_anomaly.sin() - mean_anomaly) / (1.0 - e * eccentric_anomaly.cos());
+        }
+
+        let radius = self.elements.semi_major_axis_km * (1.0 - e * eccentric_anomaly.cos());
+        let true_anomaly = ((1.0 - e * e).sqrt() * eccentric_anomaly.sin()).atan2(eccentric_anomaly.cos() - e);
+
+        let raan = self.elements.raan_deg.to_radians() + self.raan_rate * t;
+        let latitude_arg = self.elements.arg_perigee_deg.to_radians() + self.arg_perigee_rate * t + true_anomaly;
+        let inclination = self.elements.inclination_deg.to_radians();
+
+        let inertial = [
+            radius * (raan.cos() * latitude_arg.cos() - raan.sin() * latitude_arg.sin() * inclination.cos()),
+            radius * (raan.sin() * latitude_arg.cos() + raan.cos() * latitude_arg.sin() * inclination.cos()),
+            radius * latitude_arg.sin() * inclination.sin(),
+        ];
+
+        let earth_angle = self.gmst_at_epoch + EARTH_ROTATION_RAD_S * t;
+
+        [
+            inertial[0] * earth_angle.cos() + inertial[1] * earth_angle.sin(),
+            -inertial[0] * earth_angle.sin() + inertial[1] * earth_angle.cos(),
+            inertial[2],
+        ]
+    }
+
+    fn elevation_at(&self, site: &GroundSite, t: f64) -> f64 {
+        let satellite = self.position_ecef(t);
+        let (station, up) = site.ecef_and_up();
+        let range = [satellite[0] - station[0], satellite[1] - station[1], satellite[2] - station[2]];
+        let distance = (range[0] * range[0] + range[1] * range[1] + range[2] * range[2]).sqrt();
+
+        ((range[0] * up[0] + range[1] * up[1] + range[2] * up[2]) / distance).asin().to_degrees()
+    }
+
+    pub fn elevation_deg(&self, site: &GroundSite, unix_us: u64) -> f64 {
+        self.elevation_at(site, self.simulated_s(unix_us))
+    }
+
+    pub fn is_visible(&self, site: &GroundSite, unix_us: u64) -> bool {
+        self.elevation_deg(site, unix_us) >= site.min_elevation_deg
+    }
+
+    // First pass still in progress at or starting after from_us, None if nothing rises within horizon_us.
+    // A pass already underway reports from_us as its AOS
+    pub fn next_pass(&self, site: &GroundSite, from_us: u64, horizon_us: u64) -> Option<Pass> {
+        let start = self.simulated_s(from_us);
+        let end = start + horizon_us as f64 * 1e-6 * self.time_scale;
+        let above = |t: f64| self.elevation_at(site, t) - site.min_elevation_deg;
+
+        let mut t = start;
+        let aos = if above(t) >= 0.0 {
+            t
+        } else {
+            loop {
+                let next = (t + PASS_SEARCH_STEP_S).min(end);
+                if above(next) >= 0.0 {
+                    break self.edge(&above, t, next).1;
+                }
+                if next >= end {
+                    return None;
+                }
+                t = next;
+            }
+        };
+
+        let mut max_elevation = above(aos);
+        let mut t = aos;
+        let longest = aos + SECONDS_PER_DAY; // Never sets, e.g. a geostationary satellite
+        let los = loop { // Horizon only bounds the search for AOS, a pass that started in it is followed to its end
+            let next = t + PASS_SEARCH_STEP_S;
+            let elevation = above(next);
+            if elevation < 0.0 {
+                break self.edge(&above, next, t).0; // First instant out of view, so searching again from LOS finds the next pass
+            }
+            if next >= longest {
+                break next;
+            }
+            max_elevation = max_elevation.max(elevation);
+            t = next;
+        };
+
+        Some(Pass {
+            aos_us: if aos == start { from_us } else { self.wall_clock_us(aos) },
+            los_us: self.wall_clock_us(los),
+            max_elevation_deg: max_elevation + site.min_elevation_deg,
+        })
+    }
+
+    // Bisects the crossing between below (under the mask) and visible (above it), both sides to within the tolerance
+    fn edge(&self, above: &impl Fn(f64) -> f64, mut below: f64, mut visible: f64) -> (f64, f64) {
+        while (visible - below).abs() > PASS_EDGE_TOLERANCE_S {
+            let middle = (below + visible) / 2.0;
+            if above(middle) >= 0.0 {
+                visible = middle;
+            } else {
+                below = middle;
+            }
+        }
+        (below, visible)
+    }
+}
+
+// Greenwich mean sidereal angle in radians (IAU 1982)
+fn gmst(unix_s: f64) -> f64 {
+    let days = unix_s / SECONDS_PER_DAY + 2_440_587.5 - 2_451_545.0; // Julian days since J2000
+    let centuries = days / 36_525.0;
+    let degrees = 280.460_618_37 + 360.985_647_366_29 * days + 0.000_387_933 * centuries * centuries
+        - centuries.powi(3) / 38_710_000.0;
+
+    degrees.rem_euclid(360.0).to_radians()
+}
+
+// Days from 1970-01-01 to the given civil date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+// Orbit section of both configs, the ground keeps one per spacecraft it serves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OrbitConfig {
+    pub tle: Vec<String>, // Line 1 and line 2, replace elements and epoch_unix_s when given
+    pub epoch_unix_s: u64,
+    pub time_scale: f64, // Simulated seconds per wall clock second, both ends must use the same
+    pub elements: OrbitalElements,
+}
+
+impl Default for OrbitConfig {
+    fn default() -> Self {
+        Self {
+            tle: Vec::new(),
+            epoch_unix_s: 1_767_225_600, // 2026-01-01T00:00:00Z
+            time_scale: 10_000.0, // A day of passes in under 9 s
+            elements: OrbitalElements::default(),
+        }
+    }
+}
+
+impl OrbitConfig {
+    pub fn build(&self) -> Result<Orbit, String> {
+        match self.tle.as_slice() {
+            [] => Orbit::new(self.elements, self.epoch_unix_s as f64, self.time_scale),
+            [line1, line2] => Orbit::from_tle(line1, line2, self.time_scale),
+            lines => Err(format!("tle must hold both lines, got {}", lines.len())),
+        }
+    }
+}
+
+pub fn wall_clock_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_micros() as u64)
+}
@@ -0,0 +1,110 @@
+// Free-running crystal behind a simulated clock: a fixed frequency error, the parabolic temperature curve of an
+// AT-cut crystal around its turnover point and now and then a jump, so clock sync has something to track
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+const PPB: f64 = 1e9;
+const MAX_FREQUENCY_ERROR_PPM: f64 = 1_000.0; // Far past any crystal, a typo rather than an oscillator
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OscillatorConfig {
+    pub initial_offset_us: u64, // Reading at boot
+    pub frequency_error_ppm: f64, // At the turnover temperature, positive runs fast
+    pub temperature_ppb_per_c2: f64, // Slows the crystal by this times the squared distance from turnover_c
+    pub turnover_c: f64,
+    pub jump_interval_ms: u64, // Mean time between jumps, 0 for none
+    pub max_jump_us: u64, // Either way, a jump back holds the reading until the crystal catches up
+}
+
+impl Default for OscillatorConfig {
+    fn default() -> Self {
+        Self {
+            initial_offset_us: 0,
+            frequency_error_ppm: 20.0,
+            temperature_ppb_per_c2: 34.0,
+            turnover_c: 25.0,
+            jump_interval_ms: 0,
+            max_jump_us: 50_000,
+        }
+    }
+}
+
+impl OscillatorConfig {
+    pub fn build(&self) -> Result<Oscillator, String> {
+        if !(self.frequency_error_ppm.is_finite() && self.frequency_error_ppm.abs() < MAX_FREQUENCY_ERROR_PPM) {
+            return Err(format!("frequency_error_ppm must be within ±{}, got {}", MAX_FREQUENCY_ERROR_PPM, self.frequency_error_ppm));
+        }
+
+        if !(self.temperature_ppb_per_c2 >= 0.0 && self.temperature_ppb_per_c2.is_finite() && self.turnover_c.is_finite()) {
+            return Err("temperature_ppb_per_c2 must be 0 or more and turnover_c a temperature".to_string());
+        }
+
+        if self.jump_interval_ms > 0 && self.max_jump_us == 0 {
+            return Err("max_jump_us must be greater than 0 when jump_interval_ms is set".to_string());
+        }
+
+        Ok(Oscillator {
+            config: *self,
+            true_us: 0,
+            local_us: self.initial_offset_us as f64,
+            reading_us: self.initial_offset_us,
+            temperature_c: self.turnover_c,
+            next_jump_us: self.jump_interval_ms,
+            jumped_us: 0.0,
+            jumps: 0,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Oscillator {
+    config: OscillatorConfig,
+    true_us: u64, // How far the crystal has been run
+    local_us: f64, // What it has counted, jumps included
+    reading_us: u64, // Never runs backwards
+    temperature_c: f64,
+    next_jump_us: u64,
+    jumped_us: f64,
+    pub jumps: u32,
+}
+
+impl Oscillator {
+    // Positive runs fast
+    pub fn frequency_error_ppb(&self) -> f64 {
+        self.config.frequency_error_ppm * 1_000.0 - self.config.temperature_ppb_per_c2 * (self.temperature_c - self.config.turnover_c).powi(2)
+    }
+
+    // Averaged over every temperature since boot, what a fit over a long enough run should find
+    pub fn mean_frequency_error_ppb(&self) -> f64 {
+        let counted_us = self.local_us - self.config.initial_offset_us as f64 - self.jumped_us;
+        (counted_us / self.true_us.max(1) as f64 - 1.0) * PPB
+    }
+
+    // Counts the time since the last call at the temperature it ran at, then takes the new one. None keeps the
+    // temperature, a faulted sensor says nothing about the crystal
+    pub fn advance(&mut self, true_us: u64, temperature_c: Option<f64>) -> u64 {
+        let elapsed = true_us.saturating_sub(self.true_us) as f64;
+        self.true_us = self.true_us.max(true_us);
+        self.local_us += elapsed * (1.0 + self.frequency_error_ppb() / PPB);
+
+        if let Some(temperature_c) = temperature_c {
+            self.temperature_c = temperature_c;
+        }
+
+        while self.config.jump_interval_ms > 0 && self.true_us >= self.next_jump_us {
+            let mut rng = rand::thread_rng();
+            let max_jump = self.config.max_jump_us as i64;
+
+            let jump_us = rng.gen_range(-max_jump..=max_jump) as f64;
+
+            self.local_us += jump_us;
+            self.jumped_us += jump_us;
+            self.next_jump_us += rng.gen_range(self.config.jump_interval_ms / 2..=self.config.jump_interval_ms * 3 / 2).max(1);
+            self.jumps += 1;
+        }
+
+        self.reading_us = self.reading_us.max(self.local_us.max(0.0).round() as u64);
+        self.reading_us
+    }
+}
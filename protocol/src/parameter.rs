@@ -0,0 +1,223 @@
+// Onboard parameter table: values the satellite's threads read live, changed in flight by SetParameter or a config
+// reload. Every entry has a kind and limits, and every change bumps the table version so the ground can tell a dump
+// taken before a change from one taken after
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use serde::{Serialize, Deserialize};
+use crate::types::RejectReason;
+
+pub const PARAMETER_COUNT: usize = 13;
+
+const MAX_SENSOR_READING: u64 = 99_998; // Below the reading the satellite marks a corrupted sensor with
+
+// Variant order is part of the wire format and indexes the table, only append new variants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ParameterID {
+    NormalToDegradedThreshold,
+    DegradedToNormalThreshold,
+    DegradedSkippedSensorCycles,
+    FaultRecoveryMs,
+    ThermalSensorPeriodMs,
+    ThermalSensorMinData,
+    ThermalSensorMaxData,
+    PitchAndYawSensorPeriodMs,
+    PitchAndYawSensorMinData,
+    PitchAndYawSensorMaxData,
+    MoistureSensorPeriodMs,
+    MoistureSensorMinData,
+    MoistureSensorMaxData,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParameterKind {
+    Percent, // Downlink buffer fill
+    Cycles,
+    Micros,
+    Hundredths, // Sensor readings, two decimal places
+}
+
+impl ParameterKind {
+    pub fn unit(&self) -> &'static str {
+        match self {
+            ParameterKind::Percent => "%",
+            ParameterKind::Cycles => " cycles",
+            ParameterKind::Micros => "μs",
+            ParameterKind::Hundredths => "/100",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParameterLimits {
+    pub kind: ParameterKind,
+    pub min: u64,
+    pub max: u64,
+}
+
+impl ParameterID {
+    pub const ALL: [ParameterID; PARAMETER_COUNT] = [
+        ParameterID::NormalToDegradedThreshold,
+        ParameterID::DegradedToNormalThreshold,
+        ParameterID::DegradedSkippedSensorCycles,
+        ParameterID::FaultRecoveryMs,
+        ParameterID::ThermalSensorPeriodMs,
+        ParameterID::ThermalSensorMinData,
+        ParameterID::ThermalSensorMaxData,
+        ParameterID::PitchAndYawSensorPeriodMs,
+        ParameterID::PitchAndYawSensorMinData,
+        ParameterID::PitchAndYawSensorMaxData,
+        ParameterID::MoistureSensorPeriodMs,
+        ParameterID::MoistureSensorMinData,
+        ParameterID::MoistureSensorMaxData,
+    ];
+
+    pub fn limits(&self) -> ParameterLimits {
+        let (kind, min, max) = match self {
+            ParameterID::NormalToDegradedThreshold => (ParameterKind::Percent, 1, 100),
+            ParameterID::DegradedToNormalThreshold => (ParameterKind::Percent, 0, 99),
+            ParameterID::DegradedSkippedSensorCycles => (ParameterKind::Cycles, 1, 100),
+            ParameterID::FaultRecoveryMs => (ParameterKind::Micros, 1_000, 86_400_000_000), // A day
+            ParameterID::ThermalSensorPeriodMs
+            | ParameterID::PitchAndYawSensorPeriodMs
+            | ParameterID::MoistureSensorPeriodMs => (ParameterKind::Micros, 1_000, 60_000_000),
+            ParameterID::ThermalSensorMinData
+            | ParameterID::ThermalSensorMaxData
+            | ParameterID::PitchAndYawSensorMinData
+            | ParameterID::PitchAndYawSensorMaxData
+            | ParameterID::MoistureSensorMinData
+            | ParameterID::MoistureSensorMaxData => (ParameterKind::Hundredths, 0, MAX_SENSOR_READING),
+        };
+
+        ParameterLimits { kind, min, max }
+    }
+
+    pub fn check(&self, value: u64) -> Result<(), ParameterError> {
+        let limits = self.limits();
+
+        if value < limits.min || value > limits.max {
+            return Err(ParameterError::OutOfRange);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ParameterError {
+    OutOfRange, // Outside the entry's own limits
+    Conflicts, // Within them, but breaks a rule between entries, a threshold pair or a sensor's min and max
+}
+
+impl From<ParameterError> for RejectReason {
+    fn from(error: ParameterError) -> Self {
+        match error {
+            ParameterError::OutOfRange => RejectReason::OutOfRange,
+            ParameterError::Conflicts => RejectReason::Conflicts,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct ParameterChange {
+    pub parameter: ParameterID,
+    pub old_value: u64,
+    pub new_value: u64,
+    pub version: u32, // Of the table once the change is in
+}
+
+#[derive(Debug)]
+pub struct ParameterTable {
+    values: [AtomicU64; PARAMETER_COUNT], // Read without a lock by every thread
+    version: AtomicU32,
+    writer: Mutex<()>, // A change is checked against the other entries, so only one goes in at a time
+}
+
+impl ParameterTable {
+    // values in ParameterID order, every one checked as a set would
+    pub fn new(values: [u64; PARAMETER_COUNT]) -> Result<Self, (ParameterID, ParameterError)> {
+        for id in ParameterID::ALL {
+            id.check(values[id as usize]).map_err(|error| (id, error))?;
+        }
+
+        check_rules(&values)?;
+
+        Ok(Self {
+            values: values.map(AtomicU64::new),
+            version: AtomicU32::new(1),
+            writer: Mutex::new(()),
+        })
+    }
+
+    pub fn get(&self, id: ParameterID) -> u64 {
+        self.values[id as usize].load(Ordering::Acquire)
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    pub fn values(&self) -> [u64; PARAMETER_COUNT] {
+        ParameterID::ALL.map(|id| self.get(id))
+    }
+
+    // Setting the value already there changes nothing, the version included
+    pub fn set(&self, id: ParameterID, value: u64) -> Result<ParameterChange, ParameterError> {
+        let mut values = self.values();
+        values[id as usize] = value;
+
+        let mut changes = self.set_all(values).map_err(|(_, error)| error)?;
+        Ok(changes.pop().unwrap_or(ParameterChange { parameter: id, old_value: value, new_value: value, version: self.version() }))
+    }
+
+    // Every entry that differs goes in, or none does if the new values break a limit or a rule. Entries are checked
+    // together, so a threshold pair can be moved past each other in one go
+    pub fn set_all(&self, values: [u64; PARAMETER_COUNT]) -> Result<Vec<ParameterChange>, (ParameterID, ParameterError)> {
+        let _writer = self.writer.lock().unwrap();
+        let old_values = self.values();
+
+        for id in ParameterID::ALL {
+            if values[id as usize] != old_values[id as usize] {
+                id.check(values[id as usize]).map_err(|error| (id, error))?;
+            }
+        }
+
+        check_rules(&values)?;
+
+        let mut changes = Vec::new();
+
+        for id in ParameterID::ALL {
+            let new_value = values[id as usize];
+
+            if new_value == old_values[id as usize] {
+                continue;
+            }
+
+            self.values[id as usize].store(new_value, Ordering::Release);
+            let version = self.version.fetch_add(1, Ordering::AcqRel) + 1;
+
+            changes.push(ParameterChange { parameter: id, old_value: old_values[id as usize], new_value, version });
+        }
+
+        Ok(changes)
+    }
+}
+
+fn check_rules(values: &[u64; PARAMETER_COUNT]) -> Result<(), (ParameterID, ParameterError)> {
+    let value = |id: ParameterID| values[id as usize];
+
+    if value(ParameterID::DegradedToNormalThreshold) >= value(ParameterID::NormalToDegradedThreshold) { // Hysteresis, or the mode flips every cycle
+        return Err((ParameterID::DegradedToNormalThreshold, ParameterError::Conflicts));
+    }
+
+    for (min, max) in [
+        (ParameterID::ThermalSensorMinData, ParameterID::ThermalSensorMaxData),
+        (ParameterID::PitchAndYawSensorMinData, ParameterID::PitchAndYawSensorMaxData),
+        (ParameterID::MoistureSensorMinData, ParameterID::MoistureSensorMaxData),
+    ] {
+        if value(min) >= value(max) {
+            return Err((min, ParameterError::Conflicts));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use crate::types::Priority;
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+
+// Downlink radio modelled as a token bucket filled at the link bit rate. The size of a frame is only known once it is
+// encoded, so a packet goes out while the bucket is above its priority's floor and is paid for afterwards.
+// Normal and Low traffic stop at the reserve, leaving that headroom to Emergency and Critical packets
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    rate_bps: u64,
+    burst_bits: u64,
+    reserve_bits: u64,
+    tokens: i64, // Bit-microseconds so refills never lose a fraction of a bit, goes negative after an oversized frame
+    last_refill: u64,
+}
+
+impl TokenBucket {
+    pub fn new(rate_bps: u64, burst_bits: u64, reserve_bits: u64) -> Self {
+        Self {
+            rate_bps,
+            burst_bits,
+            reserve_bits: reserve_bits.min(burst_bits),
+            tokens: burst_bits as i64 * MICROS_PER_SECOND,
+            last_refill: 0,
+        }
+    }
+
+    pub fn rate_bps(&self) -> u64 {
+        self.rate_bps
+    }
+
+    // Full again at the start of a pass
+    pub fn reset(&mut self, now: u64) {
+        self.tokens = self.burst_bits as i64 * MICROS_PER_SECOND;
+        self.last_refill = now;
+    }
+
+    pub fn can_send(&mut self, priority: Priority, now: u64) -> bool {
+        self.refill(now);
+        self.tokens > Self::floor(priority, self.reserve_bits) as i64 * MICROS_PER_SECOND
+    }
+
+    pub fn spend(&mut self, bytes: usize) {
+        self.tokens -= bytes as i64 * 8 * MICROS_PER_SECOND;
+    }
+
+    pub fn available_bits(&self) -> i64 {
+        self.tokens / MICROS_PER_SECOND
+    }
+
+    // Most the bucket can let through over a pass of this length
+    pub fn budget_bits(&self, elapsed: u64) -> u64 {
+        self.burst_bits + self.rate_bps * elapsed / MICROS_PER_SECOND as u64
+    }
+
+    fn refill(&mut self, now: u64) {
+        let elapsed = now.saturating_sub(self.last_refill);
+        self.last_refill = self.last_refill.max(now);
+
+        let full = self.burst_bits as i64 * MICROS_PER_SECOND;
+        self.tokens = (self.tokens + (self.rate_bps * elapsed) as i64).min(full);
+    }
+
+    fn floor(priority: Priority, reserve_bits: u64) -> u64 {
+        match priority {
+            Priority::Emergency | Priority::Critical => 0,
+            Priority::Normal | Priority::Low => reserve_bits,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+// Follows a numbered frame stream and reports the sequence numbers skipped since the last frame.
+// 0 is SEQUENCE_NOT_CONFIRMED on both sides and is never tracked
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GapTracker {
+    pub last: u32,
+}
+
+impl GapTracker {
+    pub fn observe(&mut self, sequence_no: u32) -> Option<Range<u32>> {
+        if sequence_no == 0 || sequence_no <= self.last { // Unnumbered, duplicate or a replay filling an earlier gap
+            return None;
+        }
+
+        let first_missing = self.last + 1;
+        let has_reference = self.last != 0;
+        self.last = sequence_no;
+
+        (has_reference && sequence_no > first_missing).then_some(first_missing..sequence_no)
+    }
+}
+
+// Sequence numbers covered by one NACK, bit i of the bitmap stands for base + i
+pub const NACK_WINDOW: u32 = 64;
+
+pub fn nack_sequence_numbers(base: u32, bitmap: u64) -> impl Iterator<Item = u32> {
+    (0..NACK_WINDOW)
+        .filter(move |i| bitmap & (1 << i) != 0)
+        .map(move |i| base.wrapping_add(i))
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Arrival {
+    Unnumbered,
+    InOrder,
+    Gap(u32), // Arrived after this many sequence numbers that are now outstanding
+    Recovered,
+    Duplicate,
+}
+
+// Receive side of selective-repeat ARQ. Outstanding sequence numbers survive across passes
+// until they are recovered, given up on by the sender, or fall out of the sender's history
+#[derive(Debug, Clone)]
+pub struct SelectiveRepeat {
+    window: u32, // Depth of the sender's retransmission store
+    highest: u32,
+    missing: BTreeMap<u32, Option<u64>>, // Outstanding sequence number -> when it was last NACKed
+    pub lost: u32, // Aged out or unavailable, taken by the caller like the FrameDecoder counters
+}
+
+impl SelectiveRepeat {
+    pub fn new(window: u32) -> Self {
+        Self { window, highest: 0, missing: BTreeMap::new(), lost: 0 }
+    }
+
+    pub fn highest(&self) -> u32 {
+        self.highest
+    }
+
+    pub fn outstanding(&self) -> usize {
+        self.missing.len()
+    }
+
+    pub fn observe(&mut self, sequence_no: u32) -> Arrival {
+        if sequence_no == 0 {
+            return Arrival::Unnumbered;
+        }
+
+        if self.highest == 0 || sequence_no == self.highest + 1 {
+            self.highest = sequence_no;
+            self.expire();
+            return Arrival::InOrder;
+        }
+
+        if sequence_no <= self.highest {
+            return match self.missing.remove(&sequence_no) {
+                Some(_) => Arrival::Recovered,
+                None => Arrival::Duplicate,
+            };
+        }
+
+        let skipped = sequence_no - self.highest - 1;
+        let first_kept = (self.highest + 1).max(sequence_no.saturating_sub(self.window - 1));
+
+        self.lost += first_kept - (self.highest + 1); // Already overwritten on the sender
+        self.missing.extend((first_kept..sequence_no).map(|seq| (seq, None)));
+        self.highest = sequence_no;
+        self.expire();
+
+        Arrival::Gap(skipped)
+    }
+
+    // NACKs for every outstanding number not asked for within the last retry_after
+    pub fn nacks(&mut self, now: u64, retry_after: u64) -> Vec<(u32, u64)> {
+        let mut nacks: Vec<(u32, u64)> = Vec::new();
+
+        for (&seq, last_nacked) in self.missing.iter_mut() {
+            if last_nacked.is_some_and(|at| now.saturating_sub(at) < retry_after) {
+                continue;
+            }
+            *last_nacked = Some(now);
+
+            match nacks.last_mut() {
+                Some((base, bitmap)) if seq - *base < NACK_WINDOW => *bitmap |= 1 << (seq - *base),
+                _ => nacks.push((seq, 1)),
+            }
+        }
+
+        nacks
+    }
+
+    // The sender no longer holds these
+    pub fn abandon(&mut self, base: u32, bitmap: u64) {
+        for seq in nack_sequence_numbers(base, bitmap) {
+            if self.missing.remove(&seq).is_some() {
+                self.lost += 1;
+            }
+        }
+    }
+
+    // Another receiver took the downlink up to here, its frames are not ours to recover or count as lost
+    pub fn resume_at(&mut self, sequence_no: u32) {
+        self.missing.retain(|&seq, _| seq > sequence_no);
+        self.highest = self.highest.max(sequence_no.saturating_sub(1));
+    }
+
+    fn expire(&mut self) {
+        let oldest_held = self.highest.saturating_sub(self.window - 1);
+        let kept = self.missing.split_off(&oldest_held);
+
+        self.lost += self.missing.len() as u32;
+        self.missing = kept;
+    }
+}
@@ -0,0 +1,257 @@
+// Stored command sequences: numbered lists of steps uploaded one step per command, then started, aborted and deleted
+// by command. The store only keeps the bookkeeping, whoever polls next_due runs the commands it hands back
+use serde::{Serialize, Deserialize};
+use crate::types::{Command, RejectReason, SubsystemID};
+
+pub const MAX_SEQUENCE_STEPS: usize = 64;
+
+// What a sequence may do, the spacecraft commands plus waiting on a condition. Sequence and time-tag commands are left
+// out so a sequence can never start, change or delete another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)] // Command is ordered
+pub enum SequenceStep {
+    RotateAntenna { target_angle: u16 },
+    SetPowerMode { mode: u8 },
+    ClearSubsystemFault { subsystem_id: SubsystemID },
+    WaitFor { condition: SequenceCondition, timeout_ms: u32 }, // Aborts the sequence if still not met by then
+}
+
+impl SequenceStep {
+    pub fn command(&self) -> Option<Command> {
+        match *self {
+            SequenceStep::RotateAntenna { target_angle } => Some(Command::RotateAntenna { target_angle }),
+            SequenceStep::SetPowerMode { mode } => Some(Command::SetPowerMode { mode }),
+            SequenceStep::ClearSubsystemFault { subsystem_id } => Some(Command::ClearSubsystemFault { subsystem_id }),
+            SequenceStep::WaitFor { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SequenceCondition {
+    SubsystemHealthy { subsystem_id: SubsystemID }, // Neither faulted nor interlocked
+    InContact, // Some ground station in view
+}
+
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Copy, Clone)]
+pub enum SequenceAbort {
+    Commanded,
+    WaitTimedOut,
+    Interlocked, // A step's subsystem was faulted when it came due
+    Deleted,
+    WrongMode, // A step the satellite's mode did not allow when it came due
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum SequenceError {
+    BadStep, // index past count, count of 0 or past MAX_SEQUENCE_STEPS
+    Full,
+    Running, // Cannot be changed or started again while it runs
+    NotStored,
+    Incomplete, // Steps still missing from the upload
+    NotRunning,
+}
+
+impl From<SequenceError> for RejectReason {
+    fn from(error: SequenceError) -> Self {
+        match error {
+            SequenceError::BadStep => RejectReason::OutOfRange,
+            SequenceError::Full => RejectReason::Full,
+            SequenceError::NotStored => RejectReason::NotFound,
+            SequenceError::Running | SequenceError::Incomplete | SequenceError::NotRunning => RejectReason::WrongMode,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum SequenceProgress {
+    Run { sequence_id: u8, step: u8, steps: u8, command: Command }, // Already counted as run, the caller carries it out
+    WaitMet { sequence_id: u8, step: u8, steps: u8 },
+    TimedOut { sequence_id: u8, step: u8, steps: u8 }, // The sequence has been stopped
+    Completed { sequence_id: u8, steps: u8 },
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct SequenceSummary {
+    pub sequence_id: u8,
+    pub stored: u8,
+    pub steps: u8,
+    pub running_step: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StoredStep {
+    delay_ms: u32, // After the previous step, or after the start for the first
+    step: SequenceStep,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SequenceRun {
+    step: usize, // Next to run
+    due_at: u64,
+}
+
+#[derive(Debug, Clone)]
+struct StoredSequence {
+    sequence_id: u8,
+    steps: Vec<Option<StoredStep>>,
+    run: Option<SequenceRun>,
+}
+
+impl StoredSequence {
+    fn stored(&self) -> usize {
+        self.steps.iter().filter(|step| step.is_some()).count()
+    }
+}
+
+#[derive(Debug)]
+pub struct SequenceStore {
+    sequences: Vec<StoredSequence>, // Upload order
+    pub capacity: usize,
+}
+
+impl SequenceStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sequences: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    // Returns how many of the steps are stored. A count other than the stored one starts the upload over, so an
+    // upload is simply repeated to replace a sequence
+    pub fn upload(&mut self, sequence_id: u8, index: u8, count: u8, delay_ms: u32, step: SequenceStep) -> Result<u8, SequenceError> {
+        if count == 0 || count as usize > MAX_SEQUENCE_STEPS || index >= count {
+            return Err(SequenceError::BadStep);
+        }
+
+        let position = match self.position(sequence_id) {
+            Some(position) => position,
+            None if self.sequences.len() >= self.capacity => return Err(SequenceError::Full),
+            None => {
+                self.sequences.push(StoredSequence { sequence_id, steps: Vec::new(), run: None });
+                self.sequences.len() - 1
+            }
+        };
+
+        let sequence = &mut self.sequences[position];
+
+        if sequence.run.is_some() {
+            return Err(SequenceError::Running);
+        }
+
+        if sequence.steps.len() != count as usize {
+            sequence.steps = vec![None; count as usize];
+        }
+
+        sequence.steps[index as usize] = Some(StoredStep { delay_ms, step });
+        Ok(sequence.stored() as u8)
+    }
+
+    // Returns the number of steps
+    pub fn start(&mut self, sequence_id: u8, now: u64) -> Result<u8, SequenceError> {
+        let sequence = self.find(sequence_id)?;
+
+        if sequence.run.is_some() {
+            return Err(SequenceError::Running);
+        }
+
+        if sequence.steps.iter().any(Option::is_none) {
+            return Err(SequenceError::Incomplete);
+        }
+
+        sequence.run = Some(SequenceRun { step: 0, due_at: now + sequence.steps[0].expect("checked above").delay_ms as u64 });
+        Ok(sequence.steps.len() as u8)
+    }
+
+    // Returns the step it was stopped before
+    pub fn abort(&mut self, sequence_id: u8) -> Result<u8, SequenceError> {
+        let run = self.find(sequence_id)?.run.take().ok_or(SequenceError::NotRunning)?;
+        Ok(run.step as u8)
+    }
+
+    // Returns the step it was stopped before if it was running
+    pub fn delete(&mut self, sequence_id: u8) -> Result<Option<u8>, SequenceError> {
+        let position = self.position(sequence_id).ok_or(SequenceError::NotStored)?;
+        let sequence = self.sequences.remove(position);
+        Ok(sequence.run.map(|run| run.step as u8))
+    }
+
+    pub fn list(&self) -> Vec<SequenceSummary> {
+        self.sequences.iter().map(|sequence| SequenceSummary {
+            sequence_id: sequence.sequence_id,
+            stored: sequence.stored() as u8,
+            steps: sequence.steps.len() as u8,
+            running_step: sequence.run.map(|run| run.step as u8),
+        }).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sequences.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sequences.is_empty()
+    }
+
+    pub fn running(&self) -> usize {
+        self.sequences.iter().filter(|sequence| sequence.run.is_some()).count()
+    }
+
+    // The next thing any running sequence has to do by now, a wait that is not met yet holds up only its own sequence
+    pub fn next_due(&mut self, now: u64, is_met: impl Fn(&SequenceCondition) -> bool) -> Option<SequenceProgress> {
+        for sequence in &mut self.sequences {
+            let Some(run) = sequence.run else {
+                continue;
+            };
+
+            let steps = sequence.steps.len() as u8;
+
+            if run.step == sequence.steps.len() {
+                sequence.run = None;
+                return Some(SequenceProgress::Completed { sequence_id: sequence.sequence_id, steps });
+            }
+
+            if now < run.due_at {
+                continue;
+            }
+
+            let current = sequence.steps[run.step].expect("a sequence only starts once every step is stored");
+            let step = run.step as u8;
+
+            let progress = match current.step {
+                SequenceStep::WaitFor { condition, timeout_ms } => {
+                    if is_met(&condition) {
+                        SequenceProgress::WaitMet { sequence_id: sequence.sequence_id, step, steps }
+                    } else if now >= run.due_at + timeout_ms as u64 {
+                        sequence.run = None;
+                        return Some(SequenceProgress::TimedOut { sequence_id: sequence.sequence_id, step, steps });
+                    } else {
+                        continue;
+                    }
+                }
+                other => SequenceProgress::Run {
+                    sequence_id: sequence.sequence_id,
+                    step,
+                    steps,
+                    command: other.command().expect("every step but WaitFor is a command"),
+                },
+            };
+
+            let next = run.step + 1;
+            let due_at = sequence.steps.get(next).map_or(now, |next| now + next.expect("stored").delay_ms as u64);
+
+            sequence.run = Some(SequenceRun { step: next, due_at });
+            return Some(progress);
+        }
+
+        None
+    }
+
+    fn position(&self, sequence_id: u8) -> Option<usize> {
+        self.sequences.iter().position(|sequence| sequence.sequence_id == sequence_id)
+    }
+
+    fn find(&mut self, sequence_id: u8) -> Result<&mut StoredSequence, SequenceError> {
+        self.sequences.iter_mut().find(|sequence| sequence.sequence_id == sequence_id).ok_or(SequenceError::NotStored)
+    }
+}
@@ -0,0 +1,273 @@
+use crate::auth::{CommandAuth, AUTH_TAG_LEN};
+use crate::handshake::Capabilities;
+use crate::types::{Priority, SatelliteMessage, TelemetryPacket};
+
+// CCSDS 133.0-B Space Packet
+// Primary header (6 bytes):
+//   version (3) | type (1) | secondary header flag (1) | APID (11)
+//   sequence flags (2) | sequence count (14)
+//   data length (16) = data field length - 1
+// Secondary header (9 bytes): big-endian creation_time (8) + priority (1)
+// User data: fields of the SatelliteMessage variant selected by the APID, the sync result's auth as a big-endian
+//   counter (8) + tag (32) after its four timestamps
+pub const PRIMARY_HEADER_LEN: usize = 6;
+pub const SECONDARY_HEADER_LEN: usize = 9;
+
+pub const PACKET_VERSION: u8 = 0;
+pub const TYPE_TELEMETRY: u8 = 0;
+pub const TYPE_TELECOMMAND: u8 = 1;
+pub const SEQUENCE_FLAGS_UNSEGMENTED: u8 = 0b11;
+pub const SEQUENCE_COUNT_MODULO: u32 = 1 << 14;
+
+// APIDs per SatelliteMessage variant, 0x7FF is reserved for idle packets
+pub const APID_SYNC_REQUEST: u16 = 0x040;
+pub const APID_SYNC_RESPONSE: u16 = 0x041;
+pub const APID_SYNC_RESULT: u16 = 0x042;
+pub const APID_NACK: u16 = 0x043;
+pub const APID_TIME_CORRELATION: u16 = 0x044;
+pub const APID_COMMAND: u16 = 0x050;
+pub const APID_TELEMETRY: u16 = 0x060;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum SpacePacketError {
+    Truncated,
+    UnsupportedVersion,
+    Segmented,
+    MissingSecondaryHeader,
+    UnknownApid,
+    TypeMismatch,
+    LengthMismatch,
+    UnknownPriority,
+    Malformed,
+}
+
+// Packet type is fixed by direction, Ground -> Satellite is telecommand
+pub fn apid_of(message: &SatelliteMessage) -> (u16, u8) {
+    match message {
+        SatelliteMessage::SyncRequest => (APID_SYNC_REQUEST, TYPE_TELECOMMAND),
+        SatelliteMessage::SyncResponse { .. } => (APID_SYNC_RESPONSE, TYPE_TELEMETRY),
+        SatelliteMessage::SyncResult { .. } => (APID_SYNC_RESULT, TYPE_TELECOMMAND),
+        SatelliteMessage::Command { .. } => (APID_COMMAND, TYPE_TELECOMMAND),
+        SatelliteMessage::Telemetry { .. } => (APID_TELEMETRY, TYPE_TELEMETRY),
+        SatelliteMessage::Nack { .. } => (APID_NACK, TYPE_TELECOMMAND),
+        SatelliteMessage::TimeCorrelation { .. } => (APID_TIME_CORRELATION, TYPE_TELECOMMAND),
+    }
+}
+
+pub fn encode_space_packet(packet: &TelemetryPacket) -> Option<Vec<u8>> {
+    let (apid, packet_type) = apid_of(&packet.payload);
+
+    let user_data = match packet.payload {
+        SatelliteMessage::SyncRequest => Vec::new(),
+        SatelliteMessage::SyncResponse { ground_sent, satellite_receive, satellite_send } => {
+            [ground_sent.to_be_bytes(), satellite_receive.to_be_bytes(), satellite_send.to_be_bytes()].concat()
+        }
+        SatelliteMessage::SyncResult { ground_sent, satellite_receive, satellite_send, ground_receive, auth } => {
+            [&ground_sent.to_be_bytes()[..], &satellite_receive.to_be_bytes(), &satellite_send.to_be_bytes(), &ground_receive.to_be_bytes(),
+                &auth.counter.to_be_bytes(), &auth.tag].concat()
+        }
+        SatelliteMessage::Command { command, command_id, sent_at, execute_at, auth } => bincode::serialize(&(command, command_id, sent_at, execute_at, auth)).ok()?,
+        SatelliteMessage::Telemetry { event } => bincode::serialize(&event).ok()?,
+        SatelliteMessage::Nack { base, bitmap } => [&base.to_be_bytes()[..], &bitmap.to_be_bytes()].concat(),
+        SatelliteMessage::TimeCorrelation { correlation, auth } => bincode::serialize(&(correlation, auth)).ok()?,
+    };
+
+    let data_length = SECONDARY_HEADER_LEN + user_data.len() - 1;
+    let data_length = u16::try_from(data_length).ok()?;
+    let sequence_count = (packet.sequence_no % SEQUENCE_COUNT_MODULO) as u16;
+
+    let mut bytes = Vec::with_capacity(PRIMARY_HEADER_LEN + SECONDARY_HEADER_LEN + user_data.len());
+
+    let identification = (PACKET_VERSION as u16) << 13 | (packet_type as u16) << 12 | 1 << 11 | apid;
+    bytes.extend_from_slice(&identification.to_be_bytes());
+    bytes.extend_from_slice(&((SEQUENCE_FLAGS_UNSEGMENTED as u16) << 14 | sequence_count).to_be_bytes());
+    bytes.extend_from_slice(&data_length.to_be_bytes());
+
+    bytes.extend_from_slice(&packet.creation_time.to_be_bytes());
+    bytes.push(packet.priority as u8);
+    bytes.extend_from_slice(&user_data);
+
+    Some(bytes)
+}
+
+// Returns the packet with only the 14-bit count in sequence_no, see SequenceCounter::unwrap
+pub fn decode_space_packet(bytes: &[u8]) -> Result<TelemetryPacket, SpacePacketError> {
+    if bytes.len() < PRIMARY_HEADER_LEN + SECONDARY_HEADER_LEN {
+        return Err(SpacePacketError::Truncated);
+    }
+
+    let identification = u16::from_be_bytes([bytes[0], bytes[1]]);
+    let sequence_control = u16::from_be_bytes([bytes[2], bytes[3]]);
+    let data_length = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+
+    if (identification >> 13) as u8 != PACKET_VERSION {
+        return Err(SpacePacketError::UnsupportedVersion);
+    }
+
+    if (sequence_control >> 14) as u8 != SEQUENCE_FLAGS_UNSEGMENTED {
+        return Err(SpacePacketError::Segmented);
+    }
+
+    if identification & (1 << 11) == 0 {
+        return Err(SpacePacketError::MissingSecondaryHeader);
+    }
+
+    if bytes.len() != PRIMARY_HEADER_LEN + data_length + 1 {
+        return Err(SpacePacketError::LengthMismatch);
+    }
+
+    let packet_type = ((identification >> 12) & 1) as u8;
+    let apid = identification & 0x07FF;
+    let sequence_count = sequence_control & 0x3FFF;
+
+    let secondary = &bytes[PRIMARY_HEADER_LEN..PRIMARY_HEADER_LEN + SECONDARY_HEADER_LEN];
+    let creation_time = u64::from_be_bytes(secondary[..8].try_into().unwrap());
+    let priority = match secondary[8] {
+        0 => Priority::Low,
+        3 => Priority::Normal,
+        9 => Priority::Critical,
+        10 => Priority::Emergency,
+        _ => return Err(SpacePacketError::UnknownPriority),
+    };
+
+    let user_data = &bytes[PRIMARY_HEADER_LEN + SECONDARY_HEADER_LEN..];
+
+    let payload = match apid {
+        APID_SYNC_REQUEST if user_data.is_empty() => SatelliteMessage::SyncRequest,
+        APID_SYNC_RESPONSE if user_data.len() == 24 => SatelliteMessage::SyncResponse {
+            ground_sent: be_u64(user_data, 0),
+            satellite_receive: be_u64(user_data, 1),
+            satellite_send: be_u64(user_data, 2),
+        },
+        APID_SYNC_RESULT if user_data.len() == 40 + AUTH_TAG_LEN => SatelliteMessage::SyncResult {
+            ground_sent: be_u64(user_data, 0),
+            satellite_receive: be_u64(user_data, 1),
+            satellite_send: be_u64(user_data, 2),
+            ground_receive: be_u64(user_data, 3),
+            auth: CommandAuth { counter: be_u64(user_data, 4), tag: user_data[40..].try_into().unwrap() },
+        },
+        APID_NACK if user_data.len() == 12 => SatelliteMessage::Nack {
+            base: u32::from_be_bytes(user_data[..4].try_into().unwrap()),
+            bitmap: u64::from_be_bytes(user_data[4..].try_into().unwrap()),
+        },
+        APID_COMMAND => {
+            let (command, command_id, sent_at, execute_at, auth) = bincode::deserialize(user_data).map_err(|_| SpacePacketError::Malformed)?;
+            SatelliteMessage::Command { command, command_id, sent_at, execute_at, auth }
+        }
+        APID_TELEMETRY => SatelliteMessage::Telemetry {
+            event: bincode::deserialize(user_data).map_err(|_| SpacePacketError::Malformed)?,
+        },
+        APID_TIME_CORRELATION => {
+            let (correlation, auth) = bincode::deserialize(user_data).map_err(|_| SpacePacketError::Malformed)?;
+            SatelliteMessage::TimeCorrelation { correlation, auth }
+        }
+        APID_SYNC_REQUEST | APID_SYNC_RESPONSE | APID_SYNC_RESULT | APID_NACK => return Err(SpacePacketError::Malformed),
+        _ => return Err(SpacePacketError::UnknownApid),
+    };
+
+    if apid_of(&payload).1 != packet_type {
+        return Err(SpacePacketError::TypeMismatch);
+    }
+
+    Ok(TelemetryPacket {
+        priority,
+        creation_time,
+        payload,
+        sequence_no: sequence_count as u32,
+    })
+}
+
+// Rebuilds the full sequence_no from the 14-bit count, picking the value closest to the last one seen
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SequenceCounter {
+    last: u32,
+}
+
+impl SequenceCounter {
+    // Picks up from a full number the peer announced, so the next count unwraps next to it and not a multiple of
+    // SEQUENCE_COUNT_MODULO away
+    pub fn starting_at(sequence_no: u32) -> Self {
+        Self { last: sequence_no }
+    }
+
+    pub fn unwrap(&mut self, count: u16) -> u32 {
+        let count = count as u32 % SEQUENCE_COUNT_MODULO;
+        let base = self.last - self.last % SEQUENCE_COUNT_MODULO;
+        let half = SEQUENCE_COUNT_MODULO / 2;
+
+        let candidate = base + count;
+        let sequence = if candidate > self.last.saturating_add(half) && base >= SEQUENCE_COUNT_MODULO {
+            candidate - SEQUENCE_COUNT_MODULO
+        } else if candidate + half < self.last {
+            candidate + SEQUENCE_COUNT_MODULO
+        } else {
+            candidate
+        };
+
+        // Retransmissions of older packets must not pull the reference backwards
+        self.last = self.last.max(sequence);
+        sequence
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum WireFormat {
+    Bincode,
+    SpacePacket,
+}
+
+impl WireFormat {
+    pub fn negotiated(capabilities: Capabilities) -> Self {
+        if capabilities.contains(Capabilities::SPACE_PACKETS) {
+            WireFormat::SpacePacket
+        } else {
+            WireFormat::Bincode
+        }
+    }
+}
+
+// Encodes TelemetryPackets into frame bodies in the format agreed for the current pass.
+// The sequence counter outlives the pass so sequence numbers keep increasing across windows
+#[derive(Debug, Clone, Copy)]
+pub struct PacketCodec {
+    pub format: WireFormat,
+    sequence: SequenceCounter,
+}
+
+impl PacketCodec {
+    pub fn new(format: WireFormat) -> Self {
+        Self {
+            format,
+            sequence: SequenceCounter::default(),
+        }
+    }
+
+    // Reseeded at every handshake. A station joining after a handover, a restarted one or a long break between
+    // passes would otherwise carry a reference thousands of numbers behind the peer's
+    pub fn resume_at(&mut self, next_sequence_no: u32) {
+        self.sequence = SequenceCounter::starting_at(next_sequence_no);
+    }
+
+    pub fn encode(&self, packet: &TelemetryPacket) -> Option<Vec<u8>> {
+        match self.format {
+            WireFormat::Bincode => bincode::serialize(packet).ok(),
+            WireFormat::SpacePacket => encode_space_packet(packet),
+        }
+    }
+
+    pub fn decode(&mut self, bytes: &[u8]) -> Option<TelemetryPacket> {
+        match self.format {
+            WireFormat::Bincode => bincode::deserialize(bytes).ok(),
+            WireFormat::SpacePacket => {
+                let mut packet = decode_space_packet(bytes).ok()?;
+                packet.sequence_no = self.sequence.unwrap(packet.sequence_no as u16);
+                Some(packet)
+            }
+        }
+    }
+}
+
+// The index-th big-endian u64 of a sync packet, the length was checked against the APID
+fn be_u64(user_data: &[u8], index: usize) -> u64 {
+    u64::from_be_bytes(user_data[index * 8..(index + 1) * 8].try_into().unwrap())
+}
@@ -0,0 +1,76 @@
+// Mission elapsed time to UTC: the clock fit carries satellite uptime onto a ground station's uptime and the station's
+// boot time carries that onto UTC. Kept by the ground, uplinked after every sync exchange and written to both logs
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::clock_sync::{ClockEstimate, SyncQuality};
+
+const PPB: i128 = 1_000_000_000;
+const US_PER_DAY: u64 = 86_400_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeCorrelation {
+    pub reference_us: u64, // Satellite uptime the coefficients hold at
+    pub utc_us: u64, // UTC at reference_us, μs since the Unix epoch
+    pub drift_ppb: i64, // How much faster UTC runs than the satellite clock
+    pub error_us: u64,
+    pub quality: SyncQuality,
+}
+
+impl TimeCorrelation {
+    pub const NONE: Self = Self { reference_us: 0, utc_us: 0, drift_ppb: 0, error_us: 0, quality: SyncQuality::Unsynced };
+
+    // ground_boot_utc_us is UTC at the station's uptime 0, the clock the estimate was fitted against
+    pub fn from_estimate(estimate: &ClockEstimate, ground_boot_utc_us: u64) -> Self {
+        if estimate.quality == SyncQuality::Unsynced {
+            return Self::NONE;
+        }
+
+        Self {
+            reference_us: estimate.reference_us,
+            utc_us: ground_boot_utc_us + estimate.ground_time(estimate.reference_us),
+            drift_ppb: estimate.drift_ppb,
+            error_us: estimate.error_us,
+            quality: estimate.quality,
+        }
+    }
+
+    // None until the first exchange, an unsynced satellite clock says nothing about UTC
+    pub fn utc_us(&self, local_us: u64) -> Option<u64> {
+        if self.quality == SyncQuality::Unsynced {
+            return None;
+        }
+
+        let elapsed = local_us as i128 - self.reference_us as i128;
+        let utc = self.utc_us as i128 + elapsed + elapsed * self.drift_ppb as i128 / PPB;
+        Some(utc.max(0) as u64)
+    }
+}
+
+// UTC as ISO 8601 with microseconds, "1970-01-01T00:00:00.000000Z"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Utc(pub u64);
+
+impl fmt::Display for Utc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = civil_from_days((self.0 / US_PER_DAY) as i64);
+        let of_day = self.0 % US_PER_DAY;
+        let seconds = of_day / 1_000_000;
+
+        write!(f, "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+            year, month, day, seconds / 3_600, seconds / 60 % 60, seconds % 60, of_day % 1_000_000)
+    }
+}
+
+// Days since 1970-01-01 to a proleptic Gregorian date, counted in 400-year eras starting on March 1st
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153; // From March
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
@@ -0,0 +1,389 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::framing::{encode_frame, FrameDecoder, MAX_FRAME_BODY};
+
+const READ_CHUNK: usize = 512;
+const DATAGRAM_CAPACITY: usize = MAX_FRAME_BODY + 16; // A whole frame always fits in one datagram
+const CHANNEL_OPEN_LIMIT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    Tcp,
+    Udp,
+}
+
+// Framed link to the peer. Opened at the start of every pass and closed at the end of it.
+// Every implementation carries the same sync-marker/CRC frames, so corruption is caught the same way
+pub trait LinkTransport {
+    // The satellite side reaches out, the ground side waits for the satellite. False if no link came up
+    fn open(&mut self) -> bool;
+    fn close(&mut self);
+    fn is_connected(&self) -> bool;
+    // Bytes that are already framed go out as they are, so a relay can pass on frames it has tampered with
+    fn send_encoded(&mut self, frame: &[u8]) -> bool;
+    fn send_frame(&mut self, body: &[u8]) -> bool {
+        encode_frame(body).is_some_and(|frame| self.send_encoded(&frame))
+    }
+    // None when no complete frame arrived within the timeout. A zero timeout only takes what has already arrived
+    fn recv_frame(&mut self, timeout: Duration) -> Option<Vec<u8>>;
+    // CRC failure and resync counters of the current link
+    fn decoder(&mut self) -> &mut FrameDecoder;
+}
+
+// set_read_timeout rejects a zero duration, and rounds anything short up to a scheduler tick
+fn socket_timeout(timeout: Duration) -> Option<Duration> {
+    Some(timeout.max(Duration::from_micros(1)))
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+enum TcpEndpoint {
+    Listener(TcpListener),
+    Connector { addr: SocketAddr, connect_timeout: Duration },
+}
+
+// One TCP connection per pass
+pub struct TcpTransport {
+    endpoint: TcpEndpoint,
+    stream: Option<TcpStream>,
+    decoder: FrameDecoder,
+    write_timeout: Duration,
+    read_timeout: Option<Duration>, // Zero while the stream is non-blocking
+}
+
+impl TcpTransport {
+    pub fn listen(addr: &str, write_timeout: Duration) -> io::Result<Self> {
+        Ok(Self::with_endpoint(TcpEndpoint::Listener(TcpListener::bind(addr)?), write_timeout))
+    }
+
+    pub fn connect(addr: SocketAddr, connect_timeout: Duration, write_timeout: Duration) -> Self {
+        Self::with_endpoint(TcpEndpoint::Connector { addr, connect_timeout }, write_timeout)
+    }
+
+    // Another end on the same listening socket, so several satellites can be in a pass at once
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let endpoint = match &self.endpoint {
+            TcpEndpoint::Listener(listener) => TcpEndpoint::Listener(listener.try_clone()?),
+            TcpEndpoint::Connector { addr, connect_timeout } => TcpEndpoint::Connector { addr: *addr, connect_timeout: *connect_timeout },
+        };
+
+        Ok(Self::with_endpoint(endpoint, self.write_timeout))
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match &self.endpoint {
+            TcpEndpoint::Listener(listener) => listener.local_addr().ok(),
+            TcpEndpoint::Connector { .. } => self.stream.as_ref()?.local_addr().ok(),
+        }
+    }
+
+    fn with_endpoint(endpoint: TcpEndpoint, write_timeout: Duration) -> Self {
+        Self { endpoint, stream: None, decoder: FrameDecoder::new(), write_timeout, read_timeout: None }
+    }
+}
+
+impl LinkTransport for TcpTransport {
+    fn open(&mut self) -> bool {
+        self.close();
+
+        let stream = match &self.endpoint {
+            TcpEndpoint::Listener(listener) => listener.accept().map(|(stream, _)| stream), // Blocks until the satellite calls
+            TcpEndpoint::Connector { addr, connect_timeout } => TcpStream::connect_timeout(addr, *connect_timeout),
+        };
+
+        let Ok(stream) = stream else {
+            return false;
+        };
+
+        let _ = stream.set_write_timeout(socket_timeout(self.write_timeout));
+        let _ = stream.set_nodelay(true);
+
+        self.stream = Some(stream);
+        true
+    }
+
+    fn close(&mut self) {
+        self.stream = None;
+        self.decoder = FrameDecoder::new();
+        self.read_timeout = None;
+    }
+
+    fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    fn send_encoded(&mut self, frame: &[u8]) -> bool {
+        let Some(stream) = self.stream.as_mut() else {
+            return false;
+        };
+
+        if self.read_timeout == Some(Duration::ZERO) { // Only reads poll, writes still wait out the write timeout
+            let _ = stream.set_nonblocking(false);
+            self.read_timeout = None;
+        }
+
+        stream.write_all(frame).is_ok()
+    }
+
+    fn recv_frame(&mut self, timeout: Duration) -> Option<Vec<u8>> {
+        let mut chunk = [0u8; READ_CHUNK];
+
+        loop {
+            if let Some(frame) = self.decoder.next_frame() {
+                return Some(frame);
+            }
+
+            let stream = self.stream.as_mut()?;
+
+            if self.read_timeout != Some(timeout) {
+                if timeout.is_zero() {
+                    let _ = stream.set_nonblocking(true);
+                } else {
+                    let _ = stream.set_nonblocking(false);
+                    let _ = stream.set_read_timeout(socket_timeout(timeout));
+                }
+                self.read_timeout = Some(timeout);
+            }
+
+            match stream.read(&mut chunk) {
+                Ok(0) => { // Peer hung up
+                    self.stream = None;
+                    return None;
+                }
+                Ok(n) => self.decoder.push(&chunk[..n]),
+                Err(e) if is_timeout(&e) => return None,
+                Err(_) => {
+                    self.stream = None;
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn decoder(&mut self) -> &mut FrameDecoder {
+        &mut self.decoder
+    }
+}
+
+// One frame per datagram. The listening side learns the peer from the first datagram of the pass
+pub struct UdpTransport {
+    socket: UdpSocket,
+    fixed_peer: Option<SocketAddr>,
+    peer: Option<SocketAddr>,
+    decoder: FrameDecoder,
+}
+
+impl UdpTransport {
+    pub fn listen(addr: &str) -> io::Result<Self> {
+        Ok(Self { socket: UdpSocket::bind(addr)?, fixed_peer: None, peer: None, decoder: FrameDecoder::new() })
+    }
+
+    pub fn connect(local: &str, peer: SocketAddr) -> io::Result<Self> {
+        Ok(Self { socket: UdpSocket::bind(local)?, fixed_peer: Some(peer), peer: None, decoder: FrameDecoder::new() })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.socket.local_addr().ok()
+    }
+
+    fn recv_queued(&mut self, datagram: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.socket.set_nonblocking(true)?;
+        let received = self.socket.recv_from(datagram);
+        let _ = self.socket.set_nonblocking(false);
+        received
+    }
+
+    // Whatever is still queued belongs to a pass that is over, and would be read as the start of the next one
+    fn discard_queued(&mut self) {
+        let mut datagram = [0u8; DATAGRAM_CAPACITY];
+
+        if self.socket.set_nonblocking(true).is_ok() {
+            while self.socket.recv_from(&mut datagram).is_ok() {}
+            let _ = self.socket.set_nonblocking(false);
+        }
+    }
+}
+
+impl LinkTransport for UdpTransport {
+    fn open(&mut self) -> bool {
+        self.close();
+
+        if let Some(peer) = self.fixed_peer { // The ground only ever answers, nothing queued can be for this pass
+            self.discard_queued();
+            self.peer = Some(peer);
+            return true;
+        }
+
+        let mut datagram = [0u8; DATAGRAM_CAPACITY];
+        let _ = self.socket.set_read_timeout(None); // Blocks until the satellite calls, like a TCP accept
+
+        match self.socket.recv_from(&mut datagram) {
+            Ok((n, from)) => {
+                self.peer = Some(from);
+                self.decoder.push(&datagram[..n]);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn close(&mut self) {
+        if self.peer.take().is_some() {
+            self.discard_queued();
+        }
+        self.decoder = FrameDecoder::new();
+    }
+
+    fn is_connected(&self) -> bool {
+        self.peer.is_some()
+    }
+
+    fn send_encoded(&mut self, frame: &[u8]) -> bool {
+        self.peer.is_some_and(|peer| self.socket.send_to(frame, peer).is_ok())
+    }
+
+    fn recv_frame(&mut self, timeout: Duration) -> Option<Vec<u8>> {
+        let mut datagram = [0u8; DATAGRAM_CAPACITY];
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if let Some(frame) = self.decoder.next_frame() {
+                return Some(frame);
+            }
+
+            let peer = self.peer?;
+
+            let received = if timeout.is_zero() {
+                self.recv_queued(&mut datagram)
+            } else {
+                let _ = self.socket.set_read_timeout(socket_timeout(deadline.saturating_duration_since(Instant::now())));
+                self.socket.recv_from(&mut datagram)
+            };
+
+            match received {
+                Ok((n, from)) if from == peer => self.decoder.push(&datagram[..n]),
+                Ok(_) => {} // Not part of this link
+                Err(_) => return None,
+            }
+
+            if Instant::now() >= deadline {
+                return self.decoder.next_frame();
+            }
+        }
+    }
+
+    fn decoder(&mut self) -> &mut FrameDecoder {
+        &mut self.decoder
+    }
+}
+
+// State shared by the two ends of an in-process link
+struct ChannelLink {
+    up: AtomicBool,
+    pass: AtomicU32, // Frames are tagged with the pass they were sent in, late ones are dropped
+}
+
+// In-process link for running both network threads in one test without sockets
+pub struct ChannelTransport {
+    tx: Sender<(u32, Vec<u8>)>,
+    rx: Receiver<(u32, Vec<u8>)>,
+    link: Arc<ChannelLink>,
+    initiator: bool,
+    pass: u32,
+    decoder: FrameDecoder,
+}
+
+impl ChannelTransport {
+    // (satellite end, ground end)
+    pub fn pair() -> (ChannelTransport, ChannelTransport) {
+        let (satellite_tx, ground_rx) = mpsc::channel();
+        let (ground_tx, satellite_rx) = mpsc::channel();
+        let link = Arc::new(ChannelLink { up: AtomicBool::new(false), pass: AtomicU32::new(0) });
+
+        let end = |tx, rx, initiator| ChannelTransport {
+            tx,
+            rx,
+            link: Arc::clone(&link),
+            initiator,
+            pass: 0,
+            decoder: FrameDecoder::new(),
+        };
+
+        (end(satellite_tx, satellite_rx, true), end(ground_tx, ground_rx, false))
+    }
+}
+
+impl LinkTransport for ChannelTransport {
+    fn open(&mut self) -> bool {
+        self.decoder = FrameDecoder::new();
+
+        if self.initiator {
+            self.pass = self.link.pass.fetch_add(1, Ordering::AcqRel) + 1;
+            self.link.up.store(true, Ordering::Release);
+            return true;
+        }
+
+        let started = Instant::now();
+        while started.elapsed() < CHANNEL_OPEN_LIMIT {
+            if self.link.up.load(Ordering::Acquire) {
+                self.pass = self.link.pass.load(Ordering::Acquire);
+                return true;
+            }
+            thread::sleep(Duration::from_micros(100));
+        }
+
+        false
+    }
+
+    fn close(&mut self) {
+        if self.is_connected() {
+            self.link.up.store(false, Ordering::Release);
+        }
+        self.pass = 0;
+    }
+
+    fn is_connected(&self) -> bool {
+        self.pass != 0
+            && self.link.up.load(Ordering::Acquire)
+            && self.link.pass.load(Ordering::Acquire) == self.pass
+    }
+
+    fn send_encoded(&mut self, frame: &[u8]) -> bool {
+        self.is_connected() && self.tx.send((self.pass, frame.to_vec())).is_ok()
+    }
+
+    fn recv_frame(&mut self, timeout: Duration) -> Option<Vec<u8>> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if let Some(frame) = self.decoder.next_frame() {
+                return Some(frame);
+            }
+
+            if !self.is_connected() {
+                return None;
+            }
+
+            match self.rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok((pass, bytes)) if pass == self.pass => self.decoder.push(&bytes),
+                Ok(_) => {} // Left over from an earlier pass
+                Err(_) => return None,
+            }
+        }
+    }
+
+    fn decoder(&mut self) -> &mut FrameDecoder {
+        &mut self.decoder
+    }
+}
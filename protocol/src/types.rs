@@ -0,0 +1,490 @@
+use serde::{Serialize, Deserialize};
+use crate::handshake::{Capabilities, HandshakeReject};
+use crate::auth::{AuthReject, CommandAuth};
+use crate::clock_sync::SyncQuality;
+use crate::time_correlation::TimeCorrelation;
+use crate::sequence::{SequenceStep, SequenceAbort};
+use crate::parameter::ParameterID;
+
+// Variant order is part of the wire format (bincode uses the variant index), only append new variants
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[repr(u16)]
+pub enum TaskID {
+    None = 0,
+    // Command Tasks
+    RotateAntenna = 101,
+    SetPowerMode = 102,
+    ClearSubsystemFault = 103,
+    RequestRetransmit = 104,
+
+    // Scheduled Tasks
+    ThermalSensor = 201,
+    PitchAndYawSensor = 202,
+    MoistureSensor = 203,
+
+    GlobalSystem = 300,
+    NetworkService = 301,
+    DownlinkNetworkService = 302,
+    UplinkNetworkService = 303,
+
+    // Time Tag Commands
+    ListTimeTags = 105,
+    DeleteTimeTag = 106,
+
+    // Stored Sequence Commands
+    UploadSequenceStep = 107,
+    ListSequences = 108,
+    StartSequence = 109, // Also the progress of a running sequence
+    AbortSequence = 110,
+    DeleteSequence = 111,
+
+    // Parameter Table Commands
+    SetParameter = 112,
+    GetParameter = 113,
+    DumpParameters = 114,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[repr(u16)]
+pub enum EventID {
+    // Command Events
+    CommandNotFound = 101,
+    SubsystemFault = 102,
+    SubsystemFixed = 103,
+    CommandCompletion = 104,
+
+    // Scheduled Task Events
+    StartDelay = 201,
+    CompletionDelay = 202,
+    TaskFault = 203,
+    DataCorruption = 204,
+    TaskCompletion = 205,
+
+    // System Mode Event
+    DegradedMode = 301,
+    NormalMode = 302,
+    Startup = 303,
+    MissionAbort = 304,
+    Shutdown = 305,
+
+    // Network Events
+    MissedCommunication = 401,
+    DataLoss = 402,
+    RetransmitFailed = 403,
+    SyncStart = 404,
+    SyncOngoing = 405,
+    SyncCompleted = 406,
+    ConnectionStart = 407,
+    ConnectionEnd = 408,
+
+    // System Info
+    QueuePerformance = 501,   // Latency and Drops - Only Downlink No Event for Uplink
+    ResourceUtilization = 502, // CPU and Buffer Fill
+    NetworkPerformance = 503,
+
+    // Security Events
+    AuthenticationFailure = 601,
+
+    // Command Tracking Events
+    CommandAccepted = 105,
+    CommandRejected = 106,
+    CommandStageChange = 107, // Ground only, pending-command table transitions
+
+    // Uplink Recovery Events
+    UplinkGap = 409, // Satellite -> Ground, asks for a replay of missing uplink frames
+
+    // Downlink Budget Events
+    DownlinkUsage = 410, // End of pass report of the downlink rate limiter
+
+    // Configuration Events
+    ConfigReload = 306, // SIGHUP re-read the config file
+
+    // Ground Station Events
+    Handover = 411, // The satellite moved its link to another ground station
+    PassPredicted = 412, // Next AOS/LOS of a spacecraft over this station
+    PassPlanned = 413, // Held commands given a slot in the pass that just started
+    UplinkCarriedOver = 414, // Commands still queued at LOS, held for the next pass
+
+    // Time Tag Events
+    TimeTagQueued = 108, // A command waits onboard for its execution time
+    TimeTagListed = 109, // One per queued entry in answer to ListTimeTags
+    TimeTagDeleted = 110,
+    TimeTagExecuted = 111, // A queued command came due and started
+    TimeCorrelation = 112, // Coefficients now in effect, so a log can be re-timed later
+    SequenceStored = 113, // A step of an upload
+    SequenceListed = 114, // One per stored sequence in answer to ListSequences
+    SequenceStarted = 115,
+    SequenceStepRun = 116, // A command step carried out or a wait met
+    SequenceCompleted = 117,
+    SequenceAborted = 118,
+    SequenceDeleted = 119,
+    ParameterChanged = 120, // Old and new value, from a SetParameter or a config reload
+    ParameterValue = 121, // In answer to GetParameter, or one per entry for DumpParameters
+    ConfigChanged = 307, // One per reloadable key a reload gave a new value
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone, Copy)]
+pub enum EventData {
+    None,
+    QueuePerformance {
+        latency_ms: u64,
+        jitter_ms: u64,
+        buffer_fill_rate: u32,
+        sample_count: u32
+    },
+    SchedulingDrift { drift_ms: u32 },
+    Hardware {
+        value: u32,
+        latency_ms: u64,
+        jitter_ms: u64,
+        sample_count: u32
+    }, // Sensors
+    CorruptedHardware { value: u32, recovery_time: u64 }, // Sensors
+    Subsystem { subsystem_id: SubsystemID },
+    SystemStats { active_ms: u64, inactive_ms: u64 }, // SystemState CPU Active ms, System Uptime - Active
+    FaultRecovery { recovery_time: u64 },
+    TimeSync { offset: u64 },
+    PacketDrain { count: u32 },
+    NetworkPerformance {
+        priority: Priority,
+        latency_ms: u64,
+        jitter_ms: u64,
+        sample_count: u32
+    },
+    Handshake {
+        version: u16,
+        spacecraft_id: u16,
+        capabilities: Capabilities,
+    },
+    HandshakeRejected {
+        reason: HandshakeReject,
+        peer_version: u16,
+        peer_spacecraft_id: u16,
+    },
+    FrameIntegrity { // Running totals for the link
+        crc_failures: u32,
+        resyncs: u32,
+        decode_failures: u32,
+    },
+    AuthFailure {
+        reason: AuthReject,
+        counter: u64,
+        last_counter: u64, // Highest counter accepted so far
+    },
+    CommandAck { command_id: u32 }, // Echo of the ground-assigned ID
+    CommandStage {
+        command_id: u32,
+        stage: CommandStage,
+        attempts: u32,
+    },
+    SequenceGap { first: u32, count: u32 }, // Uplink sequence numbers never received
+    RetransmitUnavailable { base: u32, bitmap: u64 }, // Part of a NACK already gone from the history
+    DownlinkUsage {
+        bytes_sent: u64, // Framed bytes on the wire during the pass
+        budget_bytes: u64, // Most the rate limiter would have let through
+        queued: u32, // Packets still waiting in the downlink buffer when the pass ended
+    },
+    ConfigReload {
+        changed: u16, // Reloadable keys now running with a new value
+        rejected: u16, // Keys that need a restart, any of them refuses the whole reload
+    },
+    Handover {
+        from_station: u8, // Station of the previous pass
+        to_station: u8,
+    },
+    PassPrediction {
+        aos_ms: u64, // Uptime of the station that predicted it
+        los_ms: u64,
+        max_elevation: u16, // Degrees * 100
+    },
+    UplinkPlan {
+        planned: u32, // Expected to fit before LOS
+        held: u32, // Left for a later pass
+    },
+    SyncSample {
+        offset_us: i64, // Ground minus satellite, from one exchange
+        rtt_us: u64,
+    },
+    ClockSync {
+        offset_us: i64, // Ground minus satellite, as the satellite now corrects its timestamps
+        drift_ppb: i32,
+        error_us: u32,
+        quality: SyncQuality,
+    },
+    TimeTag {
+        command_id: u32,
+        execute_at: u64, // Synchronized time, the ground's clock
+        queued: u16, // Entries in the queue after the change
+    },
+    TimeTagExecution {
+        command_id: u32,
+        tagged_at: u64, // Both synchronized time
+        started_at: u64,
+    },
+    TimeCorrelation {
+        reference_us: u64, // Satellite uptime
+        utc_us: u64, // UTC at reference_us
+        drift_ppb: i32,
+        error_us: u32,
+        quality: SyncQuality,
+    },
+    Sequence {
+        sequence_id: u8,
+        step: u8, // Steps stored for uploads and listings, otherwise the step the event is about
+        steps: u8,
+    },
+    SequenceAborted {
+        sequence_id: u8,
+        step: u8, // Stopped before it
+        reason: SequenceAbort,
+    },
+    CommandRejected {
+        command_id: u32,
+        reason: RejectReason,
+    },
+    Parameter {
+        parameter: ParameterID,
+        value: u64,
+        version: u32, // Of the whole table
+    },
+    ParameterChange {
+        parameter: ParameterID,
+        old_value: u64,
+        new_value: u64,
+        version: u32, // Of the whole table once the change is in
+    },
+    ConfigChange {
+        key: u8, // Index into the RELOADABLE_KEYS of the binary that reloaded
+        old_value: u64,
+        new_value: u64,
+    },
+}
+
+// Declared in lifecycle order, a command only ever moves to a later stage
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub enum CommandStage {
+    Queued,
+    Uplinked,
+    Accepted,
+    Executed,
+    Failed,
+    TimedOut,
+}
+
+// Why the satellite turned a command down, sent with EventID::CommandRejected
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum RejectReason {
+    Interlock, // The subsystem it needs is faulted
+    OutOfRange, // A parameter outside what Command::validate allows
+    WrongMode, // Not allowed in the mode or state the satellite is in
+    Unauthorized, // Correctly signed, but under a counter already accepted
+    Stale, // Waited onboard past stale_packet_limit_ms
+    Full, // No room left onboard to hold it
+    NotFound, // Names a time tag, sequence or packet that is not there
+    Unsynced, // Time-tagged before the clock was synchronized
+    Cancelled, // A time tag the ground deleted before it came due
+    PriorityNotAllowed, // Sent at a priority only fault clears may use
+    Conflicts, // Within its own limits, but breaks a rule between parameter entries
+}
+
+impl CommandStage {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, CommandStage::Executed | CommandStage::Failed | CommandStage::TimedOut)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Event {
+    pub task_id: TaskID,
+    pub event_id: EventID,
+    pub data: EventData,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Copy, Clone)]
+pub enum SubsystemID {
+    Antenna = 0,
+    Power = 1,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Copy, Clone)]
+#[repr(u16)]
+pub enum Priority {
+    Low = 0,
+    Normal = 3,
+    Critical = 9,
+    Emergency = 10,
+}
+
+pub const MAX_TARGET_ANGLE: u16 = 35_999; // Hundredths of a degree
+
+// SetPowerMode modes, each draws more than the one before
+pub const POWER_MODE_SAFE: u8 = 0;
+pub const POWER_MODE_NOMINAL: u8 = 1;
+pub const POWER_MODE_PAYLOAD: u8 = 2; // Refused while the satellite is degraded
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Command {
+    RotateAntenna {
+        target_angle: u16,
+    },
+    SetPowerMode {
+        mode: u8
+    },
+    ClearSubsystemFault {
+        subsystem_id: SubsystemID,
+    },
+    RequestRetransmit {
+        sequence_no: u32
+    },
+    ListTimeTags,
+    DeleteTimeTag {
+        command_id: u32, // Of the time-tagged command to take out of the queue
+    },
+    UploadSequenceStep {
+        sequence_id: u8,
+        index: u8,
+        count: u8, // Steps in the whole sequence, a different count than the stored one starts the upload over
+        delay_ms: u32, // After the previous step finished
+        step: SequenceStep,
+    },
+    ListSequences,
+    StartSequence {
+        sequence_id: u8,
+    },
+    AbortSequence {
+        sequence_id: u8,
+    },
+    DeleteSequence {
+        sequence_id: u8,
+    },
+    SetParameter {
+        parameter: ParameterID,
+        value: u64,
+    },
+    GetParameter {
+        parameter: ParameterID,
+    },
+    DumpParameters,
+}
+
+impl Command {
+    pub fn required_health(&self) -> Option<SubsystemID> {
+        match self {
+            Command::RotateAntenna { .. } => Some(SubsystemID::Antenna),
+            Command::SetPowerMode { .. } => Some(SubsystemID::Power),
+            Command::ClearSubsystemFault { .. } => None,
+            Command::RequestRetransmit { .. } => None,
+            Command::ListTimeTags => None,
+            Command::DeleteTimeTag { .. } => None,
+            Command::UploadSequenceStep { .. } => None,
+            Command::ListSequences => None,
+            Command::StartSequence { .. } => None, // Each step is checked as it comes due
+            Command::AbortSequence { .. } => None,
+            Command::DeleteSequence { .. } => None,
+            Command::SetParameter { .. } => None,
+            Command::GetParameter { .. } => None,
+            Command::DumpParameters => None,
+        }
+    }
+
+    // Parameter ranges only, whatever depends on the state of the satellite is checked onboard when the command runs
+    pub fn validate(&self) -> Result<(), RejectReason> {
+        match *self {
+            Command::RotateAntenna { target_angle } if target_angle > MAX_TARGET_ANGLE => Err(RejectReason::OutOfRange),
+            Command::SetPowerMode { mode } if mode > POWER_MODE_PAYLOAD => Err(RejectReason::OutOfRange),
+            Command::UploadSequenceStep { step, .. } => step.command().map_or(Ok(()), |command| command.validate()),
+            Command::SetParameter { parameter, value } => parameter.check(value).map_err(RejectReason::from), // Rules between entries are checked onboard
+            _ => Ok(()),
+        }
+    }
+
+    pub fn task_id(&self) -> TaskID {
+        match self {
+            Command::RotateAntenna { .. } => TaskID::RotateAntenna,
+            Command::SetPowerMode { .. } => TaskID::SetPowerMode,
+            Command::ClearSubsystemFault { .. } => TaskID::ClearSubsystemFault,
+            Command::RequestRetransmit { .. } => TaskID::RequestRetransmit,
+            Command::ListTimeTags => TaskID::ListTimeTags,
+            Command::DeleteTimeTag { .. } => TaskID::DeleteTimeTag,
+            Command::UploadSequenceStep { .. } => TaskID::UploadSequenceStep,
+            Command::ListSequences => TaskID::ListSequences,
+            Command::StartSequence { .. } => TaskID::StartSequence,
+            Command::AbortSequence { .. } => TaskID::AbortSequence,
+            Command::DeleteSequence { .. } => TaskID::DeleteSequence,
+            Command::SetParameter { .. } => TaskID::SetParameter,
+            Command::GetParameter { .. } => TaskID::GetParameter,
+            Command::DumpParameters => TaskID::DumpParameters,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Copy, Clone)]
+pub enum SatelliteMessage {
+    SyncRequest,
+    SyncResponse {
+        ground_sent: u64, // t1, echoed from the request
+        satellite_receive: u64, // t2
+        satellite_send: u64, // t3, stamped as the frame goes out
+    },
+    SyncResult { // All four, so the satellite can fit its own clock against the ground's
+        ground_sent: u64,
+        satellite_receive: u64,
+        satellite_send: u64,
+        ground_receive: u64, // t4
+        auth: CommandAuth, // Signed like a command, it moves the clock time tags run on
+    },
+    Command {
+        command: Command,
+        command_id: u32, // Assigned by the ground, echoed back in acceptance, rejection and completion telemetry
+        sent_at: u64,
+        execute_at: u64, // Synchronized time to run it at, 0 runs it on arrival
+        auth: CommandAuth,
+    },
+    Telemetry {
+        event: Event,
+    },
+    Nack { base: u32, bitmap: u64 }, // Ground -> Satellite, downlink sequence numbers to send again
+    TimeCorrelation { correlation: TimeCorrelation, auth: CommandAuth }, // Ground -> Satellite, the ground's mapping after its latest exchange
+}
+
+impl SatelliteMessage {
+    pub fn command_task_id(&self) -> TaskID {
+        match self {
+            SatelliteMessage::Command { command, .. } => command.task_id(),
+            _ => TaskID::NetworkService,
+        }
+    }
+
+    // Present on the messages that need the uplink key, anything else carries no authority
+    pub fn auth(&self) -> Option<CommandAuth> {
+        match self {
+            SatelliteMessage::SyncResult { auth, .. }
+            | SatelliteMessage::Command { auth, .. }
+            | SatelliteMessage::TimeCorrelation { auth, .. } => Some(*auth),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Copy, Clone)]
+pub struct TelemetryPacket {  // When Pop From Buffer, Recreate creation time with sent time
+    pub priority: Priority,
+    pub creation_time: u64, // Network Arrival Time or Data Creation Time
+    pub payload: SatelliteMessage,
+    pub sequence_no: u32,
+}
+
+impl PartialOrd for TelemetryPacket {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TelemetryPacket {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.priority.cmp(&other.priority)
+            .then(other.creation_time.cmp(&self.creation_time))
+    }
+}
@@ -0,0 +1,198 @@
+use rts_protocol::*;
+
+const KEY_HEX: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+fn key() -> AuthKey {
+    AuthKey::from_hex(KEY_HEX).unwrap()
+}
+
+fn command(target_angle: u16) -> SatelliteMessage {
+    SatelliteMessage::Command {
+        command: Command::RotateAntenna { target_angle },
+        command_id: 7,
+        sent_at: 1_000,
+        execute_at: 5_000,
+        auth: CommandAuth::UNSIGNED,
+    }
+}
+
+fn signed(signer: &mut CommandSigner, mut message: SatelliteMessage) -> SatelliteMessage {
+    signer.sign(&mut message);
+    message
+}
+
+#[test]
+fn signed_commands_are_accepted_in_order() {
+    let mut signer = CommandSigner::new(key(), 10);
+    let mut verifier = CommandVerifier::new(key());
+
+    assert_eq!(verifier.verify(&signed(&mut signer, command(90))), Ok(()));
+    assert_eq!(verifier.verify(&signed(&mut signer, command(180))), Ok(()));
+    assert_eq!(verifier.last_counter, 11);
+}
+
+#[test]
+fn replayed_command_is_refused() {
+    let mut signer = CommandSigner::new(key(), 10);
+    let mut verifier = CommandVerifier::new(key());
+
+    let first = signed(&mut signer, command(90));
+    let second = signed(&mut signer, command(180));
+
+    assert_eq!(verifier.verify(&first), Ok(()));
+    assert_eq!(verifier.verify(&first), Err(AuthReject::Replayed));
+
+    assert_eq!(verifier.verify(&second), Ok(()));
+    assert_eq!(verifier.verify(&first), Err(AuthReject::Replayed)); // Older counter after a newer one
+}
+
+#[test]
+fn stations_signing_from_the_clock_take_turns() {
+    let mut first_station = CommandSigner::new(key(), 10);
+    let mut second_station = CommandSigner::new(key(), 500); // Started later
+    let mut verifier = CommandVerifier::new(key());
+
+    assert_eq!(verifier.verify(&signed(&mut first_station, command(90))), Ok(()));
+    assert_eq!(verifier.verify(&signed(&mut second_station, command(180))), Ok(()));
+
+    first_station.advance_to(1_000); // Its next pass comes after the second station's
+    assert_eq!(verifier.verify(&signed(&mut first_station, command(270))), Ok(()));
+    assert_eq!(verifier.last_counter, 1_000);
+
+    second_station.advance_to(20); // Never moves a counter back
+    assert_eq!(verifier.verify(&signed(&mut second_station, command(0))), Err(AuthReject::Replayed));
+}
+
+#[test]
+fn tampered_command_is_refused() {
+    let mut signer = CommandSigner::new(key(), 10);
+    let mut verifier = CommandVerifier::new(key());
+
+    let SatelliteMessage::Command { command_id, sent_at, execute_at, auth, .. } = signed(&mut signer, command(90)) else { unreachable!() };
+
+    let tampered_command = SatelliteMessage::Command { command: Command::RotateAntenna { target_angle: 270 }, command_id, sent_at, execute_at, auth };
+    assert_eq!(verifier.verify(&tampered_command), Err(AuthReject::BadTag));
+
+    let tampered_id = SatelliteMessage::Command { command: Command::RotateAntenna { target_angle: 90 }, command_id: command_id + 1, sent_at, execute_at, auth };
+    assert_eq!(verifier.verify(&tampered_id), Err(AuthReject::BadTag));
+
+    let bumped_counter = SatelliteMessage::Command {
+        command: Command::RotateAntenna { target_angle: 90 },
+        command_id,
+        sent_at,
+        execute_at,
+        auth: CommandAuth { counter: auth.counter + 100, ..auth },
+    };
+    assert_eq!(verifier.verify(&bumped_counter), Err(AuthReject::BadTag));
+
+    let retagged = SatelliteMessage::Command { command: Command::RotateAntenna { target_angle: 90 }, command_id, sent_at, execute_at: execute_at + 60_000, auth };
+    assert_eq!(verifier.verify(&retagged), Err(AuthReject::BadTag)); // A command cannot be moved to another time
+
+    let mut flipped_tag = auth;
+    flipped_tag.tag[0] ^= 0x01;
+    let flipped = SatelliteMessage::Command { command: Command::RotateAntenna { target_angle: 90 }, command_id, sent_at, execute_at, auth: flipped_tag };
+    assert_eq!(verifier.verify(&flipped), Err(AuthReject::BadTag));
+
+    // Failed attempts must not move the counter
+    assert_eq!(verifier.last_counter, 0);
+}
+
+#[test]
+fn wrong_key_and_unsigned_commands_are_refused() {
+    let mut verifier = CommandVerifier::new(key());
+    let mut impostor = CommandSigner::new(AuthKey::new([0xFF; AUTH_KEY_LEN]), 10);
+
+    assert_eq!(verifier.verify(&signed(&mut impostor, command(90))), Err(AuthReject::BadTag));
+    assert_eq!(verifier.verify(&command(90)), Err(AuthReject::BadTag));
+}
+
+#[test]
+fn messages_without_authority_pass_through() {
+    let mut verifier = CommandVerifier::new(key());
+
+    assert_eq!(verifier.verify(&SatelliteMessage::SyncRequest), Ok(()));
+    assert_eq!(verifier.verify(&SatelliteMessage::Nack { base: 1, bitmap: 1 }), Ok(()));
+    assert_eq!(verifier.last_counter, 0);
+}
+
+#[test]
+fn time_state_messages_are_signed_like_commands() {
+    let mut signer = CommandSigner::new(key(), 10);
+    let mut verifier = CommandVerifier::new(key());
+
+    let sync_result = SatelliteMessage::SyncResult { ground_sent: 1, satellite_receive: 2, satellite_send: 3, ground_receive: 4, auth: CommandAuth::UNSIGNED };
+    let correlation = SatelliteMessage::TimeCorrelation { correlation: TimeCorrelation::NONE, auth: CommandAuth::UNSIGNED };
+
+    assert_eq!(verifier.verify(&sync_result), Err(AuthReject::BadTag));
+    assert_eq!(verifier.verify(&correlation), Err(AuthReject::BadTag));
+
+    let signed_result = signed(&mut signer, sync_result);
+    assert_eq!(verifier.verify(&signed_result), Ok(()));
+    assert_eq!(verifier.verify(&signed(&mut signer, correlation)), Ok(()));
+    assert_eq!(verifier.verify(&signed_result), Err(AuthReject::Replayed));
+    assert_eq!(verifier.last_counter, 11);
+
+    // A tag is bound to its message, moving it onto an altered one gives it away
+    let SatelliteMessage::SyncResult { auth, .. } = signed_result else { unreachable!() };
+    let forged = SatelliteMessage::SyncResult { ground_sent: 1, satellite_receive: 2, satellite_send: 3, ground_receive: 4_000_000, auth: CommandAuth { counter: 20, ..auth } };
+    assert_eq!(verifier.verify(&forged), Err(AuthReject::BadTag));
+}
+
+#[test]
+fn persistent_counter_survives_a_restart() {
+    let path = std::env::temp_dir().join(format!("rts_uplink_counter_{}", std::process::id()));
+    std::fs::remove_file(&path).ok();
+
+    let mut signer = CommandSigner::new(key(), 10);
+    let captured = signed(&mut signer, command(90));
+
+    let mut verifier = CommandVerifier::persistent(key(), &path).unwrap();
+    assert_eq!(verifier.last_counter, 0); // No file yet, a first boot
+    assert_eq!(verifier.verify(&captured), Ok(()));
+    drop(verifier);
+
+    let mut restarted = CommandVerifier::persistent(key(), &path).unwrap();
+    assert_eq!(restarted.last_counter, 10);
+    assert_eq!(restarted.verify(&captured), Err(AuthReject::Replayed));
+    assert_eq!(restarted.verify(&signed(&mut signer, command(180))), Ok(()));
+    assert_eq!(restarted.store_failures(), 0);
+    drop(restarted); // Its writer would otherwise race the corrupt file below
+
+    std::fs::write(&path, "not a counter").unwrap();
+    assert!(CommandVerifier::persistent(key(), &path).is_err());
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn key_file_is_parsed_as_hex() {
+    assert!(AuthKey::from_hex(&format!("  {KEY_HEX}\n")).is_some());
+    assert!(AuthKey::from_hex(&KEY_HEX[2..]).is_none());
+    assert!(AuthKey::from_hex(&KEY_HEX.replace('0', "g")).is_none());
+
+    let path = std::env::temp_dir().join(format!("rts_uplink_key_{}", std::process::id()));
+    std::fs::write(&path, KEY_HEX).unwrap();
+
+    let loaded = AuthKey::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let mut signer = CommandSigner::new(loaded, 1);
+    assert_eq!(CommandVerifier::new(key()).verify(&signed(&mut signer, command(90))), Ok(()));
+}
+
+#[test]
+fn a_counter_that_cannot_be_written_still_accepts_the_command() {
+    let path = std::env::temp_dir().join(format!("rts_missing_{}", std::process::id())).join("uplink.counter");
+    let mut signer = CommandSigner::new(key(), 10);
+
+    let mut verifier = CommandVerifier::persistent(key(), &path).unwrap();
+    assert_eq!(verifier.verify(&signed(&mut signer, command(90))), Ok(()));
+
+    // Written from another thread, so the failure shows up a little after verify returns
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while verifier.store_failures() == 0 && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    assert_eq!(verifier.store_failures(), 1);
+    assert_eq!(verifier.verify(&signed(&mut signer, command(180))), Ok(()));
+}
@@ -0,0 +1,86 @@
+use rts_protocol::*;
+
+// Ground clock = satellite clock + offset_us + drift_ppb of the satellite time elapsed, each leg takes its own delay
+fn exchange(satellite_receive: u64, offset_us: i64, drift_ppb: i64, uplink_us: u64, downlink_us: u64) -> SyncExchange {
+    let to_ground = |local: u64| (local as i64 + offset_us + local as i64 * drift_ppb / 1_000_000_000) as u64;
+    let satellite_send = satellite_receive + 300; // Turnaround
+
+    SyncExchange {
+        ground_sent: to_ground(satellite_receive) - uplink_us,
+        satellite_receive,
+        satellite_send,
+        ground_receive: to_ground(satellite_send) + downlink_us,
+    }
+}
+
+#[test]
+fn satellite_ahead_of_ground_gives_a_negative_offset() {
+    let ahead = exchange(1_000_000, -500, 0, 100, 100);
+
+    assert_eq!(ahead.offset_us(), -500);
+    assert_eq!(ahead.rtt_us(), 200); // The turnaround is not part of it
+}
+
+#[test]
+fn quality_improves_with_exchanges() {
+    let mut estimator = ClockEstimator::new();
+    assert_eq!(estimator.estimate().quality, SyncQuality::Unsynced);
+
+    estimator.add(&exchange(1_000_000, 2_000, 0, 100, 100));
+    assert_eq!(estimator.estimate().quality, SyncQuality::Coarse);
+    assert_eq!(estimator.estimate().offset_us, 2_000);
+
+    for n in 2..=MIN_DRIFT_SAMPLES as u64 {
+        estimator.add(&exchange(n * 1_000_000, 2_000, 0, 100, 100));
+    }
+
+    assert_eq!(estimator.estimate().quality, SyncQuality::Locked);
+}
+
+#[test]
+fn drift_is_estimated_and_extrapolated() {
+    let mut estimator = ClockEstimator::new();
+
+    for n in 1..=10 {
+        estimator.add(&exchange(n * 1_000_000, 5_000, 50_000, 100 + n % 3, 100)); // 50 ppm, a little jitter on the uplink
+    }
+
+    let estimate = estimator.estimate();
+    assert!((estimate.drift_ppb - 50_000).abs() < 2_000);
+
+    let later = 20_000_000;
+    let expected = later + 5_000 + later * 50_000 / 1_000_000_000;
+    assert!(estimate.ground_time(later).abs_diff(expected) < 50); // 10 s past the last exchange
+}
+
+#[test]
+fn slow_exchanges_are_rejected() {
+    let mut estimator = ClockEstimator::new();
+
+    for n in 1..=8 {
+        assert!(estimator.add(&exchange(n * 1_000_000, 1_000, 0, 100, 100)));
+    }
+
+    let before = estimator.estimate();
+    let queued_on_the_uplink = exchange(9_000_000, 1_000, 0, 20_000, 100); // Would pull the offset 10 ms off
+
+    assert!(!estimator.add(&queued_on_the_uplink));
+    assert_eq!(estimator.estimate(), before);
+    assert_eq!(estimator.rejected, 1);
+}
+
+#[test]
+fn a_lasting_slowdown_is_accepted_in_the_end() {
+    let mut estimator = ClockEstimator::new();
+
+    for n in 1..=8 {
+        estimator.add(&exchange(n * 1_000_000, 1_000, 0, 100, 100));
+    }
+
+    let accepted: Vec<bool> = (9..=9 + RTT_OUTLIER_LIMIT as u64)
+        .map(|n| estimator.add(&exchange(n * 1_000_000, 1_000, 0, 5_000, 5_000)))
+        .collect();
+
+    assert_eq!(accepted.iter().filter(|&&kept| !kept).count(), RTT_OUTLIER_LIMIT as usize);
+    assert_eq!(accepted.last(), Some(&true));
+}
@@ -0,0 +1,89 @@
+use rts_protocol::*;
+
+fn key() -> AuthKey {
+    AuthKey::new([0x42; AUTH_KEY_LEN])
+}
+
+fn ciphers(capabilities: Capabilities) -> (LinkCipher, LinkCipher) {
+    let satellite_hello = Hello::new(1, capabilities);
+    let ground_hello = Hello::new(1, capabilities);
+
+    (
+        LinkCipher::negotiate(capabilities, &key(), &satellite_hello, &ground_hello, LinkRole::Satellite),
+        LinkCipher::negotiate(capabilities, &key(), &ground_hello, &satellite_hello, LinkRole::Ground),
+    )
+}
+
+#[test]
+fn clear_pass_hands_bodies_through() {
+    let (mut satellite, mut ground) = ciphers(Capabilities::NONE);
+
+    assert!(!satellite.is_encrypted());
+    assert_eq!(satellite.seal(b"telemetry".to_vec()), Some(b"telemetry".to_vec()));
+    assert_eq!(ground.open(b"telemetry".to_vec()), Some(b"telemetry".to_vec()));
+}
+
+#[cfg(not(feature = "encryption"))]
+#[test]
+fn encryption_is_never_offered_without_the_feature() {
+    assert_eq!(ENCRYPTION_SUPPORT, Capabilities::NONE);
+
+    let (satellite, _) = ciphers(Capabilities::ENCRYPTION);
+    assert!(!satellite.is_encrypted());
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn encrypted_frames_round_trip_both_ways() {
+    let (mut satellite, mut ground) = ciphers(Capabilities::ENCRYPTION);
+    assert!(satellite.is_encrypted() && ground.is_encrypted());
+
+    for _ in 0..3 {
+        let downlink = satellite.seal(b"telemetry".to_vec()).unwrap();
+        assert!(!downlink.windows(9).any(|window| window == b"telemetry"));
+        assert_eq!(ground.open(downlink), Some(b"telemetry".to_vec()));
+
+        let uplink = ground.seal(b"command".to_vec()).unwrap();
+        assert_eq!(satellite.open(uplink), Some(b"command".to_vec()));
+    }
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn nonces_follow_the_frame_sequence_number() {
+    let (mut satellite, _) = ciphers(Capabilities::ENCRYPTION);
+
+    let first = satellite.seal(b"same".to_vec()).unwrap();
+    let second = satellite.seal(b"same".to_vec()).unwrap();
+
+    assert_eq!(first[..8], 0u64.to_be_bytes());
+    assert_eq!(second[..8], 1u64.to_be_bytes());
+    assert_ne!(first[8..], second[8..]);
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn tampered_replayed_and_reflected_frames_are_refused() {
+    let (mut satellite, mut ground) = ciphers(Capabilities::ENCRYPTION);
+
+    let mut tampered = satellite.seal(b"telemetry".to_vec()).unwrap();
+    tampered[10] ^= 0x01;
+    assert_eq!(ground.open(tampered), None);
+
+    let frame = satellite.seal(b"telemetry".to_vec()).unwrap();
+    assert!(ground.open(frame.clone()).is_some());
+    assert_eq!(ground.open(frame.clone()), None);
+
+    // A downlink frame echoed back at the satellite was sealed for the other direction
+    assert_eq!(satellite.open(frame), None);
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn every_pass_gets_a_fresh_session_key() {
+    let (mut first_pass, _) = ciphers(Capabilities::ENCRYPTION);
+    let (_, mut second_pass_ground) = ciphers(Capabilities::ENCRYPTION);
+
+    let frame = first_pass.seal(b"telemetry".to_vec()).unwrap();
+    assert_eq!(second_pass_ground.open(frame), None);
+}
@@ -0,0 +1,100 @@
+use rts_protocol::*;
+
+fn decode_all(decoder: &mut FrameDecoder) -> Vec<Vec<u8>> {
+    let mut frames = Vec::new();
+
+    while let Some(frame) = decoder.next_frame() {
+        frames.push(frame);
+    }
+
+    frames
+}
+
+#[test]
+fn crc32_matches_reference_value() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+}
+
+#[test]
+fn frames_round_trip() {
+    let mut decoder = FrameDecoder::new();
+    decoder.push(&encode_frame(b"first").unwrap());
+    decoder.push(&encode_frame(b"").unwrap());
+    decoder.push(&encode_frame(b"third").unwrap());
+
+    assert_eq!(decode_all(&mut decoder), vec![b"first".to_vec(), Vec::new(), b"third".to_vec()]);
+    assert_eq!(decoder.crc_failures, 0);
+    assert_eq!(decoder.resyncs, 0);
+}
+
+#[test]
+fn frame_split_across_reads_is_reassembled() {
+    let frame = encode_frame(b"split payload").unwrap();
+    let mut decoder = FrameDecoder::new();
+
+    for byte in &frame[..frame.len() - 1] {
+        decoder.push(&[*byte]);
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    decoder.push(&frame[frame.len() - 1..]);
+    assert_eq!(decoder.next_frame(), Some(b"split payload".to_vec()));
+}
+
+#[test]
+fn corrupted_body_is_dropped_and_next_frame_recovered() {
+    let mut corrupted = encode_frame(b"corrupted").unwrap();
+    corrupted[8] ^= 0x01;
+
+    let mut decoder = FrameDecoder::new();
+    decoder.push(&corrupted);
+    decoder.push(&encode_frame(b"intact").unwrap());
+
+    assert_eq!(decode_all(&mut decoder), vec![b"intact".to_vec()]);
+    assert_eq!(decoder.crc_failures, 1);
+    assert_eq!(decoder.resyncs, 1);
+}
+
+#[test]
+fn corrupted_length_prefix_resyncs_on_next_marker() {
+    let mut corrupted = encode_frame(b"bad length").unwrap();
+    corrupted[4] = 0xFF; // Length > MAX_FRAME_BODY
+
+    let mut decoder = FrameDecoder::new();
+    decoder.push(&corrupted);
+    decoder.push(&encode_frame(b"intact").unwrap());
+
+    assert_eq!(decode_all(&mut decoder), vec![b"intact".to_vec()]);
+    assert_eq!(decoder.resyncs, 1);
+}
+
+#[test]
+fn garbage_before_marker_is_skipped() {
+    let mut decoder = FrameDecoder::new();
+    decoder.push(&[0x00, 0x1A, 0xCF, 0x42, 0x99]);
+    decoder.push(&encode_frame(b"after noise").unwrap());
+
+    assert_eq!(decode_all(&mut decoder), vec![b"after noise".to_vec()]);
+    assert_eq!(decoder.crc_failures, 0);
+    assert_eq!(decoder.resyncs, 1);
+}
+
+#[test]
+fn read_frame_pulls_from_reader() {
+    let mut bytes = encode_frame(b"one").unwrap();
+    bytes.extend(encode_frame(b"two").unwrap());
+
+    let mut reader = std::io::Cursor::new(bytes);
+    let mut decoder = FrameDecoder::new();
+
+    assert_eq!(decoder.read_frame(&mut reader), Some(b"one".to_vec()));
+    assert_eq!(decoder.read_frame(&mut reader), Some(b"two".to_vec()));
+    assert_eq!(decoder.read_frame(&mut reader), None);
+}
+
+#[test]
+fn oversized_body_is_refused() {
+    assert_eq!(encode_frame(&[0u8; MAX_FRAME_BODY]).map(|frame| frame.len()), Some(MAX_FRAME_BODY + FRAME_OVERHEAD));
+    assert_eq!(encode_frame(&[0u8; MAX_FRAME_BODY + 1]), None);
+    assert!(!write_frame(&mut Vec::new(), &[0u8; u16::MAX as usize + 1])); // Would once have wrapped to a length of 0
+}
@@ -0,0 +1,45 @@
+use rts_protocol::*;
+
+#[test]
+fn matching_peers_share_common_capabilities() {
+    let ground = Hello::new(1, Capabilities::AUTH.union(Capabilities::BATCHING));
+    let satellite = Hello::new(1, Capabilities::AUTH.union(Capabilities::COMPRESSION));
+
+    assert_eq!(ground.negotiate(&satellite, Capabilities::NONE), Ok(Capabilities::AUTH));
+    assert_eq!(satellite.negotiate(&ground, Capabilities::AUTH), Ok(Capabilities::AUTH));
+}
+
+#[test]
+fn version_mismatch_is_refused() {
+    let ground = Hello::new(1, Capabilities::NONE);
+    let satellite = Hello { version: PROTOCOL_VERSION + 1, ..ground };
+
+    assert_eq!(ground.negotiate(&satellite, Capabilities::NONE), Err(HandshakeReject::VersionMismatch));
+}
+
+#[test]
+fn unknown_spacecraft_is_refused() {
+    let ground = Hello::new(1, Capabilities::NONE);
+    let satellite = Hello::new(2, Capabilities::NONE);
+
+    assert_eq!(ground.negotiate(&satellite, Capabilities::NONE), Err(HandshakeReject::SpacecraftMismatch));
+}
+
+#[test]
+fn missing_required_capability_is_refused() {
+    let ground = Hello::new(1, Capabilities::AUTH);
+    let satellite = Hello::new(1, Capabilities::NONE);
+
+    assert_eq!(ground.negotiate(&satellite, Capabilities::AUTH), Err(HandshakeReject::MissingCapabilities));
+}
+
+#[test]
+fn hello_ack_round_trips() {
+    let ack = HelloAck {
+        hello: Hello::new(7, Capabilities::BATCHING),
+        rejected: Some(HandshakeReject::Timeout),
+    };
+
+    let bytes = bincode::serialize(&ack).unwrap();
+    assert_eq!(bincode::deserialize::<HelloAck>(&bytes).unwrap(), ack);
+}
@@ -0,0 +1,103 @@
+use rts_protocol::*;
+
+const ISS_LINE1: &str = "1 25544U 98067A   08264.51782528 -.00002182  00000-0 -11606-4 0  2927";
+const ISS_LINE2: &str = "2 25544  51.6416 247.4627 0006703 130.5360 325.0288 15.72125391563537";
+
+const DAY_US: u64 = 86_400_000_000;
+
+fn default_orbit() -> Orbit {
+    OrbitConfig::default().build().unwrap()
+}
+
+#[test]
+fn tle_gives_its_mean_elements() {
+    let orbit = Orbit::from_tle(ISS_LINE1, ISS_LINE2, 1.0).unwrap();
+    let elements = orbit.elements();
+
+    assert_eq!(elements.inclination_deg, 51.6416);
+    assert_eq!(elements.raan_deg, 247.4627);
+    assert!((elements.eccentricity - 0.000_670_3).abs() < 1e-12);
+    assert!((elements.semi_major_axis_km - 6730.0).abs() < 5.0); // 15.72 revs a day, about 350 km up
+    assert!((orbit.period_us() as f64 / 60e6 - 91.6).abs() < 0.5); // Minutes
+}
+
+#[test]
+fn tle_with_a_bad_checksum_is_refused() {
+    let corrupted = ISS_LINE2.replace("51.6416", "51.6417");
+
+    assert!(Orbit::from_tle(ISS_LINE1, &corrupted, 1.0).is_err());
+    assert!(Orbit::from_tle(ISS_LINE1, &ISS_LINE2[..60], 1.0).is_err());
+}
+
+#[test]
+fn orbit_inside_the_earth_is_refused() {
+    let elements = OrbitalElements { semi_major_axis_km: 6000.0, ..OrbitalElements::default() };
+
+    assert!(Orbit::new(elements, 0.0, 1.0).is_err());
+    assert!(Orbit::new(OrbitalElements::default(), 0.0, 0.0).is_err());
+}
+
+#[test]
+fn an_eccentricity_outside_an_ellipse_is_refused_as_such() {
+    for eccentricity in [1.0, 1.5, -0.1, f64::NAN] {
+        let elements = OrbitalElements { eccentricity, ..OrbitalElements::default() };
+        let error = Orbit::new(elements, 0.0, 1.0).unwrap_err();
+
+        assert!(error.starts_with("eccentricity must be in [0, 1)"), "{}: {}", eccentricity, error);
+    }
+}
+
+#[test]
+fn predicted_pass_matches_the_visibility_check() {
+    let orbit = default_orbit();
+    let site = GroundSite::default();
+    let from = OrbitConfig::default().epoch_unix_s * 1_000_000;
+
+    let pass = orbit.next_pass(&site, from, DAY_US / 10_000).expect("a 51.6 degree orbit passes over 40N every day");
+
+    assert!(pass.aos_us < pass.los_us);
+    assert!(pass.max_elevation_deg >= site.min_elevation_deg);
+    assert!(orbit.is_visible(&site, (pass.aos_us + pass.los_us) / 2));
+    assert!(!orbit.is_visible(&site, pass.aos_us - 1_000)); // 1 ms of wall clock is 10 s of orbit
+    assert!(!orbit.is_visible(&site, pass.los_us));
+}
+
+#[test]
+fn pass_in_progress_starts_now() {
+    let orbit = default_orbit();
+    let site = GroundSite::default();
+    let from = OrbitConfig::default().epoch_unix_s * 1_000_000;
+
+    let pass = orbit.next_pass(&site, from, DAY_US / 10_000).unwrap();
+    let midway = (pass.aos_us + pass.los_us) / 2;
+    let ongoing = orbit.next_pass(&site, midway, DAY_US / 10_000).unwrap();
+
+    assert_eq!(ongoing.aos_us, midway);
+    assert!(ongoing.los_us.abs_diff(pass.los_us) < 10); // μs of wall clock
+}
+
+#[test]
+fn passes_vary_in_length_and_spacing() {
+    let orbit = default_orbit();
+    let site = GroundSite::default();
+    let mut from = OrbitConfig::default().epoch_unix_s * 1_000_000;
+    let mut passes = Vec::new();
+
+    while let Some(pass) = orbit.next_pass(&site, from, DAY_US / 10_000) {
+        passes.push(pass);
+        from = pass.los_us;
+
+        if passes.len() == 4 {
+            break;
+        }
+    }
+
+    assert_eq!(passes.len(), 4);
+
+    let durations: Vec<u64> = passes.iter().map(|pass| pass.los_us - pass.aos_us).collect();
+    let gaps: Vec<u64> = passes.windows(2).map(|pair| pair[1].aos_us - pair[0].los_us).collect();
+
+    assert!(durations.iter().all(|&duration| duration > 1_000)); // Each a real pass, not the tail of the one before
+    assert!(durations.iter().any(|&duration| duration.abs_diff(durations[0]) > 1_000));
+    assert!(gaps.iter().any(|&gap| gap.abs_diff(gaps[0]) > 1_000));
+}
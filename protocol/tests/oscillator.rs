@@ -0,0 +1,91 @@
+use rts_protocol::*;
+
+fn steady(frequency_error_ppm: f64) -> OscillatorConfig {
+    OscillatorConfig { frequency_error_ppm, temperature_ppb_per_c2: 0.0, ..OscillatorConfig::default() }
+}
+
+// Ground clock is true time, each leg of the link takes 100 μs and the satellite turns the request around in 300
+fn exchange_at(oscillator: &mut Oscillator, ground_sent: u64, temperature_c: f64) -> SyncExchange {
+    let satellite_receive = oscillator.advance(ground_sent + 100, Some(temperature_c));
+    let satellite_send = oscillator.advance(ground_sent + 400, Some(temperature_c));
+
+    SyncExchange { ground_sent, satellite_receive, satellite_send, ground_receive: ground_sent + 500 }
+}
+
+#[test]
+fn a_fast_crystal_gains_its_frequency_error() {
+    let mut oscillator = steady(100.0).build().unwrap();
+
+    assert_eq!(oscillator.advance(10_000_000, None), 10_001_000); // 100 ppm of 10 s
+}
+
+#[test]
+fn the_reading_starts_at_the_initial_offset() {
+    let mut oscillator = OscillatorConfig { initial_offset_us: 5_000_000, ..steady(0.0) }.build().unwrap();
+
+    assert_eq!(oscillator.advance(0, None), 5_000_000);
+    assert_eq!(oscillator.advance(1_000, None), 5_001_000);
+}
+
+#[test]
+fn heat_slows_the_crystal_on_both_sides_of_turnover() {
+    let config = OscillatorConfig { frequency_error_ppm: 0.0, temperature_ppb_per_c2: 40.0, turnover_c: 25.0, ..OscillatorConfig::default() };
+    let mut hot = config.build().unwrap();
+    let mut cold = config.build().unwrap();
+
+    hot.advance(0, Some(35.0));
+    cold.advance(0, Some(15.0));
+
+    assert_eq!(hot.frequency_error_ppb(), -4_000.0);
+    assert_eq!(hot.advance(10_000_000, None), 9_999_960);
+    assert_eq!(cold.advance(10_000_000, None), 9_999_960);
+}
+
+#[test]
+fn jumps_never_run_the_reading_backwards() {
+    let mut oscillator = OscillatorConfig { jump_interval_ms: 1_000, max_jump_us: 100_000, ..steady(0.0) }.build().unwrap();
+    let mut last = 0;
+
+    for true_us in (0..1_000_000).step_by(500) {
+        let reading = oscillator.advance(true_us, None);
+        assert!(reading >= last);
+        last = reading;
+    }
+
+    assert!(oscillator.jumps >= 500);
+}
+
+#[test]
+fn clock_sync_converges_on_a_drifting_crystal() {
+    let mut oscillator = OscillatorConfig { initial_offset_us: 3_000_000, ..steady(40.0) }.build().unwrap();
+    let mut estimator = ClockEstimator::new();
+
+    for n in 1..=CLOCK_SYNC_WINDOW as u64 {
+        estimator.add(&exchange_at(&mut oscillator, n * 1_000_000, 25.0));
+    }
+
+    let estimate = estimator.estimate();
+    let now = 30_000_000;
+    let residual_us = estimate.ground_time(oscillator.advance(now, None)).abs_diff(now);
+
+    assert_eq!(estimate.quality, SyncQuality::Locked);
+    assert!((estimate.drift_ppb as f64 + oscillator.frequency_error_ppb()).abs() < 100.0); // The offset shrinks as fast as the crystal gains
+    assert!(residual_us < 10, "residual {} μs", residual_us);
+}
+
+#[test]
+fn clock_sync_follows_a_jump() {
+    let mut oscillator = steady(10.0).build().unwrap();
+    let mut estimator = ClockEstimator::new();
+
+    for n in 1..=8 {
+        estimator.add(&exchange_at(&mut oscillator, n * 1_000_000, 25.0));
+    }
+
+    let before = estimator.estimate().offset_us;
+    let mut jumped = OscillatorConfig { initial_offset_us: 50_000, ..steady(10.0) }.build().unwrap(); // The same crystal 50 ms ahead
+
+    assert!(estimator.add(&exchange_at(&mut jumped, 9_000_000, 25.0)));
+    assert_eq!(estimator.steps, 1);
+    assert!(estimator.estimate().offset_us.abs_diff(before - 50_000) < 20); // Less the drift over the second since
+}
@@ -0,0 +1,84 @@
+use rts_protocol::*;
+
+// The satellite's defaults: thresholds at 80% and 50%, sensors at 5, 10 and 20 ms
+const DEFAULTS: [u64; PARAMETER_COUNT] = [80, 50, 2, 100_000, 5_000, 2_500, 5_000, 10_000, 0, 36_000, 20_000, 2_500, 5_000];
+
+#[test]
+fn a_table_is_checked_when_built() {
+    let mut values = DEFAULTS;
+    values[ParameterID::FaultRecoveryMs as usize] = 0;
+    assert_eq!(ParameterTable::new(values).err(), Some((ParameterID::FaultRecoveryMs, ParameterError::OutOfRange)));
+
+    let mut values = DEFAULTS;
+    values[ParameterID::ThermalSensorMinData as usize] = 5_000;
+    assert_eq!(ParameterTable::new(values).err(), Some((ParameterID::ThermalSensorMinData, ParameterError::Conflicts)));
+
+    let table = ParameterTable::new(DEFAULTS).unwrap();
+    assert_eq!(table.values(), DEFAULTS);
+    assert_eq!(table.version(), 1);
+}
+
+#[test]
+fn a_set_reports_the_old_value_and_bumps_the_version() {
+    let table = ParameterTable::new(DEFAULTS).unwrap();
+
+    assert_eq!(table.set(ParameterID::ThermalSensorPeriodMs, 2_000), Ok(ParameterChange {
+        parameter: ParameterID::ThermalSensorPeriodMs,
+        old_value: 5_000,
+        new_value: 2_000,
+        version: 2,
+    }));
+    assert_eq!(table.get(ParameterID::ThermalSensorPeriodMs), 2_000);
+
+    let unchanged = table.set(ParameterID::ThermalSensorPeriodMs, 2_000).unwrap(); // Same value, same version
+    assert_eq!((unchanged.old_value, unchanged.version), (2_000, 2));
+}
+
+#[test]
+fn a_rejected_set_leaves_the_table_alone() {
+    let table = ParameterTable::new(DEFAULTS).unwrap();
+
+    assert_eq!(table.set(ParameterID::NormalToDegradedThreshold, 101), Err(ParameterError::OutOfRange));
+    assert_eq!(table.set(ParameterID::NormalToDegradedThreshold, 50), Err(ParameterError::Conflicts)); // No hysteresis left
+    assert_eq!(table.set(ParameterID::MoistureSensorMaxData, 2_500), Err(ParameterError::Conflicts));
+    assert_eq!(table.values(), DEFAULTS);
+    assert_eq!(table.version(), 1);
+}
+
+#[test]
+fn set_all_moves_a_threshold_pair_past_each_other() {
+    let table = ParameterTable::new(DEFAULTS).unwrap();
+
+    // One at a time, raising the lower threshold to 90 is refused before the upper one reaches 95
+    assert_eq!(table.set(ParameterID::DegradedToNormalThreshold, 90), Err(ParameterError::Conflicts));
+
+    let mut values = DEFAULTS;
+    values[ParameterID::NormalToDegradedThreshold as usize] = 95;
+    values[ParameterID::DegradedToNormalThreshold as usize] = 90;
+
+    let changes = table.set_all(values).unwrap();
+    assert_eq!(changes.iter().map(|change| (change.parameter, change.old_value, change.version)).collect::<Vec<_>>(), vec![
+        (ParameterID::NormalToDegradedThreshold, 80, 2),
+        (ParameterID::DegradedToNormalThreshold, 50, 3),
+    ]);
+    assert_eq!(table.values(), values);
+}
+
+#[test]
+fn every_parameter_has_sane_limits() {
+    for id in ParameterID::ALL {
+        let limits = id.limits();
+
+        assert!(limits.min < limits.max, "{:?}", id);
+        assert_eq!(id.check(limits.min), Ok(()));
+        assert_eq!(id.check(limits.max + 1), Err(ParameterError::OutOfRange));
+    }
+
+    assert_eq!(Command::SetParameter { parameter: ParameterID::ThermalSensorPeriodMs, value: 0 }.validate(), Err(RejectReason::OutOfRange));
+}
+
+#[test]
+fn each_error_has_its_own_reject_reason() {
+    assert_eq!(RejectReason::from(ParameterError::OutOfRange), RejectReason::OutOfRange);
+    assert_eq!(RejectReason::from(ParameterError::Conflicts), RejectReason::Conflicts);
+}
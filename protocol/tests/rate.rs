@@ -0,0 +1,55 @@
+use rts_protocol::*;
+
+#[test]
+fn full_bucket_lets_every_priority_through() {
+    let mut bucket = TokenBucket::new(8_000, 16_000, 4_000);
+    bucket.reset(0);
+
+    assert!(bucket.can_send(Priority::Low, 0));
+    assert!(bucket.can_send(Priority::Emergency, 0));
+}
+
+#[test]
+fn reserve_is_kept_for_emergency_and_critical() {
+    let mut bucket = TokenBucket::new(8_000, 16_000, 4_000);
+    bucket.reset(0);
+    bucket.spend(1_500); // 12 000 bits, leaves exactly the reserve
+
+    assert!(!bucket.can_send(Priority::Low, 0));
+    assert!(!bucket.can_send(Priority::Normal, 0));
+    assert!(bucket.can_send(Priority::Critical, 0));
+    assert!(bucket.can_send(Priority::Emergency, 0));
+}
+
+#[test]
+fn bucket_refills_at_the_link_rate_up_to_the_burst() {
+    let mut bucket = TokenBucket::new(8_000, 16_000, 4_000);
+    bucket.reset(0);
+    bucket.spend(2_000);
+
+    assert!(!bucket.can_send(Priority::Emergency, 0));
+    assert!(bucket.can_send(Priority::Emergency, 500_000)); // Half a second = 4 000 bits
+    assert_eq!(bucket.available_bits(), 4_000);
+
+    bucket.can_send(Priority::Low, 10_000_000);
+    assert_eq!(bucket.available_bits(), 16_000);
+}
+
+#[test]
+fn oversized_frame_is_paid_back_before_the_next_one() {
+    let mut bucket = TokenBucket::new(8_000, 16_000, 0);
+    bucket.reset(0);
+    bucket.spend(3_000); // 24 000 bits
+
+    assert_eq!(bucket.available_bits(), -8_000);
+    assert!(!bucket.can_send(Priority::Emergency, 1_000_000));
+    assert!(bucket.can_send(Priority::Emergency, 1_000_001));
+}
+
+#[test]
+fn budget_is_burst_plus_rate_over_the_pass() {
+    let bucket = TokenBucket::new(8_000, 16_000, 4_000);
+
+    assert_eq!(bucket.budget_bits(0), 16_000);
+    assert_eq!(bucket.budget_bits(2_000_000), 32_000);
+}
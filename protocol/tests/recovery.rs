@@ -0,0 +1,137 @@
+use rts_protocol::*;
+
+#[test]
+fn first_frame_only_sets_the_reference() {
+    let mut tracker = GapTracker::default();
+
+    assert_eq!(tracker.observe(40), None);
+    assert_eq!(tracker.last, 40);
+}
+
+#[test]
+fn skipped_sequence_numbers_are_reported_once() {
+    let mut tracker = GapTracker::default();
+
+    assert_eq!(tracker.observe(1), None);
+    assert_eq!(tracker.observe(2), None);
+    assert_eq!(tracker.observe(6), Some(3..6));
+    assert_eq!(tracker.observe(7), None);
+}
+
+#[test]
+fn replays_and_unnumbered_frames_are_ignored() {
+    let mut tracker = GapTracker::default();
+
+    tracker.observe(1);
+    tracker.observe(5);
+
+    assert_eq!(tracker.observe(3), None); // Replay of a missing frame
+    assert_eq!(tracker.observe(5), None); // Duplicate
+    assert_eq!(tracker.observe(0), None); // SEQUENCE_NOT_CONFIRMED
+    assert_eq!(tracker.last, 5);
+}
+
+#[test]
+fn nack_bitmap_lists_sequence_numbers() {
+    let sequence_numbers: Vec<u32> = nack_sequence_numbers(100, 0b1011 | 1 << 63).collect();
+
+    assert_eq!(sequence_numbers, vec![100, 101, 103, 163]);
+}
+
+#[test]
+fn gaps_are_nacked_and_recovered_selectively() {
+    let mut arq = SelectiveRepeat::new(1024);
+
+    assert_eq!(arq.observe(1), Arrival::InOrder);
+    assert_eq!(arq.observe(2), Arrival::InOrder);
+    assert_eq!(arq.observe(6), Arrival::Gap(3));
+    assert_eq!(arq.observe(80), Arrival::Gap(73));
+    assert_eq!(arq.outstanding(), 76);
+
+    let nacks = arq.nacks(1_000, 500);
+    assert_eq!(nacks, vec![(3, 0b111 | u64::MAX << 4), (67, (1 << 13) - 1)]);
+
+    assert_eq!(arq.observe(4), Arrival::Recovered);
+    assert_eq!(arq.observe(4), Arrival::Duplicate);
+    assert_eq!(arq.observe(0), Arrival::Unnumbered);
+    assert_eq!(arq.outstanding(), 75);
+    assert_eq!(arq.lost, 0);
+}
+
+#[test]
+fn nacks_are_not_repeated_before_the_retry_interval() {
+    let mut arq = SelectiveRepeat::new(1024);
+
+    arq.observe(1);
+    arq.observe(3);
+
+    assert_eq!(arq.nacks(1_000, 500), vec![(2, 1)]);
+    assert_eq!(arq.nacks(1_200, 500), vec![]);
+    assert_eq!(arq.nacks(1_500, 500), vec![(2, 1)]);
+}
+
+#[test]
+fn outstanding_numbers_age_out_of_the_sender_history() {
+    let mut arq = SelectiveRepeat::new(8);
+
+    arq.observe(1);
+    arq.observe(4); // 2 and 3 outstanding
+    assert_eq!(arq.outstanding(), 2);
+
+    for seq in 5..=10 {
+        arq.observe(seq);
+    }
+    assert_eq!(arq.outstanding(), 1); // Sender now holds 3..=10
+    assert_eq!(std::mem::take(&mut arq.lost), 1);
+
+    assert_eq!(arq.observe(40), Arrival::Gap(29)); // Only the last 7 before 40 can still be sent
+    assert_eq!(arq.outstanding(), 7);
+    assert_eq!(arq.lost, 23);
+}
+
+#[test]
+fn unavailable_numbers_are_given_up() {
+    let mut arq = SelectiveRepeat::new(1024);
+
+    arq.observe(10);
+    arq.observe(15);
+
+    arq.abandon(11, 0b101);
+    assert_eq!(arq.outstanding(), 2);
+    assert_eq!(arq.lost, 2);
+}
+
+#[test]
+fn frames_taken_by_another_station_are_not_nacked() {
+    let mut arq = SelectiveRepeat::new(1024);
+
+    arq.observe(10);
+    arq.observe(14); // Handover arrived after a frame the satellite sent first
+    arq.resume_at(12);
+
+    assert_eq!(arq.observe(12), Arrival::Duplicate);
+    assert_eq!(arq.outstanding(), 1); // Only 13 was ours
+    assert_eq!(arq.lost, 0);
+
+    arq.resume_at(30);
+    assert_eq!(arq.observe(30), Arrival::InOrder);
+    assert_eq!(arq.outstanding(), 0);
+    assert_eq!(arq.lost, 0);
+}
+
+#[test]
+fn nacks_over_space_packets_name_the_full_numbers_the_sender_keeps() {
+    let sender = PacketCodec::new(WireFormat::SpacePacket);
+    let mut receiver = PacketCodec::new(WireFormat::SpacePacket);
+    receiver.resume_at(40_000); // Two wraps in, as a handshake after earlier passes would announce
+    let mut arq = SelectiveRepeat::new(1024);
+
+    for sequence_no in (40_000..40_010).filter(|&sequence_no| sequence_no != 40_004) {
+        let packet = TelemetryPacket { priority: Priority::Normal, creation_time: 0, payload: SatelliteMessage::SyncRequest, sequence_no };
+        let received = receiver.decode(&sender.encode(&packet).unwrap()).unwrap();
+        arq.observe(received.sequence_no);
+    }
+
+    // The satellite looks its history up by exact sequence number
+    assert_eq!(arq.nacks(1_000, 500), vec![(40_004, 1)]);
+}
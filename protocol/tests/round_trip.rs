@@ -0,0 +1,207 @@
+use rts_protocol::*;
+
+const ALL_TASK_IDS: [TaskID; 22] = [
+    TaskID::None,
+    TaskID::RotateAntenna,
+    TaskID::SetPowerMode,
+    TaskID::ClearSubsystemFault,
+    TaskID::RequestRetransmit,
+    TaskID::ThermalSensor,
+    TaskID::PitchAndYawSensor,
+    TaskID::MoistureSensor,
+    TaskID::GlobalSystem,
+    TaskID::NetworkService,
+    TaskID::DownlinkNetworkService,
+    TaskID::UplinkNetworkService,
+    TaskID::ListTimeTags,
+    TaskID::DeleteTimeTag,
+    TaskID::UploadSequenceStep,
+    TaskID::ListSequences,
+    TaskID::StartSequence,
+    TaskID::AbortSequence,
+    TaskID::DeleteSequence,
+    TaskID::SetParameter,
+    TaskID::GetParameter,
+    TaskID::DumpParameters,
+];
+
+const ALL_EVENT_IDS: [EventID; 51] = [
+    EventID::CommandNotFound,
+    EventID::SubsystemFault,
+    EventID::SubsystemFixed,
+    EventID::CommandCompletion,
+    EventID::StartDelay,
+    EventID::CompletionDelay,
+    EventID::TaskFault,
+    EventID::DataCorruption,
+    EventID::TaskCompletion,
+    EventID::DegradedMode,
+    EventID::NormalMode,
+    EventID::Startup,
+    EventID::MissionAbort,
+    EventID::Shutdown,
+    EventID::MissedCommunication,
+    EventID::DataLoss,
+    EventID::RetransmitFailed,
+    EventID::SyncStart,
+    EventID::SyncOngoing,
+    EventID::SyncCompleted,
+    EventID::ConnectionStart,
+    EventID::ConnectionEnd,
+    EventID::QueuePerformance,
+    EventID::ResourceUtilization,
+    EventID::NetworkPerformance,
+    EventID::AuthenticationFailure,
+    EventID::CommandAccepted,
+    EventID::CommandRejected,
+    EventID::CommandStageChange,
+    EventID::UplinkGap,
+    EventID::DownlinkUsage,
+    EventID::ConfigReload,
+    EventID::Handover,
+    EventID::PassPredicted,
+    EventID::PassPlanned,
+    EventID::UplinkCarriedOver,
+    EventID::TimeTagQueued,
+    EventID::TimeTagListed,
+    EventID::TimeTagDeleted,
+    EventID::TimeTagExecuted,
+    EventID::TimeCorrelation,
+    EventID::SequenceStored,
+    EventID::SequenceListed,
+    EventID::SequenceStarted,
+    EventID::SequenceStepRun,
+    EventID::SequenceCompleted,
+    EventID::SequenceAborted,
+    EventID::SequenceDeleted,
+    EventID::ParameterChanged,
+    EventID::ParameterValue,
+    EventID::ConfigChanged,
+];
+
+fn all_event_data() -> Vec<EventData> {
+    vec![
+        EventData::None,
+        EventData::QueuePerformance { latency_ms: 1, jitter_ms: 2, buffer_fill_rate: 3, sample_count: 4 },
+        EventData::SchedulingDrift { drift_ms: 5 },
+        EventData::Hardware { value: 2500, latency_ms: 6, jitter_ms: 7, sample_count: 8 },
+        EventData::CorruptedHardware { value: 99999, recovery_time: 9 },
+        EventData::Subsystem { subsystem_id: SubsystemID::Power },
+        EventData::SystemStats { active_ms: 10, inactive_ms: 11 },
+        EventData::FaultRecovery { recovery_time: 12 },
+        EventData::TimeSync { offset: 13 },
+        EventData::PacketDrain { count: 14 },
+        EventData::NetworkPerformance { priority: Priority::Critical, latency_ms: 15, jitter_ms: 16, sample_count: 17 },
+        EventData::Handshake { version: PROTOCOL_VERSION, spacecraft_id: 1, capabilities: Capabilities::AUTH },
+        EventData::HandshakeRejected { reason: HandshakeReject::VersionMismatch, peer_version: 2, peer_spacecraft_id: 1 },
+        EventData::FrameIntegrity { crc_failures: 18, resyncs: 19, decode_failures: 20 },
+        EventData::AuthFailure { reason: AuthReject::Replayed, counter: 21, last_counter: 22 },
+        EventData::CommandAck { command_id: 23 },
+        EventData::CommandStage { command_id: 24, stage: CommandStage::TimedOut, attempts: 3 },
+        EventData::SequenceGap { first: 25, count: 26 },
+        EventData::RetransmitUnavailable { base: 27, bitmap: 0b101 },
+        EventData::DownlinkUsage { bytes_sent: 28, budget_bytes: 29, queued: 30 },
+        EventData::ConfigReload { changed: 31, rejected: 32 },
+        EventData::Handover { from_station: 33, to_station: 34 },
+        EventData::PassPrediction { aos_ms: 35, los_ms: 36, max_elevation: 37 },
+        EventData::UplinkPlan { planned: 38, held: 39 },
+        EventData::SyncSample { offset_us: -40, rtt_us: 41 },
+        EventData::ClockSync { offset_us: -42, drift_ppb: -43, error_us: 44, quality: SyncQuality::Locked },
+        EventData::TimeTag { command_id: 45, execute_at: 46, queued: 47 },
+        EventData::TimeTagExecution { command_id: 48, tagged_at: 49, started_at: 50 },
+        EventData::TimeCorrelation { reference_us: 51, utc_us: 52, drift_ppb: -53, error_us: 54, quality: SyncQuality::Locked },
+        EventData::Sequence { sequence_id: 55, step: 56, steps: 57 },
+        EventData::SequenceAborted { sequence_id: 58, step: 59, reason: SequenceAbort::WaitTimedOut },
+        EventData::CommandRejected { command_id: 60, reason: RejectReason::OutOfRange },
+        EventData::CommandRejected { command_id: 60, reason: RejectReason::Cancelled },
+        EventData::CommandRejected { command_id: 60, reason: RejectReason::PriorityNotAllowed },
+        EventData::CommandRejected { command_id: 60, reason: RejectReason::Conflicts },
+        EventData::Parameter { parameter: ParameterID::FaultRecoveryMs, value: 61, version: 62 },
+        EventData::ParameterChange { parameter: ParameterID::MoistureSensorMaxData, old_value: 63, new_value: 64, version: 65 },
+        EventData::ConfigChange { key: 66, old_value: 67, new_value: 68 },
+    ]
+}
+
+fn all_commands() -> Vec<Command> {
+    vec![
+        Command::RotateAntenna { target_angle: 9000 },
+        Command::SetPowerMode { mode: 1 },
+        Command::ClearSubsystemFault { subsystem_id: SubsystemID::Antenna },
+        Command::RequestRetransmit { sequence_no: 42 },
+        Command::ListTimeTags,
+        Command::DeleteTimeTag { command_id: 43 },
+        Command::UploadSequenceStep { sequence_id: 44, index: 0, count: 3, delay_ms: 45, step: SequenceStep::RotateAntenna { target_angle: 4500 } },
+        Command::UploadSequenceStep { sequence_id: 44, index: 1, count: 3, delay_ms: 0, step: SequenceStep::WaitFor {
+            condition: SequenceCondition::SubsystemHealthy { subsystem_id: SubsystemID::Power },
+            timeout_ms: 46,
+        } },
+        Command::UploadSequenceStep { sequence_id: 44, index: 2, count: 3, delay_ms: 0, step: SequenceStep::WaitFor { condition: SequenceCondition::InContact, timeout_ms: 47 } },
+        Command::ListSequences,
+        Command::StartSequence { sequence_id: 44 },
+        Command::AbortSequence { sequence_id: 44 },
+        Command::DeleteSequence { sequence_id: 44 },
+        Command::SetParameter { parameter: ParameterID::NormalToDegradedThreshold, value: 90 },
+        Command::GetParameter { parameter: ParameterID::ThermalSensorPeriodMs },
+        Command::DumpParameters,
+    ]
+}
+
+fn all_messages() -> Vec<SatelliteMessage> {
+    let mut messages = vec![
+        SatelliteMessage::SyncRequest,
+        SatelliteMessage::SyncResponse { ground_sent: 100, satellite_receive: 200, satellite_send: 250 },
+        SatelliteMessage::SyncResult { ground_sent: 100, satellite_receive: 200, satellite_send: 250, ground_receive: 300, auth: CommandAuth { counter: 301, tag: [0x5A; AUTH_TAG_LEN] } },
+        SatelliteMessage::Nack { base: 500, bitmap: 0x8000_0000_0000_0001 },
+        SatelliteMessage::TimeCorrelation { correlation: TimeCorrelation { reference_us: 600, utc_us: 601, drift_ppb: -602, error_us: 603, quality: SyncQuality::Coarse }, auth: CommandAuth { counter: 604, tag: [0xC3; AUTH_TAG_LEN] } },
+    ];
+
+    for command in all_commands() {
+        messages.push(SatelliteMessage::Command { command, command_id: 402, sent_at: 400, execute_at: 403, auth: CommandAuth { counter: 401, tag: [0xA5; AUTH_TAG_LEN] } });
+    }
+
+    for task_id in ALL_TASK_IDS {
+        for event_id in ALL_EVENT_IDS {
+            for data in all_event_data() {
+                messages.push(SatelliteMessage::Telemetry {
+                    event: Event { task_id, event_id, data, timestamp: 500 },
+                });
+            }
+        }
+    }
+
+    messages
+}
+
+#[test]
+fn every_message_round_trips() {
+    for priority in [Priority::Low, Priority::Normal, Priority::Critical, Priority::Emergency] {
+        for payload in all_messages() {
+            let packet = TelemetryPacket { priority, creation_time: 123_456, payload, sequence_no: 7 };
+
+            let bytes = bincode::serialize(&packet).unwrap();
+            let decoded: TelemetryPacket = bincode::deserialize(&bytes).unwrap();
+
+            assert_eq!(packet, decoded);
+        }
+    }
+}
+
+// bincode encodes enums as a u32 variant index, so these pin the variant order on the wire
+#[test]
+fn variant_indexes_are_stable() {
+    assert_eq!(bincode::serialize(&TaskID::UplinkNetworkService).unwrap(), 11u32.to_le_bytes());
+    assert_eq!(bincode::serialize(&EventID::NetworkPerformance).unwrap(), 24u32.to_le_bytes());
+    assert_eq!(bincode::serialize(&EventData::PacketDrain { count: 0 }).unwrap()[..4], 9u32.to_le_bytes());
+    assert_eq!(bincode::serialize(&Command::RequestRetransmit { sequence_no: 0 }).unwrap()[..4], 3u32.to_le_bytes());
+    assert_eq!(bincode::serialize(&SatelliteMessage::SyncRequest).unwrap(), 0u32.to_le_bytes());
+}
+
+// The ground ignores an ack that would move a command backwards, which relies on declaration order
+#[test]
+fn command_stages_are_ordered() {
+    assert!(CommandStage::Queued < CommandStage::Uplinked);
+    assert!(CommandStage::Uplinked < CommandStage::Accepted);
+    assert!(CommandStage::Accepted < CommandStage::Executed);
+    assert!(!CommandStage::Accepted.is_terminal());
+    assert!(CommandStage::Executed.is_terminal() && CommandStage::Failed.is_terminal() && CommandStage::TimedOut.is_terminal());
+}
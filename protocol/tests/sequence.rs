@@ -0,0 +1,104 @@
+use rts_protocol::*;
+
+const ROTATE: SequenceStep = SequenceStep::RotateAntenna { target_angle: 9000 };
+const POWER_UP: SequenceStep = SequenceStep::SetPowerMode { mode: 2 };
+const WAIT_FOR_ANTENNA: SequenceStep = SequenceStep::WaitFor {
+    condition: SequenceCondition::SubsystemHealthy { subsystem_id: SubsystemID::Antenna },
+    timeout_ms: 1_000,
+};
+
+// Rotate at once, wait for the antenna, then power up 500 μs later
+fn antenna_recovery() -> SequenceStore {
+    let mut store = SequenceStore::new(4);
+
+    store.upload(1, 0, 3, 0, ROTATE).unwrap();
+    store.upload(1, 1, 3, 0, WAIT_FOR_ANTENNA).unwrap();
+    store.upload(1, 2, 3, 500, POWER_UP).unwrap();
+
+    store
+}
+
+#[test]
+fn steps_may_arrive_in_any_order() {
+    let mut store = SequenceStore::new(4);
+
+    assert_eq!(store.upload(7, 2, 3, 0, POWER_UP), Ok(1));
+    assert_eq!(store.start(7, 0), Err(SequenceError::Incomplete));
+    assert_eq!(store.upload(7, 0, 3, 0, ROTATE), Ok(2));
+    assert_eq!(store.upload(7, 0, 3, 0, ROTATE), Ok(2)); // A retried upload stores the same step again
+    assert_eq!(store.upload(7, 1, 3, 0, ROTATE), Ok(3));
+    assert_eq!(store.start(7, 0), Ok(3));
+}
+
+#[test]
+fn a_new_count_starts_the_upload_over() {
+    let mut store = antenna_recovery();
+
+    assert_eq!(store.upload(1, 0, 2, 0, POWER_UP), Ok(1));
+    assert_eq!(store.list(), vec![SequenceSummary { sequence_id: 1, stored: 1, steps: 2, running_step: None }]);
+}
+
+#[test]
+fn bad_uploads_are_refused() {
+    let mut store = SequenceStore::new(1);
+
+    assert_eq!(store.upload(1, 3, 3, 0, ROTATE), Err(SequenceError::BadStep));
+    assert_eq!(store.upload(1, 0, 0, 0, ROTATE), Err(SequenceError::BadStep));
+    assert_eq!(store.upload(1, 0, MAX_SEQUENCE_STEPS as u8 + 1, 0, ROTATE), Err(SequenceError::BadStep));
+
+    store.upload(1, 0, 1, 0, ROTATE).unwrap();
+    assert_eq!(store.upload(2, 0, 1, 0, ROTATE), Err(SequenceError::Full));
+
+    store.start(1, 0).unwrap();
+    assert_eq!(store.upload(1, 0, 1, 0, POWER_UP), Err(SequenceError::Running));
+    assert_eq!(store.start(1, 0), Err(SequenceError::Running));
+}
+
+#[test]
+fn steps_run_after_their_delays_and_waits() {
+    let mut store = antenna_recovery();
+    let healthy = std::cell::Cell::new(false);
+    let is_met = |_: &SequenceCondition| healthy.get();
+
+    store.start(1, 100).unwrap();
+    assert_eq!(store.next_due(99, is_met), None);
+    assert_eq!(store.next_due(100, is_met), Some(SequenceProgress::Run { sequence_id: 1, step: 0, steps: 3, command: Command::RotateAntenna { target_angle: 9000 } }));
+    assert_eq!(store.next_due(200, is_met), None); // Waiting on the antenna
+
+    healthy.set(true);
+    assert_eq!(store.next_due(300, is_met), Some(SequenceProgress::WaitMet { sequence_id: 1, step: 1, steps: 3 }));
+    assert_eq!(store.next_due(799, is_met), None);
+    assert_eq!(store.next_due(800, is_met), Some(SequenceProgress::Run { sequence_id: 1, step: 2, steps: 3, command: Command::SetPowerMode { mode: 2 } }));
+    assert_eq!(store.next_due(800, is_met), Some(SequenceProgress::Completed { sequence_id: 1, steps: 3 }));
+    assert_eq!(store.running(), 0);
+}
+
+#[test]
+fn a_wait_that_times_out_stops_the_sequence() {
+    let mut store = antenna_recovery();
+
+    store.start(1, 0).unwrap();
+    store.next_due(0, |_| false);
+
+    assert_eq!(store.next_due(999, |_| false), None);
+    assert_eq!(store.next_due(1_000, |_| false), Some(SequenceProgress::TimedOut { sequence_id: 1, step: 1, steps: 3 }));
+    assert_eq!(store.list()[0].running_step, None);
+    assert_eq!(store.start(1, 2_000), Ok(3)); // Still stored, it can be started again
+}
+
+#[test]
+fn abort_and_delete_stop_a_running_sequence() {
+    let mut store = antenna_recovery();
+
+    assert_eq!(store.abort(1), Err(SequenceError::NotRunning));
+
+    store.start(1, 0).unwrap();
+    store.next_due(0, |_| false);
+    assert_eq!(store.abort(1), Ok(1));
+    assert_eq!(store.next_due(10_000, |_| true), None);
+
+    store.start(1, 0).unwrap();
+    assert_eq!(store.delete(1), Ok(Some(0)));
+    assert_eq!(store.delete(1), Err(SequenceError::NotStored));
+    assert_eq!(store.len(), 0);
+}
@@ -0,0 +1,233 @@
+use rts_protocol::*;
+
+fn sample_event() -> Event {
+    Event {
+        task_id: TaskID::ThermalSensor,
+        event_id: EventID::TaskCompletion,
+        data: EventData::Hardware { value: 2500, latency_ms: 6, jitter_ms: 7, sample_count: 8 },
+        timestamp: 500,
+    }
+}
+
+fn sample_messages() -> Vec<SatelliteMessage> {
+    vec![
+        SatelliteMessage::SyncRequest,
+        SatelliteMessage::SyncResponse { ground_sent: 100, satellite_receive: 200, satellite_send: 250 },
+        SatelliteMessage::SyncResult { ground_sent: 100, satellite_receive: 200, satellite_send: 250, ground_receive: 300, auth: CommandAuth::UNSIGNED },
+        SatelliteMessage::Command { command: Command::RotateAntenna { target_angle: 9000 }, command_id: 1, sent_at: 400, execute_at: 0, auth: CommandAuth::UNSIGNED },
+        SatelliteMessage::Command { command: Command::RequestRetransmit { sequence_no: 70_000 }, command_id: 2, sent_at: 401, execute_at: 402, auth: CommandAuth { counter: 7, tag: [0x5A; AUTH_TAG_LEN] } },
+        SatelliteMessage::Telemetry { event: sample_event() },
+        SatelliteMessage::Nack { base: 16_383, bitmap: u64::MAX },
+        SatelliteMessage::TimeCorrelation { correlation: TimeCorrelation { reference_us: 1, utc_us: 1_790_000_000_000_000, drift_ppb: -20_000, error_us: 150, quality: SyncQuality::Locked }, auth: CommandAuth { counter: 8, tag: [0xA5; AUTH_TAG_LEN] } },
+    ]
+}
+
+#[test]
+fn sync_request_matches_reference_packet() {
+    let packet = TelemetryPacket {
+        priority: Priority::Emergency,
+        creation_time: 0x0102_0304_0506_0708,
+        payload: SatelliteMessage::SyncRequest,
+        sequence_no: 5,
+    };
+
+    let reference = [
+        0x18, 0x40, // Version 0, telecommand, secondary header, APID 0x040
+        0xC0, 0x05, // Unsegmented, count 5
+        0x00, 0x08, // 9 byte data field
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, // creation_time
+        0x0A, // Emergency
+    ];
+
+    assert_eq!(encode_space_packet(&packet).unwrap(), reference);
+    assert_eq!(decode_space_packet(&reference).unwrap(), packet);
+}
+
+#[test]
+fn sync_response_matches_reference_packet_with_wrapped_count() {
+    let packet = TelemetryPacket {
+        priority: Priority::Critical,
+        creation_time: 0x10,
+        payload: SatelliteMessage::SyncResponse { ground_sent: 1, satellite_receive: 2, satellite_send: 3 },
+        sequence_no: SEQUENCE_COUNT_MODULO + 1,
+    };
+
+    let reference = [
+        0x08, 0x41, // Version 0, telemetry, secondary header, APID 0x041
+        0xC0, 0x01, // Unsegmented, 16385 wraps to count 1
+        0x00, 0x20, // 33 byte data field
+        0, 0, 0, 0, 0, 0, 0, 0x10,
+        0x09, // Critical
+        0, 0, 0, 0, 0, 0, 0, 1,
+        0, 0, 0, 0, 0, 0, 0, 2,
+        0, 0, 0, 0, 0, 0, 0, 3,
+    ];
+
+    assert_eq!(encode_space_packet(&packet).unwrap(), reference);
+    assert_eq!(decode_space_packet(&reference).unwrap(), TelemetryPacket { sequence_no: 1, ..packet });
+}
+
+#[test]
+fn sync_result_uses_full_sequence_count() {
+    let packet = TelemetryPacket {
+        priority: Priority::Low,
+        creation_time: 0,
+        payload: SatelliteMessage::SyncResult {
+            ground_sent: 1,
+            satellite_receive: 2,
+            satellite_send: 3,
+            ground_receive: 0xABCD,
+            auth: CommandAuth { counter: 0x0102, tag: [0x5A; AUTH_TAG_LEN] },
+        },
+        sequence_no: SEQUENCE_COUNT_MODULO - 1,
+    };
+
+    let reference = [&[
+        0x18, 0x42,
+        0xFF, 0xFF, // Unsegmented, count 16383
+        0x00, 0x50, // 81 byte data field
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0x00,
+        0, 0, 0, 0, 0, 0, 0, 1,
+        0, 0, 0, 0, 0, 0, 0, 2,
+        0, 0, 0, 0, 0, 0, 0, 3,
+        0, 0, 0, 0, 0, 0, 0xAB, 0xCD,
+        0, 0, 0, 0, 0, 0, 0x01, 0x02, // auth counter
+    ][..], &[0x5A; AUTH_TAG_LEN]].concat();
+
+    assert_eq!(encode_space_packet(&packet).unwrap(), reference);
+    assert_eq!(decode_space_packet(&reference).unwrap(), TelemetryPacket { sequence_no: SEQUENCE_COUNT_MODULO - 1, ..packet });
+}
+
+#[test]
+fn nack_matches_reference_packet() {
+    let packet = TelemetryPacket {
+        priority: Priority::Critical,
+        creation_time: 0,
+        payload: SatelliteMessage::Nack { base: 0x0102_0304, bitmap: 0x8000_0000_0000_0005 },
+        sequence_no: 9,
+    };
+
+    let reference = [
+        0x18, 0x43, // Version 0, telecommand, secondary header, APID 0x043
+        0xC0, 0x09,
+        0x00, 0x14, // 21 byte data field
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0x09,
+        0x01, 0x02, 0x03, 0x04, // base
+        0x80, 0, 0, 0, 0, 0, 0, 0x05, // bitmap
+    ];
+
+    assert_eq!(encode_space_packet(&packet).unwrap(), reference);
+    assert_eq!(decode_space_packet(&reference).unwrap(), packet);
+}
+
+#[test]
+fn telemetry_header_matches_reference() {
+    let event = sample_event();
+    let packet = TelemetryPacket {
+        priority: Priority::Normal,
+        creation_time: 0x20,
+        payload: SatelliteMessage::Telemetry { event },
+        sequence_no: 42,
+    };
+
+    let user_data = bincode::serialize(&event).unwrap();
+    let data_length = (9 + user_data.len() - 1) as u16;
+
+    let mut reference = vec![0x08, 0x60, 0xC0, 42];
+    reference.extend_from_slice(&data_length.to_be_bytes());
+    reference.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0x20, 0x03]);
+    reference.extend_from_slice(&user_data);
+
+    assert_eq!(encode_space_packet(&packet).unwrap(), reference);
+    assert_eq!(decode_space_packet(&reference).unwrap(), packet);
+}
+
+#[test]
+fn malformed_packets_are_rejected() {
+    let valid = [0x18, 0x40, 0xC0, 0x05, 0x00, 0x08, 0, 0, 0, 0, 0, 0, 0, 0, 0x0A];
+
+    let mut version = valid;
+    version[0] |= 0x20;
+    assert_eq!(decode_space_packet(&version), Err(SpacePacketError::UnsupportedVersion));
+
+    let mut segmented = valid;
+    segmented[2] = 0x40;
+    assert_eq!(decode_space_packet(&segmented), Err(SpacePacketError::Segmented));
+
+    let mut no_secondary = valid;
+    no_secondary[0] &= !0x08;
+    assert_eq!(decode_space_packet(&no_secondary), Err(SpacePacketError::MissingSecondaryHeader));
+
+    let mut unknown_apid = valid;
+    unknown_apid[1] = 0x7F;
+    assert_eq!(decode_space_packet(&unknown_apid), Err(SpacePacketError::UnknownApid));
+
+    let mut wrong_type = valid;
+    wrong_type[0] &= !0x10; // SyncRequest only travels as a telecommand
+    assert_eq!(decode_space_packet(&wrong_type), Err(SpacePacketError::TypeMismatch));
+
+    let mut wrong_length = valid;
+    wrong_length[5] = 0x09;
+    assert_eq!(decode_space_packet(&wrong_length), Err(SpacePacketError::LengthMismatch));
+
+    let mut unknown_priority = valid;
+    unknown_priority[14] = 0x04;
+    assert_eq!(decode_space_packet(&unknown_priority), Err(SpacePacketError::UnknownPriority));
+
+    assert_eq!(decode_space_packet(&valid[..10]), Err(SpacePacketError::Truncated));
+}
+
+#[test]
+fn sequence_count_unwraps_across_modulo() {
+    let mut counter = SequenceCounter::default();
+
+    assert_eq!(counter.unwrap(16_382), 16_382);
+    assert_eq!(counter.unwrap(16_383), 16_383);
+    assert_eq!(counter.unwrap(0), 16_384);
+    assert_eq!(counter.unwrap(1), 16_385);
+    assert_eq!(counter.unwrap(16_380), 16_380); // Late retransmission from before the wrap
+    assert_eq!(counter.unwrap(2), 16_386);
+}
+
+#[test]
+fn a_codec_resumed_at_the_peers_number_unwraps_past_earlier_wraps() {
+    let packet = TelemetryPacket { priority: Priority::Normal, creation_time: 0, payload: SatelliteMessage::SyncRequest, sequence_no: 20_003 };
+    let bytes = encode_space_packet(&packet).unwrap();
+
+    // A station that never saw the first 20 000 numbers lands a whole modulo short
+    let mut fresh = PacketCodec::new(WireFormat::SpacePacket);
+    assert_eq!(fresh.decode(&bytes).map(|packet| packet.sequence_no), Some(20_003 - SEQUENCE_COUNT_MODULO));
+
+    let mut resumed = PacketCodec::new(WireFormat::SpacePacket);
+    resumed.resume_at(20_000); // Hello::next_sequence_no
+    assert_eq!(resumed.decode(&bytes), Some(packet));
+    assert_eq!(SequenceCounter::starting_at(50_000).unwrap((49_990 % SEQUENCE_COUNT_MODULO) as u16), 49_990); // A NACKed retransmission
+}
+
+#[test]
+fn codec_round_trips_in_both_formats() {
+    for format in [WireFormat::Bincode, WireFormat::SpacePacket] {
+        let encoder = PacketCodec::new(format);
+        let mut decoder = PacketCodec::new(format);
+
+        let mut sequence_no = SEQUENCE_COUNT_MODULO - 3; // Crosses the 14-bit wrap
+
+        for _ in 0..2 {
+            for payload in sample_messages() {
+                let packet = TelemetryPacket { priority: Priority::Normal, creation_time: 123_456, payload, sequence_no };
+                let bytes = encoder.encode(&packet).unwrap();
+
+                assert_eq!(decoder.decode(&bytes), Some(packet), "{format:?}");
+                sequence_no += 1;
+            }
+        }
+    }
+}
+
+#[test]
+fn space_packets_are_negotiated_as_a_capability() {
+    assert_eq!(WireFormat::negotiated(Capabilities::NONE), WireFormat::Bincode);
+    assert_eq!(WireFormat::negotiated(Capabilities::SPACE_PACKETS.union(Capabilities::AUTH)), WireFormat::SpacePacket);
+}
@@ -0,0 +1,37 @@
+use rts_protocol::*;
+
+fn locked(offset_us: i64, drift_ppb: i64, reference_us: u64) -> ClockEstimate {
+    ClockEstimate { offset_us, drift_ppb, reference_us, error_us: 40, quality: SyncQuality::Locked }
+}
+
+#[test]
+fn utc_is_formatted_as_iso_8601() {
+    assert_eq!(Utc(0).to_string(), "1970-01-01T00:00:00.000000Z");
+    assert_eq!(Utc(951_782_400_000_001).to_string(), "2000-02-29T00:00:00.000001Z"); // Leap day of a century leap year
+    assert_eq!(Utc(1_792_152_296_789_012).to_string(), "2026-10-16T12:04:56.789012Z");
+}
+
+#[test]
+fn satellite_uptime_maps_through_the_station_clock() {
+    let ground_boot_utc_us = 1_792_152_000_000_000;
+    let correlation = TimeCorrelation::from_estimate(&locked(500_000, 0, 10_000_000), ground_boot_utc_us);
+
+    assert_eq!(correlation.utc_us, ground_boot_utc_us + 10_500_000);
+    assert_eq!(correlation.utc_us(12_000_000), Some(ground_boot_utc_us + 12_500_000));
+}
+
+#[test]
+fn drift_is_carried_past_the_reference() {
+    let correlation = TimeCorrelation::from_estimate(&locked(0, 50_000, 1_000_000), 0);
+
+    assert_eq!(correlation.utc_us(11_000_000), Some(11_000_500)); // 50 ppm of 10 s
+    assert_eq!(correlation.utc_us(0), Some(0)); // Clamped, never before the epoch
+}
+
+#[test]
+fn an_unsynced_clock_has_no_utc() {
+    let correlation = TimeCorrelation::from_estimate(&ClockEstimate::UNSYNCED, 1_792_152_000_000_000);
+
+    assert_eq!(correlation, TimeCorrelation::NONE);
+    assert_eq!(correlation.utc_us(5_000_000), None);
+}
@@ -0,0 +1,161 @@
+use std::thread;
+use std::time::Duration;
+
+use rts_protocol::*;
+
+const WAIT: Duration = Duration::from_millis(500);
+
+// Hello goes up, HelloAck comes back, the way every pass starts
+fn exchange(satellite: &mut impl LinkTransport, ground: &mut impl LinkTransport) {
+    assert!(satellite.send_frame(b"hello"));
+    assert_eq!(ground.recv_frame(WAIT), Some(b"hello".to_vec()));
+
+    assert!(ground.send_frame(b"hello ack"));
+    assert_eq!(satellite.recv_frame(WAIT), Some(b"hello ack".to_vec()));
+}
+
+#[test]
+fn channel_pair_carries_frames_both_ways() {
+    let (mut satellite, mut ground) = ChannelTransport::pair();
+    assert!(!satellite.is_connected());
+
+    assert!(satellite.open());
+    assert!(ground.open());
+    assert!(satellite.is_connected() && ground.is_connected());
+
+    exchange(&mut satellite, &mut ground);
+    assert_eq!(ground.recv_frame(Duration::from_millis(1)), None);
+}
+
+#[test]
+fn channel_close_ends_the_pass_for_both_ends() {
+    let (mut satellite, mut ground) = ChannelTransport::pair();
+
+    satellite.open();
+    ground.open();
+    satellite.close();
+
+    assert!(!ground.is_connected());
+    assert!(!ground.send_frame(b"late"));
+    assert!(!ground.open()); // Nobody calling
+}
+
+#[test]
+fn channel_drops_frames_left_over_from_an_earlier_pass() {
+    let (mut satellite, mut ground) = ChannelTransport::pair();
+
+    satellite.open();
+    ground.open();
+    assert!(satellite.send_frame(b"end of pass 1"));
+
+    satellite.close();
+    satellite.open();
+    ground.open();
+
+    assert!(satellite.send_frame(b"pass 2"));
+    assert_eq!(ground.recv_frame(WAIT), Some(b"pass 2".to_vec()));
+}
+
+#[test]
+fn tcp_transport_carries_frames() {
+    let mut ground = TcpTransport::listen("127.0.0.1:0", WAIT).unwrap();
+    let addr = ground.local_addr().unwrap();
+
+    let handle = thread::spawn(move || {
+        assert!(ground.open());
+        assert_eq!(ground.recv_frame(WAIT), Some(b"hello".to_vec()));
+        assert!(ground.send_frame(b"hello ack"));
+
+        assert_eq!(ground.recv_frame(WAIT), None); // Satellite hung up
+        assert!(!ground.is_connected());
+    });
+
+    let mut satellite = TcpTransport::connect(addr, WAIT, WAIT);
+    assert!(satellite.open());
+    assert!(satellite.send_frame(b"hello"));
+    assert_eq!(satellite.recv_frame(WAIT), Some(b"hello ack".to_vec()));
+    satellite.close();
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn tcp_listener_clones_hold_passes_at_the_same_time() {
+    let first = TcpTransport::listen("127.0.0.1:0", WAIT).unwrap();
+    let second = first.try_clone().unwrap();
+    let addr = first.local_addr().unwrap();
+    assert_eq!(second.local_addr(), Some(addr));
+
+    let handles: Vec<_> = [first, second].into_iter().map(|mut ground| thread::spawn(move || {
+        assert!(ground.open());
+        let hello = ground.recv_frame(WAIT).unwrap();
+        assert!(ground.send_frame(&hello)); // Echoed so each satellite knows it got its own link
+        ground
+    })).collect();
+
+    let mut satellites: Vec<_> = [b"spacecraft 1", b"spacecraft 2"].iter().map(|hello| {
+        let mut satellite = TcpTransport::connect(addr, WAIT, WAIT);
+        assert!(satellite.open());
+        assert!(satellite.send_frame(*hello));
+        (satellite, hello.to_vec())
+    }).collect();
+
+    for (satellite, hello) in &mut satellites {
+        assert_eq!(satellite.recv_frame(WAIT), Some(hello.clone()));
+    }
+
+    for handle in handles {
+        assert!(handle.join().unwrap().is_connected()); // Neither pass ended the other
+    }
+}
+
+#[test]
+fn zero_timeout_only_takes_frames_already_received() {
+    let mut ground = TcpTransport::listen("127.0.0.1:0", WAIT).unwrap();
+    let mut satellite = TcpTransport::connect(ground.local_addr().unwrap(), WAIT, WAIT);
+
+    assert!(satellite.open());
+    assert!(ground.open());
+    assert_eq!(ground.recv_frame(Duration::ZERO), None);
+    assert!(ground.is_connected());
+
+    assert!(satellite.send_frame(b"hello"));
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(ground.recv_frame(Duration::ZERO), Some(b"hello".to_vec()));
+
+    assert!(ground.send_frame(b"hello ack")); // Polling leaves writes blocking
+    assert_eq!(satellite.recv_frame(WAIT), Some(b"hello ack".to_vec()));
+}
+
+#[test]
+fn udp_transport_learns_the_peer_from_its_first_datagram() {
+    let mut ground = UdpTransport::listen("127.0.0.1:0").unwrap();
+    let mut satellite = UdpTransport::connect("127.0.0.1:0", ground.local_addr().unwrap()).unwrap();
+
+    assert!(satellite.open());
+    assert!(satellite.send_frame(b"hello"));
+
+    assert!(ground.open());
+    assert_eq!(ground.recv_frame(WAIT), Some(b"hello".to_vec()));
+    assert!(ground.send_frame(b"hello ack"));
+    assert_eq!(satellite.recv_frame(WAIT), Some(b"hello ack".to_vec()));
+
+    ground.close();
+    assert!(!ground.is_connected());
+    assert!(!ground.send_frame(b"no peer"));
+}
+
+#[test]
+fn corrupted_frames_are_counted_by_the_transport_decoder() {
+    let (mut satellite, mut ground) = ChannelTransport::pair();
+    satellite.open();
+    ground.open();
+
+    let mut frame = encode_frame(b"telemetry").unwrap();
+    frame[8] ^= 0x10; // Inside the body, the CRC no longer matches
+    assert!(satellite.send_encoded(&frame));
+    assert!(satellite.send_encoded(&encode_frame(b"next").unwrap()));
+
+    assert_eq!(ground.recv_frame(WAIT), Some(b"next".to_vec()));
+    assert_eq!(ground.decoder().crc_failures, 1);
+}
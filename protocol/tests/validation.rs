@@ -0,0 +1,33 @@
+use rts_protocol::*;
+
+#[test]
+fn parameters_at_their_limits_pass() {
+    assert_eq!(Command::RotateAntenna { target_angle: 0 }.validate(), Ok(()));
+    assert_eq!(Command::RotateAntenna { target_angle: MAX_TARGET_ANGLE }.validate(), Ok(()));
+    assert_eq!(Command::SetPowerMode { mode: POWER_MODE_SAFE }.validate(), Ok(()));
+    assert_eq!(Command::SetPowerMode { mode: POWER_MODE_PAYLOAD }.validate(), Ok(()));
+    assert_eq!(Command::ClearSubsystemFault { subsystem_id: SubsystemID::Power }.validate(), Ok(()));
+}
+
+#[test]
+fn parameters_past_their_limits_are_out_of_range() {
+    assert_eq!(Command::RotateAntenna { target_angle: MAX_TARGET_ANGLE + 1 }.validate(), Err(RejectReason::OutOfRange));
+    assert_eq!(Command::SetPowerMode { mode: POWER_MODE_PAYLOAD + 1 }.validate(), Err(RejectReason::OutOfRange));
+}
+
+#[test]
+fn an_uploaded_step_is_checked_like_the_command_it_runs() {
+    let upload = |step| Command::UploadSequenceStep { sequence_id: 1, index: 0, count: 1, delay_ms: 0, step };
+
+    assert_eq!(upload(SequenceStep::SetPowerMode { mode: POWER_MODE_NOMINAL }).validate(), Ok(()));
+    assert_eq!(upload(SequenceStep::SetPowerMode { mode: u8::MAX }).validate(), Err(RejectReason::OutOfRange));
+    assert_eq!(upload(SequenceStep::WaitFor { condition: SequenceCondition::InContact, timeout_ms: 0 }).validate(), Ok(()));
+}
+
+#[test]
+fn sequence_errors_map_onto_reject_reasons() {
+    assert_eq!(RejectReason::from(SequenceError::BadStep), RejectReason::OutOfRange);
+    assert_eq!(RejectReason::from(SequenceError::Full), RejectReason::Full);
+    assert_eq!(RejectReason::from(SequenceError::NotStored), RejectReason::NotFound);
+    assert_eq!(RejectReason::from(SequenceError::Incomplete), RejectReason::WrongMode);
+}
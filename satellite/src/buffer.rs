@@ -20,7 +20,7 @@ impl BoundedBuffer {
     pub fn new(capacity: usize) -> Self {
         Self {
             heap: Mutex::new(BinaryHeap::with_capacity(capacity)),
-            capacity: capacity,
+            capacity,
             metrics: Metrics {
                 last_latency_ms: AtomicU64::new(0),
                 total_latency_ms: AtomicU64::new(0),
@@ -94,47 +94,61 @@ impl BoundedBuffer {
 
     // downlink_buffer and self could be the same pointer
     pub fn push_and_log(&self, source: LogSource, item: TelemetryPacket, state: &Arc<SatelliteState>, log_tx: &SyncSender<Log>, downlink_buffer: &Arc<BoundedBuffer>) {
-        if let Some(dropped) = self.push(item) { // Buffer Drop Packet Logic
-            let task_id: TaskID = match dropped.payload {
-                SatelliteMessage::Telemetry{event} => {
-                    let _ = log_tx.try_send(Log {
-                        source: source,
-                        event: event,
-                    });
-
-                    event.task_id
-                },
-                _ => TaskID::NetworkService
-            };
-
-            let _ = log_tx.try_send(Log {
-                source: source,
+        if let Some(dropped) = self.push(item) {
+            Self::report_drop(source, dropped, state, log_tx, downlink_buffer);
+        } else if let SatelliteMessage::Telemetry { event } = item.payload {
+            let _ = log_tx.try_send(Log { source, event });
+        }
+    }
+
+    // Puts back a packet that was logged when it was first queued, only a packet it pushes out is reported
+    pub fn requeue(&self, source: LogSource, item: TelemetryPacket, state: &Arc<SatelliteState>, log_tx: &SyncSender<Log>, downlink_buffer: &Arc<BoundedBuffer>) {
+        if let Some(dropped) = self.push(item) {
+            Self::report_drop(source, dropped, state, log_tx, downlink_buffer);
+        }
+    }
+
+    // Buffer Drop Packet Logic
+    fn report_drop(source: LogSource, dropped: TelemetryPacket, state: &Arc<SatelliteState>, log_tx: &SyncSender<Log>, downlink_buffer: &Arc<BoundedBuffer>) {
+        let task_id: TaskID = match dropped.payload {
+            SatelliteMessage::Telemetry{event} => {
+                let _ = log_tx.try_send(Log {
+                    source,
+                    event,
+                });
+
+                event.task_id
+            },
+            _ => TaskID::NetworkService
+        };
+
+        let _ = log_tx.try_send(Log {
+            source,
+            event: Event {
+                task_id,
+                event_id: EventID::DataLoss,
+                data: EventData::None,
+                timestamp: state.uptime_ms(),
+            },
+        });
+
+        downlink_buffer.push(TelemetryPacket { 
+            priority: Priority::Normal, 
+            creation_time: state.uptime_ms(), 
+            payload: SatelliteMessage::Telemetry {
                 event: Event {
-                    task_id: task_id,
+                    task_id,
                     event_id: EventID::DataLoss,
                     data: EventData::None,
                     timestamp: state.uptime_ms(),
-                },
-            });
-
-            downlink_buffer.push(TelemetryPacket { 
-                priority: Priority::Normal, 
-                creation_time: state.uptime_ms(), 
-                payload: SatelliteMessage::Telemetry {
-                    event: Event {
-                        task_id: task_id,
-                        event_id: EventID::DataLoss,
-                        data: EventData::None,
-                        timestamp: state.uptime_ms(),
-                    }
-                }, 
-                sequence_no: SEQUENCE_NOT_CONFIRMED, 
-            });
-        } else {
-            if let SatelliteMessage::Telemetry { event } = item.payload {
-                let _ = log_tx.try_send(Log { source, event });
-            }
-        }
+                }
+            }, 
+            sequence_no: SEQUENCE_NOT_CONFIRMED, 
+        });
+    }
+
+    pub fn peek(&self) -> Option<TelemetryPacket> {
+        self.heap.lock().unwrap().peek().copied()
     }
 
     pub fn pop(&self) -> Option<TelemetryPacket> {
@@ -146,6 +160,10 @@ impl BoundedBuffer {
         self.heap.lock().unwrap().len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.heap.lock().unwrap().is_empty()
+    }
+
     pub fn clear(&self) {
         let mut heap = self.heap.lock().unwrap();
         heap.clear();
@@ -6,7 +6,8 @@ use std::sync::Arc;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc::{SyncSender};
 use std::thread;
-use crate::config::{VISIBILITY_WINDOW_CYCLE_MS, COMMAND_MS, COMMAND_PRIORITY, SEQUENCE_NOT_CONFIRMED, TIMESTAMP_NOT_CONFIRMED};
+use crate::config::{SEQUENCE_NOT_CONFIRMED, TIMESTAMP_NOT_CONFIRMED};
+use crate::time_tag::TimeTag;
 use thread_priority::*;
 
 pub fn run_command_executor(
@@ -15,14 +16,17 @@ pub fn run_command_executor(
     uplink_buffer: Arc<BoundedBuffer>,
     log_tx: SyncSender<Log>
 ) {
-    set_current_thread_priority(ThreadPriority::Crossplatform(COMMAND_PRIORITY.try_into().unwrap())).unwrap();
+    set_current_thread_priority(ThreadPriority::Crossplatform(state.config.command_priority.try_into().unwrap())).unwrap();
 
     while state.is_running.load(Ordering::SeqCst) {
+        run_due_time_tags(&state, &log_tx, &downlink_buffer);
+        run_sequences(&state, &log_tx, &downlink_buffer);
+
         if let Some(packet) = uplink_buffer.pop() {
             let start_time = state.uptime_ms();
             let queue_latency_ms = start_time.saturating_sub(packet.creation_time);
 
-            if queue_latency_ms > 10 * VISIBILITY_WINDOW_CYCLE_MS && packet.priority != Priority::Emergency {  // Packet Missed 10 Windows
+            if queue_latency_ms > state.config.stale_packet_limit_ms && packet.priority != Priority::Emergency {
                 downlink_buffer.stale_packet_dropped.fetch_add(1, Ordering::Relaxed);
                 downlink_buffer.packet_dropped.fetch_add(1, Ordering::Relaxed);
 
@@ -36,34 +40,160 @@ pub fn run_command_executor(
                     _ => {}
                 }
 
+                if let SatelliteMessage::Command { command, command_id, .. } = packet.payload {
+                    report_rejection(&state, &log_tx, &downlink_buffer, LogSource::CommandExecutor, command.task_id(), command_id, RejectReason::Stale);
+                }
+
                 continue;
             }
 
             uplink_buffer.metrics.insert_new_metric(queue_latency_ms);
 
-            match packet.payload {
-                SatelliteMessage::Command { command, .. } => {
-                    if let Some(requirements) = command.required_health() {
-                        let system = &state.subsystem_health[requirements as usize];
-                        if system.fault_interlock.load(Ordering::Acquire) 
-                            || system.fault.load(Ordering::Acquire) {
-                            continue;
-                        }
-                    }
-                    execute_instruction(command, &state, &log_tx, &downlink_buffer);
+            if let SatelliteMessage::Command { command, command_id, execute_at, .. } = packet.payload {
+                let checked = if packet.priority == Priority::Emergency && !matches!(command, Command::ClearSubsystemFault { .. }) {
+                    Err(RejectReason::PriorityNotAllowed) // Emergency skips the stale check, only a fault clear may claim it
+                } else if execute_at > 0 {
+                    command.validate() // The state it runs in is checked once the tag comes due
+                } else {
+                    validate(&state, &command)
+                };
+
+                match checked {
+                    Err(reason) => report_rejection(&state, &log_tx, &downlink_buffer, LogSource::CommandExecutor, command.task_id(), command_id, reason),
+                    Ok(()) if execute_at > 0 => queue_time_tag(&state, &log_tx, &downlink_buffer, command, command_id, execute_at),
+                    Ok(()) => execute_instruction(command, command_id, &state, &log_tx, &downlink_buffer),
                 }
-                _ => {}
             }
 
             state.cpu_active_ms.fetch_add(state.uptime_ms() - start_time, Ordering::SeqCst);
         }
         
-        thread::sleep(Duration::from_micros(COMMAND_MS));
+        thread::sleep(Duration::from_micros(state.config.command_ms));
+    }
+}
+
+// Everything a command has to pass before it runs, its parameters first and then the state the satellite is in
+fn validate(state: &Arc<SatelliteState>, command: &Command) -> Result<(), RejectReason> {
+    command.validate()?;
+
+    if is_interlocked(state, command) {
+        return Err(RejectReason::Interlock);
+    }
+
+    if let Command::SetPowerMode { mode } = *command
+        && mode > POWER_MODE_NOMINAL && state.degraded_mode.load(Ordering::Acquire) {
+        return Err(RejectReason::WrongMode);
+    }
+
+    Ok(())
+}
+
+fn is_interlocked(state: &Arc<SatelliteState>, command: &Command) -> bool {
+    command.required_health().is_some_and(|requirements| {
+        let system = &state.subsystem_health[requirements as usize];
+        system.fault_interlock.load(Ordering::Acquire) || system.fault.load(Ordering::Acquire)
+    })
+}
+
+// execute_at is on the ground's clock, so a tag is only taken once ours has been mapped onto it
+fn queue_time_tag(
+    state: &Arc<SatelliteState>,
+    log_tx: &SyncSender<Log>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    command: Command,
+    command_id: u32,
+    execute_at: u64,
+) {
+    if state.clock_sync.estimate().quality == SyncQuality::Unsynced {
+        report_rejection(state, log_tx, downlink_buffer, LogSource::CommandExecutor, command.task_id(), command_id, RejectReason::Unsynced);
+        return;
+    }
+
+    let tag = TimeTag { command, command_id, execute_at, station: state.clock_sync.station.load(Ordering::Acquire) };
+    let mut time_tags = state.time_tags.lock().unwrap();
+    let inserted = time_tags.insert(tag);
+    let queued = time_tags.len();
+    drop(time_tags);
+
+    match inserted {
+        Ok(()) => report_time_tag(state, log_tx, downlink_buffer, EventID::TimeTagQueued, &tag, queued),
+        Err(_) => report_rejection(state, log_tx, downlink_buffer, LogSource::CommandExecutor, command.task_id(), command_id, RejectReason::Full),
+    }
+}
+
+// Every tag whose time has come, each reporting when it actually started against when it was tagged for
+fn run_due_time_tags(state: &Arc<SatelliteState>, log_tx: &SyncSender<Log>, downlink_buffer: &Arc<BoundedBuffer>) {
+    loop {
+        let local_now = state.uptime_ms();
+        let due = state.time_tags.lock().unwrap().pop_due(|tag| state.clock_sync.ground_time(tag.station, local_now) >= tag.execute_at);
+
+        let Some(tag) = due else {
+            return;
+        };
+
+        if let Err(reason) = validate(state, &tag.command) {
+            report_rejection(state, log_tx, downlink_buffer, LogSource::CommandExecutor, tag.command.task_id(), tag.command_id, reason);
+            continue;
+        }
+
+        push_event(state, log_tx, downlink_buffer, tag.command.task_id(), EventID::TimeTagExecuted, EventData::TimeTagExecution {
+            command_id: tag.command_id,
+            tagged_at: tag.execute_at,
+            started_at: state.clock_sync.ground_time(tag.station, state.uptime_ms()),
+        });
+
+        execute_instruction(tag.command, tag.command_id, state, log_tx, downlink_buffer);
+    }
+}
+
+// Whatever each running sequence has come to, a step the satellite's state does not allow by then stops its sequence instead
+fn run_sequences(state: &Arc<SatelliteState>, log_tx: &SyncSender<Log>, downlink_buffer: &Arc<BoundedBuffer>) {
+    loop {
+        let now = state.uptime_ms();
+        let due = state.sequences.lock().unwrap().next_due(now, |condition| is_met(state, condition));
+
+        let Some(progress) = due else {
+            return;
+        };
+
+        match progress {
+            SequenceProgress::Run { sequence_id, step, steps, command } => {
+                if let Err(reason) = validate(state, &command) {
+                    let _ = state.sequences.lock().unwrap().abort(sequence_id);
+                    let abort = if reason == RejectReason::Interlock { SequenceAbort::Interlocked } else { SequenceAbort::WrongMode }; // Ranges were checked at upload
+                    report_sequence_abort(state, log_tx, downlink_buffer, TaskID::StartSequence, sequence_id, step, abort);
+                    continue;
+                }
+
+                actuate(command, state);
+                report_sequence(state, log_tx, downlink_buffer, command.task_id(), EventID::SequenceStepRun, sequence_id, step, steps);
+            },
+            SequenceProgress::WaitMet { sequence_id, step, steps } => {
+                report_sequence(state, log_tx, downlink_buffer, TaskID::StartSequence, EventID::SequenceStepRun, sequence_id, step, steps);
+            },
+            SequenceProgress::TimedOut { sequence_id, step, .. } => {
+                report_sequence_abort(state, log_tx, downlink_buffer, TaskID::StartSequence, sequence_id, step, SequenceAbort::WaitTimedOut);
+            },
+            SequenceProgress::Completed { sequence_id, steps } => {
+                report_sequence(state, log_tx, downlink_buffer, TaskID::StartSequence, EventID::SequenceCompleted, sequence_id, steps, steps);
+            },
+        }
+    }
+}
+
+fn is_met(state: &Arc<SatelliteState>, condition: &SequenceCondition) -> bool {
+    match *condition {
+        SequenceCondition::SubsystemHealthy { subsystem_id } => {
+            let system = &state.subsystem_health[subsystem_id as usize];
+            !(system.fault_interlock.load(Ordering::Acquire) || system.fault.load(Ordering::Acquire))
+        },
+        SequenceCondition::InContact => state.network.stations_in_view.load(Ordering::Acquire) != 0,
     }
 }
 
-fn execute_instruction(command: Command, state: &Arc<SatelliteState>, log_tx: &SyncSender<Log>, downlink_buffer: &Arc<BoundedBuffer>) {
-    let (task_id, event_data) = match command {
+// The commands that act on the spacecraft itself, run the same way from the uplink, a time tag or a sequence step
+fn actuate(command: Command, state: &Arc<SatelliteState>) -> TaskID {
+    match command {
         Command::ClearSubsystemFault { subsystem_id } => {
             let mut completed = false;
 
@@ -78,62 +208,336 @@ fn execute_instruction(command: Command, state: &Arc<SatelliteState>, log_tx: &S
             }
             
             if completed {
-                (TaskID::ClearSubsystemFault, EventData::Subsystem { subsystem_id })
+                TaskID::ClearSubsystemFault
             } else {
-                (TaskID::None, EventData::None)
+                TaskID::None
             }
         },
         Command::RotateAntenna { target_angle } => {
             state.subsystem_health[SubsystemID::Antenna as usize].value.store(target_angle as u32, Ordering::Relaxed);
         
-            (TaskID::RotateAntenna, EventData::None)
+            TaskID::RotateAntenna
             
         },
         Command::SetPowerMode { mode } => {
             state.subsystem_health[SubsystemID::Power as usize].value.store(mode as u32, Ordering::Relaxed);
         
-            (TaskID::SetPowerMode, EventData::None)
+            TaskID::SetPowerMode
+        },
+        _ => {
+            TaskID::None
+        }
+    }
+}
+
+fn execute_instruction(command: Command, command_id: u32, state: &Arc<SatelliteState>, log_tx: &SyncSender<Log>, downlink_buffer: &Arc<BoundedBuffer>) {
+    let task_id = match command {
+        Command::ClearSubsystemFault { .. } | Command::RotateAntenna { .. } | Command::SetPowerMode { .. } => {
+            actuate(command, state)
+        },
+        Command::ListTimeTags => {
+            let entries = state.time_tags.lock().unwrap().entries().to_vec();
+
+            for tag in &entries {
+                report_time_tag(state, log_tx, downlink_buffer, EventID::TimeTagListed, tag, entries.len());
+            }
+
+            TaskID::ListTimeTags
+        },
+        Command::DeleteTimeTag { command_id: deleted_id } => {
+            let mut time_tags = state.time_tags.lock().unwrap();
+            let deleted = time_tags.remove(deleted_id);
+            let queued = time_tags.len();
+            drop(time_tags);
+
+            let Some(tag) = deleted else {
+                report_rejection(state, log_tx, downlink_buffer, LogSource::CommandExecutor, TaskID::DeleteTimeTag, command_id, RejectReason::NotFound);
+                return;
+            };
+
+            report_time_tag(state, log_tx, downlink_buffer, EventID::TimeTagDeleted, &tag, queued);
+            report_rejection(state, log_tx, downlink_buffer, LogSource::CommandExecutor, tag.command.task_id(), tag.command_id, RejectReason::Cancelled); // Closes it out on the ground
+
+            TaskID::DeleteTimeTag
+        },
+        Command::UploadSequenceStep { sequence_id, index, count, delay_ms, step } => {
+            let uploaded = state.sequences.lock().unwrap().upload(sequence_id, index, count, delay_ms, step);
+
+            let stored = match uploaded {
+                Ok(stored) => stored,
+                Err(error) => {
+                    report_rejection(state, log_tx, downlink_buffer, LogSource::CommandExecutor, TaskID::UploadSequenceStep, command_id, error.into());
+                    return;
+                }
+            };
+
+            report_sequence(state, log_tx, downlink_buffer, TaskID::UploadSequenceStep, EventID::SequenceStored, sequence_id, stored, count);
+
+            TaskID::UploadSequenceStep
+        },
+        Command::ListSequences => {
+            let sequences = state.sequences.lock().unwrap().list();
+
+            for sequence in &sequences {
+                report_sequence(state, log_tx, downlink_buffer, TaskID::ListSequences, EventID::SequenceListed, sequence.sequence_id, sequence.stored, sequence.steps);
+            }
+
+            TaskID::ListSequences
+        },
+        Command::StartSequence { sequence_id } => {
+            let started = state.sequences.lock().unwrap().start(sequence_id, state.uptime_ms());
+
+            let steps = match started {
+                Ok(steps) => steps,
+                Err(error) => {
+                    report_rejection(state, log_tx, downlink_buffer, LogSource::CommandExecutor, TaskID::StartSequence, command_id, error.into());
+                    return;
+                }
+            };
+
+            report_sequence(state, log_tx, downlink_buffer, TaskID::StartSequence, EventID::SequenceStarted, sequence_id, 0, steps);
+
+            TaskID::StartSequence
+        },
+        Command::AbortSequence { sequence_id } => {
+            let aborted = state.sequences.lock().unwrap().abort(sequence_id);
+
+            let step = match aborted {
+                Ok(step) => step,
+                Err(error) => {
+                    report_rejection(state, log_tx, downlink_buffer, LogSource::CommandExecutor, TaskID::AbortSequence, command_id, error.into());
+                    return;
+                }
+            };
+
+            report_sequence_abort(state, log_tx, downlink_buffer, TaskID::AbortSequence, sequence_id, step, SequenceAbort::Commanded);
+
+            TaskID::AbortSequence
+        },
+        Command::DeleteSequence { sequence_id } => {
+            let mut sequences = state.sequences.lock().unwrap();
+            let summary = sequences.list().into_iter().find(|sequence| sequence.sequence_id == sequence_id);
+            let deleted = sequences.delete(sequence_id);
+            drop(sequences);
+
+            let (Ok(running_step), Some(summary)) = (deleted, summary) else {
+                report_rejection(state, log_tx, downlink_buffer, LogSource::CommandExecutor, TaskID::DeleteSequence, command_id, RejectReason::NotFound);
+                return;
+            };
+
+            if let Some(step) = running_step {
+                report_sequence_abort(state, log_tx, downlink_buffer, TaskID::DeleteSequence, sequence_id, step, SequenceAbort::Deleted);
+            }
+
+            report_sequence(state, log_tx, downlink_buffer, TaskID::DeleteSequence, EventID::SequenceDeleted, sequence_id, summary.stored, summary.steps);
+
+            TaskID::DeleteSequence
+        },
+        Command::SetParameter { parameter, value } => {
+            let change = match state.parameters.set(parameter, value) {
+                Ok(change) => change,
+                Err(error) => {
+                    report_rejection(state, log_tx, downlink_buffer, LogSource::CommandExecutor, TaskID::SetParameter, command_id, error.into());
+                    return;
+                }
+            };
+
+            report_parameter_changes(state, log_tx, downlink_buffer, LogSource::CommandExecutor, TaskID::SetParameter, &[change]);
+
+            TaskID::SetParameter
+        },
+        Command::GetParameter { parameter } => {
+            report_parameter(state, log_tx, downlink_buffer, TaskID::GetParameter, parameter);
+
+            TaskID::GetParameter
+        },
+        Command::DumpParameters => {
+            for parameter in ParameterID::ALL {
+                report_parameter(state, log_tx, downlink_buffer, TaskID::DumpParameters, parameter);
+            }
+
+            TaskID::DumpParameters
         },
         _ => {
-            (TaskID::None, EventData::None)
+            TaskID::None
         }
     };
 
     if task_id == TaskID::None {
-        downlink_buffer.push_and_log(LogSource::CommandExecutor, 
-            TelemetryPacket{
-            priority: Priority::Normal,
-            creation_time: state.uptime_ms(),
-            payload: SatelliteMessage::Telemetry {
-                    event: Event {
-                    task_id: TaskID::GlobalSystem,
-                    event_id: EventID::CommandNotFound,
-                    data: EventData::None,
-                    timestamp: state.uptime_ms(),
-                },
-            },
-            sequence_no: SEQUENCE_NOT_CONFIRMED,
-        }, 
-        state, log_tx, downlink_buffer);
-
+        report_command_status(state, log_tx, downlink_buffer, LogSource::CommandExecutor, TaskID::GlobalSystem, EventID::CommandNotFound, command_id);
         return;
     }
 
-    downlink_buffer.push_and_log(LogSource::CommandExecutor, 
+    report_command_status(state, log_tx, downlink_buffer, LogSource::CommandExecutor, task_id, EventID::CommandCompletion, command_id);
+}
+
+// Acceptance, rejection and completion all echo the ground's command ID so it can close out the command
+pub fn report_command_status(
+    state: &Arc<SatelliteState>,
+    log_tx: &SyncSender<Log>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    source: LogSource,
+    task_id: TaskID,
+    event_id: EventID,
+    command_id: u32,
+) {
+    // A lost acceptance is covered by the completion or by a ground retry, so it yields to the rest
+    let priority = if event_id == EventID::CommandAccepted { Priority::Normal } else { Priority::Critical };
+
+    downlink_buffer.push_and_log(source, 
         TelemetryPacket{
-        priority: Priority::Critical,
+        priority,
         creation_time: state.uptime_ms(),
         payload: SatelliteMessage::Telemetry {
                 event: Event {
-                    task_id: task_id,
-                    event_id: EventID::CommandCompletion,
-                    data: event_data,
+                    task_id,
+                    event_id,
+                    data: EventData::CommandAck { command_id },
                     timestamp: state.uptime_ms(),
                 },
         },
         sequence_no: SEQUENCE_NOT_CONFIRMED,
     }, 
     state, log_tx, downlink_buffer);
+}
+
+// The reason goes down with the echoed command ID, the ground fails the command either way
+pub fn report_rejection(
+    state: &Arc<SatelliteState>,
+    log_tx: &SyncSender<Log>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    source: LogSource,
+    task_id: TaskID,
+    command_id: u32,
+    reason: RejectReason,
+) {
+    downlink_buffer.push_and_log(source,
+        TelemetryPacket {
+            priority: Priority::Critical,
+            creation_time: state.uptime_ms(),
+            payload: SatelliteMessage::Telemetry {
+                event: Event {
+                    task_id,
+                    event_id: EventID::CommandRejected,
+                    data: EventData::CommandRejected { command_id, reason },
+                    timestamp: state.uptime_ms(),
+                },
+            },
+            sequence_no: SEQUENCE_NOT_CONFIRMED,
+        },
+        state, log_tx, downlink_buffer);
+}
 
-    
-}
\ No newline at end of file
+// Old and new value of each, from a SetParameter or a config reload. A value already in the table is not a change
+pub fn report_parameter_changes(
+    state: &Arc<SatelliteState>,
+    log_tx: &SyncSender<Log>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    source: LogSource,
+    task_id: TaskID,
+    changes: &[ParameterChange],
+) {
+    for change in changes.iter().filter(|change| change.old_value != change.new_value) {
+        downlink_buffer.push_and_log(source,
+            TelemetryPacket {
+                priority: Priority::Critical,
+                creation_time: state.uptime_ms(),
+                payload: SatelliteMessage::Telemetry {
+                    event: Event {
+                        task_id,
+                        event_id: EventID::ParameterChanged,
+                        data: EventData::ParameterChange {
+                            parameter: change.parameter,
+                            old_value: change.old_value,
+                            new_value: change.new_value,
+                            version: change.version,
+                        },
+                        timestamp: state.uptime_ms(),
+                    },
+                },
+                sequence_no: SEQUENCE_NOT_CONFIRMED,
+            },
+            state, log_tx, downlink_buffer);
+    }
+}
+
+fn report_parameter(
+    state: &Arc<SatelliteState>,
+    log_tx: &SyncSender<Log>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    task_id: TaskID,
+    parameter: ParameterID,
+) {
+    push_event(state, log_tx, downlink_buffer, task_id, EventID::ParameterValue, EventData::Parameter {
+        parameter,
+        value: state.parameters.get(parameter),
+        version: state.parameters.version(),
+    });
+}
+
+fn report_time_tag(
+    state: &Arc<SatelliteState>,
+    log_tx: &SyncSender<Log>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    event_id: EventID,
+    tag: &TimeTag,
+    queued: usize,
+) {
+    push_event(state, log_tx, downlink_buffer, tag.command.task_id(), event_id, EventData::TimeTag {
+        command_id: tag.command_id,
+        execute_at: tag.execute_at,
+        queued: queued as u16,
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn report_sequence(
+    state: &Arc<SatelliteState>,
+    log_tx: &SyncSender<Log>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    task_id: TaskID,
+    event_id: EventID,
+    sequence_id: u8,
+    step: u8,
+    steps: u8,
+) {
+    push_event(state, log_tx, downlink_buffer, task_id, event_id, EventData::Sequence { sequence_id, step, steps });
+}
+
+fn report_sequence_abort(
+    state: &Arc<SatelliteState>,
+    log_tx: &SyncSender<Log>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    task_id: TaskID,
+    sequence_id: u8,
+    step: u8,
+    reason: SequenceAbort,
+) {
+    push_event(state, log_tx, downlink_buffer, task_id, EventID::SequenceAborted, EventData::SequenceAborted { sequence_id, step, reason });
+}
+
+fn push_event(
+    state: &Arc<SatelliteState>,
+    log_tx: &SyncSender<Log>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    task_id: TaskID,
+    event_id: EventID,
+    data: EventData,
+) {
+    // A start time is the one the ground asked for, and a stopped sequence may leave the spacecraft half way through
+    let priority = match event_id {
+        EventID::TimeTagExecuted | EventID::SequenceAborted => Priority::Critical,
+        _ => Priority::Normal,
+    };
+
+    downlink_buffer.push_and_log(LogSource::CommandExecutor,
+        TelemetryPacket {
+            priority,
+            creation_time: state.uptime_ms(),
+            payload: SatelliteMessage::Telemetry {
+                event: Event { task_id, event_id, data, timestamp: state.uptime_ms() },
+            },
+            sequence_no: SEQUENCE_NOT_CONFIRMED,
+        },
+        state, log_tx, downlink_buffer);
+}
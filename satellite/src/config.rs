@@ -1,51 +1,362 @@
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use crate::types::{Capabilities, GroundSite, OrbitConfig, OscillatorConfig, ParameterError, ParameterID, ParameterTable, TransportKind, ENCRYPTION_SUPPORT, PARAMETER_COUNT};
+
 pub const TICK_RATE: u64 = 1000; // 1ms
 pub const SENSOR_FAULT_NOT_CONFIRMED: u16 = 0; // SENSOR FAULT TYPE NOT SET
 pub const SEQUENCE_NOT_CONFIRMED: u32 = 0; // SEQUENCE_NO is only set in network thread so any other thread will set as NOT_CONFIRMED
 pub const TIMESTAMP_NOT_CONFIRMED: u64 = 0; // FAULT TIMESTAMP NOT YET SET
-
-pub const DATA_BUFFER_CAPACITY: usize = 1024; // 10 Bits
-pub const LOG_BUFFER_CAPACITY: usize = 1024; // 10 Bits
-pub const PACKET_HISTORY_BUFFER_CAPACITY: usize = 1024; // 10 Bits
-pub const NORMAL_TO_DEGRADED_THRESHOLD: u32 = 80; // 80%
-pub const DEGRADED_TO_NORMAL_THRESHOLD: u32 = 60; // 60%
-pub const DEGRADED_SKIPPED_SENSOR_CYCLES: u64 = 3;
-
-pub const NETWORK_PORT: &str = "127.0.0.1:8000";
-pub const NETWORK_READ_TIMEOUT: u64 = 100;
-pub const NETWORK_WRITE_TIMEOUT: u64 = 1 * TICK_RATE;
+pub const SENSOR_DATA_CORRUPTION: u32 = 99999;
 
 pub const MAX_SENSORS: usize = 3;
+pub const THERMAL_SENSOR: usize = 0; // Index into SatelliteState::sensors
 pub const MAX_SUBSYSTEM: usize = 2;
 
-// Frequencies (in Microseconds)
-pub const SUBSYSTEM_FAULT_INJECTION_MS: u64 = 600 * TICK_RATE;
-pub const SENSOR_FAULT_INJECTION_MS: u64 = 600 * TICK_RATE;
-pub const SENSOR_FAULT_MS: u64 = 10 * TICK_RATE;
-pub const SENSOR_DELAY_MS: u64 = 2 * TICK_RATE;
-pub const SENSOR_DATA_CORRUPTION: u32 = 99999;
-pub const SENSOR_INCREMENT_MAX: u32 = 101;
-pub const MONITOR_MS: u64 = 5 * TICK_RATE;
-pub const COMMAND_MS: u64 = 5 * TICK_RATE;
-pub const NETWORK_MS: u64 = 2 * TICK_RATE;
-pub const MAIN_MS: u64 = 1000 * TICK_RATE;
-
-pub const FAULT_RECOVERY_MS: u64 = 200 * TICK_RATE;
-
-// Thread Priority List
-pub const MONITOR_PRIORITY: u8 = 10;
-// Thermal Sensor = 9
-pub const NETWORK_PRIORITY: u8 = 5;
-pub const COMMAND_PRIORITY: u8 = 4;
-// Pitch/Yaw Sensor = 3
-pub const SIMULATION_PRIORITY: u8 = 1;
-// Moisture Sensor = 0
-pub const LOGGING_PRIORITY: u8 = 0;
-// Sensor Priorities - Moisture = 0, Pitch/Yaw = 3, Thermal = 9 (Based on Priority Enum)
-
-// pub const NUMBER_OF_THREADS: u64 = 7;
-pub const NUMBER_OF_CORES: u64 = 4; // Lower Amount to account for other computer and also since both satellite and ground in one computer
-
-// Network Logic
-pub const INIT_HANDSHAKE_LIMIT_MS: u64 = 5 * TICK_RATE;
-pub const VISIBILITY_WINDOW_LIMIT_MS: u64 = 30 * TICK_RATE;
-pub const VISIBILITY_WINDOW_CYCLE_MS: u64 = 50 * TICK_RATE; // Visible + Invisible
+// Sensor entries of the parameter table start from these, SetParameter may change them from there
+pub const THERMAL_SENSOR_PERIOD_MS: u64 = 5 * TICK_RATE;
+pub const THERMAL_SENSOR_MIN_DATA: u64 = 2500;
+pub const THERMAL_SENSOR_MAX_DATA: u64 = 5000;
+pub const PITCH_AND_YAW_SENSOR_PERIOD_MS: u64 = 10 * TICK_RATE;
+pub const PITCH_AND_YAW_SENSOR_MIN_DATA: u64 = 0;
+pub const PITCH_AND_YAW_SENSOR_MAX_DATA: u64 = 36000;
+pub const MOISTURE_SENSOR_PERIOD_MS: u64 = 20 * TICK_RATE;
+pub const MOISTURE_SENSOR_MIN_DATA: u64 = 2500;
+pub const MOISTURE_SENSOR_MAX_DATA: u64 = 5000;
+
+pub const CONFIG_PATH_ENV: &str = "RTS_SATELLITE_CONFIG"; // Read when no path is given on the command line
+
+// Link Handshake
+pub const SUPPORTED_CAPABILITIES: Capabilities = Capabilities::SPACE_PACKETS.union(Capabilities::AUTH).union(ENCRYPTION_SUPPORT); // Features this build can speak
+pub const REQUIRED_CAPABILITIES: Capabilities = Capabilities::AUTH; // Link is refused if the ground lacks any of these
+
+const MAX_THREAD_PRIORITY: u8 = 99; // ThreadPriority::Crossplatform refuses anything higher
+pub const MAX_GROUND_STATIONS: usize = 32; // One bit each in NetworkState::stations_in_view
+
+// Keys SIGHUP may change while running. The threads read log_level from SatelliteState::tunables and the rest from
+// SatelliteState::parameters, where SetParameter may have changed them since
+pub const RELOADABLE_KEYS: [&str; 5] = [
+    "normal_to_degraded_threshold",
+    "degraded_to_normal_threshold",
+    "degraded_skipped_sensor_cycles",
+    "fault_recovery_ms",
+    "log_level",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum LogLevel {
+    Warning = 0, // Faults, losses and rejections
+    Info = 1, // Plus mode changes, passes and command progress
+    Debug = 2, // Plus every periodic sample
+}
+
+// In view while the orbit puts the satellite above min_elevation_deg, must match the site of the ground instance
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroundStation {
+    pub station_id: u8, // Must match the station_id of the ground instance behind address
+    pub address: SocketAddr,
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+    pub min_elevation_deg: f64,
+}
+
+impl GroundStation {
+    pub fn site(&self) -> GroundSite {
+        GroundSite {
+            latitude_deg: self.latitude_deg,
+            longitude_deg: self.longitude_deg,
+            min_elevation_deg: self.min_elevation_deg,
+        }
+    }
+}
+
+// Every key with a different value as "key: old -> new"
+#[derive(Debug, Default)]
+pub struct ConfigDiff {
+    pub changed: Vec<String>, // Reloadable
+    pub changed_keys: Vec<u8>, // Index into RELOADABLE_KEYS of each entry of changed
+    pub rejected: Vec<String>, // Need a restart
+}
+
+// Tunables loaded at startup, every key left out of the file keeps the value below. All *_ms values are in μs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub data_buffer_capacity: usize,
+    pub log_buffer_capacity: usize,
+    pub packet_history_buffer_capacity: usize,
+    pub recent_command_capacity: usize, // Command IDs remembered to drop ground retries of commands already accepted
+    pub time_tag_capacity: usize, // Time-tagged commands waiting onboard, more are rejected
+    pub sequence_capacity: usize, // Stored command sequences, uploads of more are rejected
+    pub normal_to_degraded_threshold: u32, // Downlink buffer fill in %
+    pub degraded_to_normal_threshold: u32,
+    pub degraded_skipped_sensor_cycles: u64,
+
+    pub link_transport: TransportKind, // Must match the ground
+    pub udp_local_addr: SocketAddr,
+    pub network_read_timeout: u64,
+    pub network_write_timeout: u64,
+
+    // Frequencies
+    pub subsystem_fault_injection_ms: u64,
+    pub sensor_fault_injection_ms: u64,
+    pub sensor_fault_ms: u64,
+    pub sensor_delay_ms: u64,
+    pub sensor_increment_max: u32,
+    pub monitor_ms: u64,
+    pub command_ms: u64,
+    pub network_ms: u64,
+    pub main_ms: u64,
+    pub fault_recovery_ms: u64, // The fix waits for a pass, so this covers the longest gap between them
+
+    // Thread Priorities, sensors take theirs from the Priority enum (Moisture = 0, Pitch/Yaw = 3, Thermal = 9)
+    pub monitor_priority: u8,
+    pub network_priority: u8,
+    pub command_priority: u8,
+    pub simulation_priority: u8,
+    pub logging_priority: u8,
+
+    pub number_of_cores: u64, // Lower Amount to account for other computer and also since both satellite and ground in one computer
+
+    // Network Logic
+    pub init_handshake_limit_ms: u64,
+    pub stale_packet_limit_ms: u64, // Queued longer than this and a packet is dropped, passes can be most of a day apart
+
+    // Downlink Rate Limit
+    pub downlink_rate_bps: u64,
+    pub downlink_burst_bits: u64, // Bucket starts every pass full
+    pub downlink_priority_reserve_bits: u64, // Only Emergency and Critical packets may spend the last of the bucket
+
+    pub spacecraft_id: u16,
+    pub uplink_key_path: String, // Shared HMAC key, hex on one line
+    pub uplink_counter_path: String, // Highest command counter accepted, kept so a restart cannot reopen old commands to replay
+
+    pub log_level: LogLevel,
+    pub log_path: String, // Give each satellite its own when several run from one directory
+
+    pub oscillator: OscillatorConfig, // Drives uptime, so every timestamp drifts from the ground's until clock sync corrects it
+    pub orbit: OrbitConfig, // Decides when each ground station is in view, the ground predicts passes from the same one
+    pub ground_stations: Vec<GroundStation>, // Last so TOML can write it as [[ground_stations]] tables
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            data_buffer_capacity: 1024,
+            log_buffer_capacity: 1024,
+            packet_history_buffer_capacity: 1024,
+            recent_command_capacity: 256,
+            time_tag_capacity: 64,
+            sequence_capacity: 16,
+            normal_to_degraded_threshold: 80,
+            degraded_to_normal_threshold: 60,
+            degraded_skipped_sensor_cycles: 3,
+
+            link_transport: TransportKind::Tcp,
+            udp_local_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            network_read_timeout: 100,
+            network_write_timeout: TICK_RATE,
+
+            subsystem_fault_injection_ms: 600 * TICK_RATE,
+            sensor_fault_injection_ms: 600 * TICK_RATE,
+            sensor_fault_ms: 10 * TICK_RATE,
+            sensor_delay_ms: 2 * TICK_RATE,
+            sensor_increment_max: 101,
+            monitor_ms: 5 * TICK_RATE,
+            command_ms: 5 * TICK_RATE,
+            network_ms: 2 * TICK_RATE,
+            main_ms: 1000 * TICK_RATE,
+            fault_recovery_ms: 10_000 * TICK_RATE,
+
+            monitor_priority: 10,
+            network_priority: 5,
+            command_priority: 4,
+            simulation_priority: 1,
+            logging_priority: 0,
+
+            number_of_cores: 4,
+
+            init_handshake_limit_ms: 5 * TICK_RATE,
+            stale_packet_limit_ms: 10_000 * TICK_RATE, // The longest gap between passes at the default time_scale
+
+            downlink_rate_bps: 512_000,
+            downlink_burst_bits: 16_000,
+            downlink_priority_reserve_bits: 4_000,
+
+            spacecraft_id: 1,
+            uplink_key_path: "uplink.key".to_string(),
+            uplink_counter_path: "uplink.counter".to_string(),
+
+            log_level: LogLevel::Debug,
+            log_path: "satellite_mission.log".to_string(),
+
+            oscillator: OscillatorConfig::default(),
+            orbit: OrbitConfig::default(),
+            ground_stations: vec![GroundStation {
+                station_id: 0,
+                address: SocketAddr::from(([127, 0, 0, 1], 8000)),
+                latitude_deg: GroundSite::default().latitude_deg,
+                longitude_deg: GroundSite::default().longitude_deg,
+                min_elevation_deg: GroundSite::default().min_elevation_deg,
+            }],
+        }
+    }
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+
+        let config: Config = toml::from_str(&text)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        config.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(config)
+    }
+
+    // The parameter table's starting values, in ParameterID order. Sensor entries have no key and start from the
+    // constants above
+    pub fn parameter_values(&self) -> [u64; PARAMETER_COUNT] {
+        ParameterID::ALL.map(|parameter| match parameter {
+            ParameterID::NormalToDegradedThreshold => self.normal_to_degraded_threshold as u64,
+            ParameterID::DegradedToNormalThreshold => self.degraded_to_normal_threshold as u64,
+            ParameterID::DegradedSkippedSensorCycles => self.degraded_skipped_sensor_cycles,
+            ParameterID::FaultRecoveryMs => self.fault_recovery_ms,
+            ParameterID::ThermalSensorPeriodMs => THERMAL_SENSOR_PERIOD_MS,
+            ParameterID::ThermalSensorMinData => THERMAL_SENSOR_MIN_DATA,
+            ParameterID::ThermalSensorMaxData => THERMAL_SENSOR_MAX_DATA,
+            ParameterID::PitchAndYawSensorPeriodMs => PITCH_AND_YAW_SENSOR_PERIOD_MS,
+            ParameterID::PitchAndYawSensorMinData => PITCH_AND_YAW_SENSOR_MIN_DATA,
+            ParameterID::PitchAndYawSensorMaxData => PITCH_AND_YAW_SENSOR_MAX_DATA,
+            ParameterID::MoistureSensorPeriodMs => MOISTURE_SENSOR_PERIOD_MS,
+            ParameterID::MoistureSensorMinData => MOISTURE_SENSOR_MIN_DATA,
+            ParameterID::MoistureSensorMaxData => MOISTURE_SENSOR_MAX_DATA,
+        })
+    }
+
+    pub fn parameters(&self) -> Result<ParameterTable, (ParameterID, ParameterError)> {
+        ParameterTable::new(self.parameter_values())
+    }
+
+    pub fn diff(&self, new: &Config) -> ConfigDiff {
+        let old = toml::Table::try_from(self).expect("Config always serializes");
+        let new = toml::Table::try_from(new).expect("Config always serializes");
+        let mut diff = ConfigDiff::default();
+
+        for (key, old_value) in &old {
+            let new_value = &new[key];
+
+            if old_value == new_value {
+                continue;
+            }
+
+            let change = format!("{}: {} -> {}", key, old_value, new_value);
+
+            if let Some(index) = RELOADABLE_KEYS.iter().position(|reloadable| reloadable == key) {
+                diff.changed.push(change);
+                diff.changed_keys.push(index as u8);
+            } else {
+                diff.rejected.push(change);
+            }
+        }
+
+        diff
+    }
+
+    // In RELOADABLE_KEYS order, what a ConfigChange event carries for each
+    pub fn reloadable_values(&self) -> [u64; RELOADABLE_KEYS.len()] {
+        [
+            self.normal_to_degraded_threshold as u64,
+            self.degraded_to_normal_threshold as u64,
+            self.degraded_skipped_sensor_cycles,
+            self.fault_recovery_ms,
+            self.log_level as u64,
+        ]
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("data_buffer_capacity", self.data_buffer_capacity as u64),
+            ("log_buffer_capacity", self.log_buffer_capacity as u64),
+            ("packet_history_buffer_capacity", self.packet_history_buffer_capacity as u64),
+            ("recent_command_capacity", self.recent_command_capacity as u64),
+            ("time_tag_capacity", self.time_tag_capacity as u64),
+            ("sequence_capacity", self.sequence_capacity as u64),
+            ("sensor_increment_max", self.sensor_increment_max as u64),
+            ("subsystem_fault_injection_ms", self.subsystem_fault_injection_ms),
+            ("sensor_fault_injection_ms", self.sensor_fault_injection_ms),
+            ("monitor_ms", self.monitor_ms),
+            ("command_ms", self.command_ms),
+            ("network_ms", self.network_ms),
+            ("main_ms", self.main_ms),
+            ("number_of_cores", self.number_of_cores),
+            ("stale_packet_limit_ms", self.stale_packet_limit_ms),
+            ("downlink_rate_bps", self.downlink_rate_bps),
+        ] {
+            if value == 0 {
+                return Err(format!("{} must be greater than 0", name));
+            }
+        }
+
+        for (name, priority) in [
+            ("monitor_priority", self.monitor_priority),
+            ("network_priority", self.network_priority),
+            ("command_priority", self.command_priority),
+            ("simulation_priority", self.simulation_priority),
+            ("logging_priority", self.logging_priority),
+        ] {
+            if priority > MAX_THREAD_PRIORITY {
+                return Err(format!("{} must be at most {}, got {}", name, MAX_THREAD_PRIORITY, priority));
+            }
+        }
+
+        if self.normal_to_degraded_threshold > 100 {
+            return Err(format!("normal_to_degraded_threshold is a percentage, got {}", self.normal_to_degraded_threshold));
+        }
+
+        if self.degraded_to_normal_threshold >= self.normal_to_degraded_threshold {
+            return Err(format!("degraded_to_normal_threshold ({}) must be below normal_to_degraded_threshold ({})",
+                self.degraded_to_normal_threshold, self.normal_to_degraded_threshold));
+        }
+
+        self.parameters().map_err(|(parameter, error)| format!("{:?} is {:?} in the parameter table", parameter, error))?;
+
+        if self.ground_stations.is_empty() || self.ground_stations.len() > MAX_GROUND_STATIONS {
+            return Err(format!("ground_stations must list 1 to {} stations, got {}", MAX_GROUND_STATIONS, self.ground_stations.len()));
+        }
+
+        for (index, station) in self.ground_stations.iter().enumerate() {
+            if self.ground_stations[..index].iter().any(|other| other.station_id == station.station_id) {
+                return Err(format!("ground_stations lists station {} twice", station.station_id));
+            }
+
+            if !(-90.0..=90.0).contains(&station.latitude_deg) || !(0.0..90.0).contains(&station.min_elevation_deg) {
+                return Err(format!("ground_stations[{}] needs a latitude_deg in [-90, 90] and a min_elevation_deg in [0, 90)", index));
+            }
+
+            if station.address == self.udp_local_addr {
+                return Err(format!("ground_stations[{}].address and udp_local_addr must differ", index));
+            }
+        }
+
+        if self.link_transport == TransportKind::Udp && self.ground_stations.len() > 1 && self.udp_local_addr.port() != 0 { // Every station gets its own socket
+            return Err("udp_local_addr must use port 0 with more than one ground station".to_string());
+        }
+
+        self.oscillator.build().map_err(|e| format!("oscillator: {}", e))?;
+        self.orbit.build().map_err(|e| format!("orbit: {}", e))?;
+
+        if self.downlink_priority_reserve_bits > self.downlink_burst_bits {
+            return Err(format!("downlink_priority_reserve_bits ({}) must not exceed downlink_burst_bits ({})",
+                self.downlink_priority_reserve_bits, self.downlink_burst_bits));
+        }
+
+        Ok(())
+    }
+}
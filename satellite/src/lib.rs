@@ -0,0 +1,11 @@
+pub mod config;
+pub mod logging;
+pub mod network;
+pub mod monitor;
+pub mod sensors;
+pub mod simulation;
+pub mod types;
+pub mod state;
+pub mod buffer;
+pub mod command;
+pub mod time_tag;
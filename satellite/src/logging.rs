@@ -1,23 +1,31 @@
-use crate::{config::LOGGING_PRIORITY, types::{Log, SubsystemID}};
+use crate::types::{Log, SubsystemID};
+use crate::config::{LogLevel, RELOADABLE_KEYS};
+use crate::state::SatelliteState;
 use std::sync::mpsc::{Receiver};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use thread_priority::*;
-use crate::types::{LogSource, TaskID, EventID, EventData, Priority};
+use crate::types::{LogSource, TaskID, EventID, EventData, Priority, HandshakeReject, AuthReject, Capabilities, Utc};
 use std::fs::OpenOptions;
 use std::io::Write as IoWrite;  
 use std::fmt::Write as FmtWrite; 
 
-pub fn run_logger(log_rx: Receiver<Log>) {
-    set_current_thread_priority(ThreadPriority::Crossplatform(LOGGING_PRIORITY.try_into().unwrap())).unwrap();
+pub fn run_logger(log_rx: Receiver<Log>, state: Arc<SatelliteState>) {
+    set_current_thread_priority(ThreadPriority::Crossplatform(state.config.logging_priority.try_into().unwrap())).unwrap();
 
     let mut format_buffer = String::with_capacity(256);
     let mut file = OpenOptions::new()
         .create(true)
         .write(true)
         .truncate(true)
-        .open("satellite_mission.log")
-        .expect("Failed to open log file");
+        .open(&state.config.log_path)
+        .unwrap_or_else(|e| panic!("Failed to open log file {}: {}", state.config.log_path, e));
 
     while let Ok(log) = log_rx.recv() {
+        if event_level(log.event.event_id) as u8 > state.tunables.log_level.load(Ordering::Relaxed) {
+            continue;
+        }
+
         format_buffer.clear();
 
         let source_str = match log.source {
@@ -33,6 +41,16 @@ pub fn run_logger(log_rx: Receiver<Log>) {
             TaskID::SetPowerMode => "Set Power Mode Command",
             TaskID::ClearSubsystemFault => "Clear Subsystem Fault Command",
             TaskID::RequestRetransmit => "Request Retransmit Command",
+            TaskID::ListTimeTags => "List Time Tags Command",
+            TaskID::DeleteTimeTag => "Delete Time Tag Command",
+            TaskID::UploadSequenceStep => "Upload Sequence Step Command",
+            TaskID::ListSequences => "List Sequences Command",
+            TaskID::StartSequence => "Start Sequence Command",
+            TaskID::AbortSequence => "Abort Sequence Command",
+            TaskID::DeleteSequence => "Delete Sequence Command",
+            TaskID::SetParameter => "Set Parameter Command",
+            TaskID::GetParameter => "Get Parameter Command",
+            TaskID::DumpParameters => "Dump Parameters Command",
 
             // Scheduled Tasks
             TaskID::ThermalSensor => "Thermal Sensor",
@@ -75,11 +93,53 @@ pub fn run_logger(log_rx: Receiver<Log>) {
             EventID::SyncCompleted => "Time Sync Complete",
             EventID::ConnectionStart => "Connection Start",
             EventID::ConnectionEnd => "Connection End",
+            EventID::Handover => "Ground Station Handover",
+            EventID::PassPredicted => "Pass Predicted",
+            EventID::PassPlanned => "Pass Planned",
+            EventID::UplinkCarriedOver => "Uplink Carried Over",
 
             // System Info
             EventID::QueuePerformance => "Queue Performance",  
             EventID::ResourceUtilization => "Resource Utilization",
-            EventID::NetworkPerformance => "Network Performance"
+            EventID::NetworkPerformance => "Network Performance",
+
+            // Security Events
+            EventID::AuthenticationFailure => "Command Authentication Failed",
+
+            // Command Tracking Events
+            EventID::CommandAccepted => "Command Accepted",
+            EventID::CommandRejected => "Command Rejected",
+            EventID::CommandStageChange => "Command Stage Changed",
+
+            // Time Tag Events
+            EventID::TimeTagQueued => "Time Tag Queued",
+            EventID::TimeTagListed => "Time Tag Listed",
+            EventID::TimeTagDeleted => "Time Tag Deleted",
+            EventID::TimeTagExecuted => "Time Tag Executed",
+            EventID::TimeCorrelation => "Time Correlation",
+
+            // Stored Sequence Events
+            EventID::SequenceStored => "Sequence Step Stored",
+            EventID::SequenceListed => "Sequence Listed",
+            EventID::SequenceStarted => "Sequence Started",
+            EventID::SequenceStepRun => "Sequence Step Run",
+            EventID::SequenceCompleted => "Sequence Completed",
+            EventID::SequenceAborted => "Sequence Aborted",
+            EventID::SequenceDeleted => "Sequence Deleted",
+
+            // Parameter Table Events
+            EventID::ParameterChanged => "Parameter Changed",
+            EventID::ParameterValue => "Parameter Value",
+
+            // Uplink Recovery Events
+            EventID::UplinkGap => "Uplink Gap Detected",
+
+            // Downlink Budget Events
+            EventID::DownlinkUsage => "Downlink Usage",
+
+            // Configuration Events
+            EventID::ConfigReload => "Config Reloaded",
+            EventID::ConfigChanged => "Config Changed",
         };
 
         let _ = write!(format_buffer, "[Satellite] [{:>7}]\tTask: [{:>30}]\tEvent: [{:>30}]\t", source_str, task_str, event_str);
@@ -112,6 +172,9 @@ let _ = write!(format_buffer, "CORRUPTED_HARDWARE: [Value: {}, Recovery Time: {}
             EventData::TimeSync { offset } => {
                 let _ = write!(format_buffer, "OFFSET: [{}μs]\t", offset);
             }
+            EventData::PacketDrain { count } => {
+                let _ = write!(format_buffer, "COUNT: [{}]\t", count);
+            }
             EventData::NetworkPerformance { priority, latency_ms, jitter_ms, sample_count } => {
                 let priority_string = match priority {
                     Priority::Emergency => "Emergency",
@@ -122,15 +185,126 @@ let _ = write!(format_buffer, "CORRUPTED_HARDWARE: [Value: {}, Recovery Time: {}
 
                 let _ = write!(format_buffer, "NETWORK PERFORMANCE: [Priority: {}, Latency: {}μs, Jitter: {}μs, Sample Count: {}]\t", priority_string, latency_ms, jitter_ms, sample_count);
             }
+            EventData::Handshake { version, spacecraft_id, capabilities } => {
+                let link_string = if capabilities.contains(Capabilities::ENCRYPTION) { "Encrypted" } else { "Clear" };
+
+                let _ = write!(format_buffer, "HANDSHAKE: [Protocol: v{}, Spacecraft: {}, Capabilities: {:#06x}, Link: {}]\t", version, spacecraft_id, capabilities.0, link_string);
+            }
+            EventData::HandshakeRejected { reason, peer_version, peer_spacecraft_id } => {
+                let reason_string = match reason {
+                    HandshakeReject::VersionMismatch => "Protocol Version Mismatch",
+                    HandshakeReject::SpacecraftMismatch => "Spacecraft Mismatch",
+                    HandshakeReject::MissingCapabilities => "Missing Required Capabilities",
+                    HandshakeReject::Timeout => "Handshake Timeout",
+                    HandshakeReject::Malformed => "Malformed Handshake",
+                    HandshakeReject::AlreadyConnected => "Spacecraft Already Connected",
+                };
+
+                let _ = write!(format_buffer, "LINK REFUSED: [Reason: {}, Peer Protocol: v{}, Peer Spacecraft: {}]\t", reason_string, peer_version, peer_spacecraft_id);
+            }
+            EventData::FrameIntegrity { crc_failures, resyncs, decode_failures } => {
+                let _ = write!(format_buffer, "FRAME INTEGRITY: [CRC Failures: {}, Resyncs: {}, Decode Failures: {}]\t", crc_failures, resyncs, decode_failures);
+            }
+            EventData::AuthFailure { reason, counter, last_counter } => {
+                let reason_string = match reason {
+                    AuthReject::BadTag => "Bad HMAC Tag",
+                    AuthReject::Replayed => "Replayed Counter",
+                };
+
+                let _ = write!(format_buffer, "AUTH FAILURE: [Reason: {}, Counter: {}, Last Accepted: {}]\t", reason_string, counter, last_counter);
+            }
+            EventData::CommandAck { command_id } => {
+                let _ = write!(format_buffer, "COMMAND: [ID: {}]\t", command_id);
+            }
+            EventData::CommandRejected { command_id, reason } => {
+                let _ = write!(format_buffer, "COMMAND: [ID: {}, Rejected: {:?}]\t", command_id, reason);
+            }
+            EventData::CommandStage { command_id, stage, attempts } => {
+                let _ = write!(format_buffer, "COMMAND: [ID: {}, Stage: {:?}, Attempts: {}]\t", command_id, stage, attempts);
+            }
+            EventData::SequenceGap { first, count } => {
+                let _ = write!(format_buffer, "UPLINK GAP: [First: {}, Count: {}]\t", first, count);
+            }
+            EventData::RetransmitUnavailable { base, bitmap } => {
+                let _ = write!(format_buffer, "NACK: [Base: {}, Unavailable: {}]\t", base, bitmap.count_ones());
+            }
+            EventData::DownlinkUsage { bytes_sent, budget_bytes, queued } => {
+                let _ = write!(format_buffer, "DOWNLINK USAGE: [Bytes Sent: {}, Budget: {} bytes, Utilization: {:.2}%, Left Queued: {}]\t", bytes_sent, budget_bytes, bytes_sent as f64 / budget_bytes.max(1) as f64 * 100.0, queued);
+            }
+            EventData::ConfigReload { changed, rejected } => {
+                let _ = write!(format_buffer, "CONFIG RELOAD: [Changed: {}, Rejected: {}]\t", changed, rejected);
+            }
+            EventData::ConfigChange { key, old_value, new_value } => {
+                let _ = write!(format_buffer, "CONFIG CHANGE: [{}: {} -> {}]\t", RELOADABLE_KEYS.get(key as usize).unwrap_or(&"-"), old_value, new_value);
+            }
+            EventData::Handover { from_station, to_station } => {
+                let _ = write!(format_buffer, "HANDOVER: [From Station: {}, To Station: {}]\t", from_station, to_station);
+            }
+            EventData::PassPrediction { aos_ms, los_ms, max_elevation } => {
+                let _ = write!(format_buffer, "PASS: [AOS: {}μs, LOS: {}μs, Max Elevation: {:.2}°]\t", aos_ms, los_ms, max_elevation as f32 / 100.0);
+            }
+            EventData::UplinkPlan { planned, held } => {
+                let _ = write!(format_buffer, "UPLINK PLAN: [Planned: {}, Held: {}]\t", planned, held);
+            }
+            EventData::SyncSample { offset_us, rtt_us } => {
+                let _ = write!(format_buffer, "SYNC SAMPLE REJECTED: [Offset: {}μs, RTT: {}μs]\t", offset_us, rtt_us);
+            }
+            EventData::ClockSync { offset_us, drift_ppb, error_us, quality } => {
+                let _ = write!(format_buffer, "CLOCK: [Offset: {}μs, Drift: {}ppb, Error: ±{}μs, Quality: {:?}]\t", offset_us, drift_ppb, error_us, quality);
+            }
+            EventData::TimeTag { command_id, execute_at, queued } => {
+                let _ = write!(format_buffer, "TIME TAG: [Command ID: {}, Execute At: {}μs, Queued: {}]\t", command_id, execute_at, queued);
+            }
+            EventData::TimeTagExecution { command_id, tagged_at, started_at } => {
+                let _ = write!(format_buffer, "TIME TAG: [Command ID: {}, Tagged At: {}μs, Started At: {}μs, Late By: {}μs]\t",
+                    command_id, tagged_at, started_at, started_at as i64 - tagged_at as i64);
+            }
+            EventData::TimeCorrelation { reference_us, utc_us, drift_ppb, error_us, quality } => {
+                let _ = write!(format_buffer, "TIME CORRELATION: [Uptime {}μs Is {}, Drift: {}ppb, Error: ±{}μs, Quality: {:?}]\t", reference_us, Utc(utc_us), drift_ppb, error_us, quality);
+            }
+            EventData::Sequence { sequence_id, step, steps } => {
+                let _ = write!(format_buffer, "SEQUENCE: [ID: {}, Step: {}/{}]\t", sequence_id, step, steps);
+            }
+            EventData::SequenceAborted { sequence_id, step, reason } => {
+                let _ = write!(format_buffer, "SEQUENCE ABORTED: [ID: {}, Before Step: {}, Reason: {:?}]\t", sequence_id, step, reason);
+            }
+            EventData::Parameter { parameter, value, version } => {
+                let _ = write!(format_buffer, "PARAMETER: [{:?}: {}{}, Table Version: {}]\t", parameter, value, parameter.limits().kind.unit(), version);
+            }
+            EventData::ParameterChange { parameter, old_value, new_value, version } => {
+                let unit = parameter.limits().kind.unit();
+                let _ = write!(format_buffer, "PARAMETER: [{:?}: {}{} -> {}{}, Table Version: {}]\t", parameter, old_value, unit, new_value, unit, version);
+            }
             EventData::None => {}
         }
 
         let _ = write!(format_buffer, "Event Timestamp: [{} uptime μs]\t", log.event.timestamp);
 
+        match state.clock_sync.correlation.lock().unwrap().utc_us(log.event.timestamp) {
+            Some(utc_us) => { let _ = write!(format_buffer, "UTC: [{}]\t", Utc(utc_us)); }
+            None => { let _ = write!(format_buffer, "UTC: [Unsynced]\t"); }
+        }
+
         // println!("{}", format_buffer);
 
         if let Err(e) = writeln!(file, "{}", format_buffer) {
             eprintln!("Failed to write to disk: {}", e);
         }
     }
-}
\ No newline at end of file
+}
+
+// Lowest log_level an event is still written at
+fn event_level(event_id: EventID) -> LogLevel {
+    match event_id {
+        EventID::CommandNotFound | EventID::SubsystemFault | EventID::StartDelay | EventID::CompletionDelay
+        | EventID::TaskFault | EventID::DataCorruption | EventID::DegradedMode | EventID::MissionAbort
+        | EventID::MissedCommunication | EventID::DataLoss | EventID::RetransmitFailed
+        | EventID::AuthenticationFailure | EventID::CommandRejected | EventID::UplinkGap
+        | EventID::SequenceAborted | EventID::ConfigReload | EventID::ConfigChanged | EventID::ParameterChanged => LogLevel::Warning, // A reload that lowers the level still shows up
+
+        EventID::TaskCompletion | EventID::SyncOngoing | EventID::QueuePerformance
+        | EventID::ResourceUtilization | EventID::NetworkPerformance => LogLevel::Debug,
+
+        _ => LogLevel::Info,
+    }
+}
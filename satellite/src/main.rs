@@ -1,41 +1,47 @@
-mod config;
-mod logging;
-mod network;
-mod monitor;
-mod sensors;
-mod simulation;
-mod types;
-mod state;
-mod buffer;
-mod command;
-
 use std::time::Duration;
 
 use std::sync::{Arc, mpsc};
-use std::sync::atomic::{Ordering};
+use std::sync::mpsc::SyncSender;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use crate::types::*;
-use crate::state::*;
-use crate::buffer::*;
-use crate::simulation::run_simulation;
-use crate::network::run_network_thread;
-use crate::sensors::run_sensor_task;
-use crate::logging::run_logger;
-use crate::monitor::{run_health_monitor, transmit_mission_abort_and_shutdown};
-use crate::command::run_command_executor;
-use crate::config::{DATA_BUFFER_CAPACITY, LOG_BUFFER_CAPACITY, MAIN_MS, MAX_SENSORS};
+use rts_satellite::types::*;
+use rts_satellite::state::*;
+use rts_satellite::buffer::*;
+use rts_satellite::simulation::run_simulation;
+use rts_satellite::network::run_network_thread;
+use rts_satellite::sensors::run_sensor_task;
+use rts_satellite::logging::run_logger;
+use rts_satellite::monitor::{run_health_monitor, transmit_mission_abort_and_shutdown};
+use rts_satellite::command::{run_command_executor, report_parameter_changes};
+use rts_satellite::config::{Config, CONFIG_PATH_ENV, MAX_SENSORS};
 
 fn main() {
-    let state = Arc::new(SatelliteState::new());
-    let downlink_buffer = Arc::new(BoundedBuffer::new(DATA_BUFFER_CAPACITY));
-    let uplink_buffer = Arc::new(BoundedBuffer::new(DATA_BUFFER_CAPACITY));
+    // Command line first, then the environment, otherwise every default
+    let config_path = std::env::args().nth(1).or_else(|| std::env::var(CONFIG_PATH_ENV).ok());
+    let mut config = match &config_path {
+        Some(config_path) => Config::load(config_path)
+            .unwrap_or_else(|e| panic!("Failed to load config from {}: {}", config_path, e)),
+        None => Config::default(),
+    };
+
+    println!("SATELLITE CONFIG: [{:?}]", config);
+
+    let uplink_key = AuthKey::load(&config.uplink_key_path)
+        .unwrap_or_else(|e| panic!("Failed to load uplink key from {}: {}", config.uplink_key_path, e));
+    let verifier = CommandVerifier::persistent(uplink_key.clone(), &config.uplink_counter_path)
+        .unwrap_or_else(|e| panic!("Failed to load uplink counter from {}: {}", config.uplink_counter_path, e));
+
+    let state = Arc::new(SatelliteState::new(config.clone()));
+    let downlink_buffer = Arc::new(BoundedBuffer::new(state.config.data_buffer_capacity));
+    let uplink_buffer = Arc::new(BoundedBuffer::new(state.config.data_buffer_capacity));
 
     let now = state.uptime_ms();
 
-    let (log_tx, log_rx) = mpsc::sync_channel::<Log>(LOG_BUFFER_CAPACITY);
+    let (log_tx, log_rx) = mpsc::sync_channel::<Log>(state.config.log_buffer_capacity);
 
+    let l_state = Arc::clone(&state);
     let logger_handle = thread::spawn(move || {
-        run_logger(log_rx);
+        run_logger(log_rx, l_state);
     });
 
     let sim_state = Arc::clone(&state);
@@ -74,9 +80,24 @@ fn main() {
     let n_downlink_buffer = Arc::clone(&downlink_buffer);
     let n_uplink_buffer = Arc::clone(&uplink_buffer);
     let n_log = log_tx.clone();
-    thread::spawn(move || {
-        run_network_thread(n_state, n_downlink_buffer, n_uplink_buffer, n_log);
-    });
+    let stations = &state.config.ground_stations;
+
+    match state.config.link_transport {
+        TransportKind::Tcp => {
+            let transports = stations.iter().map(|station| TcpTransport::connect(station.address,
+                Duration::from_micros(state.config.init_handshake_limit_ms), Duration::from_micros(state.config.network_write_timeout))).collect();
+            thread::spawn(move || {
+                run_network_thread(n_state, n_downlink_buffer, n_uplink_buffer, n_log, uplink_key, verifier, transports);
+            });
+        }
+        TransportKind::Udp => {
+            let transports = stations.iter().map(|station| UdpTransport::connect(&state.config.udp_local_addr.to_string(), station.address)
+                .expect("Failed to bind UDP socket")).collect();
+            thread::spawn(move || {
+                run_network_thread(n_state, n_downlink_buffer, n_uplink_buffer, n_log, uplink_key, verifier, transports);
+            });
+        }
+    }
 
     log_tx.try_send(Log {
         source: LogSource::Main,
@@ -97,8 +118,16 @@ fn main() {
     })
     .expect("Error setting Ctrl+C handler");
 
+    let reload_requested = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, Arc::clone(&reload_requested))
+        .expect("Error setting SIGHUP handler");
+
     while state.is_running.load(Ordering::SeqCst) && !state.is_shutdown.load(Ordering::SeqCst) {
-        thread::sleep(Duration::from_micros(MAIN_MS));
+        thread::sleep(Duration::from_micros(state.config.main_ms));
+
+        if reload_requested.swap(false, Ordering::SeqCst) {
+            reload_config(&state, &downlink_buffer, &log_tx, config_path.as_deref(), &mut config);
+        }
     }
 
     log_tx.try_send(Log {
@@ -121,6 +150,78 @@ fn main() {
     logger_handle.join().unwrap(); // Wait until All Logs printed
 }
 
+// SIGHUP, the reloadable keys take effect at once and any other change refuses the whole file. Only the keys the file
+// changes go into the parameter table, a value set in flight for any other stays
+fn reload_config(state: &Arc<SatelliteState>, downlink_buffer: &Arc<BoundedBuffer>, log_tx: &SyncSender<Log>, config_path: Option<&str>, current: &mut Config) {
+    let Some(config_path) = config_path else {
+        println!("CONFIG RELOAD REJECTED: [Started on defaults, there is no config file to re-read]");
+        return;
+    };
+
+    let new = match Config::load(config_path) {
+        Ok(new) => new,
+        Err(e) => {
+            println!("CONFIG RELOAD REJECTED: [Failed to load config from {}: {}]", config_path, e);
+            return;
+        }
+    };
+
+    let diff = current.diff(&new);
+
+    let (old_values, new_values) = (current.parameter_values(), new.parameter_values());
+    let mut values = state.parameters.values();
+
+    for parameter in ParameterID::ALL {
+        if new_values[parameter as usize] != old_values[parameter as usize] {
+            values[parameter as usize] = new_values[parameter as usize];
+        }
+    }
+
+    let (changed, rejected) = if !diff.rejected.is_empty() {
+        println!("CONFIG RELOAD REJECTED: [Restart needed for {}]", diff.rejected.join(", "));
+        (0, diff.rejected.len())
+    } else {
+        match state.parameters.set_all(values) {
+            Ok(changes) => {
+                let (old_values, new_values) = (current.reloadable_values(), new.reloadable_values());
+
+                state.tunables.apply(&new);
+                *current = new;
+                println!("CONFIG RELOAD: [{}]", diff.changed.join(", "));
+
+                for &key in &diff.changed_keys {
+                    log_tx.try_send(Log {
+                        source: LogSource::Main,
+                        event: Event {
+                            task_id: TaskID::GlobalSystem,
+                            event_id: EventID::ConfigChanged,
+                            data: EventData::ConfigChange { key, old_value: old_values[key as usize], new_value: new_values[key as usize] },
+                            timestamp: state.uptime_ms(),
+                        }
+                    }).ok();
+                }
+
+                report_parameter_changes(state, log_tx, downlink_buffer, LogSource::Main, TaskID::GlobalSystem, &changes);
+                (diff.changed.len(), 0)
+            }
+            Err((parameter, error)) => {
+                println!("CONFIG RELOAD REJECTED: [{:?} would be {:?} next to the values set in flight]", parameter, error);
+                (0, diff.changed.len())
+            }
+        }
+    };
+
+    log_tx.try_send(Log {
+        source: LogSource::Main,
+        event: Event {
+            task_id: TaskID::GlobalSystem,
+            event_id: EventID::ConfigReload,
+            data: EventData::ConfigReload { changed: changed as u16, rejected: rejected as u16 },
+            timestamp: state.uptime_ms(),
+        }
+    }).ok();
+}
+
 pub fn display_summary(state: &SatelliteState, downlink_buffer: &BoundedBuffer, uplink_buffer: &BoundedBuffer) {
     println!("--------------------------------------SUMMARY--------------------------------------");
 
@@ -146,6 +247,55 @@ pub fn display_summary(state: &SatelliteState, downlink_buffer: &BoundedBuffer,
 
     println!("NETWORK METRICS: [{:?}]", state.network.metrics);
 
+    println!("LINK INTEGRITY METRICS: [CRC Failures: {}, Resyncs: {}, Decode Failures: {}]",
+        state.network.crc_failures.load(Ordering::Relaxed),
+        state.network.resyncs.load(Ordering::Relaxed),
+        state.network.decode_failures.load(Ordering::Relaxed));
+    println!("DOWNLINK ARQ METRICS: [Retransmitted: {}, No Longer In History: {}]",
+        state.network.retransmissions.load(Ordering::Relaxed),
+        state.network.retransmit_unavailable.load(Ordering::Relaxed));
+    println!("DOWNLINK BUDGET METRICS: [Bytes Sent: {}, Budget: {} bytes, Utilization: {:.2}%, Held Back: {}, Left Queued: {}]",
+        state.network.downlink_bytes_sent.load(Ordering::Relaxed),
+        state.network.downlink_budget_bytes.load(Ordering::Relaxed),
+        state.network.downlink_bytes_sent.load(Ordering::Relaxed) as f64 / state.network.downlink_budget_bytes.load(Ordering::Relaxed).max(1) as f64 * 100.0,
+        state.network.downlink_held_back.load(Ordering::Relaxed),
+        state.network.downlink_left_queued.load(Ordering::Relaxed));
+    println!("UPLINK RECOVERY METRICS: [Last Uplink Frame: {}, Frames Missed: {}]",
+        state.network.last_uplink_sequence.load(Ordering::Relaxed),
+        state.network.uplink_frames_missed.load(Ordering::Relaxed));
+    println!("GROUND STATION METRICS: [Stations: {}, Handovers: {}]",
+        state.config.ground_stations.len(),
+        state.network.handovers.load(Ordering::Relaxed));
+
+    let estimate = state.clock_sync.estimate();
+    let oscillator = state.oscillator.lock().unwrap().clone();
+    let estimators = state.clock_sync.estimators.lock().unwrap().clone();
+    println!("CLOCK SYNC METRICS: [Quality: {:?}, Drift: {}ppb, Oscillator Mean Error: {:.0}ppb, Drift Residual: {:.0}ppb, Error: ±{}μs, Jumps: {}, Steps Followed: {}, Rejected: {}]",
+        estimate.quality,
+        estimate.drift_ppb,
+        oscillator.mean_frequency_error_ppb(),
+        estimate.drift_ppb as f64 + oscillator.mean_frequency_error_ppb(), // The offset shrinks as fast as the crystal gains
+        estimate.error_us,
+        oscillator.jumps,
+        estimators.iter().map(|estimator| estimator.steps).sum::<u32>(),
+        estimators.iter().map(|estimator| estimator.rejected).sum::<u32>());
+
+    let correlation = *state.clock_sync.correlation.lock().unwrap();
+    println!("TIME CORRELATION METRICS: [Quality: {:?}, Uptime {}μs Is {}, Drift: {}ppb, Error: ±{}μs]",
+        correlation.quality, correlation.reference_us, Utc(correlation.utc_us), correlation.drift_ppb, correlation.error_us);
+
+    let time_tags = state.time_tags.lock().unwrap();
+    println!("TIME TAG METRICS: [Still Queued: {}, Capacity: {}, Next At: {}μs]",
+        time_tags.len(), time_tags.capacity, time_tags.entries().first().map_or(0, |tag| tag.execute_at));
+    drop(time_tags);
+
+    let sequences = state.sequences.lock().unwrap();
+    println!("SEQUENCE METRICS: [Stored: {}, Running: {}, Capacity: {}]", sequences.len(), sequences.running(), sequences.capacity);
+    drop(sequences);
+
+    println!("PARAMETER METRICS: [Version: {}, Values: {:?}]", state.parameters.version(),
+            ParameterID::ALL.map(|parameter| (parameter, state.parameters.get(parameter))));
+
     println!();
 
     println!("UPLINK BUFFER METRICS: [{:?}, Average Jitter: {}, Average Latency: {}]", uplink_buffer.metrics, 
@@ -6,7 +6,7 @@ use std::sync::Arc;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc::{SyncSender};
 use std::thread;
-use crate::config::{DEGRADED_SKIPPED_SENSOR_CYCLES, DEGRADED_TO_NORMAL_THRESHOLD, FAULT_RECOVERY_MS, MONITOR_MS, MONITOR_PRIORITY, NETWORK_MS, NORMAL_TO_DEGRADED_THRESHOLD, NUMBER_OF_CORES, SENSOR_FAULT_NOT_CONFIRMED, SEQUENCE_NOT_CONFIRMED, TIMESTAMP_NOT_CONFIRMED, VISIBILITY_WINDOW_CYCLE_MS};
+use crate::config::{SENSOR_FAULT_NOT_CONFIRMED, SEQUENCE_NOT_CONFIRMED, TIMESTAMP_NOT_CONFIRMED};
 use thread_priority::*;
 
 pub fn run_health_monitor(
@@ -14,19 +14,20 @@ pub fn run_health_monitor(
     downlink_buffer: Arc<BoundedBuffer>,
     log_tx: SyncSender<Log>
 ) {
-    set_current_thread_priority(ThreadPriority::Crossplatform(MONITOR_PRIORITY.try_into().unwrap())).unwrap();
+    set_current_thread_priority(ThreadPriority::Crossplatform(state.config.monitor_priority.try_into().unwrap())).unwrap();
 
     while state.is_running.load(Ordering::SeqCst) {
         let now = state.uptime_ms();
 
         for sensor in &state.sensors {
             let last_seen = sensor.heartbeat.load(Ordering::Acquire);
+            let period = state.parameters.get(sensor.period);
             
             // "3 consecutive missed cycles" limit
             let limit: u64 = if state.degraded_mode.load(Ordering::Acquire) {
-                sensor.period * 3 * DEGRADED_SKIPPED_SENSOR_CYCLES // If Degraded and Non-Critical, Task Period Multipies by Constant
+                period * 3 * state.parameters.get(ParameterID::DegradedSkippedSensorCycles) // If Degraded and Non-Critical, Task Period Multipies by Constant
             } else {
-                sensor.period * 3 // 3 Cycles
+                period * 3 // 3 Cycles
             };
 
             if now.saturating_sub(last_seen) > limit {
@@ -51,7 +52,7 @@ pub fn run_health_monitor(
                 }, 
                 &state, &log_tx, &downlink_buffer);
 
-                if recovery_time > FAULT_RECOVERY_MS && fault_timestamp != TIMESTAMP_NOT_CONFIRMED {
+                if recovery_time > state.parameters.get(ParameterID::FaultRecoveryMs) && fault_timestamp != TIMESTAMP_NOT_CONFIRMED {
                     transmit_mission_abort_and_shutdown(&state, &downlink_buffer, &log_tx, recovery_time, now);
                 }
 
@@ -86,7 +87,7 @@ pub fn run_health_monitor(
                     &state, &log_tx, &downlink_buffer);
                 }
 
-                if recovery_time > FAULT_RECOVERY_MS && fault_timestamp != TIMESTAMP_NOT_CONFIRMED {
+                if recovery_time > state.parameters.get(ParameterID::FaultRecoveryMs) && fault_timestamp != TIMESTAMP_NOT_CONFIRMED {
                     transmit_mission_abort_and_shutdown(&state, &downlink_buffer, &log_tx, recovery_time, now);
                 }
             }
@@ -99,7 +100,7 @@ pub fn run_health_monitor(
         
         state.buffer_fill_rate.store(fill_rate_percent, Ordering::Relaxed);
 
-        if fill_rate_percent >= NORMAL_TO_DEGRADED_THRESHOLD {
+        if fill_rate_percent as u64 >= state.parameters.get(ParameterID::NormalToDegradedThreshold) {
             if !state.degraded_mode.swap(true, Ordering::Release) {
                  downlink_buffer.push_and_log(LogSource::HealthMonitor, 
                     TelemetryPacket{
@@ -122,35 +123,34 @@ pub fn run_health_monitor(
                 }, 
                 &state, &log_tx, &downlink_buffer);
             }
-        } else if fill_rate_percent < DEGRADED_TO_NORMAL_THRESHOLD {
-            if state.degraded_mode.swap(false, Ordering::Release) {
-                 downlink_buffer.push_and_log(LogSource::HealthMonitor, 
-                    TelemetryPacket{
-                    priority: Priority::Normal,
-                    creation_time: state.uptime_ms(),
-                    payload: SatelliteMessage::Telemetry {
-                            event: Event {
-                                task_id: TaskID::DownlinkNetworkService,
-                                event_id: EventID::NormalMode,
-                                data: EventData::QueuePerformance {
-                                    latency_ms: downlink_buffer.metrics.last_latency_ms.load(Ordering::Relaxed),
-                                    jitter_ms: downlink_buffer.metrics.last_jitter_ms.load(Ordering::Relaxed),
-                                    buffer_fill_rate: fill_rate_percent,
-                                    sample_count: downlink_buffer.metrics.number_of_samples.load(Ordering::Relaxed)
-                                },
-                                timestamp: now,
-                            }
-                    },
-                    sequence_no: SEQUENCE_NOT_CONFIRMED,
-                }, 
-                &state, &log_tx, &downlink_buffer);
-            }
+        } else if (fill_rate_percent as u64) < state.parameters.get(ParameterID::DegradedToNormalThreshold)
+            && state.degraded_mode.swap(false, Ordering::Release) {
+             downlink_buffer.push_and_log(LogSource::HealthMonitor, 
+                TelemetryPacket{
+                priority: Priority::Normal,
+                creation_time: state.uptime_ms(),
+                payload: SatelliteMessage::Telemetry {
+                        event: Event {
+                            task_id: TaskID::DownlinkNetworkService,
+                            event_id: EventID::NormalMode,
+                            data: EventData::QueuePerformance {
+                                latency_ms: downlink_buffer.metrics.last_latency_ms.load(Ordering::Relaxed),
+                                jitter_ms: downlink_buffer.metrics.last_jitter_ms.load(Ordering::Relaxed),
+                                buffer_fill_rate: fill_rate_percent,
+                                sample_count: downlink_buffer.metrics.number_of_samples.load(Ordering::Relaxed)
+                            },
+                            timestamp: now,
+                        }
+                },
+                sequence_no: SEQUENCE_NOT_CONFIRMED,
+            }, 
+            &state, &log_tx, &downlink_buffer);
         }
 
         let current_uptime = state.uptime_ms();
         state.cpu_active_ms.fetch_add(current_uptime - now, Ordering::SeqCst);
         let total_cpu_active_ms = state.cpu_active_ms.load(Ordering::SeqCst) as f32;
-        let total_possible_uptime: f32 = (current_uptime * NUMBER_OF_CORES).max(1) as f32; // If Below 1 then set to 1
+        let total_possible_uptime: f32 = (current_uptime * state.config.number_of_cores).max(1) as f32; // If Below 1 then set to 1
 
         let cpu_active_ms = (current_uptime as f32 * (total_cpu_active_ms / total_possible_uptime)) as u64;
 
@@ -167,7 +167,7 @@ pub fn run_health_monitor(
             }
         });
 
-        thread::sleep(Duration::from_micros(MONITOR_MS));
+        thread::sleep(Duration::from_micros(state.config.monitor_ms));
     }
 }
 
@@ -188,18 +188,18 @@ pub fn transmit_mission_abort_and_shutdown(
                 event: Event {
                     task_id: TaskID::GlobalSystem,
                     event_id: EventID::MissionAbort,
-                    data: EventData::FaultRecovery { recovery_time: recovery_time },
+                    data: EventData::FaultRecovery { recovery_time },
                     timestamp: now,
                 },
         },
         sequence_no: SEQUENCE_NOT_CONFIRMED,
     }, 
-    &state, &log_tx, &downlink_buffer);
+    state, log_tx, downlink_buffer);
 
     let wait_start = state.uptime_ms();
-    let timeout = VISIBILITY_WINDOW_CYCLE_MS * 2;
+    let timeout = state.config.stale_packet_limit_ms; // Long enough to reach the next pass
 
-    while downlink_buffer.len() > 0 {
+    while !downlink_buffer.is_empty() {
         downlink_buffer.push(TelemetryPacket{ // Sends more of MissionAbort if the previous one was lost
             priority: Priority::Emergency,
             creation_time: state.uptime_ms(),
@@ -207,7 +207,7 @@ pub fn transmit_mission_abort_and_shutdown(
                     event: Event {
                         task_id: TaskID::GlobalSystem,
                         event_id: EventID::MissionAbort,
-                        data: EventData::FaultRecovery { recovery_time: recovery_time },
+                        data: EventData::FaultRecovery { recovery_time },
                         timestamp: now,
                     },
             },
@@ -217,7 +217,7 @@ pub fn transmit_mission_abort_and_shutdown(
         if state.uptime_ms() - wait_start > timeout {
             break;
         }
-        thread::sleep(Duration::from_micros(NETWORK_MS));
+        thread::sleep(Duration::from_micros(state.config.network_ms));
     }
 
     state.is_running.store(false, Ordering::SeqCst);
@@ -1,57 +1,79 @@
 use std::sync::mpsc::{SyncSender};
 use std::time::{Duration};
+use std::collections::VecDeque;
+use std::ops::Range;
 use crate::types::{SatelliteMessage, TelemetryPacket, Log, *};
-use crate::config::{INIT_HANDSHAKE_LIMIT_MS, NETWORK_MS, NETWORK_PORT, NETWORK_PRIORITY, NETWORK_READ_TIMEOUT, NETWORK_WRITE_TIMEOUT, PACKET_HISTORY_BUFFER_CAPACITY, SEQUENCE_NOT_CONFIRMED, VISIBILITY_WINDOW_CYCLE_MS, VISIBILITY_WINDOW_LIMIT_MS};
-use std::net::TcpStream;
-use std::io::{Read, Write};
+use crate::config::{REQUIRED_CAPABILITIES, SEQUENCE_NOT_CONFIRMED, SUPPORTED_CAPABILITIES};
 use std::thread;
 use std::sync::atomic::{Ordering};
 use std::sync::Arc;
 use crate::state::SatelliteState;
 use crate::buffer::BoundedBuffer;
-use bincode;
+use crate::command::{report_command_status, report_rejection};
 use thread_priority::*;
 
-pub fn run_network_thread(
+pub fn run_network_thread<T: LinkTransport>(
     state: Arc<SatelliteState>, 
     downlink_buffer: Arc<BoundedBuffer>, 
     uplink_buffer: Arc<BoundedBuffer>,
-    log_tx: SyncSender<Log>
+    log_tx: SyncSender<Log>,
+    uplink_key: AuthKey,
+    mut verifier: CommandVerifier, // Outlives passes so old commands stay refused
+    mut transports: Vec<T>, // One per ground station, in config order
 ) {
-    set_current_thread_priority(ThreadPriority::Crossplatform(NETWORK_PRIORITY.try_into().unwrap())).unwrap();
-    let mut was_visible = false;
+    set_current_thread_priority(ThreadPriority::Crossplatform(state.config.network_priority.try_into().unwrap())).unwrap();
+    let mut attempted = 0u32; // Stations already tried in their current window, same bits as stations_in_view
+    let mut last_station: Option<usize> = None;
 
-    let mut history: Vec<Option<TelemetryPacket>> = vec![None; PACKET_HISTORY_BUFFER_CAPACITY]; // Fixed size array = no heap allocation jitter
-    let mut history_idx = 0;
+    let mut history: Vec<Option<TelemetryPacket>> = vec![None; state.config.packet_history_buffer_capacity]; // Fixed size array = no heap allocation jitter, slotted by sequence_no
 
-    while state.is_running.load(Ordering::SeqCst) {
-        let is_visible = state.network.is_visible.load(Ordering::Acquire);
+    let mut codecs = vec![PacketCodec::new(WireFormat::Bincode); transports.len()]; // Space packets only carry 14 bits of each station's uplink count
+    let mut recent_commands: VecDeque<u32> = VecDeque::with_capacity(state.config.recent_command_capacity);
+    let mut uplink_gaps = vec![GapTracker::default(); transports.len()]; // Each station numbers its own uplink, gaps left at the end of a pass are found by its next one
+    let mut downlink_budget = TokenBucket::new(state.config.downlink_rate_bps, state.config.downlink_burst_bits, state.config.downlink_priority_reserve_bits);
 
-        if is_visible && !was_visible {
+    while state.is_running.load(Ordering::SeqCst) {
+        let in_view = state.network.stations_in_view.load(Ordering::Acquire);
+        attempted &= in_view; // Out of view again, the next window gets a fresh attempt
+
+        // Once a pass ends the next station already overhead takes over, a single radio only ever holds one link
+        if let Some(station) = (0..transports.len()).find(|&i| in_view & !attempted & (1 << i) != 0) {
+            attempted |= 1 << station;
+            let transport = &mut transports[station];
+            let codec = &mut codecs[station];
             let pass_start = state.uptime_ms();
             
-            if let Ok(mut stream) = TcpStream::connect_timeout(&NETWORK_PORT.parse().unwrap(), Duration::from_micros(INIT_HANDSHAKE_LIMIT_MS)) {
-                let _ = stream.set_write_timeout(Some(Duration::from_micros(NETWORK_WRITE_TIMEOUT)));
-                let _ = stream.set_read_timeout(Some(Duration::from_micros(NETWORK_READ_TIMEOUT)));
-                let _ = stream.set_nodelay(true);
-                let mut length_buf = [0u8; 2];
+            if let Some((mut cipher, peer)) = open_link(&state, &log_tx, &uplink_key, transport) {
+                codec.format = WireFormat::negotiated(Capabilities(state.network.capabilities.load(Ordering::Acquire)));
+                codec.resume_at(peer.next_sequence_no);
 
-                let _ = log_tx.try_send(Log {
-                    source: LogSource::Network,
-                    event: Event {
-                        task_id: TaskID::NetworkService,
-                        event_id: EventID::ConnectionStart,
-                        data: EventData::None,
-                        timestamp: state.uptime_ms(),
-                    }
-                });
+                if peer.next_sequence_no <= uplink_gaps[station].last { // The station restarted and numbers its uplink from 1 again
+                    uplink_gaps[station] = GapTracker::default();
+                }
 
-                while state.uptime_ms() - pass_start < VISIBILITY_WINDOW_LIMIT_MS {
+                if let Some(previous) = last_station.filter(|&previous| previous != station) {
+                    report_handover(&state, &downlink_buffer, &log_tx, previous, station);
+                }
+                last_station = Some(station);
+                state.clock_sync.station.store(station, Ordering::Release);
+
+                let link_up = state.uptime_ms();
+                let mut pass_bytes_sent: u64 = 0;
+                let mut held_back: Option<TelemetryPacket> = None;
+                downlink_budget.reset(link_up);
+
+                while state.network.stations_in_view.load(Ordering::Acquire) & (1 << station) != 0 && transport.is_connected() {
                     
-                    if let Some(packet) = downlink_buffer.pop() {
+                    // Radio is busy, the packet stays queued until the bucket refills. Anything pushed between the peek and
+                    // the pop outranks the head, so the bucket would have let it through as well
+                    if let Some(head) = downlink_buffer.peek().filter(|head| !downlink_budget.can_send(head.priority, state.uptime_ms())) {
+                        if held_back.replace(head) != Some(head) { // Counted once, however many sweeps it waits
+                            state.network.downlink_held_back.fetch_add(1, Ordering::Relaxed);
+                        }
+                    } else if let Some(packet) = downlink_buffer.pop() {
                         let queue_latency_ms = state.uptime_ms().saturating_sub(packet.creation_time);
 
-                        if queue_latency_ms > 10 * VISIBILITY_WINDOW_CYCLE_MS && packet.priority != Priority::Emergency {  // Packet Missed 10 Windows
+                        if queue_latency_ms > state.config.stale_packet_limit_ms && packet.priority != Priority::Emergency {
                             downlink_buffer.stale_packet_dropped.fetch_add(1, Ordering::Relaxed);
                             downlink_buffer.packet_dropped.fetch_add(1, Ordering::Relaxed);
 
@@ -85,7 +107,7 @@ pub fn run_network_thread(
                                 },
                             });
 
-                        let outgoing_telemetry = TelemetryPacket {
+                        let mut outgoing_telemetry = TelemetryPacket {
                             priority: packet.priority,
                             creation_time: state.get_synchronized_timestamp(),
                             payload: packet.payload,
@@ -96,44 +118,40 @@ pub fn run_network_thread(
                             },
                         };
 
-                        if let SatelliteMessage::Telemetry { mut event } = outgoing_telemetry.payload {
+                        if let SatelliteMessage::Telemetry { ref mut event } = outgoing_telemetry.payload {
                             event.timestamp = state.synchronize_timestamp(event.timestamp);
                         }
 
-                        history[history_idx] = Some(outgoing_telemetry);
-                        history_idx = (history_idx + 1) % PACKET_HISTORY_BUFFER_CAPACITY;
+                        if let SatelliteMessage::SyncResponse { ref mut satellite_send, .. } = outgoing_telemetry.payload {
+                            *satellite_send = state.uptime_ms(); // As late as possible, time spent queued here is not on the link
+                        }
+
+                        history[outgoing_telemetry.sequence_no as usize % state.config.packet_history_buffer_capacity] = Some(outgoing_telemetry);
 
                         // Serialize and Send
-                        if let Ok(bytes) = bincode::serialize(&outgoing_telemetry) {
-                            let length = bytes.len() as u16;
-
-                            if stream.write_all(&length.to_be_bytes()).is_err() {
+                        if let Some(bytes) = codec.encode(&outgoing_telemetry).and_then(|bytes| cipher.seal(bytes)) {
+                            if !transport.send_frame(&bytes) { // Waits for the next pass, with its sequence number so nothing is numbered twice
+                                downlink_buffer.requeue(LogSource::Network, TelemetryPacket { sequence_no: outgoing_telemetry.sequence_no, ..packet }, &state, &log_tx, &downlink_buffer);
                                 break;
                             }
 
-                            if stream.write_all(&bytes).is_err() {
-                                break;
-                            }
+                            downlink_budget.spend(bytes.len() + FRAME_OVERHEAD);
+                            pass_bytes_sent += (bytes.len() + FRAME_OVERHEAD) as u64;
                         }
                     }
 
 
-                    if let Err(_) = stream.read_exact(&mut length_buf) { 
-                        continue;
-                    };
-
-                    let length = u16::from_be_bytes(length_buf) as usize;
-
-                    let mut payload_buf = vec![0u8; length];
+                    let frame = transport.recv_frame(Duration::from_micros(state.config.network_read_timeout));
+                    report_frame_integrity(&state, &log_tx, transport.decoder());
 
-                    if let Err(_) = stream.read_exact(&mut payload_buf) {
+                    let Some(payload_buf) = frame else {
                         continue;
                     };
 
                     let network_arrival_time = state.uptime_ms();
-                    if let Ok(packet) = bincode::deserialize::<TelemetryPacket>(&&payload_buf) {
+                    if let Some(packet) = cipher.open(payload_buf).and_then(|bytes| codec.decode(&bytes)) {
 
-                        if state.clock_sync.number_of_sample.load(Ordering::Relaxed) > 0 {
+                        if state.clock_sync.estimate().quality != SyncQuality::Unsynced {
                             state.network.metrics.insert_new_metric(
                                 packet.creation_time.abs_diff(state.get_synchronized_timestamp())
                             );
@@ -161,6 +179,26 @@ pub fn run_network_thread(
                             sequence_no: packet.sequence_no,
                         };
 
+                        // Commands, sync results and time correlations all need the uplink key, anyone can send the rest
+                        if let Err(reason) = verifier.verify(&packet.payload) {
+                            let counter = packet.payload.auth().map_or(0, |auth| auth.counter);
+                            reject_unauthenticated(&state, &downlink_buffer, &log_tx, reason, counter, verifier.last_counter);
+
+                            // Only a genuine command refused for its counter names the command, a bad tag means nothing in the frame can be trusted
+                            if let (AuthReject::Replayed, SatelliteMessage::Command { command, command_id, .. }) = (reason, packet.payload) {
+                                report_rejection(&state, &log_tx, &downlink_buffer, LogSource::Network, command.task_id(), command_id, RejectReason::Unauthorized);
+                            }
+                            continue;
+                        }
+
+                        // Only a signed frame may move the tracker past a gap, anyone could send an unsigned one with any number
+                        if packet.payload.auth().is_some() || packet.sequence_no == uplink_gaps[station].last + 1 {
+                            if let Some(missing) = uplink_gaps[station].observe(packet.sequence_no) {
+                                request_uplink_replay(&state, &downlink_buffer, &log_tx, missing);
+                            }
+                            state.network.last_uplink_sequence.store(uplink_gaps[station].last, Ordering::Release);
+                        }
+
                         match packet.payload {
                             SatelliteMessage::SyncRequest => {
                                 let current_sequence_no = state.network.packet_sequence_no.load(Ordering::SeqCst);
@@ -172,106 +210,58 @@ pub fn run_network_thread(
                                     payload: SatelliteMessage::SyncResponse {
                                         ground_sent: packet.creation_time,
                                         satellite_receive: network_arrival_time,
+                                        satellite_send: 0, // Stamped when it goes out
                                     },
                                     sequence_no: current_sequence_no,
                                 }, 
                                 &state, &log_tx, &downlink_buffer);
 
                                 state.network.packet_sequence_no.fetch_add(1, Ordering::SeqCst);
+                            },
+                            SatelliteMessage::SyncResult { ground_sent, satellite_receive, satellite_send, ground_receive, .. } => {
+                                update_clock_sync(&state, &downlink_buffer, &log_tx, &SyncExchange {
+                                    ground_sent,
+                                    satellite_receive,
+                                    satellite_send,
+                                    ground_receive,
+                                });
+                            }
+                            SatelliteMessage::Command { command, command_id, .. } => {
+                                report_command_status(&state, &log_tx, &downlink_buffer, LogSource::Network, command.task_id(), EventID::CommandAccepted, command_id);
 
-                                if state.clock_sync.is_calibrated.load(Ordering::SeqCst) {
-                                    let _ = log_tx.try_send(Log {
-                                        source: LogSource::Network,
-                                        event: Event {
-                                            task_id: TaskID::UplinkNetworkService,
-                                            event_id: EventID::SyncStart,
-                                            data: EventData::None,
-                                            timestamp: network_arrival_time,
-                                        }
-                                    });
-
-                                    state.clock_sync.is_calibrated.store(false, Ordering::SeqCst);
+                                // The ground retries when our ack is late, acknowledge again but only execute once
+                                if recent_commands.contains(&command_id) {
+                                    continue;
                                 }
-                            },
-                            SatelliteMessage::SyncResult { offset} => {
-                                state.clock_sync.total_offset_ms.fetch_add(offset, Ordering::Relaxed);
-                                state.clock_sync.number_of_sample.fetch_add(1, Ordering::Relaxed);
-
-                                let average = state.clock_sync.total_offset_ms.load(Ordering::Relaxed) / state.clock_sync.number_of_sample.load(Ordering::Relaxed) as u64;
-
-                                state.clock_sync.average_offset_ms.store(average, Ordering::Relaxed);
-
-                                let _ = log_tx.try_send(Log {
-                                        source: LogSource::Network,
-                                        event: Event {
-                                            task_id: TaskID::UplinkNetworkService,
-                                            event_id: EventID::SyncOngoing,
-                                            data: EventData::TimeSync { offset: offset },
-                                            timestamp: network_arrival_time,
-                                        }
-                                    });
-
-                                if state.clock_sync.number_of_sample.load(Ordering::Relaxed) % 10 == 0 { // 10 Packets = Synced
-                                    state.clock_sync.is_calibrated.store(true, Ordering::SeqCst);
-                                    let _ = log_tx.try_send(Log {
-                                        source: LogSource::Network,
-                                        event: Event {
-                                            task_id: TaskID::UplinkNetworkService,
-                                            event_id: EventID::SyncCompleted,
-                                            data: EventData::TimeSync { offset: offset },
-                                            timestamp: network_arrival_time,
-                                        }
-                                    });
+                                if recent_commands.len() == state.config.recent_command_capacity {
+                                    recent_commands.pop_front();
                                 }
+                                recent_commands.push_back(command_id);
 
-                            }
-                            SatelliteMessage::Command { command, sent_at: _} => {
-                                if let Command::RequestRetransmit { sequence_no } = command {
-                                    let current_sequence_no = state.network.packet_sequence_no.load(Ordering::SeqCst);
-
-                                    if current_sequence_no - sequence_no >= PACKET_HISTORY_BUFFER_CAPACITY as u32 {
-                                        downlink_buffer.push_and_log(LogSource::Network, 
-                                            TelemetryPacket{
-                                            priority: packet.priority,
-                                            creation_time: state.uptime_ms(),
-                                            payload: SatelliteMessage::Telemetry {
-                                                event: Event {
-                                                    task_id: TaskID::UplinkNetworkService,
-                                                    event_id: EventID::RetransmitFailed,
-                                                    data: EventData::None,
-                                                    timestamp: state.uptime_ms(),
-                                                }
-                                            },
-                                            sequence_no: current_sequence_no,
-                                        }, 
-                                        &state, &log_tx, &downlink_buffer);
-
-                                        state.network.packet_sequence_no.fetch_add(1, Ordering::SeqCst);
-                                    }
-                                    
-                                    let target_index = sequence_no % PACKET_HISTORY_BUFFER_CAPACITY as u32;
-
-                                    if let Some(lost_packet) = history[target_index as usize] {
-                                        let outgoing_history_packet = TelemetryPacket {
-                                            priority: Priority::Normal,
-                                            creation_time: network_arrival_time,
-                                            payload: lost_packet.payload,
-                                            sequence_no: lost_packet.sequence_no,
-                                        };
-
-                                        downlink_buffer.push_and_log(LogSource::Network, outgoing_history_packet, &state, &log_tx, &downlink_buffer);
-                                        }
-                                } else {
-                                    uplink_buffer.push_and_log(LogSource::Network, incoming_telemetry, &state, &log_tx, &downlink_buffer);
-                                }
+                                // A RequestRetransmit from an old ground goes on as well, NACKs replaced it and the executor reports it as not found
+                                uplink_buffer.push_and_log(LogSource::Network, incoming_telemetry, &state, &log_tx, &downlink_buffer);
                                 
                             }
+                            SatelliteMessage::Nack { base, bitmap } => {
+                                answer_nack(&state, &downlink_buffer, &log_tx, &history, base, bitmap);
+                            }
+                            SatelliteMessage::TimeCorrelation { correlation, .. } => {
+                                update_time_correlation(&state, &log_tx, correlation);
+                            }
                             _ => {}
                         };
                         
+                    } else {
+                        state.network.decode_failures.fetch_add(1, Ordering::Relaxed);
+                        log_frame_integrity(&state, &log_tx);
                     }
                 }
 
+                transport.close();
+
+                report_downlink_usage(&state, &downlink_buffer, &log_tx, pass_bytes_sent,
+                    downlink_budget.budget_bits(state.uptime_ms() - link_up) / 8);
+
                 let _ = log_tx.try_send(Log {
                     source: LogSource::Network,
                     event: Event {
@@ -303,9 +293,329 @@ pub fn run_network_thread(
             state.cpu_active_ms.fetch_add(state.uptime_ms() - pass_start, Ordering::SeqCst);
         }
 
+        thread::sleep(Duration::from_micros(state.config.network_ms)); // Polling interval
+    }
+}
+
+// Brings the link up and runs the Hello/HelloAck exchange, None if the pass cannot be used
+fn open_link(state: &Arc<SatelliteState>, log_tx: &SyncSender<Log>, uplink_key: &AuthKey, transport: &mut impl LinkTransport) -> Option<(LinkCipher, Hello)> {
+    if !transport.open() {
+        return None;
+    }
+
+    let local = Hello {
+        next_sequence_no: state.network.packet_sequence_no.load(Ordering::SeqCst),
+        ..Hello::new(state.config.spacecraft_id, SUPPORTED_CAPABILITIES)
+    };
 
-        was_visible = is_visible;
-        thread::sleep(Duration::from_micros(NETWORK_MS)); // Polling interval
+    if let Ok(bytes) = bincode::serialize(&local) {
+        transport.send_frame(&bytes);
     }
+
+    let (peer, result) = match transport.recv_frame(Duration::from_micros(state.config.init_handshake_limit_ms)) {
+        Some(bytes) => match bincode::deserialize::<HelloAck>(&bytes) {
+            Ok(HelloAck { rejected: Some(reason), hello }) => (Some(hello), Err(reason)),
+            Ok(HelloAck { rejected: None, hello }) => (Some(hello), local.negotiate(&hello, REQUIRED_CAPABILITIES)),
+            Err(_) => (None, Err(HandshakeReject::Malformed)),
+        },
+        None => (None, Err(HandshakeReject::Timeout)),
+    };
+
+    let event = match result {
+        Ok(capabilities) => {
+            state.network.capabilities.store(capabilities.0, Ordering::Release);
+
+            Event {
+                task_id: TaskID::NetworkService,
+                event_id: EventID::ConnectionStart,
+                data: EventData::Handshake {
+                    version: local.version,
+                    spacecraft_id: local.spacecraft_id,
+                    capabilities,
+                },
+                timestamp: state.uptime_ms(),
+            }
+        }
+        Err(reason) => Event {
+            task_id: TaskID::NetworkService,
+            event_id: EventID::ConnectionEnd,
+            data: EventData::HandshakeRejected {
+                reason,
+                peer_version: peer.map_or(0, |p| p.version),
+                peer_spacecraft_id: peer.map_or(0, |p| p.spacecraft_id),
+            },
+            timestamp: state.uptime_ms(),
+        },
+    };
+
+    let _ = log_tx.try_send(Log { source: LogSource::Network, event });
+
+    let (Ok(capabilities), Some(peer)) = (result, peer) else {
+        transport.close();
+        return None;
+    };
+
+    Some((LinkCipher::negotiate(capabilities, uplink_key, &local, &peer, LinkRole::Satellite), peer))
+}
+
+// Reported to the ground so it can tell a forged or replayed uplink from a lost one
+fn reject_unauthenticated(
+    state: &Arc<SatelliteState>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    log_tx: &SyncSender<Log>,
+    reason: AuthReject,
+    counter: u64,
+    last_counter: u64,
+) {
+    downlink_buffer.push_and_log(LogSource::Network,
+        TelemetryPacket {
+            priority: Priority::Critical,
+            creation_time: state.uptime_ms(),
+            payload: SatelliteMessage::Telemetry {
+                event: Event {
+                    task_id: TaskID::UplinkNetworkService,
+                    event_id: EventID::AuthenticationFailure,
+                    data: EventData::AuthFailure { reason, counter, last_counter },
+                    timestamp: state.uptime_ms(),
+                },
+            },
+            sequence_no: SEQUENCE_NOT_CONFIRMED,
+        },
+        state, log_tx, downlink_buffer);
+}
+
+// Selective repeat, only the NACKed packets are sent again and keep their sequence numbers
+fn answer_nack(
+    state: &Arc<SatelliteState>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    log_tx: &SyncSender<Log>,
+    history: &[Option<TelemetryPacket>],
+    base: u32,
+    bitmap: u64,
+) {
+    let mut unavailable = 0u64;
+
+    for sequence_no in nack_sequence_numbers(base, bitmap) {
+        let stored = history[sequence_no as usize % state.config.packet_history_buffer_capacity]
+            .filter(|packet| packet.sequence_no == sequence_no);
+
+        match stored {
+            Some(packet) => {
+                state.network.retransmissions.fetch_add(1, Ordering::Relaxed);
+                downlink_buffer.push_and_log(LogSource::Network,
+                    TelemetryPacket { creation_time: state.uptime_ms(), ..packet },
+                    state, log_tx, downlink_buffer);
+            }
+            None => unavailable |= 1 << sequence_no.wrapping_sub(base),
+        }
+    }
+
+    if unavailable == 0 {
+        return;
+    }
+
+    state.network.retransmit_unavailable.fetch_add(unavailable.count_ones(), Ordering::Relaxed);
+
+    let event = Event {
+        task_id: TaskID::DownlinkNetworkService,
+        event_id: EventID::RetransmitFailed,
+        data: EventData::RetransmitUnavailable { base, bitmap: unavailable },
+        timestamp: state.uptime_ms(),
+    };
+
+    let _ = log_tx.try_send(Log { source: LogSource::Network, event });
+
+    downlink_buffer.push_and_log(LogSource::Network,
+        TelemetryPacket {
+            priority: Priority::Critical,
+            creation_time: state.uptime_ms(),
+            payload: SatelliteMessage::Telemetry { event },
+            sequence_no: SEQUENCE_NOT_CONFIRMED,
+        },
+        state, log_tx, downlink_buffer);
+}
+
+// The ground replays the range from its uplink history
+fn request_uplink_replay(
+    state: &Arc<SatelliteState>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    log_tx: &SyncSender<Log>,
+    missing: Range<u32>,
+) {
+    let count = missing.len() as u32;
+    state.network.uplink_frames_missed.fetch_add(count, Ordering::Relaxed);
+
+    let event = Event {
+        task_id: TaskID::UplinkNetworkService,
+        event_id: EventID::UplinkGap,
+        data: EventData::SequenceGap { first: missing.start, count },
+        timestamp: state.uptime_ms(),
+    };
+
+    let _ = log_tx.try_send(Log { source: LogSource::Network, event });
+
+    downlink_buffer.push_and_log(LogSource::Network,
+        TelemetryPacket {
+            priority: Priority::Critical,
+            creation_time: state.uptime_ms(),
+            payload: SatelliteMessage::Telemetry { event },
+            sequence_no: SEQUENCE_NOT_CONFIRMED,
+        },
+        state, log_tx, downlink_buffer);
 }
 
+// Logged and downlinked first thing in the pass. Its sequence number tells the new station where its share of the
+// downlink starts, everything numbered before it went to the previous station
+fn report_handover(
+    state: &Arc<SatelliteState>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    log_tx: &SyncSender<Log>,
+    previous: usize,
+    station: usize,
+) {
+    state.network.handovers.fetch_add(1, Ordering::Relaxed);
+
+    let event = Event {
+        task_id: TaskID::NetworkService,
+        event_id: EventID::Handover,
+        data: EventData::Handover {
+            from_station: state.config.ground_stations[previous].station_id,
+            to_station: state.config.ground_stations[station].station_id,
+        },
+        timestamp: state.uptime_ms(),
+    };
+
+    downlink_buffer.push_and_log(LogSource::Network, // Logs it too
+        TelemetryPacket {
+            priority: Priority::Emergency,
+            creation_time: state.uptime_ms(),
+            payload: SatelliteMessage::Telemetry { event },
+            sequence_no: state.network.packet_sequence_no.fetch_add(1, Ordering::SeqCst),
+        },
+        state, log_tx, downlink_buffer);
+}
+
+// Every estimate goes down as telemetry, SyncCompleted while the fit is locked so the ground can sync less often
+fn update_clock_sync(
+    state: &Arc<SatelliteState>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    log_tx: &SyncSender<Log>,
+    exchange: &SyncExchange,
+) {
+    let (accepted, estimate) = state.clock_sync.add(state.clock_sync.station.load(Ordering::Acquire), exchange);
+
+    if !accepted { // Kept out of the fit, logged so a link that keeps getting slower shows up
+        let _ = log_tx.try_send(Log {
+            source: LogSource::Network,
+            event: Event {
+                task_id: TaskID::UplinkNetworkService,
+                event_id: EventID::SyncOngoing,
+                data: EventData::SyncSample { offset_us: exchange.offset_us(), rtt_us: exchange.rtt_us() },
+                timestamp: state.uptime_ms(),
+            },
+        });
+    }
+
+    downlink_buffer.push_and_log(LogSource::Network,
+        TelemetryPacket {
+            priority: Priority::Normal,
+            creation_time: state.uptime_ms(),
+            payload: SatelliteMessage::Telemetry {
+                event: Event {
+                    task_id: TaskID::UplinkNetworkService,
+                    event_id: if estimate.quality == SyncQuality::Locked { EventID::SyncCompleted } else { EventID::SyncOngoing },
+                    data: EventData::ClockSync {
+                        offset_us: estimate.offset_us,
+                        drift_ppb: estimate.drift_ppb.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                        error_us: estimate.error_us.min(u32::MAX as u64) as u32,
+                        quality: estimate.quality,
+                    },
+                    timestamp: state.uptime_ms(),
+                },
+            },
+            sequence_no: SEQUENCE_NOT_CONFIRMED,
+        },
+        state, log_tx, downlink_buffer);
+}
+
+// Only written to our own log, the ground already has it
+fn update_time_correlation(state: &Arc<SatelliteState>, log_tx: &SyncSender<Log>, correlation: TimeCorrelation) {
+    *state.clock_sync.correlation.lock().unwrap() = correlation;
+
+    let _ = log_tx.try_send(Log {
+        source: LogSource::Network,
+        event: Event {
+            task_id: TaskID::UplinkNetworkService,
+            event_id: EventID::TimeCorrelation,
+            data: EventData::TimeCorrelation {
+                reference_us: correlation.reference_us,
+                utc_us: correlation.utc_us,
+                drift_ppb: correlation.drift_ppb.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                error_us: correlation.error_us.min(u32::MAX as u64) as u32,
+                quality: correlation.quality,
+            },
+            timestamp: state.uptime_ms(),
+        },
+    });
+}
+
+// End of pass report, anything still queued waits for the next window
+fn report_downlink_usage(
+    state: &Arc<SatelliteState>,
+    downlink_buffer: &Arc<BoundedBuffer>,
+    log_tx: &SyncSender<Log>,
+    bytes_sent: u64,
+    budget_bytes: u64,
+) {
+    let queued = downlink_buffer.len() as u32;
+
+    state.network.downlink_bytes_sent.fetch_add(bytes_sent, Ordering::Relaxed);
+    state.network.downlink_budget_bytes.fetch_add(budget_bytes, Ordering::Relaxed);
+    state.network.downlink_left_queued.store(queued, Ordering::Relaxed);
+
+    downlink_buffer.push_and_log(LogSource::Network,
+        TelemetryPacket {
+            priority: Priority::Low,
+            creation_time: state.uptime_ms(),
+            payload: SatelliteMessage::Telemetry {
+                event: Event {
+                    task_id: TaskID::DownlinkNetworkService,
+                    event_id: EventID::DownlinkUsage,
+                    data: EventData::DownlinkUsage { bytes_sent, budget_bytes, queued },
+                    timestamp: state.uptime_ms(),
+                },
+            },
+            sequence_no: SEQUENCE_NOT_CONFIRMED,
+        },
+        state, log_tx, downlink_buffer);
+}
+
+// Moves the decoder's per-pass counters into the link totals
+fn report_frame_integrity(state: &Arc<SatelliteState>, log_tx: &SyncSender<Log>, decoder: &mut FrameDecoder) {
+    let crc_failures = std::mem::take(&mut decoder.crc_failures);
+    let resyncs = std::mem::take(&mut decoder.resyncs);
+
+    if crc_failures == 0 && resyncs == 0 {
+        return;
+    }
+
+    state.network.crc_failures.fetch_add(crc_failures, Ordering::Relaxed);
+    state.network.resyncs.fetch_add(resyncs, Ordering::Relaxed);
+    log_frame_integrity(state, log_tx);
+}
+
+fn log_frame_integrity(state: &Arc<SatelliteState>, log_tx: &SyncSender<Log>) {
+    let _ = log_tx.try_send(Log {
+        source: LogSource::Network,
+        event: Event {
+            task_id: TaskID::NetworkService,
+            event_id: EventID::DataCorruption,
+            data: EventData::FrameIntegrity {
+                crc_failures: state.network.crc_failures.load(Ordering::Relaxed),
+                resyncs: state.network.resyncs.load(Ordering::Relaxed),
+                decode_failures: state.network.decode_failures.load(Ordering::Relaxed),
+            },
+            timestamp: state.uptime_ms(),
+        },
+    });
+}
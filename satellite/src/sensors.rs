@@ -3,8 +3,8 @@ use std::sync::mpsc::SyncSender;
 use std::time::{Duration};
 use std::sync::atomic::Ordering;
 
-use crate::config::{DEGRADED_SKIPPED_SENSOR_CYCLES, FAULT_RECOVERY_MS, SEQUENCE_NOT_CONFIRMED, SENSOR_DELAY_MS, SENSOR_FAULT_MS, SENSOR_FAULT_NOT_CONFIRMED, TIMESTAMP_NOT_CONFIRMED};
-use crate::types::{Event, EventData, EventID, Log, LogSource, Priority, SatelliteMessage, TelemetryPacket};
+use crate::config::{SEQUENCE_NOT_CONFIRMED, SENSOR_FAULT_NOT_CONFIRMED, TIMESTAMP_NOT_CONFIRMED};
+use crate::types::{Event, EventData, EventID, Log, LogSource, ParameterID, Priority, SatelliteMessage, TelemetryPacket};
 use crate::state::SatelliteState;
 use crate::buffer::BoundedBuffer;
 use crate::monitor::transmit_mission_abort_and_shutdown;
@@ -20,20 +20,19 @@ pub fn run_sensor_task(
     let sensor = &state.sensors[sensor_index];
     set_current_thread_priority(ThreadPriority::Crossplatform((sensor.priority as u8).try_into().unwrap())).unwrap();
 
-    let interval = sensor.period;
-    let mut next_wake_time = state.uptime_ms() + interval;
+    let mut next_wake_time = state.uptime_ms() + state.parameters.get(sensor.period);
 
     while state.is_running.load(Ordering::SeqCst) {
         
         let fault_event = sensor.fault.load(Ordering::Acquire);
 
         if fault_event == EventID::TaskFault as u16 {
-            thread::sleep(Duration::from_micros(SENSOR_FAULT_MS));
+            thread::sleep(Duration::from_micros(state.config.sensor_fault_ms));
             continue;
         }
 
         if fault_event == EventID::StartDelay as u16 {
-            thread::sleep(Duration::from_micros(SENSOR_DELAY_MS));
+            thread::sleep(Duration::from_micros(state.config.sensor_delay_ms));
         }
 
         let task_start = state.uptime_ms();
@@ -54,7 +53,7 @@ pub fn run_sensor_task(
 
         let current_value = sensor.value.load(Ordering::Relaxed);
 
-        if !sensor.has_valid_value()  {
+        if !sensor.has_valid_value(&state.parameters)  {
             let fault_recovery_timestamp = state.uptime_ms();
 
             let fault_timestamp = sensor.fault_timestamp.load(Ordering::Acquire);
@@ -69,7 +68,7 @@ pub fn run_sensor_task(
                         event: Event {
                             task_id: sensor.task_id,
                             event_id: EventID::DataCorruption,
-                            data: EventData::CorruptedHardware { value: current_value, recovery_time: recovery_time },
+                            data: EventData::CorruptedHardware { value: current_value, recovery_time },
                             timestamp: fault_recovery_timestamp,
                         },
                 },
@@ -77,11 +76,12 @@ pub fn run_sensor_task(
             }, 
             &state, &log_tx, &downlink_buffer);
 
-            if recovery_time > FAULT_RECOVERY_MS && fault_timestamp != TIMESTAMP_NOT_CONFIRMED {
+            if recovery_time > state.parameters.get(ParameterID::FaultRecoveryMs) && fault_timestamp != TIMESTAMP_NOT_CONFIRMED {
                 transmit_mission_abort_and_shutdown(&state, &downlink_buffer, &log_tx, recovery_time, fault_recovery_timestamp);
             }
 
-            let reset_value = (sensor.min_data + sensor.max_data) / 2;
+            let (min_data, max_data) = sensor.data_range(&state.parameters);
+            let reset_value = (min_data + max_data) / 2;
 
             sensor.value.store(reset_value, Ordering::Relaxed);
             sensor.fault.store(SENSOR_FAULT_NOT_CONFIRMED, Ordering::Release);
@@ -89,7 +89,7 @@ pub fn run_sensor_task(
         }
 
         if fault_event == EventID::CompletionDelay as u16 {
-            thread::sleep(Duration::from_micros(SENSOR_DELAY_MS));
+            thread::sleep(Duration::from_micros(state.config.sensor_delay_ms));
         }
         
         let completion_time = state.uptime_ms();
@@ -143,8 +143,10 @@ pub fn run_sensor_task(
             &state, &log_tx, &downlink_buffer);
         }
 
+        let interval = state.parameters.get(sensor.period); // Read every cycle, a new period takes over from the next wake
+
         if state.degraded_mode.load(Ordering::Acquire) {
-            next_wake_time += interval * DEGRADED_SKIPPED_SENSOR_CYCLES; // Miss Next 3 Cycles 
+            next_wake_time += interval * state.parameters.get(ParameterID::DegradedSkippedSensorCycles); // Miss Next 3 Cycles 
         } else {
             next_wake_time += interval;
         }
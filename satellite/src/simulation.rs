@@ -1,19 +1,19 @@
 use std::{sync::atomic::Ordering};
 use std::sync::Arc;
-use crate::{config::{MAX_SENSORS, SENSOR_DATA_CORRUPTION, SIMULATION_PRIORITY}, state::SatelliteState, types::EventID};
+use crate::{config::{MAX_SENSORS, SENSOR_DATA_CORRUPTION}, state::SatelliteState, types::{EventID, wall_clock_us}};
 use std::thread;
 use std::time::Duration;
-use crate::config::{SENSOR_INCREMENT_MAX, MAX_SUBSYSTEM, SENSOR_FAULT_INJECTION_MS, SUBSYSTEM_FAULT_INJECTION_MS, TICK_RATE, VISIBILITY_WINDOW_CYCLE_MS, VISIBILITY_WINDOW_LIMIT_MS};
+use crate::config::{MAX_SUBSYSTEM, TICK_RATE};
 use rand::Rng;
 use thread_priority::*;
 
 
 
 pub fn run_simulation(state: Arc<SatelliteState>) {
-    set_current_thread_priority(ThreadPriority::Crossplatform(SIMULATION_PRIORITY.try_into().unwrap())).unwrap();
+    set_current_thread_priority(ThreadPriority::Crossplatform(state.config.simulation_priority.try_into().unwrap())).unwrap();
 
-    let mut subsystem_fault_interval = SUBSYSTEM_FAULT_INJECTION_MS;
-    let mut sensor_fault_interval = SENSOR_FAULT_INJECTION_MS;
+    let mut subsystem_fault_interval = state.config.subsystem_fault_injection_ms;
+    let mut sensor_fault_interval = state.config.sensor_fault_injection_ms;
 
     while state.is_running.load(Ordering::SeqCst) {
         let now = state.uptime_ms();
@@ -21,28 +21,35 @@ pub fn run_simulation(state: Arc<SatelliteState>) {
         for sensor in &state.sensors {
             let mut rng = rand::thread_rng();
 
-            let val: u32 = rng.gen_range(0..SENSOR_INCREMENT_MAX);
+            let val: u32 = rng.gen_range(0..state.config.sensor_increment_max);
             let is_addition: bool = rand::random();
 
 
             let current = sensor.value.load(Ordering::Relaxed);
+            let (min_data, max_data) = sensor.data_range(&state.parameters);
 
             let new_value = if is_addition {
-                current.saturating_add(val).min(sensor.max_data)
+                current.saturating_add(val).min(max_data)
             } else {
-                current.saturating_sub(val).max(sensor.min_data)
+                current.saturating_sub(val).max(min_data)
             };
 
             sensor.value.store(new_value, Ordering::Relaxed);
 
-            if !sensor.has_valid_value() {
+            if !sensor.has_valid_value(&state.parameters) {
                 sensor.fault.store(EventID::DataCorruption as u16, Ordering::Release);
                 sensor.fault_timestamp.store(state.uptime_ms(), Ordering::Release);
             }
         }
 
     
-        state.network.is_visible.store(now % VISIBILITY_WINDOW_CYCLE_MS < VISIBILITY_WINDOW_LIMIT_MS, Ordering::Release);
+        let wall_clock = wall_clock_us();
+        let stations_in_view = state.config.ground_stations.iter()
+            .enumerate()
+            .filter(|(_, station)| state.orbit.is_visible(&station.site(), wall_clock))
+            .fold(0u32, |in_view, (index, _)| in_view | 1 << index);
+
+        state.network.stations_in_view.store(stations_in_view, Ordering::Release);
 
         if now >= sensor_fault_interval {
             let sensor_index = rand::thread_rng().gen_range(0..MAX_SENSORS);
@@ -57,7 +64,7 @@ pub fn run_simulation(state: Arc<SatelliteState>) {
                 state.sensors[sensor_index].value.store(SENSOR_DATA_CORRUPTION, Ordering::Relaxed);
             }
 
-            sensor_fault_interval = now + SENSOR_FAULT_INJECTION_MS;
+            sensor_fault_interval = now + state.config.sensor_fault_injection_ms;
         }
 
         if now >= subsystem_fault_interval {
@@ -67,7 +74,7 @@ pub fn run_simulation(state: Arc<SatelliteState>) {
             state.subsystem_health[subsystem_index].fault.store(true, Ordering::Release);
             state.subsystem_health[subsystem_index].fault_interlock.store(true, Ordering::Release);
 
-            subsystem_fault_interval = now + SUBSYSTEM_FAULT_INJECTION_MS;
+            subsystem_fault_interval = now + state.config.subsystem_fault_injection_ms;
         }
 
         state.cpu_active_ms.fetch_add(state.uptime_ms() - now, Ordering::SeqCst);
@@ -1,19 +1,29 @@
-use std::sync::atomic::{AtomicU32, AtomicU16, AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU8, AtomicU32, AtomicU16, AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::time::{Instant};
-use crate::types::{TaskID, Priority, SubsystemID, Metrics};
-use crate::config::{MAX_SENSORS, TICK_RATE, MAX_SUBSYSTEM};
+use crate::types::{TaskID, Priority, SubsystemID, Metrics, Orbit, Oscillator, ClockEstimator, ClockEstimate, SyncExchange, TimeCorrelation, SequenceStore, ParameterID, ParameterTable};
+use crate::time_tag::TimeTagQueue;
+use crate::config::{Config, MAX_SENSORS, MAX_SUBSYSTEM, THERMAL_SENSOR};
 
 #[derive(Debug)]
 pub struct SatelliteState {
+    pub config: Config, // As loaded at startup
+    pub tunables: Tunables,
+    pub parameters: ParameterTable, // Built from config, then changed by SetParameter and SIGHUP
+
     // Control Flags
     pub is_running: AtomicBool,
     pub is_shutdown: AtomicBool,
     pub network: NetworkState,
     pub degraded_mode: AtomicBool,
+    pub orbit: Orbit, // Built from config.orbit
 
     // Clock
     pub clock_sync: SyncState,
     pub boot_time: Instant,
+    pub oscillator: Mutex<Oscillator>, // Built from config.oscillator, uptime is its reading
+    pub time_tags: Mutex<TimeTagQueue>,
+    pub sequences: Mutex<SequenceStore>,
 
     // Sensor
     pub sensors: [SensorState; MAX_SENSORS],
@@ -24,6 +34,24 @@ pub struct SatelliteState {
     pub buffer_fill_rate: AtomicU32, // (Current size * 100) / Capacity
 }
 
+// The RELOADABLE_KEYS of the config outside the parameter table, SIGHUP swaps them in while the threads keep running
+#[derive(Debug)]
+pub struct Tunables {
+    pub log_level: AtomicU8,
+}
+
+impl Tunables {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            log_level: AtomicU8::new(config.log_level as u8),
+        }
+    }
+
+    pub fn apply(&self, config: &Config) {
+        self.log_level.store(config.log_level as u8, Ordering::Release);
+    }
+}
+
 #[derive(Debug)]
 pub struct SubsystemState {
     pub id: SubsystemID,
@@ -40,9 +68,9 @@ pub struct SensorState {
     pub priority: Priority,
     pub data_priority: Priority,
     pub task_id: TaskID,
-    pub period: u64,
-    pub min_data: u32,
-    pub max_data: u32,
+    pub period: ParameterID, // These three are entries of SatelliteState::parameters, read live
+    pub min_data: ParameterID,
+    pub max_data: ParameterID,
 
     // Mutable Through Atomic Methods
     pub value: AtomicU32,
@@ -53,37 +81,95 @@ pub struct SensorState {
 }
 
 impl SensorState {
-    pub fn has_valid_value(&self) -> bool {
+    pub fn data_range(&self, parameters: &ParameterTable) -> (u32, u32) {
+        (parameters.get(self.min_data) as u32, parameters.get(self.max_data) as u32) // Limited to 5 digits by the table
+    }
+
+    pub fn has_valid_value(&self, parameters: &ParameterTable) -> bool {
         let value = self.value.load(Ordering::Relaxed);
+        let (min_data, max_data) = self.data_range(parameters);
 
-        value >= self.min_data && value <= self.max_data
+        value >= min_data && value <= max_data
     }
 }
 
 #[derive(Debug)]
 pub struct SyncState {
-    pub total_offset_ms: AtomicU64,
-    pub average_offset_ms: AtomicU64, 
-    pub number_of_sample: AtomicU32,
-    pub is_calibrated: AtomicBool, // Is it Syncing or not
+    pub estimators: Mutex<Vec<ClockEstimator>>, // One per ground_stations entry, every station keeps its own clock
+    pub station: AtomicUsize, // Whose clock our timestamps are mapped onto, the station of the latest pass
+    pub correlation: Mutex<TimeCorrelation>, // Our uptime onto UTC, as the ground last worked it out
+}
+
+impl SyncState {
+    pub fn estimate(&self) -> ClockEstimate {
+        self.estimators.lock().unwrap()[self.station.load(Ordering::Acquire)].estimate()
+    }
+
+    // Our clock on the clock of a given station, rather than the one of the latest pass
+    pub fn ground_time(&self, station: usize, local_us: u64) -> u64 {
+        self.estimators.lock().unwrap()[station].estimate().ground_time(local_us)
+    }
+
+    // The flag is false if the exchange was rejected as an outlier
+    pub fn add(&self, station: usize, exchange: &SyncExchange) -> (bool, ClockEstimate) {
+        let estimator = &mut self.estimators.lock().unwrap()[station];
+        (estimator.add(exchange), estimator.estimate())
+    }
 }
 
 #[derive(Debug)]
 pub struct NetworkState {
-    pub is_visible: AtomicBool,
+    pub stations_in_view: AtomicU32, // Bit i is set while ground_stations[i] is overhead
     pub metrics: Metrics,
     pub packet_sequence_no: AtomicU32,
-
+    pub capabilities: AtomicU32, // Negotiated in the handshake of the current pass
+    pub crc_failures: AtomicU32,
+    pub resyncs: AtomicU32,
+    pub decode_failures: AtomicU32,
+    pub last_uplink_sequence: AtomicU32,
+    pub uplink_frames_missed: AtomicU32, // Uplink sequence numbers skipped, each one asked for again
+    pub retransmissions: AtomicU32, // Downlink packets sent again for a NACK
+    pub retransmit_unavailable: AtomicU32,
+    pub downlink_bytes_sent: AtomicU64, // Framed bytes, every pass
+    pub downlink_budget_bytes: AtomicU64, // What the rate limiter allowed over the same passes
+    pub downlink_held_back: AtomicU32, // Packets that waited in the buffer for the bucket to refill, each counted once
+    pub downlink_left_queued: AtomicU32, // Downlink buffer length when the last pass ended
+    pub handovers: AtomicU32, // Passes that went to a different station than the one before
 }
 
 impl SatelliteState {
-    pub fn new() -> Self {
+    pub fn new(config: Config) -> Self {
         Self {
+            tunables: Tunables::new(&config),
+            parameters: config.parameters().expect("parameters are checked when the config loads"),
+            orbit: config.orbit.build().expect("orbit is checked when the config loads"),
+            oscillator: Mutex::new(config.oscillator.build().expect("oscillator is checked when the config loads")),
+            time_tags: Mutex::new(TimeTagQueue::new(config.time_tag_capacity)),
+            sequences: Mutex::new(SequenceStore::new(config.sequence_capacity)),
+            clock_sync: SyncState {
+                estimators: Mutex::new(vec![ClockEstimator::new(); config.ground_stations.len()]),
+                station: AtomicUsize::new(0),
+                correlation: Mutex::new(TimeCorrelation::NONE),
+            },
+            config,
             is_running: AtomicBool::new(true),
             is_shutdown: AtomicBool::new(false),
             network: NetworkState { 
-                is_visible: AtomicBool::new(false), 
+                stations_in_view: AtomicU32::new(0),
                 packet_sequence_no: AtomicU32::new(1),
+                capabilities: AtomicU32::new(0),
+                crc_failures: AtomicU32::new(0),
+                resyncs: AtomicU32::new(0),
+                decode_failures: AtomicU32::new(0),
+                last_uplink_sequence: AtomicU32::new(0),
+                uplink_frames_missed: AtomicU32::new(0),
+                retransmissions: AtomicU32::new(0),
+                retransmit_unavailable: AtomicU32::new(0),
+                downlink_bytes_sent: AtomicU64::new(0),
+                downlink_budget_bytes: AtomicU64::new(0),
+                downlink_held_back: AtomicU32::new(0),
+                downlink_left_queued: AtomicU32::new(0),
+                handovers: AtomicU32::new(0),
                 metrics: Metrics {
                     last_latency_ms: AtomicU64::new(0),
                     total_latency_ms: AtomicU64::new(0),
@@ -97,12 +183,6 @@ impl SatelliteState {
                 }
             },
             degraded_mode: AtomicBool::new(false),
-            clock_sync: SyncState {
-                total_offset_ms: AtomicU64::new(0),
-                average_offset_ms: AtomicU64::new(0),
-                number_of_sample: AtomicU32::new(0),
-                is_calibrated: AtomicBool::new(true),
-            },
             boot_time: Instant::now(),
             
             sensors: [
@@ -110,9 +190,9 @@ impl SatelliteState {
                     priority: Priority::Critical,
                     data_priority: Priority::Critical,
                     task_id: TaskID::ThermalSensor,
-                    period: 5 * TICK_RATE,
-                    min_data: 2500,
-                    max_data: 5000,  
+                    period: ParameterID::ThermalSensorPeriodMs,
+                    min_data: ParameterID::ThermalSensorMinData,
+                    max_data: ParameterID::ThermalSensorMaxData,
                     value: AtomicU32::new(2500),
                     heartbeat: AtomicU64::new(u64::MAX),
                     fault: AtomicU16::new(0),
@@ -133,9 +213,9 @@ impl SatelliteState {
                     priority: Priority::Normal,
                     data_priority: Priority::Normal,
                     task_id: TaskID::PitchAndYawSensor,
-                    period: 10 * TICK_RATE,
-                    min_data: 00000,
-                    max_data: 36000, 
+                    period: ParameterID::PitchAndYawSensorPeriodMs,
+                    min_data: ParameterID::PitchAndYawSensorMinData,
+                    max_data: ParameterID::PitchAndYawSensorMaxData,
                     value: AtomicU32::new(18000),
                     heartbeat: AtomicU64::new(u64::MAX),
                     fault: AtomicU16::new(0),
@@ -156,9 +236,9 @@ impl SatelliteState {
                     priority: Priority::Low,
                     data_priority: Priority::Normal,
                     task_id: TaskID::MoistureSensor,
-                    period: 20 * TICK_RATE,
-                    min_data: 2500,
-                    max_data: 5000, 
+                    period: ParameterID::MoistureSensorPeriodMs,
+                    min_data: ParameterID::MoistureSensorMinData,
+                    max_data: ParameterID::MoistureSensorMaxData,
                     value: AtomicU32::new(4500),
                     heartbeat: AtomicU64::new(u64::MAX),
                     fault: AtomicU16::new(0),
@@ -201,15 +281,19 @@ impl SatelliteState {
         }
     }
 
+    // The crystal runs hotter or colder with the thermal sensor, in centidegrees
     pub fn uptime_ms(&self) -> u64 {
-        self.boot_time.elapsed().as_micros() as u64 
+        let thermal = &self.sensors[THERMAL_SENSOR];
+        let temperature_c = thermal.has_valid_value(&self.parameters).then(|| thermal.value.load(Ordering::Relaxed) as f64 / 100.0);
+
+        self.oscillator.lock().unwrap().advance(self.boot_time.elapsed().as_micros() as u64, temperature_c)
     }
 
     pub fn get_synchronized_timestamp(&self) -> u64 {
-        self.uptime_ms() + &self.clock_sync.average_offset_ms.load(Ordering::Relaxed) 
+        self.synchronize_timestamp(self.uptime_ms())
     }
 
     pub fn synchronize_timestamp(&self, timestamp: u64) -> u64 {
-        timestamp + &self.clock_sync.average_offset_ms.load(Ordering::Relaxed)
+        self.clock_sync.estimate().ground_time(timestamp)
     }
 }
\ No newline at end of file
@@ -0,0 +1,60 @@
+use crate::types::Command;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TimeTag {
+    pub command: Command,
+    pub command_id: u32,
+    pub execute_at: u64, // Synchronized time
+    pub station: usize, // Whose clock execute_at is on, the station of the pass that uplinked it
+}
+
+// Time-tagged commands waiting onboard, soonest first. Nothing at LOS or AOS touches it, so entries wait out any number of passes
+#[derive(Debug)]
+pub struct TimeTagQueue {
+    entries: Vec<TimeTag>, // Sorted by execute_at, equal times in arrival order. Only entries of one station share a clock
+    pub capacity: usize,
+}
+
+impl TimeTagQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    // Hands the tag back if the queue is full
+    pub fn insert(&mut self, tag: TimeTag) -> Result<(), TimeTag> {
+        if self.entries.len() >= self.capacity {
+            return Err(tag);
+        }
+
+        let index = self.entries.partition_point(|entry| entry.execute_at <= tag.execute_at);
+        self.entries.insert(index, tag);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, command_id: u32) -> Option<TimeTag> {
+        let index = self.entries.iter().position(|entry| entry.command_id == command_id)?;
+        Some(self.entries.remove(index))
+    }
+
+    // The first entry is_due says has come. Every entry is asked, one on a station clock running behind another's
+    // can be due while an earlier execute_at of the other station is not
+    pub fn pop_due(&mut self, is_due: impl Fn(&TimeTag) -> bool) -> Option<TimeTag> {
+        let index = self.entries.iter().position(is_due)?;
+        Some(self.entries.remove(index))
+    }
+
+    pub fn entries(&self) -> &[TimeTag] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
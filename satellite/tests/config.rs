@@ -0,0 +1,77 @@
+use std::io;
+use std::path::PathBuf;
+
+use rts_satellite::config::Config;
+
+// Loads text through a file the way the binary does, name keeps tests running in parallel apart
+fn load(name: &str, text: &str) -> io::Result<Config> {
+    let path = std::env::temp_dir().join(format!("rts_satellite_{}_{}.toml", std::process::id(), name));
+    std::fs::write(&path, text)?;
+    let config = Config::load(&path);
+    std::fs::remove_file(&path).ok();
+    config
+}
+
+fn rejection(name: &str, text: &str) -> String {
+    load(name, text).expect_err("the config should be refused").to_string()
+}
+
+const STATION: &str = "[[ground_stations]]\nstation_id = 1\naddress = \"127.0.0.1:8000\"\nlatitude_deg = 40.0\nlongitude_deg = 0.0\nmin_elevation_deg = 10.0\n";
+
+#[test]
+fn an_empty_file_loads_the_defaults() {
+    assert_eq!(load("empty", "").unwrap(), Config::default());
+}
+
+#[test]
+fn every_shipped_config_loads() {
+    let configs = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("configs");
+
+    for file in std::fs::read_dir(configs).unwrap() {
+        let path = file.unwrap().path();
+        assert!(Config::load(&path).is_ok(), "{}: {:?}", path.display(), Config::load(&path).err());
+    }
+}
+
+#[test]
+fn an_unknown_key_is_refused() {
+    assert!(rejection("unknown", "degraded_threshold = 50").contains("unknown field"));
+}
+
+#[test]
+fn a_zero_period_is_refused() {
+    assert!(rejection("period", "monitor_ms = 0").contains("monitor_ms must be greater than 0"));
+}
+
+#[test]
+fn a_thread_priority_above_the_maximum_is_refused() {
+    assert!(rejection("priority", "simulation_priority = 100").contains("simulation_priority must be at most 99"));
+}
+
+#[test]
+fn the_degraded_threshold_is_a_percentage() {
+    assert!(rejection("percentage", "normal_to_degraded_threshold = 101").contains("normal_to_degraded_threshold is a percentage"));
+}
+
+#[test]
+fn the_degraded_thresholds_must_leave_a_hysteresis_band() {
+    let text = "normal_to_degraded_threshold = 60\ndegraded_to_normal_threshold = 60";
+    assert!(rejection("thresholds", text).contains("degraded_to_normal_threshold (60) must be below normal_to_degraded_threshold (60)"));
+}
+
+#[test]
+fn at_least_one_ground_station_is_needed() {
+    assert!(rejection("no_stations", "ground_stations = []").contains("ground_stations must list 1 to"));
+}
+
+#[test]
+fn a_station_listed_twice_is_refused() {
+    let text = format!("{}{}", STATION, STATION.replace("8000", "8002"));
+    assert!(rejection("stations", &text).contains("ground_stations lists station 1 twice"));
+}
+
+#[test]
+fn the_priority_reserve_must_fit_in_the_burst() {
+    let text = "downlink_burst_bits = 4000\ndownlink_priority_reserve_bits = 8000";
+    assert!(rejection("reserve", text).contains("downlink_priority_reserve_bits (8000) must not exceed downlink_burst_bits (4000)"));
+}
@@ -0,0 +1,32 @@
+use rts_satellite::time_tag::{TimeTag, TimeTagQueue};
+use rts_satellite::types::Command;
+
+fn tag(command_id: u32, execute_at: u64, station: usize) -> TimeTag {
+    TimeTag { command: Command::SetPowerMode { mode: 1 }, command_id, execute_at, station }
+}
+
+#[test]
+fn a_tag_on_a_clock_behind_comes_due_before_an_earlier_one_ahead() {
+    let mut queue = TimeTagQueue::new(4);
+    queue.insert(tag(1, 1_000, 0)).unwrap();
+    queue.insert(tag(2, 5_000, 1)).unwrap();
+
+    // Station 1's clock is 10 ms ahead of station 0's, so its later execute_at is reached first
+    let offsets = [0, 10_000];
+    let is_due = |now: u64| move |tag: &TimeTag| now + offsets[tag.station] >= tag.execute_at;
+
+    assert_eq!(queue.pop_due(is_due(0)).map(|tag| tag.command_id), Some(2));
+    assert_eq!(queue.pop_due(is_due(0)).map(|tag| tag.command_id), None);
+    assert_eq!(queue.pop_due(is_due(1_000)).map(|tag| tag.command_id), Some(1));
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn a_full_queue_hands_the_tag_back() {
+    let mut queue = TimeTagQueue::new(1);
+    queue.insert(tag(1, 1_000, 0)).unwrap();
+
+    assert_eq!(queue.insert(tag(2, 500, 0)).map_err(|tag| tag.command_id), Err(2));
+    assert_eq!(queue.remove(1).map(|tag| tag.command_id), Some(1));
+    assert_eq!(queue.remove(1).map(|tag| tag.command_id), None);
+}
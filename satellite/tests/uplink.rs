@@ -0,0 +1,134 @@
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rts_satellite::buffer::BoundedBuffer;
+use rts_satellite::config::Config;
+use rts_satellite::network::run_network_thread;
+use rts_satellite::state::SatelliteState;
+use rts_satellite::types::*;
+
+const KEY: [u8; AUTH_KEY_LEN] = [0x5A; AUTH_KEY_LEN];
+const DEADLINE: Duration = Duration::from_secs(10);
+
+// The satellite's network thread with the test standing in for the ground on the other end of the link
+struct Pass {
+    satellite: Arc<SatelliteState>,
+    ground: ChannelTransport,
+    thread: JoinHandle<()>,
+}
+
+impl Pass {
+    // Answers the satellite's Hello with bincode and no encryption, the ground's uplink starting at 1
+    fn open() -> Self {
+        let (satellite_end, mut ground) = ChannelTransport::pair();
+
+        let satellite = Arc::new(SatelliteState::new(Config::default()));
+        let downlink_buffer = Arc::new(BoundedBuffer::new(satellite.config.data_buffer_capacity));
+        let uplink_buffer = Arc::new(BoundedBuffer::new(satellite.config.data_buffer_capacity));
+        let (log_tx, log_rx) = mpsc::sync_channel(satellite.config.log_buffer_capacity);
+        satellite.network.stations_in_view.store(1, Ordering::Release);
+
+        let state = Arc::clone(&satellite);
+        let thread = thread::spawn(move || run_network_thread(state, downlink_buffer, uplink_buffer, log_tx,
+            AuthKey::new(KEY), CommandVerifier::new(AuthKey::new(KEY)), vec![satellite_end]));
+        thread::spawn(move || log_rx.iter().for_each(drop));
+
+        assert!(ground.open());
+        let hello: Hello = bincode::deserialize(&ground.recv_frame(DEADLINE).unwrap()).unwrap();
+        let ack = HelloAck {
+            hello: Hello { next_sequence_no: 1, ..Hello::new(hello.spacecraft_id, Capabilities::AUTH) },
+            rejected: None,
+        };
+        ground.send_frame(&bincode::serialize(&ack).unwrap());
+
+        Self { satellite, ground, thread }
+    }
+
+    fn uplink(&mut self, payload: SatelliteMessage, sequence_no: u32) {
+        let packet = TelemetryPacket { priority: Priority::Normal, creation_time: 0, payload, sequence_no };
+        assert!(self.ground.send_frame(&bincode::serialize(&packet).unwrap()));
+    }
+
+    // Every event downlinked until done says so, None if DEADLINE passes first
+    fn downlink_until(&mut self, mut done: impl FnMut(&Event) -> bool) -> Option<Vec<Event>> {
+        let deadline = Instant::now() + DEADLINE;
+        let mut events = Vec::new();
+
+        while Instant::now() < deadline {
+            let Some(bytes) = self.ground.recv_frame(Duration::from_millis(10)) else {
+                continue;
+            };
+
+            if let Ok(TelemetryPacket { payload: SatelliteMessage::Telemetry { event }, .. }) = bincode::deserialize(&bytes) {
+                events.push(event);
+
+                if done(&event) {
+                    return Some(events);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn close(mut self) -> Arc<SatelliteState> {
+        self.satellite.is_running.store(false, Ordering::SeqCst);
+        self.ground.close(); // Ends the pass, the thread only looks at is_running between passes
+        self.thread.join().unwrap();
+        self.satellite
+    }
+}
+
+fn command(command_id: u32) -> SatelliteMessage {
+    SatelliteMessage::Command { command: Command::SetPowerMode { mode: 1 }, command_id, sent_at: 0, execute_at: 0, auth: CommandAuth::UNSIGNED }
+}
+
+fn signed(signer: &mut CommandSigner, mut message: SatelliteMessage) -> SatelliteMessage {
+    signer.sign(&mut message);
+    message
+}
+
+#[test]
+fn a_forged_frame_leaves_the_uplink_gap_tracker_alone() {
+    let mut pass = Pass::open();
+    let mut signer = CommandSigner::new(AuthKey::new(KEY), 1);
+
+    pass.uplink(signed(&mut signer, command(1)), 1);
+    pass.uplink(command(2), 50_000); // Unsigned, far ahead
+    pass.uplink(SatelliteMessage::Nack { base: 1, bitmap: 0 }, 60_000); // Needs no key, but jumps the count
+    pass.uplink(signed(&mut signer, command(3)), 2);
+
+    let events = pass.downlink_until(|event| event.data == EventData::CommandAck { command_id: 3 });
+    let satellite = pass.close();
+
+    let events = events.expect("the genuine command after the forgery is acknowledged");
+    assert!(events.iter().all(|event| event.event_id != EventID::UplinkGap), "{:?}", events);
+    assert_eq!(satellite.network.last_uplink_sequence.load(Ordering::Acquire), 2);
+    assert_eq!(satellite.network.uplink_frames_missed.load(Ordering::Acquire), 0);
+}
+
+#[test]
+fn only_a_genuine_command_is_named_in_its_rejection() {
+    let mut pass = Pass::open();
+    let mut signer = CommandSigner::new(AuthKey::new(KEY), 1);
+
+    let genuine = signed(&mut signer, command(1));
+    pass.uplink(genuine, 1);
+    pass.uplink(command(2), 2); // Forged, its command_id may well be one the ground has pending
+    pass.uplink(genuine, 3); // Replayed
+
+    let events = pass.downlink_until(|event| matches!(event.data, EventData::CommandRejected { .. }));
+    pass.close();
+
+    let events = events.expect("the replay is rejected");
+    let failures: Vec<EventData> = events.iter().filter(|event| event.event_id == EventID::AuthenticationFailure).map(|event| event.data).collect();
+
+    assert_eq!(failures, vec![
+        EventData::AuthFailure { reason: AuthReject::BadTag, counter: 0, last_counter: 1 },
+        EventData::AuthFailure { reason: AuthReject::Replayed, counter: 1, last_counter: 1 },
+    ]);
+    assert_eq!(events.last().map(|event| event.data), Some(EventData::CommandRejected { command_id: 1, reason: RejectReason::Unauthorized }));
+}